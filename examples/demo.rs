@@ -185,7 +185,7 @@ fn demo_walk_deep_tree() {
     // Generate initial tree state.
     println!("generating ops...");
     let mut ops = vec![(0, "root", ids["root"])];
-    mktree_ops(&mut ops, &mut r1, ids["root"], 2, 6); //  <-- max 6 levels deep.
+    mktree_ops(&mut ops, ids["root"], 2, 6); //  <-- max 6 levels deep.
 
     println!("applying ops...");
     let ops_len = ops.len();
@@ -230,7 +230,7 @@ fn demo_truncate_log() {
     for r in replicas.iter_mut() {
         let finaldepth = rand::thread_rng().gen_range(3, 6);
         let mut ops = vec![];
-        mktree_ops(&mut ops, r, root_id, 2, finaldepth);
+        mktree_ops(&mut ops, root_id, 2, finaldepth);
         opmoves.extend(r.opmoves(ops));
     }
 
@@ -309,7 +309,7 @@ fn demo_move_to_trash() {
     ];
 
     // add some nodes under project
-    mktree_ops(&mut ops, &mut r1, ids["project"], 2, 3);
+    mktree_ops(&mut ops, ids["project"], 2, 3);
     let opmoves = r1.opmoves(ops);
     r1.apply_ops_byref(&opmoves);
     r2.apply_ops_byref(&opmoves);
@@ -375,7 +375,6 @@ Usage: tree <demo>
 // with 2 children for each parent.
 fn mktree_ops(
     ops: &mut Vec<(TypeId, TypeMeta, TypeActor)>,
-    r: &mut TreeReplica<TypeId, TypeMeta, TypeActor>,
     parent_id: u64,
     depth: usize,
     max_depth: usize,
@@ -388,7 +387,7 @@ fn mktree_ops(
         let name = if i == 0 { "a" } else { "b" };
         let child_id = new_id();
         ops.push((parent_id, name, child_id));
-        mktree_ops(ops, r, child_id, depth + 1, max_depth);
+        mktree_ops(ops, child_id, depth + 1, max_depth);
     }
 }
 
@@ -412,7 +411,7 @@ fn new_id() -> TypeId {
 }
 
 // print a treenode, recursively
-fn print_treenode<ID, TM>(tree: &Tree<ID, TM>, node_id: &ID, depth: usize, with_id: bool)
+fn print_treenode<ID, TM>(tree: &Tree<ID, TM>, node_id: &ID, depth: usize)
 where
     ID: TreeId + std::fmt::Debug,
     TM: TreeMeta + std::fmt::Debug,
@@ -428,7 +427,7 @@ where
     println!("{:indent$}{}", "", meta, indent = depth * 2);
 
     for c in tree.children(node_id) {
-        print_treenode(tree, &c, depth + 1, with_id);
+        print_treenode(tree, &c, depth + 1);
     }
 }
 
@@ -438,7 +437,7 @@ where
     ID: TreeId + std::fmt::Debug,
     TM: TreeMeta + std::fmt::Debug,
 {
-    print_treenode(tree, root, 0, false);
+    print_treenode(tree, root, 0);
 }
 
 // print trees for two replicas
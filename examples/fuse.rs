@@ -0,0 +1,46 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Mounts a TreeReplica at the given mountpoint.
+//!
+//! usage: cargo run --example fuse --features fuse -- <mountpoint>
+//!
+//! Directories may then be created, renamed and removed with the usual
+//! tools.  The ops generated by each call are printed to stdout.
+
+use crdt_tree::fs::FileSystem;
+use crdt_tree::fuse::FuseAdapter;
+use crdt_tree::TreeReplica;
+use std::env;
+
+type TypeId = u64;
+type TypeActor = u64;
+
+const ROOT: TypeId = 0;
+const TRASH: TypeId = 1;
+
+fn main() {
+    let mountpoint = match env::args().nth(1) {
+        Some(m) => m,
+        None => {
+            eprintln!("usage: fuse <mountpoint>");
+            std::process::exit(1);
+        }
+    };
+
+    let replica = TreeReplica::<TypeId, String, TypeActor>::new(rand::random());
+    let fs = FileSystem::new(replica, ROOT, TRASH, rand::random::<TypeId>);
+    let adapter = FuseAdapter::new(fs).on_ops(Box::new(|ops| {
+        for op in ops {
+            println!("{:?}", op);
+        }
+    }));
+
+    if let Err(e) = adapter.mount(&mountpoint) {
+        eprintln!("mount failed: {}", e);
+        std::process::exit(1);
+    }
+}
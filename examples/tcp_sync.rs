@@ -0,0 +1,145 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+// Two replicas syncing over TCP, using the length-delimited frames and
+// sync messages of crdt_tree::net.
+//
+// A server replica listens on a local port, and serves each connection on
+// its own thread.  A client replica connects, catches up, sends an op as
+// it is generated and disconnects.  Both replicas then change while
+// disconnected, and the client reconnects and catches up again, using the
+// version vectors exchanged on connecting to find the ops each side is
+// missing.
+//
+// Run with: cargo run --example tcp_sync --features net
+
+use crdt_tree::net::{handle_message, read_frame, write_frame, NetError, SyncMessage};
+use crdt_tree::{TreeReplica, WalkControl};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// define some concrete types to instantiate our Tree data structures with.
+type TypeId = u64;
+type TypeMeta = String;
+type TypeActor = u64;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeMessage = SyncMessage<TypeId, TypeMeta, TypeActor>;
+
+fn main() -> Result<(), NetError> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    println!("server listening on {}", addr);
+
+    let server = Arc::new(Mutex::new(TypeReplica::new(1)));
+    move_node(&mut server.lock().unwrap(), 0, "root", 1);
+    move_node(&mut server.lock().unwrap(), 1, "docs", 2);
+
+    // the server handles two connections: the client's first session, and
+    // its reconnection.
+    let replica = server.clone();
+    let acceptor = thread::spawn(move || -> Result<(), NetError> {
+        for stream in listener.incoming().take(2) {
+            serve(&replica, stream?)?;
+        }
+        Ok(())
+    });
+
+    // first session: catch up, then send a new op as it is generated.
+    let mut client = TypeReplica::new(2);
+    let mut stream = TcpStream::connect(addr)?;
+    catch_up(&mut client, &mut stream)?;
+    println!("client caught up: {} nodes", client.tree().num_nodes());
+    let op = move_node(&mut client, 2, "readme", 3);
+    write_frame(&mut stream, &TypeMessage::Ops(vec![op]))?;
+    stream.shutdown(Shutdown::Both)?;
+    println!("client disconnected");
+
+    // both replicas change while disconnected.
+    move_node(&mut server.lock().unwrap(), 1, "src", 4);
+    move_node(&mut client, 1, "tests", 5);
+
+    // second session: each side sends the ops the other missed.
+    let mut stream = TcpStream::connect(addr)?;
+    catch_up(&mut client, &mut stream)?;
+    stream.shutdown(Shutdown::Write)?;
+    // wait for the server to read the client's ops.
+    while read_frame::<_, TypeMessage>(&mut stream)?.is_some() {}
+    acceptor.join().expect("server thread panicked")?;
+
+    let server = server.lock().unwrap();
+    println!("\nserver tree:");
+    print_tree(&server);
+    println!("\nclient tree:");
+    print_tree(&client);
+    assert_eq!(server.tree(), client.tree());
+    println!("\nreplicas converged");
+    Ok(())
+}
+
+// serves a single connection, applying and answering messages from the
+// peer until it disconnects.
+fn serve(replica: &Mutex<TypeReplica>, mut stream: TcpStream) -> Result<(), NetError> {
+    let hello = TypeMessage::Hello(replica.lock().unwrap().version_vector().clone());
+    write_frame(&mut stream, &hello)?;
+    while let Some(msg) = read_frame(&mut stream)? {
+        let reply = handle_message(&mut replica.lock().unwrap(), msg);
+        if let Some(reply) = reply {
+            write_frame(&mut stream, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+// exchanges version vectors with the peer, sends the ops it is missing and
+// applies the ops it sends back.
+fn catch_up(replica: &mut TypeReplica, stream: &mut TcpStream) -> Result<(), NetError> {
+    write_frame(
+        stream,
+        &TypeMessage::Hello(replica.version_vector().clone()),
+    )?;
+    let mut caught_up = false;
+    while !caught_up {
+        let msg = match read_frame(stream)? {
+            Some(msg) => msg,
+            None => break,
+        };
+        // the peer's answer to our Hello completes the catch-up.
+        caught_up = !matches!(msg, TypeMessage::Hello(_));
+        if let TypeMessage::SnapshotRequired = msg {
+            println!("peer truncated its log; a snapshot is needed");
+        }
+        if let Some(reply) = handle_message(replica, msg) {
+            write_frame(stream, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+// generates and applies an op moving child_id under parent_id, returning
+// it for sending to peers.
+fn move_node(
+    replica: &mut TypeReplica,
+    parent_id: TypeId,
+    name: &str,
+    child_id: TypeId,
+) -> crdt_tree::OpMove<TypeId, TypeMeta, TypeActor> {
+    let op = replica.opmove(parent_id, name.to_string(), child_id);
+    replica.apply_op(op.clone());
+    op
+}
+
+// prints the tree rooted at node 0, indented by depth, visiting children
+// in id order so that equal trees print the same.
+fn print_tree(replica: &TypeReplica) {
+    let tree = replica.tree();
+    tree.walk_dfs_by(&0, TypeId::cmp, |tree, node_id, depth| {
+        if let Some(node) = tree.find(node_id) {
+            println!("{}{}", "  ".repeat(depth), node.metadata());
+        }
+        WalkControl::Continue
+    });
+}
@@ -0,0 +1,40 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Concrete aliases for the generic types, with IDs and actors fixed, so
+//! only the metadata type is left to choose.
+
+use super::{LogOpMove, OpMove, State, TreeReplica};
+
+/// `TreeReplica` with u64 node IDs and actors.
+pub type U64TreeReplica<TM> = TreeReplica<u64, TM, u64>;
+
+/// `State` with u64 node IDs and actors.
+pub type U64State<TM> = State<u64, TM, u64>;
+
+/// `OpMove` with u64 node IDs and actors.
+pub type U64OpMove<TM> = OpMove<u64, TM, u64>;
+
+/// `LogOpMove` with u64 node IDs and actors.
+pub type U64LogOpMove<TM> = LogOpMove<u64, TM, u64>;
+
+/// `TreeReplica` with UUID node IDs and actors.  Requires the `uuid`
+/// feature.
+#[cfg(feature = "uuid")]
+pub type UuidTreeReplica<TM> = TreeReplica<uuid::Uuid, TM, uuid::Uuid>;
+
+/// `State` with UUID node IDs and actors.  Requires the `uuid` feature.
+#[cfg(feature = "uuid")]
+pub type UuidState<TM> = State<uuid::Uuid, TM, uuid::Uuid>;
+
+/// `OpMove` with UUID node IDs and actors.  Requires the `uuid` feature.
+#[cfg(feature = "uuid")]
+pub type UuidOpMove<TM> = OpMove<uuid::Uuid, TM, uuid::Uuid>;
+
+/// `LogOpMove` with UUID node IDs and actors.  Requires the `uuid`
+/// feature.
+#[cfg(feature = "uuid")]
+pub type UuidLogOpMove<TM> = LogOpMove<uuid::Uuid, TM, uuid::Uuid>;
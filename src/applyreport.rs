@@ -0,0 +1,50 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+
+/// `ApplyReport` summarizes what became of a batch of ops.  See
+/// `TreeReplica::apply_ops_report`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ApplyReport {
+    /// ops applied that moved their node
+    pub applied: usize,
+    /// ops applied that the tree ignored, eg to avoid a cycle, or as they
+    /// would have exceeded the quotas
+    pub no_effect: usize,
+    /// ops already applied, and skipped
+    pub duplicates: usize,
+    /// ops rejected by the drift guard
+    pub drifted: usize,
+    /// ops rejected as their actor was tombstoned
+    pub tombstoned: usize,
+    /// ops rejected by the log limit
+    pub over_limit: usize,
+    /// the number of log entries undone, and redone, to apply ops older
+    /// than ones already applied
+    pub undo_redo_steps: usize,
+    /// the replica's lamport counter before the batch
+    pub counter_before: u64,
+    /// the replica's lamport counter after the batch
+    pub counter_after: u64,
+}
+
+impl ApplyReport {
+    /// returns the number of ops in the batch.
+    pub fn total(&self) -> usize {
+        self.applied + self.no_effect + self.ignored()
+    }
+
+    /// returns the number of ops not applied, for any reason.
+    pub fn ignored(&self) -> usize {
+        self.duplicates + self.drifted + self.tombstoned + self.over_limit
+    }
+
+    /// returns how far the batch advanced the replica's lamport counter.
+    pub fn clock_advance(&self) -> u64 {
+        self.counter_after - self.counter_before
+    }
+}
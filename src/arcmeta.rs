@@ -0,0 +1,164 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// `ArcMeta` wraps metadata in an `Arc`, so that it is cheap to clone.
+///
+/// Applying an op clones its metadata into the tree, and undoing and
+/// redoing ops clones it again into log entries.  For large metadata, eg
+/// file attributes or blobs, use `ArcMeta<TM>` as the tree's metadata type
+/// so that the op, its log entry and the tree node all share one
+/// allocation.
+///
+/// `ArcMeta` derefs to the metadata, and is compared, hashed, formatted
+/// and serialized as the metadata itself.
+///
+/// ```text
+/// let mut r: TreeReplica<u64, ArcMeta<FileAttrs>, u8> = TreeReplica::new(1);
+/// r.apply_op(r.opmove(parent_id, ArcMeta::new(attrs), child_id));
+/// ```
+pub struct ArcMeta<TM>(Arc<TM>);
+
+impl<TM> ArcMeta<TM> {
+    /// wraps metadata
+    pub fn new(metadata: TM) -> Self {
+        Self(Arc::new(metadata))
+    }
+
+    /// returns true if a and b share the same allocation.
+    #[inline]
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+
+    /// returns the number of `ArcMeta` sharing this metadata.
+    #[inline]
+    pub fn ref_count(this: &Self) -> usize {
+        Arc::strong_count(&this.0)
+    }
+
+    /// returns the underlying `Arc`
+    #[inline]
+    pub fn as_arc(&self) -> &Arc<TM> {
+        &self.0
+    }
+}
+
+impl<TM: Clone> ArcMeta<TM> {
+    /// returns a mutable reference to the metadata, first copying it if
+    /// it is shared.
+    pub fn make_mut(this: &mut Self) -> &mut TM {
+        Arc::make_mut(&mut this.0)
+    }
+
+    /// returns the metadata, copying it only if it is shared.
+    pub fn into_inner(this: Self) -> TM {
+        Arc::try_unwrap(this.0).unwrap_or_else(|arc| (*arc).clone())
+    }
+}
+
+impl<TM> Clone for ArcMeta<TM> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<TM> Deref for ArcMeta<TM> {
+    type Target = TM;
+
+    #[inline]
+    fn deref(&self) -> &TM {
+        &self.0
+    }
+}
+
+impl<TM> AsRef<TM> for ArcMeta<TM> {
+    #[inline]
+    fn as_ref(&self) -> &TM {
+        &self.0
+    }
+}
+
+impl<TM> Borrow<TM> for ArcMeta<TM> {
+    #[inline]
+    fn borrow(&self) -> &TM {
+        &self.0
+    }
+}
+
+impl<TM> From<TM> for ArcMeta<TM> {
+    fn from(metadata: TM) -> Self {
+        Self::new(metadata)
+    }
+}
+
+impl<TM> From<Arc<TM>> for ArcMeta<TM> {
+    fn from(metadata: Arc<TM>) -> Self {
+        Self(metadata)
+    }
+}
+
+impl<TM: Default> Default for ArcMeta<TM> {
+    fn default() -> Self {
+        Self::new(TM::default())
+    }
+}
+
+impl<TM: PartialEq> PartialEq for ArcMeta<TM> {
+    fn eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other) || *self.0 == *other.0
+    }
+}
+
+impl<TM: Eq> Eq for ArcMeta<TM> {}
+
+impl<TM: PartialOrd> PartialOrd for ArcMeta<TM> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (*self.0).partial_cmp(&*other.0)
+    }
+}
+
+impl<TM: Ord> Ord for ArcMeta<TM> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (*self.0).cmp(&*other.0)
+    }
+}
+
+impl<TM: std::hash::Hash> std::hash::Hash for ArcMeta<TM> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (*self.0).hash(state)
+    }
+}
+
+impl<TM: fmt::Debug> fmt::Debug for ArcMeta<TM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (*self.0).fmt(f)
+    }
+}
+
+impl<TM: fmt::Display> fmt::Display for ArcMeta<TM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (*self.0).fmt(f)
+    }
+}
+
+impl<TM: Serialize> Serialize for ArcMeta<TM> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (*self.0).serialize(serializer)
+    }
+}
+
+impl<'de, TM: Deserialize<'de>> Deserialize<'de> for ArcMeta<TM> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TM::deserialize(deserializer).map(Self::new)
+    }
+}
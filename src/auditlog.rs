@@ -0,0 +1,156 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Resolve, State, TieBreak, TreeId, TreeMeta, TreeNode};
+use crdts::Actor;
+
+/// `AuditRecord` describes one op in a `State`'s log, for archiving.  See
+/// `State::audit_log`.
+///
+/// Metadata is not included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord<ID, A> {
+    /// the actor that generated the op
+    pub actor: A,
+    /// the op's lamport counter
+    pub counter: u64,
+    /// the wall-clock time claimed by the actor, in milliseconds since the
+    /// UNIX epoch, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_time: Option<u64>,
+    /// the node moved
+    pub child_id: ID,
+    /// the parent the op moved the node under
+    pub parent_id: ID,
+    /// the node's parent before the op.  None if it was not in the tree.
+    pub old_parent_id: Option<ID>,
+    /// the node's parent after the op, which differs from parent_id if the
+    /// op was ignored, eg to avoid a cycle.  None if it is not in the tree.
+    pub new_parent_id: Option<ID>,
+    /// the op's provenance, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Vec<u8>>,
+}
+
+/// `AuditFilter` selects the records returned by `State::audit_log`.  The
+/// default selects every record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditFilter<A> {
+    actor: Option<A>,
+    wall_time: Option<Range<u64>>,
+}
+
+impl<A> Default for AuditFilter<A> {
+    fn default() -> Self {
+        Self {
+            actor: None,
+            wall_time: None,
+        }
+    }
+}
+
+impl<A: Actor> AuditFilter<A> {
+    /// creates a filter selecting every record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// selects records of ops generated by actor.
+    pub fn with_actor(mut self, actor: A) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// selects records of ops with a wall-clock time within range, in
+    /// milliseconds since the UNIX epoch.  Ops without one are excluded.
+    pub fn with_wall_time(mut self, range: Range<u64>) -> Self {
+        self.wall_time = Some(range);
+        self
+    }
+
+    /// returns true if record is selected.
+    pub fn matches<ID>(&self, record: &AuditRecord<ID, A>) -> bool {
+        if matches!(&self.actor, Some(actor) if actor != &record.actor) {
+            return false;
+        }
+        match (&self.wall_time, record.wall_time) {
+            (None, _) => true,
+            (Some(range), Some(t)) => range.contains(&t),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// returns a record of each op in the log selected by filter, oldest
+    /// first.
+    ///
+    /// Only ops still in the log are included, ie not those removed by
+    /// ::truncate_log_before().
+    pub fn audit_log(&self, filter: &AuditFilter<A>) -> Vec<AuditRecord<ID, A>> {
+        // the node each child had after the entry being visited, walking
+        // from newest to oldest.
+        let mut after: HashMap<&ID, Option<&TreeNode<ID, TM>>> = HashMap::new();
+        let mut records = vec![];
+        for entry in self.log() {
+            let child_id = entry.child_id();
+            let node = *after
+                .entry(child_id)
+                .or_insert_with(|| self.tree().find(child_id));
+            after.insert(child_id, entry.oldp().as_ref());
+
+            let record = AuditRecord {
+                actor: entry.timestamp().actor_id().clone(),
+                counter: entry.timestamp().counter(),
+                wall_time: entry.wall_time(),
+                child_id: child_id.clone(),
+                parent_id: entry.parent_id().clone(),
+                old_parent_id: entry.oldp().as_ref().map(|n| n.parent_id().clone()),
+                new_parent_id: node.map(|n| n.parent_id().clone()),
+                provenance: entry.provenance().map(|p| p.to_vec()),
+            };
+            if filter.matches(&record) {
+                records.push(record);
+            }
+        }
+        records.reverse();
+        records
+    }
+
+    /// writes the records returned by ::audit_log() to writer as JSON
+    /// lines, ie one JSON object per line, and returns the number written.
+    ///
+    /// Requires the `serde_json` feature.
+    #[cfg(feature = "serde_json")]
+    pub fn export_audit_log<W: std::io::Write>(
+        &self,
+        filter: &AuditFilter<A>,
+        mut writer: W,
+    ) -> serde_json::Result<usize>
+    where
+        ID: Serialize,
+        A: Serialize,
+    {
+        let records = self.audit_log(filter);
+        for record in &records {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+        }
+        Ok(records.len())
+    }
+}
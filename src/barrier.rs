@@ -0,0 +1,41 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+
+use super::Clock;
+use crdts::Actor;
+
+/// `Barrier` is a replica's announcement that it will never generate an
+/// op with a timestamp below its clock.  See `TreeReplica::barrier`.
+///
+/// The causally stable threshold only advances as each replica is heard
+/// from.  A replica with no ops to send emits barriers instead, so that
+/// its peers may still truncate their logs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Barrier<A: Actor> {
+    clock: Clock<A>,
+}
+
+impl<A: Actor> Barrier<A> {
+    /// creates a barrier announcing that clock's actor will only generate
+    /// ops with counters above clock's.
+    pub fn new(clock: Clock<A>) -> Self {
+        Self { clock }
+    }
+
+    /// returns the announcing actor
+    #[inline]
+    pub fn actor(&self) -> &A {
+        self.clock.actor_id()
+    }
+
+    /// returns the clock
+    #[inline]
+    pub fn clock(&self) -> &Clock<A> {
+        &self.clock
+    }
+}
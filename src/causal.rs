@@ -0,0 +1,88 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Ops carrying the causal context in which they were generated.
+//!
+//! Lamport timestamps give a total order, so ops that were generated
+//! concurrently cannot be told apart from ops where one replica had seen
+//! the other's op.  A `CausalOpMove` adds the originating replica's
+//! version vector, ie the latest counter it had seen from each replica,
+//! to an `OpMove`.  Together with the op's own timestamp (its dot), this
+//! allows a receiver to:
+//!
+//! * tell whether two ops are concurrent, eg to report conflicts.
+//! * detect ops whose causal dependencies it has not yet seen, and
+//!   buffer them until it has.  See `TreeReplica::apply_causal_op`.
+
+use serde::{Deserialize, Serialize};
+
+use super::{OpMove, TreeId, TreeMeta, VersionVector};
+use crdts::Actor;
+
+/// `CausalContext` is the version vector of the replica that generated
+/// an op, at the time it was generated.
+pub type CausalContext<A> = VersionVector<A>;
+
+/// `CausalOpMove` is an `OpMove` plus the causal context of the replica
+/// that generated it.  See `TreeReplica::causal_opmove`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CausalOpMove<ID: TreeId, TM: TreeMeta, A: Actor> {
+    op: OpMove<ID, TM, A>,
+    context: CausalContext<A>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> CausalOpMove<ID, TM, A> {
+    /// creates a causal op from op and the context in which it was
+    /// generated.
+    pub fn new(op: OpMove<ID, TM, A>, context: CausalContext<A>) -> Self {
+        Self { op, context }
+    }
+
+    /// returns the op
+    #[inline]
+    pub fn op(&self) -> &OpMove<ID, TM, A> {
+        &self.op
+    }
+
+    /// returns the context in which the op was generated.
+    #[inline]
+    pub fn context(&self) -> &CausalContext<A> {
+        &self.context
+    }
+
+    /// returns true if self was seen by the replica that generated other.
+    #[inline]
+    pub fn happened_before(&self, other: &Self) -> bool {
+        other.context.contains(self.op.timestamp())
+    }
+
+    /// returns true if neither op was seen by the replica that generated
+    /// the other, ie they are concurrent.
+    pub fn is_concurrent(&self, other: &Self) -> bool {
+        self.op.timestamp() != other.op.timestamp()
+            && !self.happened_before(other)
+            && !other.happened_before(self)
+    }
+
+    /// returns true if every op in this op's context is included in seen,
+    /// so the op can be applied without violating causality.
+    #[inline]
+    pub fn is_ready(&self, seen: &CausalContext<A>) -> bool {
+        seen.includes(&self.context)
+    }
+
+    /// returns the op, discarding its context.
+    #[inline]
+    pub fn into_op(self) -> OpMove<ID, TM, A> {
+        self.op
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> From<CausalOpMove<ID, TM, A>> for OpMove<ID, TM, A> {
+    fn from(op: CausalOpMove<ID, TM, A>) -> Self {
+        op.into_op()
+    }
+}
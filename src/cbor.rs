@@ -0,0 +1,137 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! CBOR encoding of ops and snapshots.
+//!
+//! Any serializable type can be encoded, eg `OpMove`, `Vec<OpMove>` or
+//! `State`, with `to_vec`.
+//!
+//! `to_canonical_vec` produces the deterministic encoding described in
+//! RFC 8949 section 4.2.1, with map keys sorted by their encoded bytes and
+//! definite lengths, so that equal values always encode to identical
+//! bytes.  Use it when hashing or signing ops.  Hash sets are encoded as
+//! arrays in iteration order, which differs between instances, so a
+//! `State` must be encoded with `state_to_canonical_vec` instead.  That
+//! encodes only the log and the tree's nodes, and the tree's indexes are
+//! rebuilt by `state_from_slice`.
+//!
+//! Requires the `cbor` feature.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use ciborium::value::Value;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Clock, LogOpMove, State, Tree, TreeId, TreeMeta, TreeNode};
+use crdts::Actor;
+
+/// Errors returned when encoding or decoding CBOR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CborError {
+    /// the value could not be encoded.
+    Encode(String),
+    /// the bytes could not be decoded.
+    Decode(String),
+}
+
+impl fmt::Display for CborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Encode(e) => write!(f, "cbor encode error: {}", e),
+            Self::Decode(e) => write!(f, "cbor decode error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CborError {}
+
+/// encodes value as CBOR.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, CborError> {
+    let mut bytes = vec![];
+    ciborium::ser::into_writer(value, &mut bytes).map_err(|e| CborError::Encode(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// encodes value as canonical CBOR.  Equal values produce identical bytes.
+pub fn to_canonical_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, CborError> {
+    let mut value = Value::serialized(value).map_err(|e| CborError::Encode(e.to_string()))?;
+    canonicalize(&mut value)?;
+    to_vec(&value)
+}
+
+/// decodes a value from CBOR, as returned by `to_vec` or `to_canonical_vec`.
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CborError> {
+    ciborium::de::from_reader(bytes).map_err(|e| CborError::Decode(e.to_string()))
+}
+
+// a state's log, nodes, the timestamp its log was truncated before and
+// that of its oldest compacted entry, as encoded by state_to_canonical_vec.
+type Snapshot<ID, TM, A> = (
+    Vec<LogOpMove<ID, TM, A>>,
+    HashMap<ID, TreeNode<ID, TM>>,
+    Option<Clock<A>>,
+    Option<Clock<A>>,
+);
+
+/// encodes state as canonical CBOR.  Equal states produce identical bytes.
+pub fn state_to_canonical_vec<ID, TM, A>(state: &State<ID, TM, A>) -> Result<Vec<u8>, CborError>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + Serialize,
+    A: Actor + Serialize,
+{
+    let nodes: HashMap<&ID, &TreeNode<ID, TM>> = state.tree().iter().collect();
+    to_canonical_vec(&(
+        state.log(),
+        nodes,
+        state.truncated_before(),
+        state.compacted_from(),
+    ))
+}
+
+/// decodes a state, as returned by `state_to_canonical_vec`.
+pub fn state_from_slice<ID, TM, A>(bytes: &[u8]) -> Result<State<ID, TM, A>, CborError>
+where
+    ID: TreeId + DeserializeOwned,
+    TM: TreeMeta + DeserializeOwned,
+    A: Actor + DeserializeOwned,
+{
+    let (log, nodes, truncated_before, compacted_from): Snapshot<ID, TM, A> = from_slice(bytes)?;
+    let mut tree = Tree::new();
+    for (child_id, node) in nodes {
+        tree.add_node(child_id, node);
+    }
+    let mut state = State::from((log, tree));
+    state.set_truncated_before(truncated_before);
+    state.set_compacted_from(compacted_from);
+    Ok(state)
+}
+
+// sorts map entries by the bytewise order of their encoded keys,
+// recursively.
+fn canonicalize(value: &mut Value) -> Result<(), CborError> {
+    match value {
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                canonicalize(item)?;
+            }
+        }
+        Value::Map(entries) => {
+            let mut keyed = Vec::with_capacity(entries.len());
+            for (mut k, mut v) in entries.drain(..) {
+                canonicalize(&mut k)?;
+                canonicalize(&mut v)?;
+                keyed.push((to_vec(&k)?, k, v));
+            }
+            keyed.sort_by(|a, b| a.0.cmp(&b.0));
+            entries.extend(keyed.into_iter().map(|(_, k, v)| (k, v)));
+        }
+        Value::Tag(_, inner) => canonicalize(inner)?,
+        _ => {}
+    }
+    Ok(())
+}
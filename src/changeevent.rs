@@ -0,0 +1,52 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+
+use super::TreeId;
+
+/// `ChangeEvent` describes the net effect on one node of the ops applied
+/// since some point in time.  See `TreeReplica::changes_since`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeEvent<ID: TreeId, TM> {
+    /// the node was added to the tree.
+    Created {
+        /// node id
+        id: ID,
+        /// the node's parent id
+        parent_id: ID,
+        /// the node's metadata
+        metadata: TM,
+    },
+    /// the node was moved, and/or its metadata changed.
+    Moved {
+        /// node id
+        id: ID,
+        /// the node's parent id before the change
+        old_parent_id: ID,
+        /// the node's parent id
+        parent_id: ID,
+        /// the node's metadata
+        metadata: TM,
+    },
+    /// the node was removed from the tree.
+    Deleted {
+        /// node id
+        id: ID,
+        /// the node's parent id before the change
+        old_parent_id: ID,
+    },
+}
+
+impl<ID: TreeId, TM> ChangeEvent<ID, TM> {
+    /// returns the id of the changed node
+    #[inline]
+    pub fn id(&self) -> &ID {
+        match self {
+            Self::Created { id, .. } | Self::Moved { id, .. } | Self::Deleted { id, .. } => id,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Checkpointing: periodic snapshots plus incremental log segments.
+//!
+//! Replaying a replica's entire op history at startup does not scale.
+//! Instead, a `Checkpointer` periodically takes a `Snapshot` of the tree
+//! and, between snapshots, wraps the ops applied to the replica in
+//! `Segment`s.  The application persists both, and at startup calls
+//! `TreeReplica::restore` with the latest snapshot and the segments
+//! recorded after it.  Segments from earlier checkpoints can be deleted
+//! once a new snapshot is persisted.
+//!
+//! A snapshot holds the tree and only the log entries that are not yet
+//! causally stable, as the older entries can never be undone.  Its size
+//! is therefore proportional to the tree, so long as every replica's
+//! latest time is tracked and advances.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Clock, LogOpMove, OpMove, Tree, TreeId, TreeMeta, TreeReplica, VersionVector};
+use crdts::Actor;
+
+/// `Snapshot` is a replica's tree and clocks at a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot<ID: TreeId, TM: TreeMeta, A: Actor> {
+    id: u64,
+    tree: Tree<ID, TM>,
+    log: Vec<LogOpMove<ID, TM, A>>, // entries not yet causally stable.
+    // the timestamp the log was truncated before, if ever.
+    #[serde(default = "Option::default")]
+    truncated_before: Option<Clock<A>>,
+    time: Clock<A>,
+    latest_time_by_replica: VersionVector<A>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Snapshot<ID, TM, A> {
+    // creates a snapshot.  see TreeReplica::snapshot.
+    pub(crate) fn new(
+        id: u64,
+        tree: Tree<ID, TM>,
+        log: Vec<LogOpMove<ID, TM, A>>,
+        truncated_before: Option<Clock<A>>,
+        time: Clock<A>,
+        latest_time_by_replica: VersionVector<A>,
+    ) -> Self {
+        Self {
+            id,
+            tree,
+            log,
+            truncated_before,
+            time,
+            latest_time_by_replica,
+        }
+    }
+
+    /// returns checkpoint id
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// returns the tree
+    #[inline]
+    pub fn tree(&self) -> &Tree<ID, TM> {
+        &self.tree
+    }
+
+    /// returns the log entries held by the snapshot, newest first.
+    #[inline]
+    pub fn log(&self) -> &[LogOpMove<ID, TM, A>] {
+        &self.log
+    }
+
+    /// returns the timestamp the snapshot's log was truncated before, if
+    /// ever.  Ops older than this are taken as applied by a replica
+    /// restored from the snapshot.  See `State::truncated_before`.
+    #[inline]
+    pub fn truncated_before(&self) -> Option<&Clock<A>> {
+        self.truncated_before.as_ref()
+    }
+
+    /// returns the replica's lamport time when the snapshot was taken.
+    #[inline]
+    pub fn time(&self) -> &Clock<A> {
+        &self.time
+    }
+
+    /// returns the latest timestamp seen from each replica.
+    #[inline]
+    pub fn latest_time_by_replica(&self) -> &VersionVector<A> {
+        &self.latest_time_by_replica
+    }
+
+    /// returns the causally stable threshold when the snapshot was taken.
+    ///
+    /// Every op older than this is reflected in the tree, and no longer
+    /// in the log.
+    pub fn causally_stable_threshold(&self) -> Option<&Clock<A>> {
+        self.latest_time_by_replica.clocks().min()
+    }
+
+    // returns the snapshot's parts.  see TreeReplica::restore.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        Tree<ID, TM>,
+        Vec<LogOpMove<ID, TM, A>>,
+        Option<Clock<A>>,
+        Clock<A>,
+        VersionVector<A>,
+    ) {
+        (
+            self.tree,
+            self.log,
+            self.truncated_before,
+            self.time,
+            self.latest_time_by_replica,
+        )
+    }
+}
+
+/// `Segment` holds ops applied to a replica after a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment<ID: TreeId, TM: TreeMeta, A: Actor> {
+    checkpoint_id: u64,
+    seq: u64,
+    ops: Vec<OpMove<ID, TM, A>>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Segment<ID, TM, A> {
+    /// returns id of the checkpoint that this segment follows.
+    #[inline]
+    pub fn checkpoint_id(&self) -> u64 {
+        self.checkpoint_id
+    }
+
+    /// returns sequence number of this segment within its checkpoint.
+    #[inline]
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// returns the ops, in the order they were applied.
+    #[inline]
+    pub fn ops(&self) -> &[OpMove<ID, TM, A>] {
+        &self.ops
+    }
+
+    // returns the ops, consuming self.
+    pub(crate) fn into_ops(self) -> Vec<OpMove<ID, TM, A>> {
+        self.ops
+    }
+}
+
+/// `Checkpointer` decides when to take snapshots and numbers segments.
+///
+/// ```text
+/// let ops = ...;                       // local or remote ops
+/// replica.apply_ops_byref(&ops);
+/// persist(checkpointer.record(ops));
+/// if checkpointer.is_due() {
+///     persist(checkpointer.checkpoint(&replica));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Checkpointer {
+    interval: usize,
+    checkpoint_id: u64,
+    next_seq: u64,
+    ops_since: usize,
+}
+
+impl Checkpointer {
+    /// creates a `Checkpointer` that calls for a snapshot after every
+    /// interval ops.
+    ///
+    /// checkpoint_id is the id of the latest persisted snapshot, or 0
+    /// if there is none.
+    pub fn new(interval: usize, checkpoint_id: u64) -> Self {
+        Self {
+            interval,
+            checkpoint_id,
+            next_seq: 0,
+            ops_since: 0,
+        }
+    }
+
+    /// returns id of the latest checkpoint
+    #[inline]
+    pub fn checkpoint_id(&self) -> u64 {
+        self.checkpoint_id
+    }
+
+    /// returns a segment holding ops, which must already have been
+    /// applied to the replica, in the same order.
+    pub fn record<ID: TreeId, TM: TreeMeta, A: Actor>(
+        &mut self,
+        ops: Vec<OpMove<ID, TM, A>>,
+    ) -> Segment<ID, TM, A> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.ops_since += ops.len();
+        Segment {
+            checkpoint_id: self.checkpoint_id,
+            seq,
+            ops,
+        }
+    }
+
+    /// returns true if interval or more ops have been recorded since the
+    /// latest checkpoint.
+    #[inline]
+    pub fn is_due(&self) -> bool {
+        self.ops_since >= self.interval
+    }
+
+    /// returns a snapshot of replica, starting a new checkpoint.
+    ///
+    /// Once the snapshot is persisted, segments of earlier checkpoints
+    /// are no longer needed.
+    pub fn checkpoint<ID, TM, A>(&mut self, replica: &TreeReplica<ID, TM, A>) -> Snapshot<ID, TM, A>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor + std::fmt::Debug,
+    {
+        self.checkpoint_id += 1;
+        self.next_seq = 0;
+        self.ops_since = 0;
+        replica.snapshot(self.checkpoint_id)
+    }
+}
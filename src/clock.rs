@@ -4,7 +4,6 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
-use crdts::quickcheck::{Arbitrary, Gen};
 use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
 
@@ -97,24 +96,6 @@ impl<A: Actor> Hash for Clock<A> {
     }
 }
 
-// Generate arbitrary (random) clocks.  needed by quickcheck.
-impl<A: Actor + Arbitrary> Arbitrary for Clock<A> {
-    fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        Self {
-            actor_id: A::arbitrary(g),
-            counter: u64::arbitrary(g),
-        }
-    }
-
-    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
-        let mut shrunk_clocks = Vec::new();
-        if self.counter > 0 {
-            shrunk_clocks.push(Self::new(self.actor_id.clone(), Some(self.counter - 1)));
-        }
-        Box::new(shrunk_clocks.into_iter())
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,244 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! A compact, versioned binary wire format for replication.
+//!
+//! Each message is a 4 byte header followed by a bincode payload:
+//!
+//! ```text
+//! +-----+-----+---------+------+---------------------+
+//! | 'C' | 'T' | version | kind | payload (bincode)   |
+//! +-----+-----+---------+------+---------------------+
+//! ```
+//!
+//! The version is `FORMAT_VERSION` at the time of encoding.  A decoder
+//! rejects messages with a version it does not know, rather than
+//! misinterpreting them.  The kind identifies the encoded type, eg
+//! `OpMove` or `State`, so that a message cannot be decoded as the wrong
+//! type by accident.
+//!
+//! # Compatibility
+//!
+//! Encoders always write `FORMAT_VERSION`.  Decoders read versions from
+//! `MIN_FORMAT_VERSION` to `FORMAT_VERSION`.  When the format changes,
+//! `FORMAT_VERSION` is bumped and decoders keep reading the previous
+//! version, converting its messages to the current types via
+//! `Wire::decode_payload`, so that a fleet of replicas can be upgraded one
+//! at a time: a replica that has been upgraded still reads ops from one
+//! that has not.  The previous version is dropped when the format changes
+//! again, so upgraded replicas must not send to ones more than one version
+//! behind.
+//!
+//! Version 1 is the first released format, so there is no previous
+//! version yet.
+//!
+//! Requires the `codec` feature.
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    Barrier, CausalOpMove, LogOpMove, OpMove, Resolve, Snapshot, State, TieBreak, TreeId, TreeMeta,
+};
+use crdts::Actor;
+
+/// the current wire format version, written in every header.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// the oldest wire format version that can be decoded.
+pub const MIN_FORMAT_VERSION: u8 = 1;
+
+// identifies the start of a message.
+const MAGIC: [u8; 2] = *b"CT";
+
+// magic, version and kind.
+const HEADER_LEN: usize = 4;
+
+/// `Wire` is implemented by types that can be sent with `encode`.
+pub trait Wire: Serialize + DeserializeOwned {
+    /// identifies the type in message headers.  Unique per type.
+    const KIND: u8;
+
+    /// decodes a payload written with format version, from
+    /// `MIN_FORMAT_VERSION` to `FORMAT_VERSION`.
+    ///
+    /// The default decodes the current format, which suits types whose
+    /// format has not changed since `MIN_FORMAT_VERSION`.
+    fn decode_payload<B: Read>(_version: u8, reader: B) -> Result<Self, CodecError> {
+        deserialize_from(reader)
+    }
+}
+
+impl<ID, TM, A> Wire for OpMove<ID, TM, A>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 1;
+}
+
+impl<ID, TM, A> Wire for Vec<OpMove<ID, TM, A>>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 2;
+}
+
+impl<ID, TM, A> Wire for LogOpMove<ID, TM, A>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 3;
+}
+
+impl<ID, TM, A, T, R> Wire for State<ID, TM, A, T, R>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    const KIND: u8 = 4;
+}
+
+impl<ID, TM, A> Wire for Snapshot<ID, TM, A>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 5;
+}
+
+impl<ID, TM, A> Wire for CausalOpMove<ID, TM, A>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 6;
+}
+
+impl<A> Wire for Barrier<A>
+where
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 9;
+}
+
+/// Errors returned when encoding or decoding a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    /// the message ends before its header or payload is complete.
+    Truncated,
+    /// the message does not start with the expected magic bytes.
+    BadMagic,
+    /// the message was encoded with an unknown format version.
+    UnsupportedVersion(u8),
+    /// the message holds a different type than the one requested.
+    WrongKind {
+        /// kind of the requested type
+        expected: u8,
+        /// kind found in the header
+        found: u8,
+    },
+    /// the payload could not be serialized or deserialized.
+    Payload(String),
+    /// the message could not be compressed or decompressed.
+    Compression(String),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "message truncated"),
+            Self::BadMagic => write!(f, "bad magic bytes"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported format version: {}", v),
+            Self::WrongKind { expected, found } => {
+                write!(
+                    f,
+                    "wrong message kind: expected {}, found {}",
+                    expected, found
+                )
+            }
+            Self::Payload(e) => write!(f, "bad payload: {}", e),
+            Self::Compression(e) => write!(f, "compression error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// encodes value as a message, with a header.
+pub fn encode<T: Wire>(value: &T) -> Result<Vec<u8>, CodecError> {
+    let mut bytes = vec![];
+    encode_into(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+/// encodes value as a message, with a header, writing it to writer.
+pub fn encode_into<W: Write, T: Wire>(mut writer: W, value: &T) -> Result<(), CodecError> {
+    writer
+        .write_all(&[MAGIC[0], MAGIC[1], FORMAT_VERSION, T::KIND])
+        .map_err(|e| CodecError::Payload(e.to_string()))?;
+    bincode::serialize_into(writer, value).map_err(|e| CodecError::Payload(e.to_string()))
+}
+
+/// decodes a message, as returned by `encode`.
+pub fn decode<T: Wire>(bytes: &[u8]) -> Result<T, CodecError> {
+    decode_from(bytes)
+}
+
+/// decodes a message, as written by `encode_into`, from reader.
+pub fn decode_from<R: Read, T: Wire>(mut reader: R) -> Result<T, CodecError> {
+    let mut head = [0u8; HEADER_LEN];
+    if let Err(e) = reader.read_exact(&mut head) {
+        return Err(match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => CodecError::Truncated,
+            _ => CodecError::Payload(e.to_string()),
+        });
+    }
+    let (version, kind) = header(&head)?;
+    if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&version) {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+    if kind != T::KIND {
+        return Err(CodecError::WrongKind {
+            expected: T::KIND,
+            found: kind,
+        });
+    }
+    T::decode_payload(version, reader)
+}
+
+// deserializes a bincode payload.
+fn deserialize_from<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, CodecError> {
+    bincode::deserialize_from(reader).map_err(|e| match *e {
+        bincode::ErrorKind::Io(ref io) if io.kind() == std::io::ErrorKind::UnexpectedEof => {
+            CodecError::Truncated
+        }
+        _ => CodecError::Payload(e.to_string()),
+    })
+}
+
+/// returns the (version, kind) of a message, without decoding it.
+pub fn header(bytes: &[u8]) -> Result<(u8, u8), CodecError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(CodecError::Truncated);
+    }
+    if bytes[..2] != MAGIC {
+        return Err(CodecError::BadMagic);
+    }
+    Ok((bytes[2], bytes[3]))
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+// Collections used for a Tree's internal state.
+//
+// By default these are the std collections.  With the `persistent`
+// feature, they are the persistent collections of the `im` crate, which
+// share structure between clones, so that cloning a Tree is O(1) and a
+// clone is only copied piecemeal, as either copy is modified.
+//
+// Only the API common to both is used, plus the helpers below.
+
+#[cfg(not(feature = "persistent"))]
+pub(crate) use std::collections::{HashMap as Map, HashSet as Set};
+
+#[cfg(feature = "persistent")]
+pub(crate) use im::{HashMap as Map, HashSet as Set};
+
+#[cfg(not(feature = "persistent"))]
+pub(crate) type Seq<T> = Vec<T>;
+#[cfg(not(feature = "persistent"))]
+pub(crate) type SeqIter<'a, T> = std::slice::Iter<'a, T>;
+#[cfg(not(feature = "persistent"))]
+pub(crate) type SeqIntoIter<T> = std::vec::IntoIter<T>;
+
+#[cfg(feature = "persistent")]
+pub(crate) type Seq<T> = im::Vector<T>;
+#[cfg(feature = "persistent")]
+pub(crate) type SeqIter<'a, T> = im::vector::Iter<'a, T>;
+#[cfg(feature = "persistent")]
+pub(crate) type SeqIntoIter<T> = im::vector::ConsumingIter<T>;
+
+// appends value to the end of seq.
+#[cfg(not(feature = "persistent"))]
+#[inline]
+pub(crate) fn push<T>(seq: &mut Seq<T>, value: T) {
+    seq.push(value)
+}
+
+#[cfg(feature = "persistent")]
+#[inline]
+pub(crate) fn push<T: Clone>(seq: &mut Seq<T>, value: T) {
+    seq.push_back(value)
+}
+
+// removes and returns the last value in seq.
+#[cfg(not(feature = "persistent"))]
+#[inline]
+pub(crate) fn pop<T>(seq: &mut Seq<T>) -> Option<T> {
+    seq.pop()
+}
+
+#[cfg(feature = "persistent")]
+#[inline]
+pub(crate) fn pop<T: Clone>(seq: &mut Seq<T>) -> Option<T> {
+    seq.pop_back()
+}
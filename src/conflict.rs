@@ -0,0 +1,379 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::{Clock, LogOpMove, Resolve, State, TieBreak, TreeId, TreeMeta, TreeNode};
+use crdts::Actor;
+
+/// `Conflict` describes an op whose move did not last, as found by
+/// `State::conflicts_since`.  Applications can use it to tell a user eg
+/// "your move of X was overridden by Alice", rather than have the change
+/// vanish silently.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Conflict<ID, A: Actor> {
+    /// the op moved child_id, but a later op of another actor moved it
+    /// again.
+    Overridden {
+        /// the moved node.
+        child_id: ID,
+        /// timestamp of the op that was overridden.
+        op: Clock<A>,
+        /// timestamp of the op that overrode it.
+        by: Clock<A>,
+    },
+    /// the op was ignored, as it would have introduced a cycle.
+    Cycle {
+        /// the node the op would have moved.
+        child_id: ID,
+        /// timestamp of the ignored op.
+        op: Clock<A>,
+    },
+    /// the op was ignored, as child_id did not have the op's expected
+    /// parent.  See `OpMove::with_expected_parent`.
+    PreconditionFailed {
+        /// the node the op would have moved.
+        child_id: ID,
+        /// timestamp of the ignored op.
+        op: Clock<A>,
+    },
+}
+
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta + PartialEq,
+    A: Actor,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// returns the conflicts among ops with timestamps after since, found
+    /// by analyzing the log, ordered by the timestamp of the losing op.
+    ///
+    /// Lamport timestamps do not record whether an op's replica had seen
+    /// an earlier op, so an op is reported as `Conflict::Overridden` by any
+    /// later op of another actor on the same node, whether concurrent or
+    /// a deliberate move of the other actor's change.
+    ///
+    /// Ops truncated from the log are not analyzed.  Ops ignored for
+    /// exceeding the `Quotas`, or in strict mode, are reported as
+    /// `Conflict::Cycle`, as are, with a `Resolve` strategy other than the
+    /// default, ops that were declined, or moved elsewhere, or whose
+    /// metadata was merged.
+    pub fn conflicts_since(&self, since: &Clock<A>) -> Vec<Conflict<ID, A>> {
+        let newer = self
+            .log()
+            .iter()
+            .take_while(|e| T::cmp(e.timestamp(), since) == Ordering::Greater);
+
+        // walking from newest to oldest, the node each child had after the
+        // entry being visited, and the newest entry that moved it.
+        let mut after: HashMap<&ID, Option<&TreeNode<ID, TM>>> = HashMap::new();
+        let mut moved_by: HashMap<&ID, &LogOpMove<ID, TM, A>> = HashMap::new();
+        let mut conflicts = vec![];
+
+        for entry in newer {
+            let child_id = entry.child_id();
+            let node = *after
+                .entry(child_id)
+                .or_insert_with(|| self.tree().find(child_id));
+            // an entry took effect if the node had its parent and metadata
+            // after it.
+            let applied = matches!(node, Some(n)
+                if n.parent_id() == entry.parent_id() && n.metadata() == entry.metadata());
+
+            if applied {
+                if let Some(winner) = moved_by.get(child_id) {
+                    if winner.timestamp().actor_id() != entry.timestamp().actor_id() {
+                        conflicts.push(Conflict::Overridden {
+                            child_id: child_id.clone(),
+                            op: entry.timestamp().clone(),
+                            by: winner.timestamp().clone(),
+                        });
+                    }
+                }
+                moved_by.insert(child_id, entry);
+            } else {
+                let old_parent = entry.oldp().as_ref().map(|n| n.parent_id());
+                let conflict = match entry.expected_parent_id() {
+                    Some(expected) if old_parent != Some(expected) => {
+                        Conflict::PreconditionFailed {
+                            child_id: child_id.clone(),
+                            op: entry.timestamp().clone(),
+                        }
+                    }
+                    _ => Conflict::Cycle {
+                        child_id: child_id.clone(),
+                        op: entry.timestamp().clone(),
+                    },
+                };
+                conflicts.push(conflict);
+            }
+            after.insert(child_id, entry.oldp().as_ref());
+        }
+
+        conflicts.reverse();
+        conflicts
+    }
+}
+
+/// `ConflictKind` is the kind of a `ConflictEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictKind {
+    /// the op's move was replaced by a later op of another actor on the
+    /// same node.
+    Overridden,
+    /// the op was ignored, as it would have introduced a cycle.
+    Cycle,
+    /// the op was ignored, as the node did not have the op's expected
+    /// parent.  See `OpMove::with_expected_parent`.
+    PreconditionFailed,
+    /// the op was ignored, as it would have exceeded the state's
+    /// `Quotas`.
+    QuotaExceeded,
+    /// the op was ignored, as its parent was not in the tree, in strict
+    /// mode.  See `State::set_strict`.
+    ParentMissing,
+    /// the op was ignored, as the state's `Resolve` strategy declined to
+    /// replace the node's parent and metadata.
+    Declined,
+    /// the op, previously ignored, took effect when redone after an
+    /// earlier op was applied.
+    Reinstated,
+}
+
+/// `ConflictEvent` reports a conflict caused by applying an op, as it
+/// happened, whereas `Conflict` is found afterwards from the log.  See
+/// `State::apply_op_with_conflicts` and `TreeReplica::set_conflict_handler`.
+///
+/// Applying an op older than ops already applied undoes and redoes them,
+/// which may change their effect, eg an op that took effect may now be
+/// ignored.  Such events are reported for the redone op, by the applied
+/// one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConflictEvent<ID, A: Actor> {
+    kind: ConflictKind,
+    child_id: ID,
+    op: Clock<A>,
+    by: Option<Clock<A>>,
+}
+
+impl<ID, A: Actor> ConflictEvent<ID, A> {
+    /// returns the kind of conflict
+    #[inline]
+    pub fn kind(&self) -> ConflictKind {
+        self.kind
+    }
+
+    /// returns the node the op moved, or would have moved
+    #[inline]
+    pub fn child_id(&self) -> &ID {
+        &self.child_id
+    }
+
+    /// returns the timestamp of the losing op, or for
+    /// `ConflictKind::Reinstated` of the op now taking effect.
+    #[inline]
+    pub fn op(&self) -> &Clock<A> {
+        &self.op
+    }
+
+    /// returns the timestamp of the winning op, ie the op responsible for
+    /// the conflict, if known.  Not known if eg the op was truncated from
+    /// the log.
+    #[inline]
+    pub fn by(&self) -> Option<&Clock<A>> {
+        self.by.as_ref()
+    }
+}
+
+/// A callback for conflicts.  See `TreeReplica::set_conflict_handler`.
+pub type ConflictHandler<ID, A> = Box<dyn FnMut(ConflictEvent<ID, A>) + Send>;
+
+// OnConflict holds a replica's conflict handler, if any.
+//
+// It is a local setting of the replica, so a clone of the replica starts
+// without a handler, and replicas compare equal regardless.
+pub(crate) struct OnConflict<ID, A: Actor> {
+    pub(crate) handler: Option<ConflictHandler<ID, A>>,
+}
+
+impl<ID, A: Actor> Default for OnConflict<ID, A> {
+    fn default() -> Self {
+        Self { handler: None }
+    }
+}
+
+impl<ID, A: Actor> Clone for OnConflict<ID, A> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<ID, A: Actor> PartialEq for OnConflict<ID, A> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<ID, A: Actor> Eq for OnConflict<ID, A> {}
+
+impl<ID, A: Actor> fmt::Debug for OnConflict<ID, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnConflict")
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
+
+// conflict reporting for State::apply_op_with_conflicts.  log entries
+// passed in have just been done, so the tree reflects their effect.
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    // returns the node log placed in the tree, if it took effect.  This is
+    // log's own node, unless the strategy R placed another.
+    pub(crate) fn placed_node(&self, log: &LogOpMove<ID, TM, A>) -> Option<&TreeNode<ID, TM>> {
+        match log.placement() {
+            Some(p) if p.took_effect() => self.tree().find(log.child_id()),
+            _ => None,
+        }
+    }
+
+    // returns true if log took effect.
+    pub(crate) fn has_placed(&self, log: &LogOpMove<ID, TM, A>) -> bool {
+        self.placed_node(log).is_some()
+    }
+
+    // reports conflicts of log, just done at the head of the log.
+    pub(crate) fn report_do(
+        &self,
+        log: &LogOpMove<ID, TM, A>,
+        events: &mut Vec<ConflictEvent<ID, A>>,
+    ) {
+        if self.has_placed(log) {
+            let replaced = log.oldp().as_ref().and_then(|oldp| self.placed_by(oldp));
+            if let Some(replaced) = replaced {
+                if replaced.timestamp().actor_id() != log.timestamp().actor_id() {
+                    events.push(ConflictEvent {
+                        kind: ConflictKind::Overridden,
+                        child_id: log.child_id().clone(),
+                        op: replaced.timestamp().clone(),
+                        by: Some(log.timestamp().clone()),
+                    });
+                }
+            }
+        } else {
+            let kind = self.ignored_kind(log);
+            events.push(ConflictEvent {
+                kind,
+                child_id: log.child_id().clone(),
+                op: log.timestamp().clone(),
+                by: self.ignored_by(log, kind),
+            });
+        }
+    }
+
+    // reports conflicts of log, just redone after applying the op with
+    // timestamp applied, which placed node placed, if any.
+    pub(crate) fn report_redo(
+        &self,
+        log: &LogOpMove<ID, TM, A>,
+        was_applied: bool,
+        placed: Option<&TreeNode<ID, TM>>,
+        applied: Clock<A>,
+        events: &mut Vec<ConflictEvent<ID, A>>,
+    ) {
+        let is_applied = self.has_placed(log);
+        let kind = match (was_applied, is_applied) {
+            (true, false) => Some(self.ignored_kind(log)),
+            (false, true) => Some(ConflictKind::Reinstated),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            events.push(ConflictEvent {
+                kind,
+                child_id: log.child_id().clone(),
+                op: log.timestamp().clone(),
+                by: Some(applied.clone()),
+            });
+        }
+
+        // log now replaces the applied op's move.
+        let replaces_applied = matches!((log.oldp().as_ref(), placed),
+            (Some(oldp), Some(placed)) if TreeNode::ptr_eq(oldp, placed));
+        if is_applied && replaces_applied && log.timestamp().actor_id() != applied.actor_id() {
+            events.push(ConflictEvent {
+                kind: ConflictKind::Overridden,
+                child_id: log.child_id().clone(),
+                op: applied,
+                by: Some(log.timestamp().clone()),
+            });
+        }
+    }
+
+    // returns the newest log entry whose node is node, ie the op that
+    // placed it.
+    fn placed_by(&self, node: &TreeNode<ID, TM>) -> Option<&LogOpMove<ID, TM, A>> {
+        self.log().iter().find(|e| TreeNode::ptr_eq(e.node(), node))
+    }
+
+    // returns why log was ignored, as do_log_op checks.  A move elsewhere
+    // by the strategy R counts as a cycle.
+    fn ignored_kind(&self, log: &LogOpMove<ID, TM, A>) -> ConflictKind {
+        let old_parent = log.oldp().as_ref().map(|n| n.parent_id());
+        if log.child_id() == log.parent_id()
+            || self.tree().is_ancestor(log.parent_id(), log.child_id())
+        {
+            ConflictKind::Cycle
+        } else if matches!(log.expected_parent_id(), Some(e) if old_parent != Some(e)) {
+            ConflictKind::PreconditionFailed
+        } else if !self.admits_parent(log.parent_id()) {
+            ConflictKind::ParentMissing
+        } else if !self
+            .quotas()
+            .admits(self.tree(), log.child_id(), log.parent_id())
+        {
+            ConflictKind::QuotaExceeded
+        } else {
+            ConflictKind::Declined
+        }
+    }
+
+    // returns the timestamp of the op that caused log to be ignored: for
+    // a cycle the newest op that placed a node between log's parent and
+    // child, and otherwise the op that placed the child.
+    fn ignored_by(&self, log: &LogOpMove<ID, TM, A>, kind: ConflictKind) -> Option<Clock<A>> {
+        let tree = self.tree();
+        let culprit = match kind {
+            ConflictKind::Cycle => {
+                let mut path = HashSet::new();
+                let mut id = log.parent_id();
+                while id != log.child_id() {
+                    path.insert(id);
+                    match tree.find(id) {
+                        Some(node) => id = node.parent_id(),
+                        None => break,
+                    }
+                }
+                self.log().iter().find(|e| {
+                    path.contains(e.child_id())
+                        && matches!(tree.find(e.child_id()),
+                            Some(n) if TreeNode::ptr_eq(n, e.node()))
+                })
+            }
+            _ => tree.find(log.child_id()).and_then(|n| self.placed_by(n)),
+        };
+        culprit.map(|e| e.timestamp().clone())
+    }
+}
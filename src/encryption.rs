@@ -0,0 +1,606 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Encryption of metadata at rest.
+//!
+//! `MetaKey` seals the metadata of ops, states and snapshots, ie replaces
+//! it with `Sealed` ciphertext, before they are serialized to storage, and
+//! opens it again after they are loaded.  IDs, timestamps and the tree's
+//! structure stay in the clear, so a sync service can store and relay a
+//! tree without learning eg its file names.
+//!
+//! `EncryptedStorage` wraps a `Storage` backend, sealing everything it
+//! stores, so a `StoredReplica` works with plaintext metadata as usual:
+//!
+//! ```text
+//! let storage = EncryptedStorage::new(SledStorage::open(path)?, MetaKey::new(key));
+//! let replica = StoredReplica::open(actor, storage)?;
+//! ```
+//!
+//! `EncryptedOpMove` seals an op's metadata end to end, to the public keys
+//! of its recipients, so a relay can order and forward ops that it cannot
+//! read.  Recipients open it with their secret key as they apply it:
+//!
+//! ```text
+//! let op = EncryptedOpMove::seal(&r1.opmove(0, meta, 1), &[bob.public_key()])?;
+//! // ... relayed to bob ...
+//! r2.apply_encrypted_op(&op, &bob)?;
+//! ```
+//!
+//! Metadata is encrypted with ChaCha20-Poly1305, with a random nonce, and
+//! the node's ID as associated data, so sealed metadata cannot be moved
+//! to another node undetected.  An `EncryptedOpMove` is sealed with a
+//! random key, which is sealed to each recipient with X25519 and
+//! XChaCha20-Poly1305, ie a NaCl box.
+//!
+//! Requires the `encryption` feature.
+
+use std::fmt;
+use std::ops::Bound;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use crypto_box::ChaChaBox;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    Clock, LogOpMove, OpMove, Resolve, Snapshot, State, Storage, TieBreak, Tree, TreeId, TreeMeta,
+    TreeNode, TreeReplica,
+};
+use crdts::Actor;
+
+pub use crypto_box::{PublicKey, SecretKey};
+
+// length of the nonce that prefixes each ciphertext.
+const NONCE_LEN: usize = 12;
+
+// length of the nonce that prefixes each sealed key.
+const BOX_NONCE_LEN: usize = 24;
+
+/// An error returned when metadata cannot be sealed or opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// the metadata or ID could not be serialized or deserialized.
+    Payload(String),
+    /// the ciphertext was tampered with, or sealed with another key or
+    /// for another node.
+    Decrypt,
+    /// the op was not sealed to the key.
+    NotRecipient,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Payload(e) => write!(f, "bad metadata: {}", e),
+            Self::Decrypt => write!(f, "metadata could not be decrypted"),
+            Self::NotRecipient => write!(f, "op is not sealed to this key"),
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+/// `Sealed` is encrypted metadata, as sealed by `MetaKey`.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Sealed(Vec<u8>);
+
+impl Sealed {
+    /// returns the nonce and ciphertext.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Sealed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sealed({} bytes)", self.0.len())
+    }
+}
+
+/// `MetaKey` seals and opens metadata with a 256 bit key.  See the module
+/// docs.
+#[derive(Clone)]
+pub struct MetaKey {
+    cipher: ChaCha20Poly1305,
+}
+
+impl fmt::Debug for MetaKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetaKey(..)")
+    }
+}
+
+impl MetaKey {
+    /// creates a key.
+    pub fn new(key: [u8; 32]) -> Self {
+        Self::from_key(Key::from_slice(&key))
+    }
+
+    fn from_key(key: &Key) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(key),
+        }
+    }
+
+    /// encrypts the metadata of node child_id.
+    pub fn seal<ID, TM>(&self, child_id: &ID, metadata: &TM) -> Result<Sealed, EncryptionError>
+    where
+        ID: Serialize,
+        TM: Serialize,
+    {
+        let msg = bincode::serialize(metadata).map_err(payload)?;
+        let aad = bincode::serialize(child_id).map_err(payload)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &msg,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Decrypt)?;
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        Ok(Sealed(bytes))
+    }
+
+    /// decrypts the metadata of node child_id, as sealed by ::seal().
+    pub fn open<ID, TM>(&self, child_id: &ID, sealed: &Sealed) -> Result<TM, EncryptionError>
+    where
+        ID: Serialize,
+        TM: DeserializeOwned,
+    {
+        if sealed.0.len() < NONCE_LEN {
+            return Err(EncryptionError::Decrypt);
+        }
+        let (nonce, ciphertext) = sealed.0.split_at(NONCE_LEN);
+        let aad = bincode::serialize(child_id).map_err(payload)?;
+        let msg = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| EncryptionError::Decrypt)?;
+        bincode::deserialize(&msg).map_err(payload)
+    }
+
+    /// returns op with its metadata sealed.
+    pub fn seal_op<ID, TM, A>(
+        &self,
+        op: &OpMove<ID, TM, A>,
+    ) -> Result<OpMove<ID, Sealed, A>, EncryptionError>
+    where
+        ID: TreeId + Serialize,
+        TM: TreeMeta + Serialize,
+        A: Actor,
+    {
+        map_op(op, |id, m| self.seal(id, m))
+    }
+
+    /// returns op with its metadata opened.
+    pub fn open_op<ID, TM, A>(
+        &self,
+        op: &OpMove<ID, Sealed, A>,
+    ) -> Result<OpMove<ID, TM, A>, EncryptionError>
+    where
+        ID: TreeId + Serialize,
+        TM: TreeMeta + DeserializeOwned,
+        A: Actor,
+    {
+        map_op(op, |id, m| self.open(id, m))
+    }
+
+    /// returns a copy of state with all metadata sealed, in the tree and
+    /// the log.
+    pub fn seal_state<ID, TM, A, T, R>(
+        &self,
+        state: &State<ID, TM, A, T, R>,
+    ) -> Result<State<ID, Sealed, A, T>, EncryptionError>
+    where
+        ID: TreeId + Serialize,
+        TM: TreeMeta + Serialize,
+        A: Actor,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let tree = map_tree(state.tree(), |id, m| self.seal(id, m))?;
+        let log = map_log(state.log(), |id, m| self.seal(id, m))?;
+        let mut sealed = State::from((log, tree));
+        sealed.set_truncated_before(state.truncated_before().cloned());
+        sealed.set_compacted_from(state.compacted_from().cloned());
+        Ok(sealed)
+    }
+
+    /// returns a copy of state with all metadata opened.
+    ///
+    /// Settings that are not serialized, eg `Quotas`, must be set again.
+    pub fn open_state<ID, TM, A, T, R>(
+        &self,
+        state: &State<ID, Sealed, A, T>,
+    ) -> Result<State<ID, TM, A, T, R>, EncryptionError>
+    where
+        ID: TreeId + Serialize,
+        TM: TreeMeta + DeserializeOwned,
+        A: Actor,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let tree = map_tree(state.tree(), |id, m| self.open(id, m))?;
+        let log = map_log(state.log(), |id, m| self.open(id, m))?;
+        let mut opened = State::from((log, tree));
+        opened.set_truncated_before(state.truncated_before().cloned());
+        opened.set_compacted_from(state.compacted_from().cloned());
+        Ok(opened)
+    }
+
+    /// returns a copy of snapshot with all metadata sealed.
+    pub fn seal_snapshot<ID, TM, A>(
+        &self,
+        snapshot: &Snapshot<ID, TM, A>,
+    ) -> Result<Snapshot<ID, Sealed, A>, EncryptionError>
+    where
+        ID: TreeId + Serialize,
+        TM: TreeMeta + Serialize,
+        A: Actor,
+    {
+        map_snapshot(snapshot, |id, m| self.seal(id, m))
+    }
+
+    /// returns a copy of snapshot with all metadata opened.
+    pub fn open_snapshot<ID, TM, A>(
+        &self,
+        snapshot: &Snapshot<ID, Sealed, A>,
+    ) -> Result<Snapshot<ID, TM, A>, EncryptionError>
+    where
+        ID: TreeId + Serialize,
+        TM: TreeMeta + DeserializeOwned,
+        A: Actor,
+    {
+        map_snapshot(snapshot, |id, m| self.open(id, m))
+    }
+}
+
+fn payload(e: bincode::Error) -> EncryptionError {
+    EncryptionError::Payload(e.to_string())
+}
+
+// returns op with metadata mapped by f, which is given the child ID.
+fn map_op<ID, X, Y, A, F>(
+    op: &OpMove<ID, X, A>,
+    mut f: F,
+) -> Result<OpMove<ID, Y, A>, EncryptionError>
+where
+    ID: TreeId,
+    X: TreeMeta,
+    Y: TreeMeta,
+    A: Actor,
+    F: FnMut(&ID, &X) -> Result<Y, EncryptionError>,
+{
+    let mut mapped = OpMove::new(
+        op.timestamp().clone(),
+        op.parent_id().clone(),
+        f(op.child_id(), op.metadata())?,
+        op.child_id().clone(),
+    );
+    mapped.set_wall_time(op.wall_time());
+    mapped.set_expected_parent_id(op.expected_parent_id().cloned());
+    mapped.set_provenance(op.provenance().map(|p| p.to_vec()));
+    Ok(mapped)
+}
+
+fn map_log<ID, X, Y, A, F>(
+    log: &[LogOpMove<ID, X, A>],
+    mut f: F,
+) -> Result<Vec<LogOpMove<ID, Y, A>>, EncryptionError>
+where
+    ID: TreeId,
+    X: TreeMeta,
+    Y: TreeMeta,
+    A: Actor,
+    F: FnMut(&ID, &X) -> Result<Y, EncryptionError>,
+{
+    log.iter()
+        .map(|entry| {
+            let child_id = entry.child_id();
+            let oldp = match entry.oldp().as_ref() {
+                Some(n) => Some(TreeNode::new(
+                    n.parent_id().clone(),
+                    f(child_id, n.metadata())?,
+                )),
+                None => None,
+            };
+            let mut op = OpMove::new(
+                entry.timestamp().clone(),
+                entry.parent_id().clone(),
+                f(child_id, entry.metadata())?,
+                child_id.clone(),
+            );
+            op.set_wall_time(entry.wall_time());
+            op.set_expected_parent_id(entry.expected_parent_id().cloned());
+            op.set_provenance(entry.provenance().map(|p| p.to_vec()));
+            let mut mapped = LogOpMove::new(op, oldp);
+            mapped.set_placement(entry.placement());
+            Ok(mapped)
+        })
+        .collect()
+}
+
+fn map_tree<ID, X, Y, F>(tree: &Tree<ID, X>, mut f: F) -> Result<Tree<ID, Y>, EncryptionError>
+where
+    ID: TreeId,
+    X: TreeMeta,
+    Y: TreeMeta,
+    F: FnMut(&ID, &X) -> Result<Y, EncryptionError>,
+{
+    let mut mapped = Tree::new();
+    for (child_id, node) in tree.iter() {
+        let metadata = f(child_id, node.metadata())?;
+        mapped.add_node(
+            child_id.clone(),
+            TreeNode::new(node.parent_id().clone(), metadata),
+        );
+    }
+    Ok(mapped)
+}
+
+fn map_snapshot<ID, X, Y, A, F>(
+    snapshot: &Snapshot<ID, X, A>,
+    mut f: F,
+) -> Result<Snapshot<ID, Y, A>, EncryptionError>
+where
+    ID: TreeId,
+    X: TreeMeta,
+    Y: TreeMeta,
+    A: Actor,
+    F: FnMut(&ID, &X) -> Result<Y, EncryptionError>,
+{
+    Ok(Snapshot::new(
+        snapshot.id(),
+        map_tree(snapshot.tree(), &mut f)?,
+        map_log(snapshot.log(), &mut f)?,
+        snapshot.truncated_before().cloned(),
+        snapshot.time().clone(),
+        snapshot.latest_time_by_replica().clone(),
+    ))
+}
+
+/// An error returned by `EncryptedStorage`.
+#[derive(Debug)]
+pub enum EncryptedStorageError<E> {
+    /// the underlying storage failed.
+    Storage(E),
+    /// metadata could not be sealed or opened.
+    Encryption(EncryptionError),
+}
+
+impl<E: fmt::Display> fmt::Display for EncryptedStorageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Storage(e) => write!(f, "storage error: {}", e),
+            Self::Encryption(e) => write!(f, "encryption error: {}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for EncryptedStorageError<E> {}
+
+impl<E> From<EncryptionError> for EncryptedStorageError<E> {
+    fn from(e: EncryptionError) -> Self {
+        Self::Encryption(e)
+    }
+}
+
+/// `EncryptedStorage` is a `Storage` that seals metadata before passing
+/// ops and snapshots to the storage S, and opens it when loading them.
+/// See the module docs.
+#[derive(Debug)]
+pub struct EncryptedStorage<S> {
+    inner: S,
+    key: MetaKey,
+}
+
+impl<S> EncryptedStorage<S> {
+    /// wraps storage, sealing metadata with key.
+    pub fn new(inner: S, key: MetaKey) -> Self {
+        Self { inner, key }
+    }
+
+    /// returns the underlying storage.
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// returns the underlying storage, consuming self.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<ID, TM, A, S> Storage<ID, TM, A> for EncryptedStorage<S>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor,
+    S: Storage<ID, Sealed, A>,
+{
+    type Error = EncryptedStorageError<S::Error>;
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot<ID, TM, A>) -> Result<(), Self::Error> {
+        let sealed = self.key.seal_snapshot(snapshot)?;
+        self.inner
+            .put_snapshot(&sealed)
+            .map_err(EncryptedStorageError::Storage)
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot<ID, TM, A>>, Self::Error> {
+        match self
+            .inner
+            .latest_snapshot()
+            .map_err(EncryptedStorageError::Storage)?
+        {
+            Some(sealed) => Ok(Some(self.key.open_snapshot(&sealed)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn append_op(&mut self, op: &OpMove<ID, TM, A>) -> Result<(), Self::Error> {
+        let sealed = self.key.seal_op(op)?;
+        self.inner
+            .append_op(&sealed)
+            .map_err(EncryptedStorageError::Storage)
+    }
+
+    fn scan_ops(
+        &self,
+        from: Bound<&Clock<A>>,
+        to: Bound<&Clock<A>>,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, Self::Error> {
+        self.inner
+            .scan_ops(from, to)
+            .map_err(EncryptedStorageError::Storage)?
+            .iter()
+            .map(|op| Ok(self.key.open_op(op)?))
+            .collect()
+    }
+
+    fn remove_ops_before(&mut self, timestamp: &Clock<A>) -> Result<usize, Self::Error> {
+        self.inner
+            .remove_ops_before(timestamp)
+            .map_err(EncryptedStorageError::Storage)
+    }
+}
+
+// a content key sealed to one recipient.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct SealedKey {
+    recipient: [u8; 32],
+    // nonce + ciphertext
+    sealed: Vec<u8>,
+}
+
+/// `EncryptedOpMove` is an op whose metadata is sealed to the public keys
+/// of its recipients.  The timestamp, IDs and other fields stay in the
+/// clear, so that relays can order, deduplicate and forward it.  See the
+/// module docs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EncryptedOpMove<ID: TreeId, A: Actor> {
+    op: OpMove<ID, Sealed, A>,
+    ephemeral: [u8; 32],
+    keys: Vec<SealedKey>,
+}
+
+impl<ID: TreeId + Serialize, A: Actor> EncryptedOpMove<ID, A> {
+    /// seals op's metadata to recipients.
+    pub fn seal<TM>(
+        op: &OpMove<ID, TM, A>,
+        recipients: &[PublicKey],
+    ) -> Result<Self, EncryptionError>
+    where
+        TM: TreeMeta + Serialize,
+    {
+        let content_key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let sealed_op = MetaKey::from_key(&content_key).seal_op(op)?;
+        let ephemeral = SecretKey::generate(&mut OsRng);
+        let keys = recipients
+            .iter()
+            .map(|recipient| {
+                let nonce = ChaChaBox::generate_nonce(&mut OsRng);
+                let ciphertext = ChaChaBox::new(recipient, &ephemeral)
+                    .encrypt(&nonce, content_key.as_slice())
+                    .map_err(|_| EncryptionError::Decrypt)?;
+                let mut sealed = nonce.to_vec();
+                sealed.extend(ciphertext);
+                Ok(SealedKey {
+                    recipient: recipient.to_bytes(),
+                    sealed,
+                })
+            })
+            .collect::<Result<_, EncryptionError>>()?;
+        Ok(Self {
+            op: sealed_op,
+            ephemeral: ephemeral.public_key().to_bytes(),
+            keys,
+        })
+    }
+
+    /// returns the op with its metadata opened by key, a recipient's
+    /// secret key.
+    pub fn open<TM>(&self, key: &SecretKey) -> Result<OpMove<ID, TM, A>, EncryptionError>
+    where
+        TM: TreeMeta + DeserializeOwned,
+    {
+        let recipient = key.public_key().to_bytes();
+        let sealed = self
+            .keys
+            .iter()
+            .find(|k| k.recipient == recipient)
+            .map(|k| &k.sealed)
+            .ok_or(EncryptionError::NotRecipient)?;
+        if sealed.len() < BOX_NONCE_LEN {
+            return Err(EncryptionError::Decrypt);
+        }
+        let (nonce, ciphertext) = sealed.split_at(BOX_NONCE_LEN);
+        let content_key = ChaChaBox::new(&PublicKey::from(self.ephemeral), key)
+            .decrypt(crypto_box::Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| EncryptionError::Decrypt)?;
+        if content_key.len() != 32 {
+            return Err(EncryptionError::Decrypt);
+        }
+        MetaKey::from_key(Key::from_slice(&content_key)).open_op(&self.op)
+    }
+
+    /// returns the op with its metadata sealed.
+    #[inline]
+    pub fn sealed_op(&self) -> &OpMove<ID, Sealed, A> {
+        &self.op
+    }
+
+    /// returns the op's timestamp.
+    #[inline]
+    pub fn timestamp(&self) -> &Clock<A> {
+        self.op.timestamp()
+    }
+
+    /// returns the public keys the op is sealed to.
+    pub fn recipients(&self) -> Vec<PublicKey> {
+        self.keys
+            .iter()
+            .map(|k| PublicKey::from(k.recipient))
+            .collect()
+    }
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + DeserializeOwned,
+    A: Actor + fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// opens op with key, a recipient's secret key, and applies it.
+    ///
+    /// Returns an error, and applies nothing, if op cannot be opened.
+    pub fn apply_encrypted_op(
+        &mut self,
+        op: &EncryptedOpMove<ID, A>,
+        key: &SecretKey,
+    ) -> Result<(), EncryptionError> {
+        let op = op.open(key)?;
+        self.apply_op(op);
+        Ok(())
+    }
+}
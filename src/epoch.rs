@@ -0,0 +1,230 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Clock, LogOpMove, Resolve, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// `Epoch` is a numbered period of a group's history.  Ops older than an
+/// epoch's boundary belong to earlier epochs.  See `Epochs`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Epoch<A: Actor> {
+    number: u64,
+    boundary: Clock<A>,
+}
+
+impl<A: Actor> Epoch<A> {
+    /// returns the epoch number
+    #[inline]
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// returns the boundary, ie the clock of the replica that started the
+    /// epoch
+    #[inline]
+    pub fn boundary(&self) -> &Clock<A> {
+        &self.boundary
+    }
+}
+
+/// `EpochAck` is a member's acknowledgement of an epoch, announcing that
+/// it has sent every op it generated before the epoch's boundary, and
+/// will only generate newer ops.  See `Epochs::acknowledge`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EpochAck<A: Actor> {
+    actor: A,
+    epoch: Epoch<A>,
+}
+
+impl<A: Actor> EpochAck<A> {
+    /// returns the acknowledging member
+    #[inline]
+    pub fn actor(&self) -> &A {
+        &self.actor
+    }
+
+    /// returns the epoch acknowledged
+    #[inline]
+    pub fn epoch(&self) -> &Epoch<A> {
+        &self.epoch
+    }
+}
+
+/// `Epochs` coordinates log truncation among a fixed group of members, as
+/// an alternative to truncating to the causally stable threshold with
+/// `TreeReplica::truncate_log`.
+///
+/// A member starts an epoch, and each member acknowledges it once seen.
+/// When every member has acknowledged the current epoch, ops from earlier
+/// epochs can no longer arrive, so they are dropped from the log.  Unlike
+/// the causally stable threshold, this does not wait on the next op of
+/// each member, or track actors outside the group.
+///
+/// As for the causally stable threshold, each member's ops and acks must
+/// be delivered in the order it sent them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epochs<A: Actor> {
+    members: BTreeSet<A>,
+    current: Option<Epoch<A>>,
+    // members acknowledging the current epoch or newer ones.
+    acks: BTreeMap<Epoch<A>, BTreeSet<A>>,
+}
+
+impl<A: Actor> Epochs<A> {
+    /// creates a tracker for a group of members, which should include
+    /// the local replica.
+    pub fn new<I: IntoIterator<Item = A>>(members: I) -> Self {
+        Self {
+            members: members.into_iter().collect(),
+            current: None,
+            acks: BTreeMap::new(),
+        }
+    }
+
+    /// returns the members
+    #[inline]
+    pub fn members(&self) -> &BTreeSet<A> {
+        &self.members
+    }
+
+    /// returns the latest epoch acknowledged locally, if any
+    #[inline]
+    pub fn current(&self) -> Option<&Epoch<A>> {
+        self.current.as_ref()
+    }
+
+    /// returns true if every member has acknowledged the current epoch.
+    pub fn is_complete(&self) -> bool {
+        match &self.current {
+            Some(epoch) => self
+                .acks
+                .get(epoch)
+                .is_some_and(|acks| acks.is_superset(&self.members)),
+            None => false,
+        }
+    }
+
+    /// starts a new epoch, bounded by replica's clock, and returns the
+    /// local acknowledgement of it, for sending to the other members.
+    pub fn start<ID, TM, T, R>(&mut self, replica: &mut TreeReplica<ID, TM, A, T, R>) -> EpochAck<A>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: std::fmt::Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let number = self.current.as_ref().map_or(0, |e| e.number) + 1;
+        let boundary = Clock::new(replica.id().clone(), Some(replica.time().counter()));
+        let epoch = Epoch { number, boundary };
+        self.adopt(replica, epoch.clone());
+        self.ack_of(replica.id(), epoch)
+    }
+
+    /// acknowledges epoch, started by another member, and returns the
+    /// acknowledgement, for sending to the other members.  Ops the
+    /// replica generates from now on are newer than the epoch's boundary.
+    ///
+    /// Returns None if epoch is no newer than the current one.  Of
+    /// epochs started concurrently with the same number, the one with the
+    /// newest boundary prevails.
+    pub fn acknowledge<ID, TM, T, R>(
+        &mut self,
+        replica: &mut TreeReplica<ID, TM, A, T, R>,
+        epoch: &Epoch<A>,
+    ) -> Option<EpochAck<A>>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: std::fmt::Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        if matches!(&self.current, Some(current) if current >= epoch) {
+            return None;
+        }
+        self.adopt(replica, epoch.clone());
+        Some(self.ack_of(replica.id(), epoch.clone()))
+    }
+
+    /// records a member's acknowledgement.  Once every member has
+    /// acknowledged the current epoch, the replica's log entries older
+    /// than its boundary are dropped, and returned, newest first.
+    ///
+    /// Acknowledgements of an epoch newer than the current one are kept
+    /// until it is acknowledged locally.  Those of older epochs, or from
+    /// non-members, are ignored.
+    pub fn apply_ack<ID, TM, T, R>(
+        &mut self,
+        replica: &mut TreeReplica<ID, TM, A, T, R>,
+        ack: &EpochAck<A>,
+    ) -> Vec<LogOpMove<ID, TM, A>>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: std::fmt::Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        if !self.members.contains(&ack.actor)
+            || matches!(&self.current, Some(current) if current > &ack.epoch)
+        {
+            return vec![];
+        }
+        self.acks
+            .entry(ack.epoch.clone())
+            .or_default()
+            .insert(ack.actor.clone());
+        self.truncate(replica)
+    }
+
+    // makes epoch current, recording the local acknowledgement.
+    fn adopt<ID, TM, T, R>(&mut self, replica: &mut TreeReplica<ID, TM, A, T, R>, epoch: Epoch<A>)
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: std::fmt::Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        replica.merge_time(&epoch.boundary);
+        self.acks = self.acks.split_off(&epoch);
+        self.acks
+            .entry(epoch.clone())
+            .or_default()
+            .insert(replica.id().clone());
+        self.current = Some(epoch);
+        self.truncate(replica);
+    }
+
+    fn ack_of(&self, actor: &A, epoch: Epoch<A>) -> EpochAck<A> {
+        EpochAck {
+            actor: actor.clone(),
+            epoch,
+        }
+    }
+
+    // drops entries before the current epoch if it is complete.
+    fn truncate<ID, TM, T, R>(
+        &mut self,
+        replica: &mut TreeReplica<ID, TM, A, T, R>,
+    ) -> Vec<LogOpMove<ID, TM, A>>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: std::fmt::Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        match &self.current {
+            Some(epoch) if self.is_complete() => replica.truncate_log_to(epoch.boundary.clone()),
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,508 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! A filesystem facade over `TreeReplica`.
+//!
+//! `FileSystem` operates on string paths such as `/home/bob` and
+//! generates the underlying `OpMove` operations, as described in the
+//! paper:  a directory is created by moving a fresh ID under its parent,
+//! renamed or moved by moving it to a new parent and/or name, and
+//! deleted by moving it to a trash node.
+//!
+//! Each method applies its ops to the replica and returns them, so that
+//! the caller can send them to other replicas.
+
+use std::fmt;
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{OpMove, StringMeta, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// `FsMeta` is implemented by metadata types that hold a node's name.
+pub trait FsMeta: TreeMeta {
+    /// returns the node's name.
+    fn name(&self) -> &str;
+
+    /// returns metadata for a new node with the given name.
+    fn from_name(name: &str) -> Self;
+
+    /// returns a copy of this metadata with name replaced.
+    fn with_name(&self, name: &str) -> Self {
+        Self::from_name(name)
+    }
+}
+
+impl FsMeta for String {
+    fn name(&self) -> &str {
+        self
+    }
+
+    fn from_name(name: &str) -> Self {
+        name.to_string()
+    }
+}
+
+impl FsMeta for StringMeta {
+    fn name(&self) -> &str {
+        self.as_str()
+    }
+
+    fn from_name(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+/// Errors returned by `FileSystem` operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsError {
+    /// no node exists at the path.
+    NotFound(String),
+    /// a node already exists at the path.
+    AlreadyExists(String),
+    /// the path is empty, refers to the root, or contains an empty name.
+    InvalidPath(String),
+    /// a node cannot be moved inside itself.
+    MoveIntoSelf(String),
+    /// an error occurred reading or writing an on-disk directory.
+    Io(String),
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(p) => write!(f, "no such file or directory: {}", p),
+            Self::AlreadyExists(p) => write!(f, "file exists: {}", p),
+            Self::InvalidPath(p) => write!(f, "invalid path: {}", p),
+            Self::MoveIntoSelf(p) => write!(f, "cannot move inside itself: {}", p),
+            Self::Io(e) => write!(f, "i/o error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FsError {}
+
+/// `FileSystem` wraps a `TreeReplica` and provides path based operations.
+///
+/// Paths are `/` separated and relative to the root node, eg `/home/bob`.
+/// Neither the root nor trash need exist as nodes in the tree; they only
+/// have to be distinct IDs agreed upon by all replicas.
+///
+/// Concurrent ops may leave two siblings with the same name.  Lookups
+/// then return an arbitrary one of them.  See `UniqueNames` for a policy
+/// that resolves such conflicts.
+pub struct FileSystem<ID: TreeId, TM: FsMeta, A: Actor> {
+    replica: TreeReplica<ID, TM, A>,
+    root_id: ID,
+    trash_id: ID,
+    new_id: fn() -> ID,
+}
+
+// ops generated by a single call.
+type OpMoves<ID, TM, A> = Vec<OpMove<ID, TM, A>>;
+
+// name index extractor.
+fn node_name<TM: FsMeta>(meta: &TM) -> Option<String> {
+    Some(meta.name().to_string())
+}
+
+impl<ID, TM, A> FileSystem<ID, TM, A>
+where
+    ID: TreeId + Send + Sync + 'static,
+    TM: FsMeta + 'static,
+    A: Actor + Debug,
+{
+    /// creates a `FileSystem` over replica.
+    ///
+    /// new_id must return a globally unique ID each time it is called,
+    /// eg a random UUID.
+    pub fn new(
+        mut replica: TreeReplica<ID, TM, A>,
+        root_id: ID,
+        trash_id: ID,
+        new_id: fn() -> ID,
+    ) -> Self {
+        replica.tree_mut().index_names(node_name::<TM>);
+        Self {
+            replica,
+            root_id,
+            trash_id,
+            new_id,
+        }
+    }
+
+    /// returns the underlying replica
+    #[inline]
+    pub fn replica(&self) -> &TreeReplica<ID, TM, A> {
+        &self.replica
+    }
+
+    /// returns the underlying replica mutably, eg to apply remote ops.
+    #[inline]
+    pub fn replica_mut(&mut self) -> &mut TreeReplica<ID, TM, A> {
+        &mut self.replica
+    }
+
+    /// returns the underlying replica, consuming self.
+    pub fn into_replica(self) -> TreeReplica<ID, TM, A> {
+        self.replica
+    }
+
+    /// returns root ID
+    #[inline]
+    pub fn root_id(&self) -> &ID {
+        &self.root_id
+    }
+
+    /// returns trash ID
+    #[inline]
+    pub fn trash_id(&self) -> &ID {
+        &self.trash_id
+    }
+
+    /// returns ID of the node at path, or None if it does not exist.
+    ///
+    /// `/` (or an empty path) is the root.
+    pub fn lookup(&self, path: &str) -> Option<ID> {
+        let tree = self.replica.tree();
+        let mut id = self.root_id.clone();
+        for name in components(path) {
+            id = tree.child_by_name(&id, &name.to_string())?;
+        }
+        Some(id)
+    }
+
+    /// returns the sorted names of the children of the directory at path.
+    pub fn ls(&self, path: &str) -> Result<Vec<String>, FsError> {
+        let id = self.existing(path)?;
+        let tree = self.replica.tree();
+        let mut names: Vec<String> = tree
+            .children(&id)
+            .iter()
+            .filter_map(|c| tree.find(c))
+            .map(|n| n.metadata().name().to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// creates a directory at path.  Its parent must already exist.
+    pub fn mkdir(&mut self, path: &str) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        let (parent_path, name) = split(path)?;
+        let parent_id = self.existing(parent_path)?;
+        self.vacant(&parent_id, name, path)?;
+        let op = self
+            .replica
+            .opmove(parent_id, TM::from_name(name), (self.new_id)());
+        Ok(self.apply(vec![op]))
+    }
+
+    /// returns ID of the child of parent_id named name, if any.
+    pub fn child(&self, parent_id: &ID, name: &str) -> Option<ID> {
+        self.replica
+            .tree()
+            .child_by_name(parent_id, &name.to_string())
+    }
+
+    /// returns the (name, ID) of each child of parent_id, sorted by name.
+    pub fn entries(&self, parent_id: &ID) -> Vec<(String, ID)> {
+        let tree = self.replica.tree();
+        let mut entries: Vec<(String, ID)> = tree
+            .children(parent_id)
+            .into_iter()
+            .filter_map(|c| tree.find(&c).map(|n| (n.metadata().name().to_string(), c)))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// creates a directory named name under parent_id, returning its ID.
+    ///
+    /// Unlike `mkdir`, the parent is addressed by ID, as filesystem
+    /// adapters that track inodes do.
+    pub fn mkdir_at(
+        &mut self,
+        parent_id: &ID,
+        name: &str,
+    ) -> Result<(ID, OpMoves<ID, TM, A>), FsError> {
+        valid_name(name)?;
+        self.vacant(parent_id, name, name)?;
+        let id = (self.new_id)();
+        let op = self
+            .replica
+            .opmove(parent_id.clone(), TM::from_name(name), id.clone());
+        Ok((id, self.apply(vec![op])))
+    }
+
+    /// creates a directory at path, along with any missing parents.
+    ///
+    /// Succeeds without generating ops if the directory already exists.
+    pub fn mkdir_all(&mut self, path: &str) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        let tree = self.replica.tree();
+        let mut parent_id = self.root_id.clone();
+        let mut new_dirs = vec![];
+        let mut existing = true;
+        for name in components(path) {
+            let found = if existing {
+                tree.child_by_name(&parent_id, &name.to_string())
+            } else {
+                None
+            };
+            match found {
+                Some(id) => parent_id = id,
+                None => {
+                    existing = false;
+                    let id = (self.new_id)();
+                    new_dirs.push((parent_id, TM::from_name(name), id.clone()));
+                    parent_id = id;
+                }
+            }
+        }
+        let ops = self.replica.opmoves(new_dirs);
+        Ok(self.apply(ops))
+    }
+
+    /// ingests the directory hierarchy under src, on disk, into the
+    /// directory at path, which must exist.
+    ///
+    /// id_for is called with the path of each new directory, eg
+    /// `/import/a/b`, and returns its ID.  Deriving IDs from paths, eg by
+    /// hashing them, means that replicas importing the same hierarchy
+    /// generate the same IDs.  Existing directories are reused, so a
+    /// repeated import only generates ops for new ones.
+    ///
+    /// Only directories are imported, as `FsMeta` holds no file data.
+    /// Symbolic links are not followed.
+    pub fn import_dir<F>(
+        &mut self,
+        src: &Path,
+        path: &str,
+        mut id_for: F,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, FsError>
+    where
+        F: FnMut(&str) -> ID,
+    {
+        let root_id = self.existing(path)?;
+        let root_path = path.trim_end_matches('/').to_string();
+        let mut new_dirs = vec![];
+        let mut stack = vec![(src.to_path_buf(), root_id, root_path, true)];
+        while let Some((dir, parent_id, parent_path, existing)) = stack.pop() {
+            for (name, src_path) in subdirs(&dir)? {
+                let child_path = format!("{}/{}", parent_path, name);
+                let found = if existing {
+                    self.child(&parent_id, &name)
+                } else {
+                    None
+                };
+                let (id, existing) = match found {
+                    Some(id) => (id, true),
+                    None => {
+                        let id = id_for(&child_path);
+                        new_dirs.push((parent_id.clone(), TM::from_name(&name), id.clone()));
+                        (id, false)
+                    }
+                };
+                stack.push((src_path, id, child_path, existing));
+            }
+        }
+        let ops = self.replica.opmoves(new_dirs);
+        Ok(self.apply(ops))
+    }
+
+    /// creates a directory on disk for each descendant of the directory
+    /// at path, under dest, which must exist.
+    ///
+    /// Directories that already exist on disk are left as they are.  Names
+    /// that are not valid path components, eg `..`, are rejected.
+    pub fn export_dir(&self, path: &str, dest: &Path) -> Result<(), FsError> {
+        let id = self.existing(path)?;
+        let mut stack = vec![(id, dest.to_path_buf())];
+        while let Some((id, dir)) = stack.pop() {
+            for (name, child_id) in self.entries(&id) {
+                valid_name(&name)?;
+                let child_dir = dir.join(&name);
+                match std::fs::create_dir(&child_dir) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                    Err(e) => return Err(io_error(&child_dir, e)),
+                }
+                stack.push((child_id, child_dir));
+            }
+        }
+        Ok(())
+    }
+
+    /// renames the node at path, keeping it in the same directory.
+    pub fn rename(
+        &mut self,
+        path: &str,
+        new_name: &str,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        let (parent_path, _) = split(path)?;
+        if new_name.contains('/') {
+            return Err(FsError::InvalidPath(new_name.to_string()));
+        }
+        let to = format!("{}/{}", parent_path, new_name);
+        self.mv(path, &to)
+    }
+
+    /// moves the node at from (and its descendants) to the path to,
+    /// which must not exist.  The parent of to must exist.
+    pub fn mv(&mut self, from: &str, to: &str) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        split(from)?;
+        let (to_parent_path, to_name) = split(to)?;
+        let child_id = self.existing(from)?;
+        let parent_id = self.existing(to_parent_path)?;
+        self.relocate(&child_id, &parent_id, to_name, to)
+    }
+
+    /// removes the node at path, along with its descendants, by moving
+    /// it to the trash.
+    pub fn rm(&mut self, path: &str) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        split(path)?;
+        let child_id = self.existing(path)?;
+        self.discard(&child_id, path)
+    }
+
+    /// moves the child of parent_id named name (and its descendants)
+    /// under new_parent_id, naming it new_name.  new_parent_id must not
+    /// already have a child named new_name.
+    pub fn rename_at(
+        &mut self,
+        parent_id: &ID,
+        name: &str,
+        new_parent_id: &ID,
+        new_name: &str,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        valid_name(new_name)?;
+        let child_id = self
+            .child(parent_id, name)
+            .ok_or_else(|| FsError::NotFound(name.to_string()))?;
+        self.relocate(&child_id, new_parent_id, new_name, new_name)
+    }
+
+    /// removes the child of parent_id named name, along with its
+    /// descendants, by moving it to the trash.
+    pub fn remove_at(
+        &mut self,
+        parent_id: &ID,
+        name: &str,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        let child_id = self
+            .child(parent_id, name)
+            .ok_or_else(|| FsError::NotFound(name.to_string()))?;
+        self.discard(&child_id, name)
+    }
+
+    // moves child_id under parent_id as name.  path is reported in errors.
+    fn relocate(
+        &mut self,
+        child_id: &ID,
+        parent_id: &ID,
+        name: &str,
+        path: &str,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        if parent_id == child_id || self.replica.tree().is_ancestor(parent_id, child_id) {
+            return Err(FsError::MoveIntoSelf(path.to_string()));
+        }
+        self.vacant(parent_id, name, path)?;
+        let metadata = match self.replica.tree().find(child_id) {
+            Some(n) => n.metadata().with_name(name),
+            None => return Err(FsError::NotFound(path.to_string())),
+        };
+        let op = self
+            .replica
+            .opmove(parent_id.clone(), metadata, child_id.clone());
+        Ok(self.apply(vec![op]))
+    }
+
+    // moves child_id to the trash.  path is reported in errors.
+    fn discard(&mut self, child_id: &ID, path: &str) -> Result<Vec<OpMove<ID, TM, A>>, FsError> {
+        let metadata = match self.replica.tree().find(child_id) {
+            Some(n) => n.metadata().clone(),
+            None => return Err(FsError::NotFound(path.to_string())),
+        };
+        let op = self
+            .replica
+            .opmove(self.trash_id.clone(), metadata, child_id.clone());
+        Ok(self.apply(vec![op]))
+    }
+
+    // returns ID of the node at path, or NotFound.
+    fn existing(&self, path: &str) -> Result<ID, FsError> {
+        self.lookup(path)
+            .ok_or_else(|| FsError::NotFound(path.to_string()))
+    }
+
+    // returns AlreadyExists if parent_id has a child named name.
+    fn vacant(&self, parent_id: &ID, name: &str, path: &str) -> Result<(), FsError> {
+        match self
+            .replica
+            .tree()
+            .child_by_name(parent_id, &name.to_string())
+        {
+            Some(_) => Err(FsError::AlreadyExists(path.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    // applies ops to replica and returns them.
+    fn apply(&mut self, ops: Vec<OpMove<ID, TM, A>>) -> Vec<OpMove<ID, TM, A>> {
+        self.replica.apply_ops_byref(&ops);
+        ops
+    }
+}
+
+// returns non-empty components of path.
+pub(crate) fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|c| !c.is_empty())
+}
+
+// splits path into (parent path, name).  The root cannot be split.
+fn split(path: &str) -> Result<(&str, &str), FsError> {
+    let trimmed = path.trim_end_matches('/');
+    let (parent, name) = match trimmed.rfind('/') {
+        Some(idx) => (&trimmed[..idx], &trimmed[idx + 1..]),
+        None => ("", trimmed),
+    };
+    if valid_name(name).is_err() {
+        return Err(FsError::InvalidPath(path.to_string()));
+    }
+    Ok((parent, name))
+}
+
+// returns the sorted (name, path) of each subdirectory of dir.
+fn subdirs(dir: &Path) -> Result<Vec<(String, PathBuf)>, FsError> {
+    let mut subdirs = vec![];
+    for entry in std::fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let entry = entry.map_err(|e| io_error(dir, e))?;
+        let file_type = entry.file_type().map_err(|e| io_error(&entry.path(), e))?;
+        if !file_type.is_dir() {
+            continue;
+        }
+        match entry.file_name().into_string() {
+            Ok(name) => subdirs.push((name, entry.path())),
+            Err(name) => return Err(FsError::InvalidPath(name.to_string_lossy().into_owned())),
+        }
+    }
+    // sorted, so that ops are generated in the same order every time.
+    subdirs.sort();
+    Ok(subdirs)
+}
+
+// returns an Io error for path.
+fn io_error(path: &Path, e: io::Error) -> FsError {
+    FsError::Io(format!("{}: {}", path.display(), e))
+}
+
+// returns InvalidPath unless name is a single, non-special component.
+fn valid_name(name: &str) -> Result<(), FsError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(FsError::InvalidPath(name.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,289 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! A FUSE adapter that mounts a `FileSystem` as a userspace filesystem.
+//!
+//! Every tree node is presented as a directory.  Inodes are allocated
+//! lazily as nodes are looked up and mapped to their `TreeId`; the root
+//! node is always inode 1.  Directory entries are read from each node's
+//! metadata via `FsMeta`.
+//!
+//! Ops generated by filesystem calls are applied locally and passed to
+//! the `on_ops` callback, so that they can be sent to other replicas.
+//! Remote ops can be applied through `FuseAdapter::file_system_mut`.
+//!
+//! Requires the `fuse` feature.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use fuser::{
+    FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyDirectory, ReplyEmpty, ReplyEntry,
+    Request,
+};
+
+use super::fs::{FileSystem, FsError, FsMeta};
+use super::{OpMove, TreeId};
+use crdts::Actor;
+
+// inode of the root directory, as expected by the kernel.
+const ROOT_INO: u64 = 1;
+
+// how long the kernel may cache entries and attributes.  Remote ops can
+// change the tree at any time, so this is kept short.
+const TTL: Duration = Duration::from_secs(1);
+
+/// Callback invoked with the ops generated by each filesystem call.
+pub type OnOps<ID, TM, A> = Box<dyn FnMut(&[OpMove<ID, TM, A>]) + Send>;
+
+/// `FuseAdapter` implements `fuser::Filesystem` over a `FileSystem`.
+pub struct FuseAdapter<ID: TreeId, TM: FsMeta, A: Actor> {
+    fs: FileSystem<ID, TM, A>,
+    inodes: HashMap<u64, ID>,
+    ids: HashMap<ID, u64>,
+    next_ino: u64,
+    on_ops: Option<OnOps<ID, TM, A>>,
+    uid: u32,
+    gid: u32,
+}
+
+impl<ID, TM, A> FuseAdapter<ID, TM, A>
+where
+    ID: TreeId + Send + Sync + 'static,
+    TM: FsMeta + 'static,
+    A: Actor + Debug,
+{
+    /// creates a `FuseAdapter` over fs.
+    pub fn new(fs: FileSystem<ID, TM, A>) -> Self {
+        let mut adapter = Self {
+            fs,
+            inodes: HashMap::new(),
+            ids: HashMap::new(),
+            next_ino: ROOT_INO,
+            on_ops: None,
+            // SAFETY: getuid() and getgid() are always successful.
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+        };
+        let root_id = adapter.fs.root_id().clone();
+        adapter.ino(&root_id);
+        adapter
+    }
+
+    /// sets a callback that receives the ops generated by each call.
+    pub fn on_ops(mut self, f: OnOps<ID, TM, A>) -> Self {
+        self.on_ops = Some(f);
+        self
+    }
+
+    /// returns the underlying `FileSystem`
+    #[inline]
+    pub fn file_system(&self) -> &FileSystem<ID, TM, A> {
+        &self.fs
+    }
+
+    /// returns the underlying `FileSystem` mutably, eg to apply remote ops.
+    #[inline]
+    pub fn file_system_mut(&mut self) -> &mut FileSystem<ID, TM, A> {
+        &mut self.fs
+    }
+
+    /// mounts the filesystem at mountpoint, blocking until it is unmounted.
+    pub fn mount<P: AsRef<Path>>(self, mountpoint: P) -> std::io::Result<()> {
+        let options = [
+            MountOption::FSName("crdt_tree".to_string()),
+            MountOption::DefaultPermissions,
+        ];
+        fuser::mount2(self, mountpoint, &options)
+    }
+
+    // returns inode for id, allocating one if necessary.
+    fn ino(&mut self, id: &ID) -> u64 {
+        if let Some(ino) = self.ids.get(id) {
+            return *ino;
+        }
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.inodes.insert(ino, id.clone());
+        self.ids.insert(id.clone(), ino);
+        ino
+    }
+
+    // returns ID of a node in the tree for ino.
+    fn id(&self, ino: u64) -> Option<ID> {
+        let id = self.inodes.get(&ino)?;
+        if id == self.fs.root_id() || self.fs.replica().tree().find(id).is_some() {
+            Some(id.clone())
+        } else {
+            None
+        }
+    }
+
+    // returns attributes of the directory at ino.
+    fn attr(&self, ino: u64, id: &ID) -> FileAttr {
+        let now = SystemTime::now();
+        let nlink = 2 + self.fs.replica().tree().children(id).len() as u32;
+        FileAttr {
+            ino,
+            size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            crtime: now,
+            kind: FileType::Directory,
+            perm: 0o755,
+            nlink,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: 512,
+            flags: 0,
+        }
+    }
+
+    // passes ops to the on_ops callback.
+    fn emit(&mut self, ops: &[OpMove<ID, TM, A>]) {
+        if let Some(f) = self.on_ops.as_mut() {
+            f(ops);
+        }
+    }
+}
+
+// maps an FsError to an errno.
+fn errno(e: &FsError) -> i32 {
+    match e {
+        FsError::NotFound(_) => libc::ENOENT,
+        FsError::AlreadyExists(_) => libc::EEXIST,
+        FsError::InvalidPath(_) => libc::EINVAL,
+        FsError::MoveIntoSelf(_) => libc::EINVAL,
+        FsError::Io(_) => libc::EIO,
+    }
+}
+
+impl<ID, TM, A> Filesystem for FuseAdapter<ID, TM, A>
+where
+    ID: TreeId + Send + Sync + 'static,
+    TM: FsMeta + 'static,
+    A: Actor + Debug,
+{
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let (parent_id, name) = match (self.id(parent), name.to_str()) {
+            (Some(p), Some(n)) => (p, n),
+            _ => return reply.error(libc::ENOENT),
+        };
+        match self.fs.child(&parent_id, name) {
+            Some(id) => {
+                let ino = self.ino(&id);
+                reply.entry(&TTL, &self.attr(ino, &id), 0)
+            }
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.id(ino) {
+            Some(id) => reply.attr(&TTL, &self.attr(ino, &id)),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let id = match self.id(ino) {
+            Some(id) => id,
+            None => return reply.error(libc::ENOENT),
+        };
+        let mut entries = vec![(ino, ".".to_string()), (ino, "..".to_string())];
+        for (name, child_id) in self.fs.entries(&id) {
+            entries.push((self.ino(&child_id), name));
+        }
+        for (i, (child_ino, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            // the offset passed back to us is that of the next entry.
+            if reply.add(child_ino, (i + 1) as i64, FileType::Directory, name) {
+                break;
+            }
+        }
+        reply.ok()
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let (parent_id, name) = match (self.id(parent), name.to_str()) {
+            (Some(p), Some(n)) => (p, n),
+            _ => return reply.error(libc::ENOENT),
+        };
+        match self.fs.mkdir_at(&parent_id, name) {
+            Ok((id, ops)) => {
+                self.emit(&ops);
+                let ino = self.ino(&id);
+                reply.entry(&TTL, &self.attr(ino, &id), 0)
+            }
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let (parent_id, name) = match (self.id(parent), name.to_str()) {
+            (Some(p), Some(n)) => (p, n),
+            _ => return reply.error(libc::ENOENT),
+        };
+        match self.fs.child(&parent_id, name) {
+            Some(id) if !self.fs.replica().tree().children(&id).is_empty() => {
+                return reply.error(libc::ENOTEMPTY)
+            }
+            Some(_) => {}
+            None => return reply.error(libc::ENOENT),
+        }
+        match self.fs.remove_at(&parent_id, name) {
+            Ok(ops) => {
+                self.emit(&ops);
+                reply.ok()
+            }
+            Err(e) => reply.error(errno(&e)),
+        }
+    }
+
+    fn rename(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        _flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        let ids = (self.id(parent), self.id(newparent));
+        let names = (name.to_str(), newname.to_str());
+        match (ids, names) {
+            ((Some(p), Some(np)), (Some(n), Some(nn))) => match self.fs.rename_at(&p, n, &np, nn) {
+                Ok(ops) => {
+                    self.emit(&ops);
+                    reply.ok()
+                }
+                Err(e) => reply.error(errno(&e)),
+            },
+            _ => reply.error(libc::ENOENT),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! `arbitrary::Arbitrary` impls, so fuzz harnesses, eg cargo-fuzz, can
+//! generate structured ops from raw fuzzer input.  An op batch is a
+//! `Vec<OpMove>`, which is `Arbitrary` via these impls.
+//!
+//! ```text
+//! fuzz_target!(|ops: Vec<OpMove<u8, u8, u8>>| {
+//!     let mut state: State<u8, u8, u8> = State::new();
+//!     state.apply_ops_into(ops);
+//!     assert!(state.tree().check_invariants().is_ok());
+//! });
+//! ```
+//!
+//! Requires the `arbitrary` feature.
+
+use arbitrary::{size_hint, Arbitrary, Result, Unstructured};
+use crdts::Actor;
+
+use super::{Clock, OpMove, TreeId, TreeMeta};
+
+impl<'a, A: Actor + Arbitrary<'a>> Arbitrary<'a> for Clock<A> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self::new(A::arbitrary(u)?, Some(u64::arbitrary(u)?)))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and(A::size_hint(depth), u64::size_hint(depth))
+    }
+}
+
+impl<'a, ID, TM, A> Arbitrary<'a> for OpMove<ID, TM, A>
+where
+    ID: TreeId + Arbitrary<'a>,
+    TM: TreeMeta + Arbitrary<'a>,
+    A: Actor + Arbitrary<'a>,
+{
+    /// generates an op, with a wall-clock time, an expected parent and a
+    /// provenance if the input says so.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut op = Self::new(
+            Clock::arbitrary(u)?,
+            ID::arbitrary(u)?,
+            TM::arbitrary(u)?,
+            ID::arbitrary(u)?,
+        );
+        op.set_wall_time(Option::arbitrary(u)?);
+        op.set_expected_parent_id(Option::arbitrary(u)?);
+        op.set_provenance(Option::arbitrary(u)?);
+        Ok(op)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        size_hint::and_all(&[
+            Clock::<A>::size_hint(depth),
+            ID::size_hint(depth),
+            TM::size_hint(depth),
+            ID::size_hint(depth),
+            Option::<u64>::size_hint(depth),
+            Option::<ID>::size_hint(depth),
+            Option::<Vec<u8>>::size_hint(depth),
+        ])
+    }
+}
@@ -0,0 +1,398 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! gRPC service for replicating a tree between processes.
+//!
+//! `SyncServer` implements the `TreeSync` service in
+//! `proto/crdt_tree.proto` for a replica owned by a `ReplicaHandle`, and
+//! `SyncClient` calls it.  The messages are those in the `proto` module.
+//!
+//! ```text
+//! // server
+//! let handle = ReplicaHandle::spawn(TreeReplica::new(1));
+//! Server::builder()
+//!     .add_service(SyncServer::new(handle.clone()))
+//!     .serve(addr)
+//!     .await?;
+//!
+//! // client
+//! let handle = ReplicaHandle::spawn(TreeReplica::new(2));
+//! let mut client = SyncClient::connect("http://[::1]:50051").await?;
+//! client.sync(&handle).await?;
+//! ```
+//!
+//! Requires the `grpc` feature.
+
+use std::convert::{Infallible, TryFrom, TryInto};
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+
+use tonic::body::BoxBody;
+use tonic::codec::ProstCodec;
+use tonic::codegen::{empty_body, http, Body, BoxFuture, Context, Poll, Service, StdError};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Response, Status};
+
+use super::proto::{
+    ProtoBytes, ProtoError, ProtoOpMoveList, ProtoPullOpsReply, ProtoPushOpsReply,
+    ProtoSnapshotRequest, ProtoState, ProtoVersionVector,
+};
+use super::{HandleError, OpMove, ReplicaHandle, State, TreeId, TreeMeta, VersionVector};
+use crdts::Actor;
+
+// the service's name, and the paths of its methods.
+const SERVICE: &str = "crdt_tree.TreeSync";
+const PUSH_OPS: &str = "/crdt_tree.TreeSync/PushOps";
+const PULL_OPS_SINCE: &str = "/crdt_tree.TreeSync/PullOpsSince";
+const GET_SNAPSHOT: &str = "/crdt_tree.TreeSync/GetSnapshot";
+const EXCHANGE_VERSION_VECTORS: &str = "/crdt_tree.TreeSync/ExchangeVersionVectors";
+
+/// Errors returned by `SyncClient`.
+#[derive(Debug)]
+pub enum SyncError {
+    /// the connection to the server failed.
+    Transport(tonic::transport::Error),
+    /// the server returned an error.
+    Status(Status),
+    /// a message from the server could not be converted.
+    Proto(ProtoError),
+    /// the local replica's task has stopped.
+    Handle(HandleError),
+    /// ops the local replica is missing were truncated from the server's
+    /// log.  The replica must be brought up to date from a snapshot.
+    SnapshotRequired,
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "sync transport error: {}", e),
+            Self::Status(s) => write!(f, "sync server error: {}", s),
+            Self::Proto(e) => write!(f, "sync message error: {}", e),
+            Self::Handle(e) => write!(f, "sync replica error: {}", e),
+            Self::SnapshotRequired => write!(f, "sync requires a snapshot"),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+impl From<tonic::transport::Error> for SyncError {
+    fn from(e: tonic::transport::Error) -> Self {
+        Self::Transport(e)
+    }
+}
+
+impl From<Status> for SyncError {
+    fn from(s: Status) -> Self {
+        Self::Status(s)
+    }
+}
+
+impl From<ProtoError> for SyncError {
+    fn from(e: ProtoError) -> Self {
+        Self::Proto(e)
+    }
+}
+
+impl From<HandleError> for SyncError {
+    fn from(e: HandleError) -> Self {
+        Self::Handle(e)
+    }
+}
+
+// returns ops decoded from protobuf messages.
+fn decode_ops<ID, TM, A>(ops: ProtoOpMoveList) -> Result<Vec<OpMove<ID, TM, A>>, ProtoError>
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    ops.ops.into_iter().map(OpMove::try_from).collect()
+}
+
+/// `SyncServer` serves the `TreeSync` gRPC service for the replica owned
+/// by a `ReplicaHandle`.  Add it to a `tonic::transport::Server`.
+pub struct SyncServer<ID: TreeId, TM: TreeMeta, A: Actor> {
+    handle: ReplicaHandle<ID, TM, A>,
+}
+
+impl<ID, TM, A> SyncServer<ID, TM, A>
+where
+    ID: TreeId + ProtoBytes + Send + Sync + 'static,
+    TM: TreeMeta + ProtoBytes + Send + Sync + 'static,
+    A: Actor + ProtoBytes + fmt::Debug + Send + Sync + 'static,
+{
+    /// creates a server for the replica owned by handle.
+    pub fn new(handle: ReplicaHandle<ID, TM, A>) -> Self {
+        Self { handle }
+    }
+
+    async fn push_ops(
+        handle: ReplicaHandle<ID, TM, A>,
+        ops: ProtoOpMoveList,
+    ) -> Result<ProtoPushOpsReply, Status> {
+        let ops = decode_ops(ops).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        handle.apply_ops(ops).await.map_err(unavailable)?;
+        Ok(ProtoPushOpsReply {})
+    }
+
+    async fn pull_ops_since(
+        handle: ReplicaHandle<ID, TM, A>,
+        seen: ProtoVersionVector,
+    ) -> Result<ProtoPullOpsReply, Status> {
+        let seen =
+            VersionVector::try_from(seen).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let missing = handle
+            .query(move |r| {
+                r.missing_ops(&seen)
+                    .map(|ops| ops.iter().map(|op| op.into()).collect())
+            })
+            .await
+            .map_err(unavailable)?;
+        Ok(ProtoPullOpsReply {
+            snapshot_required: missing.is_none(),
+            ops: missing.unwrap_or_default(),
+        })
+    }
+
+    async fn get_snapshot(
+        handle: ReplicaHandle<ID, TM, A>,
+        _request: ProtoSnapshotRequest,
+    ) -> Result<ProtoState, Status> {
+        handle
+            .query(|r| r.state().into())
+            .await
+            .map_err(unavailable)
+    }
+
+    async fn exchange_version_vectors(
+        handle: ReplicaHandle<ID, TM, A>,
+        _theirs: ProtoVersionVector,
+    ) -> Result<ProtoVersionVector, Status> {
+        handle
+            .query(|r| r.version_vector().into())
+            .await
+            .map_err(unavailable)
+    }
+}
+
+// returns the status for a request to a stopped replica.
+fn unavailable(e: HandleError) -> Status {
+    Status::unavailable(e.to_string())
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Clone for SyncServer<ID, TM, A> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<ID, TM, A, B> Service<http::Request<B>> for SyncServer<ID, TM, A>
+where
+    ID: TreeId + ProtoBytes + Send + Sync + 'static,
+    TM: TreeMeta + ProtoBytes + Send + Sync + 'static,
+    A: Actor + ProtoBytes + fmt::Debug + Send + Sync + 'static,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let handle = self.handle.clone();
+        match req.uri().path() {
+            PUSH_OPS => Box::pin(unary(req, |m| Self::push_ops(handle, m))),
+            PULL_OPS_SINCE => Box::pin(unary(req, |m| Self::pull_ops_since(handle, m))),
+            GET_SNAPSHOT => Box::pin(unary(req, |m| Self::get_snapshot(handle, m))),
+            EXCHANGE_VERSION_VECTORS => {
+                Box::pin(unary(req, |m| Self::exchange_version_vectors(handle, m)))
+            }
+            _ => Box::pin(async {
+                // grpc-status 12 is UNIMPLEMENTED.
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", "12")
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap())
+            }),
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> NamedService for SyncServer<ID, TM, A> {
+    const NAME: &'static str = SERVICE;
+}
+
+// serves a unary request with f.
+async fn unary<B, Req, Resp, F, Fut>(
+    req: http::Request<B>,
+    f: F,
+) -> Result<http::Response<BoxBody>, Infallible>
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+    Req: prost::Message + Default + Send + 'static,
+    Resp: prost::Message + Send + 'static,
+    F: FnOnce(Req) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Resp, Status>> + Send + 'static,
+{
+    let mut grpc = Grpc::new(ProstCodec::<Resp, Req>::default());
+    Ok(grpc.unary(Unary(Some(f)), req).await)
+}
+
+// adapts a function to a tonic UnaryService, for a single request.
+struct Unary<F>(Option<F>);
+
+impl<Req, Resp, F, Fut> UnaryService<Req> for Unary<F>
+where
+    Req: Send + 'static,
+    F: FnOnce(Req) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Resp, Status>> + Send + 'static,
+{
+    type Response = Resp;
+    type Future = BoxFuture<Response<Resp>, Status>;
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let f = self.0.take();
+        Box::pin(async move {
+            match f {
+                Some(f) => f(request.into_inner()).await.map(Response::new),
+                None => Err(Status::internal("request already served")),
+            }
+        })
+    }
+}
+
+/// `SyncClient` calls the `TreeSync` gRPC service of a remote replica.
+#[derive(Debug, Clone)]
+pub struct SyncClient<ID: TreeId, TM: TreeMeta, A: Actor> {
+    inner: tonic::client::Grpc<Channel>,
+    ops: PhantomData<OpMove<ID, TM, A>>,
+}
+
+impl<ID, TM, A> SyncClient<ID, TM, A>
+where
+    ID: TreeId + ProtoBytes + Send + Sync + 'static,
+    TM: TreeMeta + ProtoBytes + Send + Sync + 'static,
+    A: Actor + ProtoBytes + fmt::Debug + Send + Sync + 'static,
+{
+    /// connects to the server at dst, eg "http://[::1]:50051".
+    pub async fn connect<D>(dst: D) -> Result<Self, SyncError>
+    where
+        D: TryInto<Endpoint>,
+        D::Error: Into<StdError>,
+    {
+        let channel = Endpoint::new(dst)?.connect().await?;
+        Ok(Self::new(channel))
+    }
+
+    /// creates a client using channel.
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            inner: tonic::client::Grpc::new(channel),
+            ops: PhantomData,
+        }
+    }
+
+    /// sends ops to the server, which applies them.
+    pub async fn push_ops(&mut self, ops: &[OpMove<ID, TM, A>]) -> Result<(), SyncError> {
+        let ops = ProtoOpMoveList {
+            ops: ops.iter().map(|op| op.into()).collect(),
+        };
+        let _: ProtoPushOpsReply = self.call(PUSH_OPS, ops).await?;
+        Ok(())
+    }
+
+    /// returns the server's ops not included in seen, oldest first.
+    ///
+    /// Returns `SyncError::SnapshotRequired` if some were truncated from
+    /// the server's log.
+    pub async fn pull_ops_since(
+        &mut self,
+        seen: &VersionVector<A>,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, SyncError> {
+        let reply: ProtoPullOpsReply = self
+            .call(PULL_OPS_SINCE, ProtoVersionVector::from(seen))
+            .await?;
+        if reply.snapshot_required {
+            return Err(SyncError::SnapshotRequired);
+        }
+        Ok(decode_ops(ProtoOpMoveList { ops: reply.ops })?)
+    }
+
+    /// returns the server's state.
+    pub async fn get_snapshot(&mut self) -> Result<State<ID, TM, A>, SyncError> {
+        let state: ProtoState = self.call(GET_SNAPSHOT, ProtoSnapshotRequest {}).await?;
+        Ok(State::try_from(state)?)
+    }
+
+    /// sends ours, and returns the server's version vector.
+    pub async fn exchange_version_vectors(
+        &mut self,
+        ours: &VersionVector<A>,
+    ) -> Result<VersionVector<A>, SyncError> {
+        let theirs: ProtoVersionVector = self
+            .call(EXCHANGE_VERSION_VECTORS, ProtoVersionVector::from(ours))
+            .await?;
+        Ok(VersionVector::try_from(theirs)?)
+    }
+
+    /// brings the local replica and the server up to date with each
+    /// other, pulling the ops the replica is missing and pushing the ops
+    /// the server is missing.
+    ///
+    /// Returns the number of ops (pulled, pushed).  If the replica's log
+    /// has been truncated past ops the server is missing, those are not
+    /// pushed.
+    pub async fn sync(
+        &mut self,
+        handle: &ReplicaHandle<ID, TM, A>,
+    ) -> Result<(usize, usize), SyncError> {
+        let ours = handle.query(|r| r.version_vector().clone()).await?;
+        let theirs = self.exchange_version_vectors(&ours).await?;
+
+        let pulled = self.pull_ops_since(&ours).await?;
+        let num_pulled = pulled.len();
+        handle.apply_ops(pulled).await?;
+
+        let pushed = handle
+            .query(move |r| r.missing_ops(&theirs))
+            .await?
+            .unwrap_or_default();
+        if !pushed.is_empty() {
+            self.push_ops(&pushed).await?;
+        }
+        Ok((num_pulled, pushed.len()))
+    }
+
+    // calls method path with req.
+    async fn call<Req, Resp>(&mut self, path: &'static str, req: Req) -> Result<Resp, Status>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        self.inner
+            .ready()
+            .await
+            .map_err(|e| Status::unknown(format!("service was not ready: {}", e)))?;
+        let path = http::uri::PathAndQuery::from_static(path);
+        self.inner
+            .unary(Request::new(req), path, ProstCodec::default())
+            .await
+            .map(Response::into_inner)
+    }
+}
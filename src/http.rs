@@ -0,0 +1,130 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! HTTP endpoints, with JSON bodies, for replicating a tree.
+//!
+//! `router` serves the replica owned by a `ReplicaHandle`:
+//!
+//! ```text
+//! POST /ops               applies the ops in the body, a JSON array.
+//! GET  /ops?since=<vv>    returns the ops not included in the version
+//!                         vector vv, as a JSON array, oldest first.
+//!                         Without since, returns all ops in the log.
+//! GET  /snapshot          returns the replica's state.
+//! ```
+//!
+//! vv is a JSON version vector, ie an object mapping each actor to its
+//! latest clock, url-encoded.  If ops the caller is missing were truncated
+//! from the log, GET /ops fails with 409 Conflict, and the caller must
+//! fetch a snapshot instead.
+//!
+//! The endpoints suit peers that cannot speak gRPC, eg in a browser, and
+//! debugging with curl:
+//!
+//! ```text
+//! let handle = ReplicaHandle::spawn(TreeReplica::new(1));
+//! axum::Server::bind(&addr)
+//!     .serve(router(handle).into_make_service())
+//!     .await?;
+//!
+//! $ curl http://localhost:8080/ops
+//! ```
+//!
+//! Requires the `http` feature.
+
+use std::fmt;
+
+use axum::extract::{Json, Query, State as Extract};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{HandleError, OpMove, ReplicaHandle, State, TreeId, TreeMeta, VersionVector};
+use crdts::Actor;
+
+// an error response: a status code and a plain text message.
+type ErrorResponse = (StatusCode, String);
+
+// query parameters of GET /ops.
+#[derive(Deserialize)]
+struct OpsQuery {
+    since: Option<String>,
+}
+
+/// returns a router serving the endpoints in the module docs for the
+/// replica owned by handle.
+pub fn router<ID, TM, A>(handle: ReplicaHandle<ID, TM, A>) -> Router
+where
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync + 'static,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync + 'static,
+    A: Actor + Serialize + DeserializeOwned + fmt::Debug + Send + Sync + 'static,
+{
+    Router::new()
+        .route(
+            "/ops",
+            get(pull_ops::<ID, TM, A>).post(push_ops::<ID, TM, A>),
+        )
+        .route("/snapshot", get(snapshot::<ID, TM, A>))
+        .with_state(handle)
+}
+
+async fn push_ops<ID, TM, A>(
+    Extract(handle): Extract<ReplicaHandle<ID, TM, A>>,
+    Json(ops): Json<Vec<OpMove<ID, TM, A>>>,
+) -> Result<StatusCode, ErrorResponse>
+where
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync + 'static,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync + 'static,
+    A: Actor + Serialize + DeserializeOwned + fmt::Debug + Send + Sync + 'static,
+{
+    handle.apply_ops(ops).await.map_err(unavailable)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pull_ops<ID, TM, A>(
+    Extract(handle): Extract<ReplicaHandle<ID, TM, A>>,
+    Query(query): Query<OpsQuery>,
+) -> Result<Json<Vec<OpMove<ID, TM, A>>>, ErrorResponse>
+where
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync + 'static,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync + 'static,
+    A: Actor + Serialize + DeserializeOwned + fmt::Debug + Send + Sync + 'static,
+{
+    let seen: VersionVector<A> = match query.since {
+        Some(since) => serde_json::from_str(&since)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("bad since: {}", e)))?,
+        None => VersionVector::new(),
+    };
+    let missing = handle
+        .query(move |r| r.missing_ops(&seen))
+        .await
+        .map_err(unavailable)?;
+    match missing {
+        Some(ops) => Ok(Json(ops)),
+        None => Err((StatusCode::CONFLICT, "snapshot required".to_string())),
+    }
+}
+
+async fn snapshot<ID, TM, A>(
+    Extract(handle): Extract<ReplicaHandle<ID, TM, A>>,
+) -> Result<Json<State<ID, TM, A>>, ErrorResponse>
+where
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync + 'static,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync + 'static,
+    A: Actor + Serialize + DeserializeOwned + fmt::Debug + Send + Sync + 'static,
+{
+    let state = handle
+        .query(|r| r.state().clone())
+        .await
+        .map_err(unavailable)?;
+    Ok(Json(state))
+}
+
+// returns the response for a request to a stopped replica.
+fn unavailable(e: HandleError) -> ErrorResponse {
+    (StatusCode::SERVICE_UNAVAILABLE, e.to_string())
+}
@@ -0,0 +1,146 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::convert::TryFrom;
+#[cfg(not(feature = "persistent"))]
+use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+#[cfg(not(feature = "persistent"))]
+use hashbrown::HashTable;
+
+#[cfg(feature = "persistent")]
+use super::collections::Map;
+use super::collections::{self, Seq};
+use super::TreeId;
+
+// a small, copyable stand-in for an interned ID.
+pub(crate) type Handle = u32;
+
+// Interner maps each ID to a Handle and back.
+//
+// Each ID is stored once, in ids, and the table holds only handles,
+// hashed by the ID they stand for.  Released handles are reused.
+//
+// With the `persistent` feature, the table is a persistent map from ID to
+// handle instead, so that it can be shared between clones.  Each ID is
+// then stored twice.
+#[derive(Clone)]
+pub(crate) struct Interner<ID: TreeId> {
+    ids: Seq<Option<ID>>, // handle => ID, or None if released.
+    #[cfg(not(feature = "persistent"))]
+    table: HashTable<Handle>,
+    #[cfg(not(feature = "persistent"))]
+    hasher: RandomState,
+    #[cfg(feature = "persistent")]
+    table: Map<ID, Handle>,
+    free: Seq<Handle>,
+}
+
+impl<ID: TreeId> Default for Interner<ID> {
+    fn default() -> Self {
+        Self {
+            ids: Seq::new(),
+            #[cfg(not(feature = "persistent"))]
+            table: HashTable::new(),
+            #[cfg(not(feature = "persistent"))]
+            hasher: RandomState::new(),
+            #[cfg(feature = "persistent")]
+            table: Map::new(),
+            free: Seq::new(),
+        }
+    }
+}
+
+impl<ID: TreeId> Interner<ID> {
+    // returns the handle for id, if interned.
+    #[cfg(not(feature = "persistent"))]
+    pub(crate) fn get(&self, id: &ID) -> Option<Handle> {
+        let ids = &self.ids;
+        self.table
+            .find(self.hasher.hash_one(id), |&h| {
+                ids[h as usize].as_ref() == Some(id)
+            })
+            .copied()
+    }
+
+    // returns the handle for id, if interned.
+    #[cfg(feature = "persistent")]
+    pub(crate) fn get(&self, id: &ID) -> Option<Handle> {
+        self.table.get(id).copied()
+    }
+
+    // returns the handle for id, interning it if need be.
+    pub(crate) fn intern(&mut self, id: &ID) -> Handle {
+        if let Some(h) = self.get(id) {
+            return h;
+        }
+        let h = match collections::pop(&mut self.free) {
+            Some(h) => {
+                self.ids[h as usize] = Some(id.clone());
+                h
+            }
+            None => {
+                let h = Handle::try_from(self.ids.len()).expect("too many ids to intern");
+                collections::push(&mut self.ids, Some(id.clone()));
+                h
+            }
+        };
+        self.insert_handle(id, h);
+        h
+    }
+
+    // adds h to the table, as the handle for id.
+    #[cfg(not(feature = "persistent"))]
+    fn insert_handle(&mut self, id: &ID, h: Handle) {
+        let (ids, hasher) = (&self.ids, &self.hasher);
+        self.table.insert_unique(hasher.hash_one(id), h, |&h| {
+            hasher.hash_one(ids[h as usize].as_ref().expect("interned"))
+        });
+    }
+
+    #[cfg(feature = "persistent")]
+    fn insert_handle(&mut self, id: &ID, h: Handle) {
+        self.table.insert(id.clone(), h);
+    }
+
+    // returns the ID for handle h, which must be interned.
+    #[inline]
+    pub(crate) fn id(&self, h: Handle) -> &ID {
+        self.ids[h as usize].as_ref().expect("handle is interned")
+    }
+
+    // releases handle h, so that it may be reused for another ID.
+    pub(crate) fn release(&mut self, h: Handle) {
+        if let Some(id) = self.ids[h as usize].take() {
+            self.remove_handle(&id, h);
+            collections::push(&mut self.free, h);
+        }
+    }
+
+    // removes h, the handle for id, from the table.
+    #[cfg(not(feature = "persistent"))]
+    fn remove_handle(&mut self, id: &ID, h: Handle) {
+        if let Ok(entry) = self.table.find_entry(self.hasher.hash_one(id), |&e| e == h) {
+            entry.remove();
+        }
+    }
+
+    #[cfg(feature = "persistent")]
+    fn remove_handle(&mut self, id: &ID, _h: Handle) {
+        self.table.remove(id);
+    }
+
+    // returns one more than the highest handle ever returned.
+    #[inline]
+    pub(crate) fn capacity(&self) -> usize {
+        self.ids.len()
+    }
+
+    // returns the IDs, indexed by handle, consuming self.
+    pub(crate) fn into_ids(self) -> Seq<Option<ID>> {
+        self.ids
+    }
+}
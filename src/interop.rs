@@ -0,0 +1,70 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Conversions to the trees of the `indextree` and `ego-tree` crates, eg
+//! for rendering or layout.
+//!
+//! Requires the `indextree` or `ego-tree` feature.
+
+use super::{Tree, TreeId, TreeMeta};
+
+impl<ID: TreeId, TM: TreeMeta> Tree<ID, TM> {
+    /// returns a copy of the subtree under parent_id as an `indextree`
+    /// arena, and the arena node of parent_id.
+    /// not used by crdt algo.
+    ///
+    /// Each arena node holds a node's ID and metadata.  parent_id is
+    /// included even if it is not a node, eg a top-level parent, with
+    /// metadata None.  Children are appended in the order set by
+    /// set_child_order(), if any.
+    ///
+    /// Requires the `indextree` feature.
+    #[cfg(feature = "indextree")]
+    pub fn to_indextree(
+        &self,
+        parent_id: &ID,
+    ) -> (indextree::Arena<(ID, Option<TM>)>, indextree::NodeId) {
+        let mut arena = indextree::Arena::with_capacity(self.subtree_size(parent_id) + 1);
+        let root = arena.new_node((parent_id.clone(), self.metadata(parent_id).cloned()));
+        let mut stack = vec![(parent_id.clone(), root)];
+        while let Some((id, node)) = stack.pop() {
+            for child_id in self.children(&id) {
+                let child = arena.new_node((child_id.clone(), self.metadata(&child_id).cloned()));
+                node.append(child, &mut arena);
+                stack.push((child_id, child));
+            }
+        }
+        (arena, root)
+    }
+
+    /// returns a copy of the subtree under parent_id as an `ego_tree::Tree`,
+    /// rooted at parent_id.
+    /// not used by crdt algo.
+    ///
+    /// Each node holds a node's ID and metadata, as for ::to_indextree().
+    ///
+    /// Requires the `ego-tree` feature.
+    #[cfg(feature = "ego-tree")]
+    pub fn to_ego_tree(&self, parent_id: &ID) -> ego_tree::Tree<(ID, Option<TM>)> {
+        let mut tree = ego_tree::Tree::with_capacity(
+            (parent_id.clone(), self.metadata(parent_id).cloned()),
+            self.subtree_size(parent_id) + 1,
+        );
+        let mut stack = vec![(parent_id.clone(), tree.root().id())];
+        while let Some((id, node)) = stack.pop() {
+            for child_id in self.children(&id) {
+                let meta = self.metadata(&child_id).cloned();
+                let child = tree
+                    .get_mut(node)
+                    .expect("node exists")
+                    .append((child_id.clone(), meta))
+                    .id();
+                stack.push((child_id, child));
+            }
+        }
+        tree
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::fmt;
+use std::fmt::Debug;
+
+/// `InvariantViolation` describes the first inconsistency found by
+/// `Tree::check_invariants()`.
+///
+/// Violations other than `Cycle` can be fixed with `Tree::repair()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation<ID> {
+    /// the node is an ancestor of itself.
+    Cycle(ID),
+    /// a node is missing from the children index of its parent.
+    MissingChild {
+        /// parent of the node.
+        parent_id: ID,
+        /// the node.
+        child_id: ID,
+    },
+    /// the children index of a parent lists a node that is not its child.
+    StaleChild {
+        /// parent listed in the children index.
+        parent_id: ID,
+        /// the node.
+        child_id: ID,
+    },
+    /// an index derived from the triples (heights, subtree sizes or roots)
+    /// does not match the triples.
+    IndexMismatch(&'static str),
+}
+
+impl<ID: Debug> fmt::Display for InvariantViolation<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cycle(id) => write!(f, "node {:?} is an ancestor of itself", id),
+            Self::MissingChild {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "node {:?} is missing from children of {:?}",
+                child_id, parent_id
+            ),
+            Self::StaleChild {
+                parent_id,
+                child_id,
+            } => write!(
+                f,
+                "node {:?} is listed in children of {:?} but is not its child",
+                child_id, parent_id
+            ),
+            Self::IndexMismatch(index) => write!(f, "{} index does not match tree", index),
+        }
+    }
+}
+
+impl<ID: Debug> std::error::Error for InvariantViolation<ID> {}
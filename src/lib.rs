@@ -25,7 +25,11 @@
 #![deny(missing_docs)]
 
 mod tree;
-pub use self::tree::Tree;
+pub use self::tree::{RelativePath, SubtreeIter, Tree, TreeIntoIter, TreeIter, WalkControl};
+
+mod interner;
+
+mod collections;
 
 mod state;
 pub use self::state::State;
@@ -33,6 +37,12 @@ pub use self::state::State;
 mod clock;
 pub use self::clock::Clock;
 
+pub mod tiebreak;
+pub use self::tiebreak::{ActorOrder, HashOrder, TieBreak};
+
+pub mod resolve;
+pub use self::resolve::{Kleppmann, MergeMetadata, Resolve};
+
 mod opmove;
 pub use self::opmove::OpMove;
 
@@ -43,10 +53,195 @@ mod treeid;
 pub use self::treeid::TreeId;
 
 mod treemeta;
-pub use self::treemeta::TreeMeta;
+pub use self::treemeta::{MergeMeta, TreeMeta};
+
+pub mod meta;
+
+mod treemacro;
+
+mod treebuilder;
+pub use self::treebuilder::{NodeDef, TreeBuilder};
+
+mod arcmeta;
+pub use self::arcmeta::ArcMeta;
+
+mod stringmeta;
+pub use self::stringmeta::StringMeta;
+
+pub mod aliases;
+pub use self::aliases::{U64LogOpMove, U64OpMove, U64State, U64TreeReplica};
+#[cfg(feature = "uuid")]
+pub use self::aliases::{UuidLogOpMove, UuidOpMove, UuidState, UuidTreeReplica};
+
+pub mod prelude;
 
 mod treenode;
 pub use self::treenode::TreeNode;
 
+#[cfg(feature = "serde_json")]
+mod treejson;
+#[cfg(feature = "serde_json")]
+pub use self::treejson::JsonNode;
+
+mod invariantviolation;
+pub use self::invariantviolation::InvariantViolation;
+
+mod nodeindex;
+
+mod uniquenames;
+pub use self::uniquenames::UniqueNames;
+
+mod checkpoint;
+pub use self::checkpoint::{Checkpointer, Segment, Snapshot};
+
+mod conflict;
+pub use self::conflict::{Conflict, ConflictEvent, ConflictHandler, ConflictKind};
+
+mod opgroup;
+pub use self::opgroup::{OpGroup, OpGroupError};
+
+mod treediff;
+pub use self::treediff::diff;
+
+mod subtree;
+
+mod quotas;
+pub use self::quotas::Quotas;
+
+mod logstats;
+pub use self::logstats::LogStats;
+
+mod auditlog;
+pub use self::auditlog::{AuditFilter, AuditRecord};
+
 mod treereplica;
 pub use self::treereplica::TreeReplica;
+
+mod replicaexport;
+pub use self::replicaexport::{ImportError, ReplicaExport, EXPORT_VERSION};
+
+mod treesnapshot;
+pub use self::treesnapshot::TreeSnapshot;
+
+mod wallclock;
+pub use self::wallclock::{DriftGuard, DriftPolicy};
+
+mod loglimit;
+pub use self::loglimit::{LogLimit, LogLimitPolicy};
+
+mod applyreport;
+pub use self::applyreport::ApplyReport;
+
+mod barrier;
+pub use self::barrier::Barrier;
+
+mod epoch;
+pub use self::epoch::{Epoch, EpochAck, Epochs};
+
+mod scoped;
+pub use self::scoped::UnstableSubtree;
+
+mod versionvector;
+pub use self::versionvector::VersionVector;
+
+mod causal;
+pub use self::causal::{CausalContext, CausalOpMove};
+
+mod changeevent;
+pub use self::changeevent::ChangeEvent;
+
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+
+#[cfg(feature = "testing")]
+pub mod test_utils;
+
+#[cfg(feature = "testing")]
+pub mod simulation;
+
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+
+#[cfg(feature = "tokio")]
+mod subscribe;
+#[cfg(feature = "tokio")]
+pub use self::subscribe::{AppliedOp, ApplyOutcome};
+
+#[cfg(feature = "tokio")]
+mod replicahandle;
+#[cfg(feature = "tokio")]
+pub use self::replicahandle::{HandleError, ReplicaHandle};
+
+mod outbox;
+pub use self::outbox::Outbox;
+
+mod seen;
+
+mod storage;
+pub use self::storage::{MemoryStorage, Storage};
+
+mod storedreplica;
+pub use self::storedreplica::StoredReplica;
+
+pub mod migrate;
+
+#[cfg(feature = "wal")]
+mod wal;
+#[cfg(feature = "wal")]
+pub use self::wal::{Wal, WalError, MAX_RECORD_LEN};
+
+#[cfg(feature = "wal")]
+pub mod recovery;
+
+#[cfg(feature = "rocksdb-storage")]
+mod rocksstorage;
+#[cfg(feature = "rocksdb-storage")]
+pub use self::rocksstorage::{RocksStorage, RocksStorageError};
+
+#[cfg(feature = "sled-storage")]
+mod sledstorage;
+#[cfg(feature = "sled-storage")]
+pub use self::sledstorage::{SledStorage, SledStorageError};
+
+pub mod fs;
+
+#[cfg(feature = "cbor")]
+pub mod cbor;
+
+#[cfg(feature = "codec")]
+pub mod codec;
+
+#[cfg(feature = "codec")]
+pub mod streaming;
+
+#[cfg(all(feature = "codec", feature = "rayon"))]
+pub mod parallel;
+
+#[cfg(any(feature = "indextree", feature = "ego-tree"))]
+pub mod interop;
+
+#[cfg(feature = "encryption")]
+pub mod encryption;
+
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+
+#[cfg(feature = "net")]
+pub mod net;
+
+#[cfg(feature = "protobuf")]
+pub mod proto;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use self::wasm::WasmReplica;
+
+#[cfg(feature = "fuse")]
+pub mod fuse;
@@ -0,0 +1,58 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+
+/// What a `LogLimit` does when the log is full, ie when truncating it to
+/// the causally stable threshold does not make room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLimitPolicy {
+    /// new ops are not applied.
+    ///
+    /// Warning: a rejected op is never applied, so replicas that do not
+    /// reject it will diverge from this one.
+    Reject,
+    /// the oldest entries are discarded, even though not causally stable.
+    ///
+    /// Warning: an op older than a discarded entry is then applied without
+    /// undoing and redoing it, so replicas may diverge from this one.
+    UnsafeTruncate,
+    /// the oldest entries are removed, as for `UnsafeTruncate`, and kept
+    /// for the application to move to storage.  See
+    /// `TreeReplica::take_spilled`.
+    ///
+    /// Entries truncated as causally stable are kept too.
+    Spill,
+}
+
+/// `LogLimit` caps the number of entries held in a replica's log, which
+/// otherwise grows without bound while any peer is offline, as the
+/// causally stable threshold can not advance.  See
+/// `TreeReplica::set_log_limit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLimit {
+    max_len: usize,
+    policy: LogLimitPolicy,
+}
+
+impl LogLimit {
+    /// creates a limit of max_len log entries.
+    pub fn new(max_len: usize, policy: LogLimitPolicy) -> Self {
+        Self { max_len, policy }
+    }
+
+    /// returns the maximum number of log entries
+    #[inline]
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// returns the policy
+    #[inline]
+    pub fn policy(&self) -> LogLimitPolicy {
+        self.policy
+    }
+}
@@ -4,7 +4,7 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::{Eq, PartialEq};
 
 use super::{Clock, OpMove, TreeId, TreeMeta, TreeNode};
@@ -34,44 +34,131 @@ use crdts::Actor;
 /// The `get_parent()` function implements this.
 /// ----
 /// [1] <https://martin.kleppmann.com/papers/move-op.pdf>
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// In this implementation, the op's parent and metadata are held in a
+/// shared `TreeNode`, which is also the node placed in the tree when the
+/// op is applied.  When the child is moved again, that node becomes the
+/// oldp of the later entry, so each parent and metadata pair is stored
+/// once, however many times the node is moved, undone and redone.
+#[derive(Debug, Clone)]
 pub struct LogOpMove<ID: TreeId, TM: TreeMeta, A: Actor> {
-    // an operation that is being logged.
-    op: OpMove<ID, TM, A>,
+    // the operation that is being logged, with its parent and metadata
+    // held in node.
+    timestamp: Clock<A>,
+    child_id: ID,
+    node: TreeNode<ID, TM>,
+    wall_time: Option<u64>,
+    expected_parent_id: Option<ID>,
+    provenance: Option<Vec<u8>>,
 
     /// parent and metadata prior to application of op.
     /// None if `op.child_id` did not previously exist in tree.
     oldp: Option<TreeNode<ID, TM>>,
+
+    // what applying the op did to the tree, or None if it has not been
+    // applied, or was deserialized without it.
+    placement: Option<Placement>,
+}
+
+// what applying a log entry did to its child's node.  Recorded so that
+// the tree and log can share nodes again once deserialized.  See
+// State::share_nodes().
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Placement {
+    // the entry's own node was placed.
+    Placed,
+    // another node was placed, eg with merged metadata or a parent chosen
+    // by Resolve::on_cycle().
+    Replaced,
+    // the entry was ignored.
+    Ignored,
+}
+
+impl Placement {
+    // returns true if the entry changed the tree.
+    #[inline]
+    pub(crate) fn took_effect(self) -> bool {
+        self != Self::Ignored
+    }
+}
+
+// the placement is left out, as it is bookkeeping derived from the tree
+// the entry was applied to.
+impl<ID: TreeId, TM: TreeMeta + PartialEq, A: Actor> PartialEq for LogOpMove<ID, TM, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.timestamp == other.timestamp
+            && self.child_id == other.child_id
+            && self.node == other.node
+            && self.wall_time == other.wall_time
+            && self.expected_parent_id == other.expected_parent_id
+            && self.provenance == other.provenance
+            && self.oldp == other.oldp
+    }
 }
 
+impl<ID: TreeId, TM: TreeMeta + Eq, A: Actor> Eq for LogOpMove<ID, TM, A> {}
+
 impl<ID: TreeId, TM: TreeMeta, A: Actor> LogOpMove<ID, TM, A> {
     /// create a new instance of `LogOpMove`
     pub fn new(op: OpMove<ID, TM, A>, oldp: Option<TreeNode<ID, TM>>) -> LogOpMove<ID, TM, A> {
-        LogOpMove { op, oldp }
+        let wall_time = op.wall_time();
+        let expected_parent_id = op.expected_parent_id().cloned();
+        let provenance = op.provenance().map(|p| p.to_vec());
+        let (timestamp, parent_id, metadata, child_id) = op.into_parts();
+        LogOpMove {
+            timestamp,
+            child_id,
+            node: TreeNode::new(parent_id, metadata),
+            wall_time,
+            expected_parent_id,
+            provenance,
+            oldp,
+            placement: None,
+        }
     }
 
     /// returns `timestamp` reference
     #[inline]
     pub fn timestamp(&self) -> &Clock<A> {
-        self.op.timestamp()
+        &self.timestamp
     }
 
     /// returns `parent_id` reference
     #[inline]
     pub fn parent_id(&self) -> &ID {
-        self.op.parent_id()
+        self.node.parent_id()
     }
 
     /// returns `metadata` reference
     #[inline]
     pub fn metadata(&self) -> &TM {
-        self.op.metadata()
+        self.node.metadata()
     }
 
     /// returns `child_id` reference
     #[inline]
     pub fn child_id(&self) -> &ID {
-        self.op.child_id()
+        &self.child_id
+    }
+
+    /// returns wall-clock time claimed by the op's originating replica,
+    /// if any.
+    #[inline]
+    pub fn wall_time(&self) -> Option<u64> {
+        self.wall_time
+    }
+
+    /// returns the parent the child must have for the op to take effect,
+    /// if the op is conditional.  See `OpMove::with_expected_parent`.
+    #[inline]
+    pub fn expected_parent_id(&self) -> Option<&ID> {
+        self.expected_parent_id.as_ref()
+    }
+
+    /// returns the op's provenance, if any.  See `OpMove::with_provenance`.
+    #[inline]
+    pub fn provenance(&self) -> Option<&[u8]> {
+        self.provenance.as_deref()
     }
 
     /// returns oldp reference
@@ -81,8 +168,109 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> LogOpMove<ID, TM, A> {
     }
 
     /// converts `LogOpMove` into an `OpMove`
+    ///
+    /// The parent and metadata are copied only if they are still shared,
+    /// eg with the tree.
     #[inline]
     pub fn op_into(self) -> OpMove<ID, TM, A> {
-        self.op
+        let (parent_id, metadata) = self.node.into_parts();
+        let mut op = OpMove::new(self.timestamp, parent_id, metadata, self.child_id);
+        op.set_wall_time(self.wall_time);
+        op.set_expected_parent_id(self.expected_parent_id);
+        op.set_provenance(self.provenance);
+        op
+    }
+
+    // returns the node holding the op's parent and metadata.
+    #[inline]
+    pub(crate) fn node(&self) -> &TreeNode<ID, TM> {
+        &self.node
+    }
+
+    // replaces oldp.  used when an entry is redone.
+    #[inline]
+    pub(crate) fn set_oldp(&mut self, oldp: Option<TreeNode<ID, TM>>) {
+        self.oldp = oldp;
+    }
+
+    // returns what applying the entry did to the tree, if known.
+    #[inline]
+    pub(crate) fn placement(&self) -> Option<Placement> {
+        self.placement
+    }
+
+    // records what applying the entry did to the tree.
+    #[inline]
+    pub(crate) fn set_placement(&mut self, placement: Option<Placement>) {
+        self.placement = placement;
+    }
+}
+
+// serialized form of a log entry, unchanged by sharing of nodes.
+#[derive(Serialize)]
+#[serde(rename = "LogOpMove")]
+struct LogOpMoveRef<'a, ID: TreeId, TM: TreeMeta, A: Actor> {
+    op: OpMoveRef<'a, ID, TM, A>,
+    oldp: Option<&'a TreeNode<ID, TM>>,
+    placement: Option<Placement>,
+}
+
+#[derive(Serialize)]
+#[serde(rename = "OpMove")]
+struct OpMoveRef<'a, ID: TreeId, TM: TreeMeta, A: Actor> {
+    timestamp: &'a Clock<A>,
+    parent_id: &'a ID,
+    metadata: &'a TM,
+    child_id: &'a ID,
+    wall_time: Option<u64>,
+    expected_parent_id: Option<&'a ID>,
+    provenance: Option<&'a [u8]>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "LogOpMove")]
+struct LogOpMoveData<ID: TreeId, TM: TreeMeta, A: Actor> {
+    op: OpMove<ID, TM, A>,
+    oldp: Option<TreeNode<ID, TM>>,
+    // absent from logs serialized before it was recorded.
+    #[serde(default)]
+    placement: Option<Placement>,
+}
+
+impl<ID, TM, A> Serialize for LogOpMove<ID, TM, A>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + Serialize,
+    A: Actor + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        LogOpMoveRef {
+            op: OpMoveRef {
+                timestamp: &self.timestamp,
+                parent_id: self.parent_id(),
+                metadata: self.metadata(),
+                child_id: &self.child_id,
+                wall_time: self.wall_time,
+                expected_parent_id: self.expected_parent_id(),
+                provenance: self.provenance(),
+            },
+            oldp: self.oldp().as_ref(),
+            placement: self.placement,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, ID, TM, A> Deserialize<'de> for LogOpMove<ID, TM, A>
+where
+    ID: TreeId + Deserialize<'de>,
+    TM: TreeMeta + Deserialize<'de>,
+    A: Actor + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = LogOpMoveData::deserialize(deserializer)?;
+        let mut entry = Self::new(data.op, data.oldp);
+        entry.placement = data.placement;
+        Ok(entry)
     }
 }
@@ -0,0 +1,142 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem::size_of;
+
+use super::{Clock, LogOpMove, Resolve, State, TieBreak, TreeId, TreeMeta, TreeNode};
+use crdts::Actor;
+
+/// `LogStats` summarizes a `State`'s log.  See `State::log_stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogStats<A: Actor> {
+    entries: usize,
+    ops_by_actor: BTreeMap<A, usize>,
+    oldest: Option<Clock<A>>,
+    newest: Option<Clock<A>>,
+    no_ops: usize,
+    estimated_bytes: usize,
+}
+
+impl<A: Actor> LogStats<A> {
+    /// returns the number of log entries.
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    /// returns the number of log entries generated by each actor.
+    pub fn ops_by_actor(&self) -> &BTreeMap<A, usize> {
+        &self.ops_by_actor
+    }
+
+    /// returns the timestamp of the oldest log entry, if any.
+    pub fn oldest(&self) -> Option<&Clock<A>> {
+        self.oldest.as_ref()
+    }
+
+    /// returns the timestamp of the newest log entry, if any.
+    pub fn newest(&self) -> Option<&Clock<A>> {
+        self.newest.as_ref()
+    }
+
+    /// returns the number of entries that left the tree unchanged, eg as
+    /// their op was ignored to avoid a cycle.
+    pub fn no_ops(&self) -> usize {
+        self.no_ops
+    }
+
+    /// returns an estimate of the memory held by the log, in bytes.
+    ///
+    /// Nodes shared between entries, and with the tree, are counted once.
+    /// Heap data owned by IDs and metadata, eg a String's buffer, is not
+    /// counted.
+    pub fn estimated_bytes(&self) -> usize {
+        self.estimated_bytes
+    }
+}
+
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta + PartialEq,
+    A: Actor,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// returns statistics about the log, for capacity planning and
+    /// debugging.
+    ///
+    /// Takes time linear in the length of the log.
+    pub fn log_stats(&self) -> LogStats<A> {
+        let log = self.log();
+        let mut ops_by_actor = BTreeMap::new();
+        let mut nodes: HashSet<*const ()> = HashSet::new();
+        // the node each child had after the entry being visited, walking
+        // from newest to oldest.
+        let mut after: HashMap<&ID, Option<&TreeNode<ID, TM>>> = HashMap::new();
+        let mut no_ops = 0;
+
+        for entry in log {
+            *ops_by_actor
+                .entry(entry.timestamp().actor_id().clone())
+                .or_insert(0) += 1;
+            nodes.insert(entry.node().as_ptr());
+            if let Some(oldp) = entry.oldp().as_ref() {
+                nodes.insert(oldp.as_ptr());
+            }
+
+            let child_id = entry.child_id();
+            let node = *after
+                .entry(child_id)
+                .or_insert_with(|| self.tree().find(child_id));
+            if node == entry.oldp().as_ref() {
+                no_ops += 1;
+            }
+            after.insert(child_id, entry.oldp().as_ref());
+        }
+
+        let node_bytes = size_of::<(ID, TM)>() + 2 * size_of::<usize>();
+        LogStats {
+            entries: log.len(),
+            ops_by_actor,
+            oldest: log.last().map(|e| e.timestamp().clone()),
+            newest: log.first().map(|e| e.timestamp().clone()),
+            no_ops,
+            estimated_bytes: log.capacity() * size_of::<LogOpMove<ID, TM, A>>()
+                + nodes.len() * node_bytes,
+        }
+    }
+
+    /// returns the log entries with timestamps from `from`, inclusive, up
+    /// to `to`, exclusive, newest first.
+    ///
+    /// The log is ordered by timestamp, as compared by the `TieBreak`
+    /// strategy, so the range is found by binary search.
+    pub fn log_range(
+        &self,
+        from: &Clock<A>,
+        to: &Clock<A>,
+    ) -> impl DoubleEndedIterator<Item = &LogOpMove<ID, TM, A>> + ExactSizeIterator {
+        let log = self.log();
+        let start = log.partition_point(|e| T::cmp(e.timestamp(), to) != Ordering::Less);
+        let end = log
+            .partition_point(|e| T::cmp(e.timestamp(), from) != Ordering::Less)
+            .max(start);
+        log[start..end].iter()
+    }
+
+    /// returns the log entries with timestamps after `since`, exclusive,
+    /// newest first, eg to catch up a peer that has seen up to `since`.
+    /// Reverse the iterator for oldest first.
+    pub fn log_after(
+        &self,
+        since: &Clock<A>,
+    ) -> impl DoubleEndedIterator<Item = &LogOpMove<ID, TM, A>> + ExactSizeIterator {
+        let log = self.log();
+        log[..log.partition_point(|e| T::cmp(e.timestamp(), since) == Ordering::Greater)].iter()
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Typed metadata records, whose fields are updated independently.
+//!
+//! `meta_record!` declares a struct whose fields are each optional.  An
+//! op's metadata sets only the fields it changes, and with the
+//! `MergeMetadata` strategy, an op leaving a node under the same parent
+//! overwrites just those fields.  So a concurrent rename and chmod of the
+//! same node both survive:
+//!
+//! ```text
+//! crdt_tree::meta_record! {
+//!     pub struct FileMeta {
+//!         pub name: String,
+//!         pub mode: u32,
+//!         pub mtime: u64,
+//!     }
+//! }
+//!
+//! let mut r: TreeReplica<u64, FileMeta, u8, ActorOrder, MergeMetadata> = TreeReplica::new(1);
+//! let chmod = FileMeta { mode: Some(0o644), ..Default::default() };
+//! r.apply_op(r.opmove(parent_id, chmod, child_id));
+//! ```
+//!
+//! Each field is a last-writer-wins register keyed by op timestamp: ops
+//! are applied in timestamp order, undoing and redoing later ops as
+//! needed, so the op whose fields are merged is always the latest.
+//!
+//! Moving a node under another parent replaces its metadata, as usual,
+//! so fields the op does not set are cleared.
+
+/// declares a struct of optional fields, implementing `MergeMeta`.  See
+/// the `meta` module.
+///
+/// The struct derives `Debug`, `Clone`, `Default` and `PartialEq`.  Other
+/// attributes, eg further derives, are passed through.
+#[macro_export]
+macro_rules! meta_record {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Default, PartialEq)]
+        $vis struct $name {
+            $($(#[$field_attr])* $field_vis $field: ::std::option::Option<$ty>,)*
+        }
+
+        impl $crate::MergeMeta for $name {
+            fn merge(&mut self, other: Self) {
+                $(
+                    if other.$field.is_some() {
+                        self.$field = other.$field;
+                    }
+                )*
+            }
+        }
+    };
+}
@@ -0,0 +1,294 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Migration of ops, logs, states and snapshots to new ID and metadata
+//! types, eg when an application re-keys its nodes or changes the format
+//! of its metadata.
+//!
+//! A `Migration` holds a mapper for IDs and one for metadata, and applies
+//! them to every ID and metadata in a value, including those in log
+//! entries, so that the migrated log can still undo and redo ops.  IDs
+//! must map one to one and the same way every time, or the migrated tree
+//! will not match one built by applying the migrated ops.  Actors and
+//! timestamps are unchanged.
+//!
+//! `Migration::storage` copies the latest snapshot and the ops of one
+//! `Storage` to another.  Storages that use `codec` decode messages of
+//! the previous format version, and write the current one, so this also
+//! upgrades persisted data, even with identity mappers.
+//!
+//! Settings that are not serialized, eg quotas or a tree's child order,
+//! are not carried over.
+
+use std::fmt;
+use std::ops::Bound;
+
+use super::{
+    LogOpMove, OpMove, Resolve, Snapshot, State, Storage, TieBreak, Tree, TreeId, TreeMeta,
+    TreeNode,
+};
+use crdts::Actor;
+
+/// Errors returned when migrating.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrateError<E> {
+    /// a mapper failed.
+    Map(E),
+    /// the source or destination storage failed.
+    Storage(String),
+}
+
+impl<E: fmt::Display> fmt::Display for MigrateError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Map(e) => write!(f, "mapping failed: {}", e),
+            Self::Storage(e) => write!(f, "storage error: {}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for MigrateError<E> {}
+
+/// `Migration` maps the IDs of values with map_id, and their metadata with
+/// map_meta.  See the module docs.
+pub struct Migration<FI, FM> {
+    map_id: FI,
+    map_meta: FM,
+}
+
+impl<FI, FM> Migration<FI, FM> {
+    /// creates a migration mapping IDs with map_id and metadata with
+    /// map_meta.
+    ///
+    /// Use `|id| Ok(id.clone())` to keep the IDs, or likewise metadata.
+    pub fn new(map_id: FI, map_meta: FM) -> Self {
+        Self { map_id, map_meta }
+    }
+
+    /// returns op with its IDs and metadata mapped.
+    pub fn op<ID, TM, A, ID2, TM2, E>(
+        &mut self,
+        op: &OpMove<ID, TM, A>,
+    ) -> Result<OpMove<ID2, TM2, A>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        let mut new = OpMove::new(
+            op.timestamp().clone(),
+            (self.map_id)(op.parent_id())?,
+            (self.map_meta)(op.metadata())?,
+            (self.map_id)(op.child_id())?,
+        );
+        new.set_wall_time(op.wall_time());
+        new.set_expected_parent_id(op.expected_parent_id().map(&mut self.map_id).transpose()?);
+        new.set_provenance(op.provenance().map(|p| p.to_vec()));
+        Ok(new)
+    }
+
+    /// returns ops with their IDs and metadata mapped.
+    pub fn ops<ID, TM, A, ID2, TM2, E>(
+        &mut self,
+        ops: &[OpMove<ID, TM, A>],
+    ) -> Result<Vec<OpMove<ID2, TM2, A>>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        ops.iter().map(|op| self.op(op)).collect()
+    }
+
+    /// returns node with its parent and metadata mapped.
+    pub fn node<ID, TM, ID2, TM2, E>(
+        &mut self,
+        node: &TreeNode<ID, TM>,
+    ) -> Result<TreeNode<ID2, TM2>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        Ok(TreeNode::new(
+            (self.map_id)(node.parent_id())?,
+            (self.map_meta)(node.metadata())?,
+        ))
+    }
+
+    /// returns log entry with its IDs and metadata mapped, including those
+    /// of the node's previous parent and metadata.
+    pub fn log_entry<ID, TM, A, ID2, TM2, E>(
+        &mut self,
+        entry: &LogOpMove<ID, TM, A>,
+    ) -> Result<LogOpMove<ID2, TM2, A>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        let mut op = OpMove::new(
+            entry.timestamp().clone(),
+            (self.map_id)(entry.parent_id())?,
+            (self.map_meta)(entry.metadata())?,
+            (self.map_id)(entry.child_id())?,
+        );
+        op.set_wall_time(entry.wall_time());
+        op.set_expected_parent_id(
+            entry
+                .expected_parent_id()
+                .map(&mut self.map_id)
+                .transpose()?,
+        );
+        op.set_provenance(entry.provenance().map(|p| p.to_vec()));
+        let oldp = entry.oldp().as_ref().map(|n| self.node(n)).transpose()?;
+        let mut mapped = LogOpMove::new(op, oldp);
+        mapped.set_placement(entry.placement());
+        Ok(mapped)
+    }
+
+    /// returns log with its IDs and metadata mapped, in the same order.
+    pub fn log<ID, TM, A, ID2, TM2, E>(
+        &mut self,
+        log: &[LogOpMove<ID, TM, A>],
+    ) -> Result<Vec<LogOpMove<ID2, TM2, A>>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        log.iter().map(|entry| self.log_entry(entry)).collect()
+    }
+
+    /// returns tree with its IDs and metadata mapped.
+    pub fn tree<ID, TM, ID2, TM2, E>(&mut self, tree: &Tree<ID, TM>) -> Result<Tree<ID2, TM2>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        let mut new = Tree::new();
+        for (child_id, node) in tree.iter() {
+            let node = self.node(node)?;
+            new.add_node((self.map_id)(child_id)?, node);
+        }
+        let detached = tree
+            .detached_ids()
+            .iter()
+            .map(&mut self.map_id)
+            .collect::<Result<_, _>>()?;
+        new.mark_detached(detached);
+        Ok(new)
+    }
+
+    /// returns state with its IDs and metadata mapped.
+    pub fn state<ID, TM, A, T, R, ID2, TM2, R2, E>(
+        &mut self,
+        state: &State<ID, TM, A, T, R>,
+    ) -> Result<State<ID2, TM2, A, T, R2>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        R2: Resolve<ID2, TM2, A>,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        let log = self.log(state.log())?;
+        let tree = self.tree(state.tree())?;
+        let mut mapped = State::from((log, tree));
+        mapped.set_truncated_before(state.truncated_before().cloned());
+        mapped.set_compacted_from(state.compacted_from().cloned());
+        Ok(mapped)
+    }
+
+    /// returns snapshot with its IDs and metadata mapped.
+    pub fn snapshot<ID, TM, A, ID2, TM2, E>(
+        &mut self,
+        snapshot: &Snapshot<ID, TM, A>,
+    ) -> Result<Snapshot<ID2, TM2, A>, E>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+    {
+        Ok(Snapshot::new(
+            snapshot.id(),
+            self.tree(snapshot.tree())?,
+            self.log(snapshot.log())?,
+            snapshot.truncated_before().cloned(),
+            snapshot.time().clone(),
+            snapshot.latest_time_by_replica().clone(),
+        ))
+    }
+
+    /// copies the latest snapshot, if any, and every op of from to to,
+    /// with their IDs and metadata mapped, and returns the number of ops
+    /// copied.
+    ///
+    /// On error, to may hold some of the copied data.
+    pub fn storage<ID, TM, A, ID2, TM2, E, S, S2>(
+        &mut self,
+        from: &S,
+        to: &mut S2,
+    ) -> Result<usize, MigrateError<E>>
+    where
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor,
+        ID2: TreeId,
+        TM2: TreeMeta,
+        FI: FnMut(&ID) -> Result<ID2, E>,
+        FM: FnMut(&TM) -> Result<TM2, E>,
+        S: Storage<ID, TM, A>,
+        S2: Storage<ID2, TM2, A>,
+    {
+        let read = |e: S::Error| MigrateError::Storage(e.to_string());
+        let write = |e: S2::Error| MigrateError::Storage(e.to_string());
+        if let Some(snapshot) = from.latest_snapshot().map_err(read)? {
+            let snapshot = self.snapshot(&snapshot).map_err(MigrateError::Map)?;
+            to.put_snapshot(&snapshot).map_err(write)?;
+        }
+        let ops = from
+            .scan_ops(Bound::Unbounded, Bound::Unbounded)
+            .map_err(read)?;
+        for op in &ops {
+            let op = self.op(op).map_err(MigrateError::Map)?;
+            to.append_op(&op).map_err(write)?;
+        }
+        Ok(ops.len())
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! MessagePack encoding of op batches and snapshots.
+//!
+//! `to_vec` encodes structs as arrays, which is the most compact form.
+//! `to_vec_named` encodes structs as maps keyed by field name, which is
+//! what peers that decode into dynamic types usually expect.  Both are
+//! decoded by `from_slice`.
+//!
+//! Requires the `msgpack` feature.
+
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Errors returned when encoding or decoding MessagePack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MsgpackError {
+    /// the value could not be encoded.
+    Encode(String),
+    /// the bytes could not be decoded.
+    Decode(String),
+}
+
+impl fmt::Display for MsgpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Encode(e) => write!(f, "msgpack encode error: {}", e),
+            Self::Decode(e) => write!(f, "msgpack decode error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MsgpackError {}
+
+/// encodes value as MessagePack, with structs as arrays.
+///
+/// eg `to_vec(&ops)` for a `Vec<OpMove>` batch, or `to_vec(&state)`.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, MsgpackError> {
+    rmp_serde::to_vec(value).map_err(|e| MsgpackError::Encode(e.to_string()))
+}
+
+/// encodes value as MessagePack, with structs as maps keyed by field name.
+pub fn to_vec_named<T: Serialize>(value: &T) -> Result<Vec<u8>, MsgpackError> {
+    rmp_serde::to_vec_named(value).map_err(|e| MsgpackError::Encode(e.to_string()))
+}
+
+/// decodes a value from MessagePack, as returned by `to_vec` or
+/// `to_vec_named`.
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, MsgpackError> {
+    rmp_serde::from_slice(bytes).map_err(|e| MsgpackError::Decode(e.to_string()))
+}
@@ -0,0 +1,197 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Length-delimited framing of codec messages over byte streams, eg TCP,
+//! and a minimal sync protocol built on it.
+//!
+//! Each frame is a 4 byte big-endian payload length followed by a message
+//! encoded by the `codec` module:
+//!
+//! ```text
+//! +------------------+--------------------------+
+//! | length (u32, BE) | codec message            |
+//! +------------------+--------------------------+
+//! ```
+//!
+//! Peers exchange `SyncMessage`s.  On (re)connecting, each peer sends
+//! `Hello` with its version vector, and answers the other's `Hello` with
+//! the ops it is missing.  Ops generated afterwards are sent as they
+//! occur.  `handle_message` implements the receiving side:
+//!
+//! ```text
+//! write_frame(&mut stream, &SyncMessage::Hello(replica.version_vector().clone()))?;
+//! while let Some(msg) = read_frame(&mut stream)? {
+//!     if let Some(reply) = handle_message(&mut replica, msg) {
+//!         write_frame(&mut stream, &reply)?;
+//!     }
+//! }
+//! ```
+//!
+//! I/O is blocking, so a stream is normally serviced by its own thread.
+//! See examples/tcp_sync.rs.
+//!
+//! Requires the `net` feature.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::codec::{self, CodecError, Wire};
+use super::{OpMove, Resolve, TieBreak, TreeId, TreeMeta, TreeReplica, VersionVector};
+use crdts::Actor;
+
+/// largest frame accepted by `read_frame`, in bytes.  Guards against
+/// allocating huge buffers for a corrupt length prefix.
+pub const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+/// Errors returned when reading or writing frames.
+#[derive(Debug)]
+pub enum NetError {
+    /// the underlying stream failed.
+    Io(io::Error),
+    /// a frame's payload could not be encoded or decoded.
+    Codec(CodecError),
+    /// a frame's length prefix exceeds `MAX_FRAME_LEN`.
+    FrameTooLarge(u32),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "i/o error: {}", e),
+            Self::Codec(e) => write!(f, "codec error: {}", e),
+            Self::FrameTooLarge(len) => write!(f, "frame too large: {} bytes", len),
+        }
+    }
+}
+
+impl std::error::Error for NetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Codec(e) => Some(e),
+            Self::FrameTooLarge(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<CodecError> for NetError {
+    fn from(e: CodecError) -> Self {
+        Self::Codec(e)
+    }
+}
+
+/// A message exchanged by peers syncing a tree.  See the module docs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "ID: Serialize, TM: Serialize, A: Serialize",
+    deserialize = "ID: Deserialize<'de>, TM: Deserialize<'de>, A: Deserialize<'de>"
+))]
+pub enum SyncMessage<ID: TreeId, TM: TreeMeta, A: Actor> {
+    /// the sender's version vector.  The receiver replies with the ops the
+    /// sender is missing.
+    Hello(VersionVector<A>),
+    /// ops for the receiver to apply, oldest first.
+    Ops(Vec<OpMove<ID, TM, A>>),
+    /// ops the receiver is missing were truncated from the sender's log,
+    /// so the receiver needs a snapshot.
+    SnapshotRequired,
+}
+
+impl<ID, TM, A> Wire for SyncMessage<ID, TM, A>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 7;
+}
+
+/// writes value to writer as a single length-delimited frame, and
+/// flushes writer.
+pub fn write_frame<W: Write, T: Wire>(writer: &mut W, value: &T) -> Result<(), NetError> {
+    let bytes = codec::encode(value)?;
+    let len = u32::try_from(bytes.len()).unwrap_or(u32::MAX);
+    if len > MAX_FRAME_LEN {
+        return Err(NetError::FrameTooLarge(len));
+    }
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// reads a single length-delimited frame from reader.
+///
+/// Returns None if reader is at end of stream before the frame starts, eg
+/// because the peer closed the connection.
+pub fn read_frame<R: Read, T: Wire>(reader: &mut R) -> Result<Option<T>, NetError> {
+    let mut prefix = [0u8; 4];
+    // distinguish a clean close from one part way through the prefix.
+    let n = read_full(reader, &mut prefix)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n < prefix.len() {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    let len = u32::from_be_bytes(prefix);
+    if len > MAX_FRAME_LEN {
+        return Err(NetError::FrameTooLarge(len));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(codec::decode(&bytes)?))
+}
+
+/// applies msg, received from a peer, to replica, and returns the reply
+/// to send back, if any.
+pub fn handle_message<ID, TM, A, T, R>(
+    replica: &mut TreeReplica<ID, TM, A, T, R>,
+    msg: SyncMessage<ID, TM, A>,
+) -> Option<SyncMessage<ID, TM, A>>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    match msg {
+        SyncMessage::Hello(seen) => Some(match replica.missing_ops(&seen) {
+            Some(ops) => SyncMessage::Ops(ops),
+            None => SyncMessage::SnapshotRequired,
+        }),
+        SyncMessage::Ops(ops) => {
+            replica.apply_ops(ops);
+            None
+        }
+        SyncMessage::SnapshotRequired => None,
+    }
+}
+
+// reads into buf until it is full or reader is at end of stream, returning
+// the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
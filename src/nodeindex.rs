@@ -0,0 +1,223 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::any::Any;
+use std::fmt;
+use std::hash::Hash;
+
+use super::collections::{Map, Set};
+use super::{TreeId, TreeMeta, TreeNode};
+
+// An optional secondary index over tree nodes, kept up to date by
+// `Tree` as nodes are added and removed.
+pub(crate) trait NodeIndex<ID: TreeId, TM: TreeMeta>: Send + Sync {
+    fn insert(&mut self, child_id: &ID, node: &TreeNode<ID, TM>);
+    fn remove(&mut self, child_id: &ID, node: &TreeNode<ID, TM>);
+    fn clear(&mut self);
+    fn box_clone(&self) -> Box<dyn NodeIndex<ID, TM>>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+// The set of optional indexes enabled on a `Tree`.
+//
+// Indexes are derived from the tree's triples, so they are neither
+// serialized nor compared.
+pub(crate) struct NodeIndexes<ID: TreeId, TM: TreeMeta> {
+    indexes: Vec<Box<dyn NodeIndex<ID, TM>>>,
+}
+
+impl<ID: TreeId, TM: TreeMeta> NodeIndexes<ID, TM> {
+    // adds an index, replacing any existing index of the same type.
+    pub(crate) fn set<T: NodeIndex<ID, TM> + 'static>(&mut self, index: T) {
+        self.indexes.retain(|i| !i.as_any().is::<T>());
+        self.indexes.push(Box::new(index));
+    }
+
+    // returns the index of type T, if enabled.
+    pub(crate) fn get<T: NodeIndex<ID, TM> + 'static>(&self) -> Option<&T> {
+        self.indexes
+            .iter()
+            .find_map(|i| i.as_any().downcast_ref::<T>())
+    }
+
+    pub(crate) fn insert(&mut self, child_id: &ID, node: &TreeNode<ID, TM>) {
+        for i in self.indexes.iter_mut() {
+            i.insert(child_id, node);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, child_id: &ID, node: &TreeNode<ID, TM>) {
+        for i in self.indexes.iter_mut() {
+            i.remove(child_id, node);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for i in self.indexes.iter_mut() {
+            i.clear();
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> Default for NodeIndexes<ID, TM> {
+    fn default() -> Self {
+        Self {
+            indexes: Vec::new(),
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> Clone for NodeIndexes<ID, TM> {
+    fn clone(&self) -> Self {
+        Self {
+            indexes: self.indexes.iter().map(|i| i.box_clone()).collect(),
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> fmt::Debug for NodeIndexes<ID, TM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NodeIndexes({})", self.indexes.len())
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> PartialEq for NodeIndexes<ID, TM> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> Eq for NodeIndexes<ID, TM> {}
+
+// Indexes nodes by a key extracted from their metadata.
+pub(crate) struct MetaIndex<ID, TM, K> {
+    key: fn(&TM) -> Option<K>,
+    nodes: Map<K, Set<ID>>, // key => [child_id]
+}
+
+impl<ID, TM, K> MetaIndex<ID, TM, K> {
+    pub(crate) fn new(key: fn(&TM) -> Option<K>) -> Self {
+        Self {
+            key,
+            nodes: Map::new(),
+        }
+    }
+
+    // returns nodes whose metadata has the given key.
+    pub(crate) fn find(&self, key: &K) -> Option<&Set<ID>>
+    where
+        K: Eq + Hash,
+    {
+        self.nodes.get(key)
+    }
+}
+
+impl<ID, TM, K> NodeIndex<ID, TM> for MetaIndex<ID, TM, K>
+where
+    ID: TreeId + Send + Sync + 'static,
+    TM: TreeMeta + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    fn insert(&mut self, child_id: &ID, node: &TreeNode<ID, TM>) {
+        if let Some(k) = (self.key)(node.metadata()) {
+            self.nodes.entry(k).or_default().insert(child_id.clone());
+        }
+    }
+
+    fn remove(&mut self, child_id: &ID, node: &TreeNode<ID, TM>) {
+        if let Some(k) = (self.key)(node.metadata()) {
+            if let Some(list) = self.nodes.get_mut(&k) {
+                list.remove(child_id);
+                if list.is_empty() {
+                    self.nodes.remove(&k);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    fn box_clone(&self) -> Box<dyn NodeIndex<ID, TM>> {
+        Box::new(Self {
+            key: self.key,
+            nodes: self.nodes.clone(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Indexes nodes by (parent_id, name), where name is extracted from
+// their metadata.  ie, directory entries.
+pub(crate) struct NameIndex<ID, TM, N> {
+    name: fn(&TM) -> Option<N>,
+    nodes: Map<(ID, N), Set<ID>>, // (parent_id, name) => [child_id]
+}
+
+impl<ID, TM, N> NameIndex<ID, TM, N> {
+    pub(crate) fn new(name: fn(&TM) -> Option<N>) -> Self {
+        Self {
+            name,
+            nodes: Map::new(),
+        }
+    }
+
+    // returns children of parent_id with the given name.
+    pub(crate) fn find(&self, parent_id: ID, name: N) -> Option<&Set<ID>>
+    where
+        ID: Eq + Hash,
+        N: Eq + Hash,
+    {
+        self.nodes.get(&(parent_id, name))
+    }
+}
+
+impl<ID, TM, N> NodeIndex<ID, TM> for NameIndex<ID, TM, N>
+where
+    ID: TreeId + Send + Sync + 'static,
+    TM: TreeMeta + 'static,
+    N: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    fn insert(&mut self, child_id: &ID, node: &TreeNode<ID, TM>) {
+        if let Some(n) = (self.name)(node.metadata()) {
+            self.nodes
+                .entry((node.parent_id().clone(), n))
+                .or_default()
+                .insert(child_id.clone());
+        }
+    }
+
+    fn remove(&mut self, child_id: &ID, node: &TreeNode<ID, TM>) {
+        if let Some(n) = (self.name)(node.metadata()) {
+            let key = (node.parent_id().clone(), n);
+            if let Some(list) = self.nodes.get_mut(&key) {
+                list.remove(child_id);
+                if list.is_empty() {
+                    self.nodes.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    fn box_clone(&self) -> Box<dyn NodeIndex<ID, TM>> {
+        Box::new(Self {
+            name: self.name,
+            nodes: self.nodes.clone(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::{OpMove, Resolve, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// An error returned when ops do not form a valid `OpGroup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpGroupError {
+    /// the group has no ops.
+    Empty,
+    /// the ops were generated by more than one actor.
+    MixedActors,
+    /// the ops' counters are not consecutive, in order.
+    NotConsecutive,
+}
+
+impl fmt::Display for OpGroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "op group is empty"),
+            Self::MixedActors => write!(f, "op group mixes ops of several actors"),
+            Self::NotConsecutive => write!(f, "op group counters are not consecutive"),
+        }
+    }
+}
+
+impl std::error::Error for OpGroupError {}
+
+/// `OpGroup` is a list of moves, such as "rename a directory and move
+/// three files into it", that replicas apply all together, or not at all.
+/// See `TreeReplica::opgroup` and `TreeReplica::apply_group`.
+///
+/// The moves are ops of one actor with consecutive counters, so once
+/// applied they are adjacent in the log, except for any op a peer
+/// generated concurrently with a counter in the same range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(
+    try_from = "Vec<OpMove<ID, TM, A>>",
+    into = "Vec<OpMove<ID, TM, A>>",
+    bound(
+        serialize = "ID: Serialize, TM: Serialize, A: Serialize",
+        deserialize = "ID: Deserialize<'de>, TM: Deserialize<'de>, A: Deserialize<'de>"
+    )
+)]
+pub struct OpGroup<ID: TreeId, TM: TreeMeta, A: Actor> {
+    ops: Vec<OpMove<ID, TM, A>>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> OpGroup<ID, TM, A> {
+    /// creates a group of ops, which must be ops of one actor with
+    /// consecutive counters, in order.
+    pub fn new(ops: Vec<OpMove<ID, TM, A>>) -> Result<Self, OpGroupError> {
+        let first = ops.first().ok_or(OpGroupError::Empty)?.timestamp();
+        for (i, op) in ops.iter().enumerate() {
+            if op.timestamp().actor_id() != first.actor_id() {
+                return Err(OpGroupError::MixedActors);
+            }
+            if op.timestamp().counter() != first.counter() + i as u64 {
+                return Err(OpGroupError::NotConsecutive);
+            }
+        }
+        Ok(Self { ops })
+    }
+
+    /// returns the ops, in order.
+    #[inline]
+    pub fn ops(&self) -> &[OpMove<ID, TM, A>] {
+        &self.ops
+    }
+
+    /// returns the ops, in order, consuming the group.
+    #[inline]
+    pub fn into_ops(self) -> Vec<OpMove<ID, TM, A>> {
+        self.ops
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> TryFrom<Vec<OpMove<ID, TM, A>>> for OpGroup<ID, TM, A> {
+    type Error = OpGroupError;
+
+    fn try_from(ops: Vec<OpMove<ID, TM, A>>) -> Result<Self, Self::Error> {
+        Self::new(ops)
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> From<OpGroup<ID, TM, A>> for Vec<OpMove<ID, TM, A>> {
+    fn from(group: OpGroup<ID, TM, A>) -> Self {
+        group.ops
+    }
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + std::fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// generates a group of ops from a list of tuples (parent_id,
+    /// metadata, child_id), like ::opmoves().
+    ///
+    /// Returns `OpGroupError::Empty` if moves is empty.
+    pub fn opgroup(&self, moves: Vec<(ID, TM, ID)>) -> Result<OpGroup<ID, TM, A>, OpGroupError> {
+        OpGroup::new(self.opmoves(moves))
+    }
+
+    /// applies every op of group, or none of them, and returns true if
+    /// they were applied.
+    ///
+    /// No ops are applied if every op has already been applied, or if a
+    /// drift guard with `DriftPolicy::Reject` would reject any of them, or
+    /// a log limit with `LogLimitPolicy::Reject` would reject the last.
+    /// Ops already applied, eg individually, are skipped.
+    pub fn apply_group(&mut self, group: OpGroup<ID, TM, A>) -> bool {
+        if group.ops.iter().all(|op| self.has_seen(op.timestamp())) {
+            return false;
+        }
+        if group.ops.iter().any(|op| self.drift_rejects(op)) {
+            return false;
+        }
+        let unseen: Vec<_> = group
+            .ops
+            .iter()
+            .map(|op| op.timestamp().clone())
+            .filter(|t| !self.has_seen(t))
+            .collect();
+        if !self.make_room(&unseen) {
+            return false;
+        }
+        self.apply_ops(group.ops);
+        true
+    }
+}
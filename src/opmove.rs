@@ -8,7 +8,6 @@ use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, PartialEq};
 
 use super::{Clock, LogOpMove, TreeId, TreeMeta};
-use crdts::quickcheck::{Arbitrary, Gen};
 use crdts::Actor;
 use std::hash::Hash;
 
@@ -72,6 +71,15 @@ pub struct OpMove<ID: TreeId, TM: TreeMeta, A: Actor> {
     metadata: TM,
     /// child identifier
     child_id: ID,
+    /// wall-clock time claimed by the originating replica, in
+    /// milliseconds since the UNIX epoch.  optional, and not used for
+    /// ordering ops.
+    wall_time: Option<u64>,
+    /// parent the child must have for the op to take effect.  optional.
+    expected_parent_id: Option<ID>,
+    /// opaque context, eg the device and app version that generated the
+    /// op.  optional, and ignored by the algorithm.
+    provenance: Option<Vec<u8>>,
 }
 
 impl<ID: TreeId, TM: TreeMeta, A: Actor> OpMove<ID, TM, A> {
@@ -83,9 +91,50 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> OpMove<ID, TM, A> {
             parent_id,
             metadata,
             child_id,
+            wall_time: None,
+            expected_parent_id: None,
+            provenance: None,
         }
     }
 
+    /// returns the op with wall_time, in milliseconds since the UNIX
+    /// epoch.
+    ///
+    /// Wall-clock time is informational, eg for auditing which replica's
+    /// op won.  Ops are ordered by timestamp only.
+    #[inline]
+    pub fn with_wall_time(mut self, wall_time: u64) -> Self {
+        self.wall_time = Some(wall_time);
+        self
+    }
+
+    /// returns the op made conditional on the child having parent
+    /// expected_parent_id when the op is applied.
+    ///
+    /// A conditional op whose condition does not hold, eg as a concurrent
+    /// op moved the child first, or as the child does not exist, leaves
+    /// the tree unchanged, like an op that would introduce a cycle.  Ops
+    /// are applied in timestamp order, undoing and redoing later ops as
+    /// needed, so the condition is evaluated against the same tree on
+    /// every replica.  This lets an application avoid clobbering
+    /// concurrent moves it has not seen.
+    #[inline]
+    pub fn with_expected_parent(mut self, expected_parent_id: ID) -> Self {
+        self.expected_parent_id = Some(expected_parent_id);
+        self
+    }
+
+    /// returns the op with provenance, an opaque blob identifying where
+    /// the op came from, eg a device ID, app version or request ID.
+    ///
+    /// Provenance is kept in the log, for debugging and auditing, but is
+    /// otherwise ignored.
+    #[inline]
+    pub fn with_provenance(mut self, provenance: Vec<u8>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
     /// returns timestamp reference
     #[inline]
     pub fn timestamp(&self) -> &Clock<A> {
@@ -109,6 +158,49 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> OpMove<ID, TM, A> {
     pub fn child_id(&self) -> &ID {
         &self.child_id
     }
+
+    /// returns wall-clock time claimed by the originating replica, if any.
+    #[inline]
+    pub fn wall_time(&self) -> Option<u64> {
+        self.wall_time
+    }
+
+    /// returns the parent the child must have for the op to take effect,
+    /// if the op is conditional.
+    #[inline]
+    pub fn expected_parent_id(&self) -> Option<&ID> {
+        self.expected_parent_id.as_ref()
+    }
+
+    /// returns the op's provenance, if any.
+    #[inline]
+    pub fn provenance(&self) -> Option<&[u8]> {
+        self.provenance.as_deref()
+    }
+
+    // returns (timestamp, parent_id, metadata, child_id), consuming self.
+    #[inline]
+    pub(crate) fn into_parts(self) -> (Clock<A>, ID, TM, ID) {
+        (self.timestamp, self.parent_id, self.metadata, self.child_id)
+    }
+
+    // sets or clears wall_time.
+    #[inline]
+    pub(crate) fn set_wall_time(&mut self, wall_time: Option<u64>) {
+        self.wall_time = wall_time;
+    }
+
+    // sets or clears expected_parent_id.
+    #[inline]
+    pub(crate) fn set_expected_parent_id(&mut self, expected_parent_id: Option<ID>) {
+        self.expected_parent_id = expected_parent_id;
+    }
+
+    // sets or clears provenance.
+    #[inline]
+    pub(crate) fn set_provenance(&mut self, provenance: Option<Vec<u8>>) {
+        self.provenance = provenance;
+    }
 }
 
 impl<ID: TreeId, A: Actor, TM: TreeMeta> From<LogOpMove<ID, TM, A>> for OpMove<ID, TM, A> {
@@ -117,18 +209,3 @@ impl<ID: TreeId, A: Actor, TM: TreeMeta> From<LogOpMove<ID, TM, A>> for OpMove<I
         l.op_into()
     }
 }
-
-// For testing with quicktest
-impl<ID: TreeId + Arbitrary, A: Actor + Arbitrary, TM: TreeMeta + Arbitrary> Arbitrary
-    for OpMove<ID, TM, A>
-{
-    /// generates an arbitrary (random) OpMove
-    fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        Self::new(
-            Clock::arbitrary(g),
-            ID::arbitrary(g),
-            TM::arbitrary(g),
-            ID::arbitrary(g),
-        )
-    }
-}
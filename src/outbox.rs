@@ -0,0 +1,124 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{Clock, OpMove, TreeId, TreeMeta};
+use crdts::Actor;
+
+/// `Outbox` holds locally generated ops until every peer has
+/// acknowledged them.  See `TreeReplica::outbox_mut`.
+///
+/// A network integration registers its peers with ::add_peer(), calls
+/// ::ack() as peers acknowledge ops, and periodically retransmits
+/// ::unacked_ops() to each peer.  Once an op has been acknowledged by
+/// every registered peer, it is dropped.
+///
+/// Ops generated while no peers are registered are not held.  A peer
+/// added later receives only ops generated after it was added, and must
+/// be brought up to date by other means, eg a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outbox<ID: TreeId, TM: TreeMeta, A: Actor> {
+    ops: BTreeMap<Clock<A>, OpMove<ID, TM, A>>,
+    acked: HashMap<A, HashSet<Clock<A>>>, // by peer, acked ops still held.
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Outbox<ID, TM, A> {
+    /// creates an empty outbox, with no peers.
+    pub fn new() -> Self {
+        Self {
+            ops: BTreeMap::new(),
+            acked: HashMap::new(),
+        }
+    }
+
+    /// registers peer.  Ops held from now on must be acknowledged by it.
+    pub fn add_peer(&mut self, peer: A) {
+        self.acked.entry(peer).or_default();
+    }
+
+    /// unregisters peer, dropping any ops it was the last to acknowledge.
+    pub fn remove_peer(&mut self, peer: &A) {
+        if self.acked.remove(peer).is_some() {
+            let ops: Vec<Clock<A>> = self.ops.keys().cloned().collect();
+            for timestamp in ops {
+                self.release_if_acked(&timestamp);
+            }
+        }
+    }
+
+    /// returns an iterator over registered peers, in arbitrary order.
+    pub fn peers(&self) -> impl Iterator<Item = &A> {
+        self.acked.keys()
+    }
+
+    /// holds op until every registered peer has acknowledged it.
+    pub fn push(&mut self, op: OpMove<ID, TM, A>) {
+        if !self.acked.is_empty() {
+            self.ops.insert(op.timestamp().clone(), op);
+        }
+    }
+
+    /// records that peer has received the op with timestamp.
+    ///
+    /// Returns false if peer is not registered or the op is not held.
+    pub fn ack(&mut self, peer: &A, timestamp: &Clock<A>) -> bool {
+        if !self.ops.contains_key(timestamp) {
+            return false;
+        }
+        match self.acked.get_mut(peer) {
+            Some(acked) => {
+                acked.insert(timestamp.clone());
+                self.release_if_acked(timestamp);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// returns ops not yet acknowledged by peer, oldest first.
+    ///
+    /// Returns no ops if peer is not registered.
+    pub fn unacked_ops(&self, peer: &A) -> Vec<&OpMove<ID, TM, A>> {
+        match self.acked.get(peer) {
+            Some(acked) => self
+                .ops
+                .iter()
+                .filter(|(t, _)| !acked.contains(*t))
+                .map(|(_, op)| op)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// returns the number of ops held.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// returns true if no ops are held.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    // drops the op with timestamp if every peer has acknowledged it.
+    fn release_if_acked(&mut self, timestamp: &Clock<A>) {
+        if self.acked.values().all(|a| a.contains(timestamp)) {
+            self.ops.remove(timestamp);
+            for acked in self.acked.values_mut() {
+                acked.remove(timestamp);
+            }
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Default for Outbox<ID, TM, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Parallel decoding and validation of op batches.
+//!
+//! A large batch of `codec` encoded ops, eg received from a peer that was
+//! offline, is decoded and checked by a validator, eg verifying each op's
+//! signature, on all rayon threads.  Applying the ops to a tree remains
+//! serial.
+//!
+//! Requires the `codec` and `rayon` features.
+
+use std::fmt;
+
+use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::codec::{self, CodecError};
+use super::{ApplyReport, OpMove, Resolve, State, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// `BatchError` is the first message of a batch, in batch order, that
+/// could not be decoded or was rejected by the validator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchError<E> {
+    /// the message could not be decoded
+    Decode {
+        /// the index of the message in the batch
+        index: usize,
+        /// the decoding error
+        error: CodecError,
+    },
+    /// the validator rejected the op
+    Invalid {
+        /// the index of the message in the batch
+        index: usize,
+        /// the validator's error
+        error: E,
+    },
+}
+
+impl<E> BatchError<E> {
+    /// returns the index of the message in the batch
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Decode { index, .. } | Self::Invalid { index, .. } => *index,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BatchError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode { index, error } => write!(f, "message {}: {}", index, error),
+            Self::Invalid { index, error } => write!(f, "message {} is invalid: {}", index, error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for BatchError<E> {}
+
+/// decodes a batch of ops, each encoded by `codec::encode`, and checks
+/// each with validate, in parallel.  validate is given the encoded message
+/// and the op, eg to verify a signature of the message.
+///
+/// Returns the ops in batch order, or the first error in batch order.
+pub fn decode_batch<M, ID, TM, A, V, E>(
+    messages: &[M],
+    validate: V,
+) -> Result<Vec<OpMove<ID, TM, A>>, BatchError<E>>
+where
+    M: AsRef<[u8]> + Sync,
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync,
+    A: Actor + Serialize + DeserializeOwned + Send + Sync,
+    V: Fn(&[u8], &OpMove<ID, TM, A>) -> Result<(), E> + Sync,
+    E: Send,
+{
+    let results: Vec<Result<OpMove<ID, TM, A>, BatchError<E>>> = messages
+        .par_iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let bytes = message.as_ref();
+            let op = codec::decode(bytes).map_err(|error| BatchError::Decode { index, error })?;
+            validate(bytes, &op).map_err(|error| BatchError::Invalid { index, error })?;
+            Ok(op)
+        })
+        .collect();
+    results.into_iter().collect()
+}
+
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync,
+    A: Actor + Serialize + DeserializeOwned + Send + Sync,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// decodes and validates a batch of ops in parallel, as
+    /// `decode_batch`, sorts them in parallel, then applies them with
+    /// ::merge_sorted_ops(), and returns the number of ops in the batch.
+    ///
+    /// If any message fails, no op is applied.
+    pub fn apply_encoded_batch<M, V, E>(
+        &mut self,
+        messages: &[M],
+        validate: V,
+    ) -> Result<usize, BatchError<E>>
+    where
+        M: AsRef<[u8]> + Sync,
+        V: Fn(&[u8], &OpMove<ID, TM, A>) -> Result<(), E> + Sync,
+        E: Send,
+    {
+        let mut ops = decode_batch(messages, validate)?;
+        let len = ops.len();
+        ops.par_sort_by(|a, b| T::cmp(a.timestamp(), b.timestamp()));
+        self.merge_sorted_ops(ops);
+        Ok(len)
+    }
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId + Serialize + DeserializeOwned + Send + Sync,
+    TM: TreeMeta + Serialize + DeserializeOwned + Send + Sync,
+    A: Actor + std::fmt::Debug + Serialize + DeserializeOwned + Send + Sync,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// decodes and validates a batch of ops in parallel, as
+    /// `decode_batch`, then applies them in batch order, as
+    /// ::apply_ops_report().
+    ///
+    /// If any message fails, no op is applied.
+    pub fn apply_encoded_batch<M, V, E>(
+        &mut self,
+        messages: &[M],
+        validate: V,
+    ) -> Result<ApplyReport, BatchError<E>>
+    where
+        M: AsRef<[u8]> + Sync,
+        V: Fn(&[u8], &OpMove<ID, TM, A>) -> Result<(), E> + Sync,
+        E: Send,
+    {
+        let ops = decode_batch(messages, validate)?;
+        Ok(self.apply_ops_report(ops))
+    }
+}
@@ -0,0 +1,23 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Commonly used types, for glob import:
+//!
+//! ```text
+//! use crdt_tree::prelude::*;
+//!
+//! let mut r: UuidTreeReplica<StringMeta> = TreeReplica::new(Uuid::new_v4());
+//! ```
+
+pub use crate::aliases::{U64LogOpMove, U64OpMove, U64State, U64TreeReplica};
+#[cfg(feature = "uuid")]
+pub use crate::aliases::{UuidLogOpMove, UuidOpMove, UuidState, UuidTreeReplica};
+pub use crate::{
+    tree, ArcMeta, Clock, LogOpMove, NodeDef, OpMove, State, StringMeta, Tree, TreeBuilder, TreeId,
+    TreeMeta, TreeNode, TreeReplica,
+};
+#[cfg(feature = "uuid")]
+pub use uuid::Uuid;
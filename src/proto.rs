@@ -0,0 +1,455 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Protobuf messages and conversions, for replicating with non-Rust peers.
+//!
+//! The messages in this module match the schema in
+//! `proto/crdt_tree.proto`, with a `Proto` prefix, eg `ProtoOpMove` for
+//! the `OpMove` message.  IDs, metadata and actor IDs are carried as
+//! opaque bytes, converted via the `ProtoBytes` trait.
+//!
+//! Conversions to protobuf messages are infallible, via `From`.
+//! Conversions back use `TryFrom`, as a message from a peer may be
+//! missing fields or hold bytes that are not a valid value.
+//!
+//! Requires the `protobuf` feature.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::logopmove::Placement;
+use super::{Clock, LogOpMove, OpMove, State, Tree, TreeId, TreeMeta, TreeNode, VersionVector};
+use crdts::Actor;
+
+/// `ProtoBytes` is implemented by ID, metadata and actor types that can be
+/// carried in protobuf messages.
+pub trait ProtoBytes: Sized {
+    /// returns the value's byte encoding.
+    fn to_proto_bytes(&self) -> Vec<u8>;
+
+    /// returns the value for bytes, or None if they are not a valid encoding.
+    fn from_proto_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_proto_bytes_uint {
+    ($($t:ty),*) => {
+        $(
+            impl ProtoBytes for $t {
+                fn to_proto_bytes(&self) -> Vec<u8> {
+                    self.to_be_bytes().to_vec()
+                }
+
+                fn from_proto_bytes(bytes: &[u8]) -> Option<Self> {
+                    <[u8; std::mem::size_of::<$t>()]>::try_from(bytes)
+                        .ok()
+                        .map(<$t>::from_be_bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_proto_bytes_uint!(u8, u16, u32, u64, u128);
+
+impl ProtoBytes for String {
+    fn to_proto_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn from_proto_bytes(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl ProtoBytes for Vec<u8> {
+    fn to_proto_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_proto_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+/// Errors returned when converting a protobuf message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtoError {
+    /// a required message field is missing.
+    MissingField(&'static str),
+    /// a bytes field does not hold a valid value.
+    InvalidBytes(&'static str),
+}
+
+impl fmt::Display for ProtoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingField(name) => write!(f, "missing field: {}", name),
+            Self::InvalidBytes(name) => write!(f, "invalid bytes in field: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ProtoError {}
+
+/// A Lamport clock: a counter and the actor that owns it.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoClock {
+    /// the actor's ID
+    #[prost(bytes = "vec", tag = "1")]
+    pub actor_id: Vec<u8>,
+    /// the counter
+    #[prost(uint64, tag = "2")]
+    pub counter: u64,
+}
+
+/// A node's parent and metadata.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoTreeNode {
+    /// the parent's ID
+    #[prost(bytes = "vec", tag = "1")]
+    pub parent_id: Vec<u8>,
+    /// the node's metadata
+    #[prost(bytes = "vec", tag = "2")]
+    pub metadata: Vec<u8>,
+}
+
+/// Moves child_id to be a child of parent_id, with metadata.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoOpMove {
+    /// the op's timestamp
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: Option<ProtoClock>,
+    /// the new parent's ID
+    #[prost(bytes = "vec", tag = "2")]
+    pub parent_id: Vec<u8>,
+    /// the node's new metadata
+    #[prost(bytes = "vec", tag = "3")]
+    pub metadata: Vec<u8>,
+    /// the moved node's ID
+    #[prost(bytes = "vec", tag = "4")]
+    pub child_id: Vec<u8>,
+    /// wall-clock time claimed by the originating replica, in
+    /// milliseconds since the UNIX epoch
+    #[prost(uint64, optional, tag = "5")]
+    pub wall_time: Option<u64>,
+    /// the parent the moved node must have for the op to take effect, if
+    /// the op is conditional
+    #[prost(bytes = "vec", optional, tag = "6")]
+    pub expected_parent_id: Option<Vec<u8>>,
+    /// opaque context, eg the device that generated the op
+    #[prost(bytes = "vec", optional, tag = "7")]
+    pub provenance: Option<Vec<u8>>,
+}
+
+/// An OpMove as stored in the log, with the node's previous parent and
+/// metadata, if any.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoLogOpMove {
+    /// the op
+    #[prost(message, optional, tag = "1")]
+    pub op: Option<ProtoOpMove>,
+    /// the node's previous parent and metadata
+    #[prost(message, optional, tag = "2")]
+    pub oldp: Option<ProtoTreeNode>,
+    /// what applying the op did to the tree, if known: 0 placed its
+    /// node, 1 placed another, 2 ignored it
+    #[prost(uint32, optional, tag = "3")]
+    pub placement: Option<u32>,
+}
+
+/// A node in the tree, indexed by child_id.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoTreeEntry {
+    /// the node's ID
+    #[prost(bytes = "vec", tag = "1")]
+    pub child_id: Vec<u8>,
+    /// the node's parent and metadata
+    #[prost(message, optional, tag = "2")]
+    pub node: Option<ProtoTreeNode>,
+}
+
+/// A replica's state: the log, in descending timestamp order, and the
+/// current tree.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoState {
+    /// the log
+    #[prost(message, repeated, tag = "1")]
+    pub log: Vec<ProtoLogOpMove>,
+    /// the tree's nodes
+    #[prost(message, repeated, tag = "2")]
+    pub tree: Vec<ProtoTreeEntry>,
+    /// the timestamp the log was truncated before, if ever
+    #[prost(message, optional, tag = "3")]
+    pub truncated_before: Option<ProtoClock>,
+    /// the timestamp of the oldest entry collapsed by compaction, if any
+    #[prost(message, optional, tag = "4")]
+    pub compacted_from: Option<ProtoClock>,
+}
+
+/// The latest timestamp seen from each replica.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoVersionVector {
+    /// the latest timestamp from each actor
+    #[prost(message, repeated, tag = "1")]
+    pub clocks: Vec<ProtoClock>,
+}
+
+/// A list of ops, oldest first.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoOpMoveList {
+    /// the ops
+    #[prost(message, repeated, tag = "1")]
+    pub ops: Vec<ProtoOpMove>,
+}
+
+/// Reply to PushOps.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoPushOpsReply {}
+
+/// Reply to PullOpsSince: the ops the caller has not applied.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoPullOpsReply {
+    /// the ops, oldest first
+    #[prost(message, repeated, tag = "1")]
+    pub ops: Vec<ProtoOpMove>,
+    /// set if ops the caller is missing were truncated from the log, in
+    /// which case ops is empty and the caller needs a snapshot.
+    #[prost(bool, tag = "2")]
+    pub snapshot_required: bool,
+}
+
+/// Request for GetSnapshot.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoSnapshotRequest {}
+
+// decodes a bytes field.
+fn decode<T: ProtoBytes>(bytes: &[u8], field: &'static str) -> Result<T, ProtoError> {
+    T::from_proto_bytes(bytes).ok_or(ProtoError::InvalidBytes(field))
+}
+
+// returns a required message field.
+fn required<T>(value: Option<T>, field: &'static str) -> Result<T, ProtoError> {
+    value.ok_or(ProtoError::MissingField(field))
+}
+
+impl<A: Actor + ProtoBytes> From<&Clock<A>> for ProtoClock {
+    fn from(clock: &Clock<A>) -> Self {
+        Self {
+            actor_id: clock.actor_id().to_proto_bytes(),
+            counter: clock.counter(),
+        }
+    }
+}
+
+impl<A: Actor + ProtoBytes> TryFrom<ProtoClock> for Clock<A> {
+    type Error = ProtoError;
+
+    fn try_from(clock: ProtoClock) -> Result<Self, ProtoError> {
+        let actor_id = decode(&clock.actor_id, "actor_id")?;
+        Ok(Self::new(actor_id, Some(clock.counter)))
+    }
+}
+
+impl<A: Actor + ProtoBytes> From<&VersionVector<A>> for ProtoVersionVector {
+    fn from(vv: &VersionVector<A>) -> Self {
+        Self {
+            clocks: vv.clocks().map(|c| c.into()).collect(),
+        }
+    }
+}
+
+impl<A: Actor + ProtoBytes> TryFrom<ProtoVersionVector> for VersionVector<A> {
+    type Error = ProtoError;
+
+    fn try_from(vv: ProtoVersionVector) -> Result<Self, ProtoError> {
+        let clocks = vv
+            .clocks
+            .into_iter()
+            .map(Clock::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(clocks.iter().collect())
+    }
+}
+
+impl<ID, TM> From<&TreeNode<ID, TM>> for ProtoTreeNode
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+{
+    fn from(node: &TreeNode<ID, TM>) -> Self {
+        Self {
+            parent_id: node.parent_id().to_proto_bytes(),
+            metadata: node.metadata().to_proto_bytes(),
+        }
+    }
+}
+
+impl<ID, TM> TryFrom<ProtoTreeNode> for TreeNode<ID, TM>
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+{
+    type Error = ProtoError;
+
+    fn try_from(node: ProtoTreeNode) -> Result<Self, ProtoError> {
+        Ok(Self::new(
+            decode(&node.parent_id, "parent_id")?,
+            decode(&node.metadata, "metadata")?,
+        ))
+    }
+}
+
+impl<ID, TM, A> From<&OpMove<ID, TM, A>> for ProtoOpMove
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    fn from(op: &OpMove<ID, TM, A>) -> Self {
+        Self {
+            timestamp: Some(op.timestamp().into()),
+            parent_id: op.parent_id().to_proto_bytes(),
+            metadata: op.metadata().to_proto_bytes(),
+            child_id: op.child_id().to_proto_bytes(),
+            wall_time: op.wall_time(),
+            expected_parent_id: op.expected_parent_id().map(|id| id.to_proto_bytes()),
+            provenance: op.provenance().map(|p| p.to_vec()),
+        }
+    }
+}
+
+impl<ID, TM, A> TryFrom<ProtoOpMove> for OpMove<ID, TM, A>
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    type Error = ProtoError;
+
+    fn try_from(op: ProtoOpMove) -> Result<Self, ProtoError> {
+        let mut result = Self::new(
+            Clock::try_from(required(op.timestamp, "timestamp")?)?,
+            decode(&op.parent_id, "parent_id")?,
+            decode(&op.metadata, "metadata")?,
+            decode(&op.child_id, "child_id")?,
+        );
+        result.set_wall_time(op.wall_time);
+        if let Some(expected) = op.expected_parent_id {
+            result.set_expected_parent_id(Some(decode(&expected, "expected_parent_id")?));
+        }
+        result.set_provenance(op.provenance);
+        Ok(result)
+    }
+}
+
+impl<ID, TM, A> From<&LogOpMove<ID, TM, A>> for ProtoLogOpMove
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    fn from(entry: &LogOpMove<ID, TM, A>) -> Self {
+        let mut op = OpMove::new(
+            entry.timestamp().clone(),
+            entry.parent_id().clone(),
+            entry.metadata().clone(),
+            entry.child_id().clone(),
+        );
+        op.set_wall_time(entry.wall_time());
+        op.set_expected_parent_id(entry.expected_parent_id().cloned());
+        op.set_provenance(entry.provenance().map(|p| p.to_vec()));
+        Self {
+            op: Some((&op).into()),
+            oldp: entry.oldp().as_ref().map(|n| n.into()),
+            placement: entry.placement().map(|p| match p {
+                Placement::Placed => 0,
+                Placement::Replaced => 1,
+                Placement::Ignored => 2,
+            }),
+        }
+    }
+}
+
+impl<ID, TM, A> TryFrom<ProtoLogOpMove> for LogOpMove<ID, TM, A>
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    type Error = ProtoError;
+
+    fn try_from(entry: ProtoLogOpMove) -> Result<Self, ProtoError> {
+        let op = OpMove::try_from(required(entry.op, "op")?)?;
+        let oldp = match entry.oldp {
+            Some(n) => Some(TreeNode::try_from(n)?),
+            None => None,
+        };
+        let mut log = Self::new(op, oldp);
+        // an unknown placement is dropped, which only leaves the entry's
+        // nodes unshared.
+        log.set_placement(match entry.placement {
+            Some(0) => Some(Placement::Placed),
+            Some(1) => Some(Placement::Replaced),
+            Some(2) => Some(Placement::Ignored),
+            _ => None,
+        });
+        Ok(log)
+    }
+}
+
+impl<ID, TM, A> From<&State<ID, TM, A>> for ProtoState
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    fn from(state: &State<ID, TM, A>) -> Self {
+        Self {
+            log: state.log().iter().map(|e| e.into()).collect(),
+            tree: state
+                .tree()
+                .iter()
+                .map(|(child_id, node)| ProtoTreeEntry {
+                    child_id: child_id.to_proto_bytes(),
+                    node: Some(node.into()),
+                })
+                .collect(),
+            truncated_before: state.truncated_before().map(|t| t.into()),
+            compacted_from: state.compacted_from().map(|t| t.into()),
+        }
+    }
+}
+
+impl<ID, TM, A> TryFrom<ProtoState> for State<ID, TM, A>
+where
+    ID: TreeId + ProtoBytes,
+    TM: TreeMeta + ProtoBytes,
+    A: Actor + ProtoBytes,
+{
+    type Error = ProtoError;
+
+    fn try_from(state: ProtoState) -> Result<Self, ProtoError> {
+        let log = state
+            .log
+            .into_iter()
+            .map(LogOpMove::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tree = Tree::new();
+        for entry in state.tree {
+            let child_id = decode(&entry.child_id, "child_id")?;
+            let node = TreeNode::try_from(required(entry.node, "node")?)?;
+            tree.add_node(child_id, node);
+        }
+        let mut restored = Self::from((log, tree));
+        if let Some(t) = state.truncated_before {
+            restored.set_truncated_before(Some(Clock::try_from(t)?));
+        }
+        if let Some(t) = state.compacted_from {
+            restored.set_compacted_from(Some(Clock::try_from(t)?));
+        }
+        Ok(restored)
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Tree, TreeId, TreeMeta};
+
+/// `Quotas` limits the shape of a tree, as a guard against pathological
+/// trees, eg in multi-tenant services.  See `State::set_quotas`.
+///
+/// A move that would exceed a quota is ignored.  Quotas are checked as
+/// each op is applied, in timestamp order, so every replica with the same
+/// quotas ignores the same ops.  Every replica of a tree must use the
+/// same quotas, else they may not converge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Quotas {
+    max_depth: Option<usize>,
+    max_children: Option<usize>,
+    max_nodes: Option<usize>,
+}
+
+impl Quotas {
+    /// returns quotas with no limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// limits the depth of nodes, ie their number of ancestors.  A node
+    /// whose parent is not itself a node has depth 1.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// limits the number of children of each parent.
+    pub fn with_max_children(mut self, max_children: usize) -> Self {
+        self.max_children = Some(max_children);
+        self
+    }
+
+    /// limits the number of nodes in the tree.
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// returns the max depth, if limited.
+    #[inline]
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// returns the max children per parent, if limited.
+    #[inline]
+    pub fn max_children(&self) -> Option<usize> {
+        self.max_children
+    }
+
+    /// returns the max number of nodes, if limited.
+    #[inline]
+    pub fn max_nodes(&self) -> Option<usize> {
+        self.max_nodes
+    }
+
+    // returns true if moving child_id under parent_id keeps tree within
+    // the quotas.  A node staying under its parent, eg renamed, is always
+    // admitted.
+    pub(crate) fn admits<ID: TreeId, TM: TreeMeta>(
+        &self,
+        tree: &Tree<ID, TM>,
+        child_id: &ID,
+        parent_id: &ID,
+    ) -> bool {
+        let current = tree.find(child_id);
+        if matches!(current, Some(n) if n.parent_id() == parent_id) {
+            return true;
+        }
+        if let Some(max_nodes) = self.max_nodes {
+            if current.is_none() && tree.num_nodes() >= max_nodes {
+                return false;
+            }
+        }
+        if let Some(max_children) = self.max_children {
+            if tree.num_children(parent_id) >= max_children {
+                return false;
+            }
+        }
+        if let Some(max_depth) = self.max_depth {
+            let depth = tree.depth(parent_id).unwrap_or(0) + 1;
+            // the subtree under child_id moves with it.  its height is
+            // indexed, so this costs O(depth).
+            if depth + tree.height(child_id) > max_depth {
+                return false;
+            }
+        }
+        true
+    }
+}
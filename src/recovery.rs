@@ -0,0 +1,183 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Crash recovery from a snapshot file plus a write-ahead log.
+//!
+//! A replica that applies ops through a `Wal` periodically calls
+//! `checkpoint`, which saves a snapshot and then empties the log.  At
+//! startup, `recover` loads the snapshot, replays the log entries after
+//! it, and verifies the tree's invariants.
+//!
+//! Snapshots are written to a temporary file which is then renamed over
+//! the previous one, so a crash leaves either the old or the new snapshot
+//! intact.  A crash between saving the snapshot and emptying the log is
+//! also harmless, as logged ops already reflected in the snapshot are
+//! skipped.
+//!
+//! Damaged files are reported with a `RecoveryError` rather than a panic.
+//! In particular, a crash during an append leaves an incomplete record at
+//! the end of the log, reported as `WalError::TruncatedTail`, which can
+//! be discarded with `Wal::truncate` before recovering again.
+//!
+//! Requires the `wal` feature.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::codec::{self, CodecError};
+use super::{
+    InvariantViolation, Resolve, Snapshot, TieBreak, TreeId, TreeMeta, TreeReplica, Wal, WalError,
+};
+use crdts::Actor;
+
+/// Errors returned by `recover`.
+#[derive(Debug)]
+pub enum RecoveryError<ID> {
+    /// the snapshot file could not be read.
+    Io(io::Error),
+    /// the snapshot file does not match its checksum.
+    SnapshotChecksum,
+    /// the snapshot could not be decoded.
+    Snapshot(CodecError),
+    /// the log could not be read, or is damaged.
+    Wal(WalError),
+    /// the recovered tree is inconsistent.
+    Invariant(InvariantViolation<ID>),
+}
+
+impl<ID: fmt::Debug> fmt::Display for RecoveryError<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "snapshot i/o error: {}", e),
+            Self::SnapshotChecksum => write!(f, "snapshot fails checksum"),
+            Self::Snapshot(e) => write!(f, "snapshot codec error: {}", e),
+            Self::Wal(e) => write!(f, "{}", e),
+            Self::Invariant(e) => write!(f, "recovered tree is invalid: {}", e),
+        }
+    }
+}
+
+impl<ID: fmt::Debug> std::error::Error for RecoveryError<ID> {}
+
+impl<ID> From<WalError> for RecoveryError<ID> {
+    fn from(e: WalError) -> Self {
+        Self::Wal(e)
+    }
+}
+
+/// saves snapshot to path, replacing any previous snapshot atomically.
+pub fn save_snapshot<ID, TM, A>(path: &Path, snapshot: &Snapshot<ID, TM, A>) -> Result<(), WalError>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    let payload = codec::encode(snapshot)?;
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(&crc32fast::hash(&payload).to_le_bytes())?;
+        file.write_all(&payload)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    sync_parent(path)?;
+    Ok(())
+}
+
+// syncs the directory containing path, so that a rename into it is
+// durable.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// loads the snapshot saved at path, or None if there is none.
+pub fn load_snapshot<ID, TM, A>(
+    path: &Path,
+) -> Result<Option<Snapshot<ID, TM, A>>, RecoveryError<ID>>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(RecoveryError::Io(e)),
+    };
+    if bytes.len() < 4 {
+        return Err(RecoveryError::SnapshotChecksum);
+    }
+    let (crc, payload) = bytes.split_at(4);
+    if crc32fast::hash(payload).to_le_bytes() != crc {
+        return Err(RecoveryError::SnapshotChecksum);
+    }
+    codec::decode(payload)
+        .map(Some)
+        .map_err(RecoveryError::Snapshot)
+}
+
+/// saves a snapshot of replica to path, then empties wal.
+pub fn checkpoint<ID, TM, A, T, R>(
+    replica: &TreeReplica<ID, TM, A, T, R>,
+    checkpoint_id: u64,
+    path: &Path,
+    wal: &mut Wal,
+) -> Result<(), WalError>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + fmt::Debug + Serialize + DeserializeOwned,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    save_snapshot(path, &replica.snapshot(checkpoint_id))?;
+    wal.reset()
+}
+
+/// recovers a replica from the snapshot saved at path and the ops in wal.
+///
+/// If there is no snapshot, a replica is created for actor and every
+/// logged op is replayed.  Otherwise actor is ignored, and only the
+/// logged ops that are not reflected in the snapshot are replayed.
+///
+/// The recovered tree's invariants are checked before it is returned.
+pub fn recover<ID, TM, A>(
+    actor: A,
+    path: &Path,
+    wal: &Wal,
+) -> Result<TreeReplica<ID, TM, A>, RecoveryError<ID>>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + fmt::Debug + Serialize + DeserializeOwned,
+{
+    let mut replica = match load_snapshot(path)? {
+        Some(snapshot) => TreeReplica::restore(snapshot, vec![]),
+        None => TreeReplica::new(actor),
+    };
+    // ops in the snapshot's log, or older than it was truncated before,
+    // are ignored by the replica.
+    replica.apply_ops(wal.read_ops()?);
+    replica
+        .tree()
+        .check_invariants()
+        .map_err(RecoveryError::Invariant)?;
+    Ok(replica)
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::{
+    CausalOpMove, Clock, DriftGuard, LogLimit, LogOpMove, Quotas, Tree, TreeId, TreeMeta,
+    VersionVector,
+};
+use crdts::Actor;
+
+/// the current `ReplicaExport` format version.
+pub const EXPORT_VERSION: u16 = 1;
+
+/// `ReplicaExport` is everything needed to resume a `TreeReplica`
+/// elsewhere, eg from a backup or on another device: its tree and log,
+/// its clocks, causal ops awaiting their dependencies, and its local
+/// settings.  See `TreeReplica::export`.
+///
+/// Not included are the outbox, spilled log entries, the conflict handler
+/// and subscribers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicaExport<ID: TreeId, TM: TreeMeta, A: Actor> {
+    pub(crate) version: u16,
+    pub(crate) tree: Tree<ID, TM>,
+    pub(crate) log: Vec<LogOpMove<ID, TM, A>>,
+    pub(crate) time: Clock<A>,
+    pub(crate) latest_time_by_replica: VersionVector<A>,
+    pub(crate) truncated_before: Option<Clock<A>>,
+    pub(crate) compacted_from: Option<Clock<A>>,
+    pub(crate) pending: Vec<CausalOpMove<ID, TM, A>>,
+    pub(crate) undo_stack: Vec<Clock<A>>,
+    pub(crate) redo_stack: Vec<Clock<A>>,
+    pub(crate) wall_clock: bool,
+    pub(crate) provenance: Option<Vec<u8>>,
+    pub(crate) drift_guard: Option<DriftGuard>,
+    pub(crate) tombstones: Vec<A>,
+    pub(crate) log_limit: Option<LogLimit>,
+    pub(crate) quotas: Quotas,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> ReplicaExport<ID, TM, A> {
+    /// returns the format version the export was made with.
+    #[inline]
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// returns the exported replica's clock.
+    #[inline]
+    pub fn time(&self) -> &Clock<A> {
+        &self.time
+    }
+}
+
+/// An error returned by `TreeReplica::import`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// the export was made with a format version this build does not
+    /// support.
+    UnsupportedVersion(u16),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(v) => write!(f, "unsupported export version: {}", v),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
@@ -0,0 +1,196 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! An actor-style handle to a replica owned by a background task.
+//!
+//! `ReplicaHandle::spawn` moves a `TreeReplica` into a tokio task.  The
+//! handle, which is cheap to clone and share between tasks, sends each
+//! request to the task over a channel, and the task runs requests one at
+//! a time, in the order received, and answers each via a oneshot channel.
+//! So no locking is needed, and reads never observe a partially applied
+//! op.
+//!
+//! ```text
+//! let handle = ReplicaHandle::spawn(TreeReplica::new(actor));
+//! let op = handle.move_node(parent_id, metadata, child_id).await?;
+//! let n = handle.query(|r| r.tree().num_nodes()).await?;
+//! let ops = handle.update(|r| r.subscribe()).await?;
+//! ```
+//!
+//! The task runs until every handle is dropped, or ::stop() is called.
+//!
+//! Requires the `tokio` feature.
+
+use std::fmt;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{
+    ActorOrder, Kleppmann, OpMove, Resolve, TieBreak, TreeId, TreeMeta, TreeReplica, TreeSnapshot,
+};
+use crdts::Actor;
+
+// number of requests queued before senders wait.
+const CAPACITY: usize = 256;
+
+/// Errors returned by `ReplicaHandle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleError {
+    /// the replica's task has stopped, eg after ::stop() was called.
+    Stopped,
+}
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stopped => write!(f, "replica task has stopped"),
+        }
+    }
+}
+
+impl std::error::Error for HandleError {}
+
+// a request run by the replica's task.
+type Job<ID, TM, A, T, R> = Box<dyn FnOnce(&mut TreeReplica<ID, TM, A, T, R>) + Send>;
+
+enum Command<ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>> {
+    Run(Job<ID, TM, A, T, R>),
+    Stop(oneshot::Sender<TreeReplica<ID, TM, A, T, R>>),
+}
+
+/// `ReplicaHandle` is a handle to a `TreeReplica` owned by a background
+/// task.  See the module docs.
+pub struct ReplicaHandle<
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor,
+    T: TieBreak<A> = ActorOrder,
+    R: Resolve<ID, TM, A> = Kleppmann,
+> {
+    sender: mpsc::Sender<Command<ID, TM, A, T, R>>,
+}
+
+impl<ID, TM, A, T, R> ReplicaHandle<ID, TM, A, T, R>
+where
+    ID: TreeId + Send + Sync + 'static,
+    TM: TreeMeta + Send + Sync + 'static,
+    A: Actor + fmt::Debug + Send + Sync + 'static,
+    T: TieBreak<A> + Send + 'static,
+    R: Resolve<ID, TM, A> + Send + 'static,
+{
+    /// moves replica into a new task, on the current tokio runtime, and
+    /// returns a handle to it.
+    ///
+    /// Panics if called outside a tokio runtime.
+    pub fn spawn(replica: TreeReplica<ID, TM, A, T, R>) -> Self {
+        let (sender, mut receiver) = mpsc::channel(CAPACITY);
+        tokio::spawn(async move {
+            let mut replica = replica;
+            while let Some(command) = receiver.recv().await {
+                match command {
+                    Command::Run(job) => job(&mut replica),
+                    Command::Stop(reply) => {
+                        // the caller may have given up waiting.
+                        let _ = reply.send(replica);
+                        return;
+                    }
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    /// runs f with the replica, returning its result.
+    pub async fn query<O, F>(&self, f: F) -> Result<O, HandleError>
+    where
+        F: FnOnce(&TreeReplica<ID, TM, A, T, R>) -> O + Send + 'static,
+        O: Send + 'static,
+    {
+        self.update(move |r| f(r)).await
+    }
+
+    /// runs f with the replica, mutably, returning its result.
+    pub async fn update<O, F>(&self, f: F) -> Result<O, HandleError>
+    where
+        F: FnOnce(&mut TreeReplica<ID, TM, A, T, R>) -> O + Send + 'static,
+        O: Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        let job: Job<ID, TM, A, T, R> = Box::new(move |r| {
+            // the caller may have given up waiting.
+            let _ = reply.send(f(r));
+        });
+        self.send(Command::Run(job)).await?;
+        response.await.map_err(|_| HandleError::Stopped)
+    }
+
+    /// applies op.  See `TreeReplica::apply_op`.
+    pub async fn apply_op(&self, op: OpMove<ID, TM, A>) -> Result<(), HandleError> {
+        self.update(move |r| r.apply_op(op)).await
+    }
+
+    /// applies ops, without interleaving other requests.
+    pub async fn apply_ops(&self, ops: Vec<OpMove<ID, TM, A>>) -> Result<(), HandleError> {
+        self.update(move |r| r.apply_ops(ops)).await
+    }
+
+    /// generates an op moving child_id under parent_id, applies it and
+    /// returns it, eg for sending to peers.
+    pub async fn move_node(
+        &self,
+        parent_id: ID,
+        metadata: TM,
+        child_id: ID,
+    ) -> Result<OpMove<ID, TM, A>, HandleError> {
+        self.update(move |r| {
+            let op = r.opmove(parent_id, metadata, child_id);
+            r.apply_op(op.clone());
+            op
+        })
+        .await
+    }
+
+    /// returns a read-only view of the tree.  See
+    /// `TreeReplica::read_snapshot`.
+    pub async fn read_snapshot(&self) -> Result<TreeSnapshot<ID, TM, A>, HandleError> {
+        self.query(|r| r.read_snapshot()).await
+    }
+
+    /// stops the task once requests already sent have run, and returns
+    /// the replica.  Other handles then return `HandleError::Stopped`.
+    pub async fn stop(self) -> Result<TreeReplica<ID, TM, A, T, R>, HandleError> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Stop(reply)).await?;
+        response.await.map_err(|_| HandleError::Stopped)
+    }
+
+    // sends command to the task.
+    async fn send(&self, command: Command<ID, TM, A, T, R>) -> Result<(), HandleError> {
+        self.sender
+            .send(command)
+            .await
+            .map_err(|_| HandleError::Stopped)
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>> Clone
+    for ReplicaHandle<ID, TM, A, T, R>
+{
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>> fmt::Debug
+    for ReplicaHandle<ID, TM, A, T, R>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplicaHandle")
+            .field("stopped", &self.sender.is_closed())
+            .finish()
+    }
+}
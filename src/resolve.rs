@@ -0,0 +1,92 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Strategies for resolving moves that conflict with the tree.
+//!
+//! By default, as in the paper, a move that would introduce a cycle is
+//! ignored, and a move of a node already in the tree replaces its parent
+//! and metadata, ie the last writer wins.  `MergeMetadata` merges
+//! metadata that is itself a CRDT instead.
+//!
+//! A `State` or `TreeReplica` may be given another strategy as its last
+//! type parameter, eg
+//!
+//! ```text
+//! let mut r: TreeReplica<u64, String, u8, ActorOrder, MyResolve> = TreeReplica::new(1);
+//! ```
+//!
+//! A strategy decides from the tree and the op alone, and ops are
+//! applied in the same order everywhere, so replicas using the same
+//! strategy converge.  Every replica of a tree must use the same strategy.
+
+use std::fmt::Debug;
+
+use super::{LogOpMove, MergeMeta, Tree, TreeId, TreeMeta, TreeNode};
+use crdts::Actor;
+
+/// `Resolve` decides how a move that conflicts with the tree is resolved.
+///
+/// The methods are called with the tree as it is before op, and must
+/// only depend on their arguments.
+pub trait Resolve<ID: TreeId, TM: TreeMeta, A: Actor>:
+    Debug + Clone + Default + PartialEq + Eq
+{
+    /// returns a parent to move op's child under, with op's metadata,
+    /// instead of op's parent, which would introduce a cycle, or None to
+    /// ignore op.
+    ///
+    /// op is ignored anyway if the returned parent would also introduce
+    /// a cycle.  The default ignores op.
+    fn on_cycle(_tree: &Tree<ID, TM>, _op: &LogOpMove<ID, TM, A>) -> Option<ID> {
+        None
+    }
+
+    /// returns true if op, which moves a node already in the tree, should
+    /// replace current, the node's parent and metadata, or false to
+    /// ignore op.
+    ///
+    /// The default replaces it, ie the last writer wins.
+    fn replaces(
+        _tree: &Tree<ID, TM>,
+        _op: &LogOpMove<ID, TM, A>,
+        _current: &TreeNode<ID, TM>,
+    ) -> bool {
+        true
+    }
+
+    /// returns the metadata of op's child when op moves it under current's
+    /// parent, ie leaves the child where it is, or None to replace
+    /// current's metadata with op's.
+    ///
+    /// The default replaces it, ie the last writer wins.
+    fn merge_metadata(_current: &TreeNode<ID, TM>, _op: &LogOpMove<ID, TM, A>) -> Option<TM> {
+        None
+    }
+}
+
+/// `Kleppmann` resolves conflicts as in the paper: moves introducing a
+/// cycle are ignored, and the last writer wins.  This is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Kleppmann;
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Resolve<ID, TM, A> for Kleppmann {}
+
+/// `MergeMetadata` resolves conflicts as `Kleppmann` does, except that an
+/// op leaving a node under the same parent merges its metadata into the
+/// node's, rather than replacing it.  For metadata that is itself a CRDT,
+/// see `MergeMeta`.
+///
+/// Moving a node under another parent replaces its metadata.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeMetadata;
+
+impl<ID: TreeId, TM: MergeMeta, A: Actor> Resolve<ID, TM, A> for MergeMetadata {
+    fn merge_metadata(current: &TreeNode<ID, TM>, op: &LogOpMove<ID, TM, A>) -> Option<TM> {
+        let mut metadata = current.metadata().clone();
+        metadata.merge(op.metadata().clone());
+        Some(metadata)
+    }
+}
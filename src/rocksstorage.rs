@@ -0,0 +1,345 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+
+use rocksdb::{ColumnFamily, Direction, IteratorMode, Options, WriteBatch, WriteOptions, DB};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::codec::{self, CodecError};
+use super::{Clock, OpMove, Snapshot, Storage, Tree, TreeId, TreeMeta, TreeNode};
+use crdts::Actor;
+
+const CF_OPS: &str = "ops";
+const CF_TREE: &str = "tree";
+const CF_META: &str = "meta";
+const CF_SNAPSHOTS: &str = "snapshots";
+
+/// Errors returned by `RocksStorage`.
+#[derive(Debug)]
+pub enum RocksStorageError {
+    /// the database returned an error.
+    Rocks(rocksdb::Error),
+    /// a stored value could not be encoded or decoded.
+    Codec(CodecError),
+}
+
+impl fmt::Display for RocksStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rocks(e) => write!(f, "rocksdb error: {}", e),
+            Self::Codec(e) => write!(f, "codec error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RocksStorageError {}
+
+impl From<rocksdb::Error> for RocksStorageError {
+    fn from(e: rocksdb::Error) -> Self {
+        Self::Rocks(e)
+    }
+}
+
+impl From<CodecError> for RocksStorageError {
+    fn from(e: CodecError) -> Self {
+        Self::Codec(e)
+    }
+}
+
+/// `RocksStorage` is a `Storage` backed by a RocksDB database, for
+/// replicas with very large logs and trees.
+///
+/// Data is kept in four column families:
+///
+/// * `ops`: ops keyed by (counter, actor), so that key order follows
+///   timestamp order for range scans and range deletes.
+/// * `tree`: the latest snapshot's tree triples, keyed by child id.
+/// * `meta`: an index from metadata to child ids, see `find_by_meta`.
+/// * `snapshots`: the latest snapshot's log and clocks, keyed by
+///   checkpoint id.
+///
+/// Only the latest snapshot is kept.  Storing a snapshot writes just the
+/// triples that changed since the previous one, so the tree is not
+/// rewritten at every checkpoint.  Nodes can be looked up with
+/// `find_node` and `find_by_meta` without loading the tree.
+///
+/// If `sync` is set, every write is synced to disk before returning, so
+/// that an op is durable before it is applied.
+///
+/// Requires the `rocksdb-storage` feature.
+pub struct RocksStorage {
+    db: DB,
+    sync: bool,
+}
+
+impl RocksStorage {
+    /// opens or creates a database at path.
+    pub fn open<P: AsRef<Path>>(path: P, sync: bool) -> Result<Self, RocksStorageError> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, path, [CF_OPS, CF_TREE, CF_META, CF_SNAPSHOTS])?;
+        Ok(Self { db, sync })
+    }
+
+    /// returns the underlying database
+    #[inline]
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    /// flushes all column families and the write-ahead log to disk.
+    pub fn flush(&self) -> Result<(), RocksStorageError> {
+        for name in &[CF_OPS, CF_TREE, CF_META, CF_SNAPSHOTS] {
+            self.db.flush_cf(self.cf(name))?;
+        }
+        self.db.flush_wal(true)?;
+        Ok(())
+    }
+
+    /// returns the node for child_id in the latest snapshot's tree.
+    pub fn find_node<ID, TM>(
+        &self,
+        child_id: &ID,
+    ) -> Result<Option<TreeNode<ID, TM>>, RocksStorageError>
+    where
+        ID: TreeId + Serialize + DeserializeOwned,
+        TM: TreeMeta + Serialize + DeserializeOwned,
+    {
+        match self.db.get_cf(self.cf(CF_TREE), to_bytes(child_id)?)? {
+            Some(value) => Ok(Some(from_bytes(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// returns ids of nodes in the latest snapshot's tree whose metadata
+    /// equals meta.
+    pub fn find_by_meta<ID, TM>(&self, meta: &TM) -> Result<Vec<ID>, RocksStorageError>
+    where
+        ID: TreeId + DeserializeOwned,
+        TM: TreeMeta + Serialize,
+    {
+        let prefix = meta_prefix(meta)?;
+        let mut ids = vec![];
+        let mode = IteratorMode::From(&prefix, Direction::Forward);
+        for entry in self.db.iterator_cf(self.cf(CF_META), mode) {
+            let (key, _) = entry?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            ids.push(from_bytes(&key[prefix.len()..])?);
+        }
+        Ok(ids)
+    }
+
+    // returns the column family named name, which is created by open.
+    fn cf(&self, name: &str) -> &ColumnFamily {
+        self.db
+            .cf_handle(name)
+            .expect("column family is created by open")
+    }
+
+    // writes batch, syncing if sync is set.
+    fn write(&self, batch: WriteBatch) -> Result<(), RocksStorageError> {
+        let mut opts = WriteOptions::default();
+        opts.set_sync(self.sync);
+        self.db.write_opt(batch, &opts)?;
+        Ok(())
+    }
+}
+
+// encodes a key or triple.  unlike codec::encode there is no header, so
+// that keys sort by their content.
+fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecError> {
+    bincode::serialize(value).map_err(|e| CodecError::Payload(e.to_string()))
+}
+
+// decodes a key or triple, as returned by to_bytes.
+fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+    bincode::deserialize(bytes).map_err(|e| CodecError::Payload(e.to_string()))
+}
+
+// returns the key for timestamp.
+fn op_key<A: Actor + Serialize>(timestamp: &Clock<A>) -> Result<Vec<u8>, CodecError> {
+    let mut key = timestamp.counter().to_be_bytes().to_vec();
+    key.extend(to_bytes(timestamp.actor_id())?);
+    Ok(key)
+}
+
+// returns the timestamp for key, as returned by op_key.
+fn op_key_timestamp<A: Actor + DeserializeOwned>(key: &[u8]) -> Result<Clock<A>, CodecError> {
+    if key.len() < 8 {
+        return Err(CodecError::Truncated);
+    }
+    let mut counter = [0u8; 8];
+    counter.copy_from_slice(&key[..8]);
+    Ok(Clock::new(
+        from_bytes(&key[8..])?,
+        Some(u64::from_be_bytes(counter)),
+    ))
+}
+
+// returns the prefix of meta index keys for meta.  The encoded metadata
+// is length-prefixed so that one value's keys are never a prefix of
+// another's.
+fn meta_prefix<TM: Serialize>(meta: &TM) -> Result<Vec<u8>, CodecError> {
+    let meta = to_bytes(meta)?;
+    let mut prefix = (meta.len() as u64).to_be_bytes().to_vec();
+    prefix.extend(meta);
+    Ok(prefix)
+}
+
+// returns the meta index key for a node.
+fn meta_key<ID: Serialize, TM: Serialize>(child_id: &ID, meta: &TM) -> Result<Vec<u8>, CodecError> {
+    let mut key = meta_prefix(meta)?;
+    key.extend(to_bytes(child_id)?);
+    Ok(key)
+}
+
+impl<ID, TM, A> Storage<ID, TM, A> for RocksStorage
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    type Error = RocksStorageError;
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot<ID, TM, A>) -> Result<(), RocksStorageError> {
+        let (cf_tree, cf_meta) = (self.cf(CF_TREE), self.cf(CF_META));
+        let mut batch = WriteBatch::default();
+
+        // remove stored triples that are gone or changed.
+        let mut unchanged = HashSet::new();
+        for entry in self.db.iterator_cf(cf_tree, IteratorMode::Start) {
+            let (key, value) = entry?;
+            let child_id: ID = from_bytes(&key)?;
+            // triples are compared by encoding, as TM need not be PartialEq.
+            match snapshot.tree().find(&child_id) {
+                Some(node) if to_bytes(node)?[..] == value[..] => {
+                    unchanged.insert(child_id);
+                }
+                _ => {
+                    let stored: TreeNode<ID, TM> = from_bytes(&value)?;
+                    batch.delete_cf(cf_tree, &key);
+                    batch.delete_cf(cf_meta, meta_key(&child_id, stored.metadata())?);
+                }
+            }
+        }
+        // write triples that are new or changed.
+        for (child_id, node) in snapshot.tree().iter() {
+            if !unchanged.contains(child_id) {
+                batch.put_cf(cf_tree, to_bytes(child_id)?, to_bytes(node)?);
+                batch.put_cf(cf_meta, meta_key(child_id, node.metadata())?, b"");
+            }
+        }
+
+        // the log and clocks are stored without the tree.
+        let header = Snapshot::new(
+            snapshot.id(),
+            Tree::new(),
+            snapshot.log().to_vec(),
+            snapshot.truncated_before().cloned(),
+            snapshot.time().clone(),
+            snapshot.latest_time_by_replica().clone(),
+        );
+        let cf_snapshots = self.cf(CF_SNAPSHOTS);
+        let id = snapshot.id().to_be_bytes();
+        batch.delete_range_cf(cf_snapshots, &0u64.to_be_bytes(), &id);
+        batch.put_cf(cf_snapshots, id, codec::encode(&header)?);
+        self.write(batch)
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot<ID, TM, A>>, RocksStorageError> {
+        let header: Snapshot<ID, TM, A> = match self
+            .db
+            .iterator_cf(self.cf(CF_SNAPSHOTS), IteratorMode::End)
+            .next()
+        {
+            Some(entry) => codec::decode(&entry?.1)?,
+            None => return Ok(None),
+        };
+        let mut tree = Tree::new();
+        for entry in self.db.iterator_cf(self.cf(CF_TREE), IteratorMode::Start) {
+            let (key, value) = entry?;
+            tree.add_node(from_bytes(&key)?, from_bytes(&value)?);
+        }
+        let id = header.id();
+        let (_, log, truncated_before, time, latest_time_by_replica) = header.into_parts();
+        Ok(Some(Snapshot::new(
+            id,
+            tree,
+            log,
+            truncated_before,
+            time,
+            latest_time_by_replica,
+        )))
+    }
+
+    fn append_op(&mut self, op: &OpMove<ID, TM, A>) -> Result<(), RocksStorageError> {
+        let mut batch = WriteBatch::default();
+        batch.put_cf(self.cf(CF_OPS), op_key(op.timestamp())?, codec::encode(op)?);
+        self.write(batch)
+    }
+
+    fn scan_ops(
+        &self,
+        from: Bound<&Clock<A>>,
+        to: Bound<&Clock<A>>,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, RocksStorageError> {
+        // scan whole counters, as actor encodings need not sort like
+        // actors, then filter on exact timestamps.
+        let start = match from {
+            Bound::Included(t) | Bound::Excluded(t) => t.counter().to_be_bytes().to_vec(),
+            Bound::Unbounded => vec![],
+        };
+        let mode = IteratorMode::From(&start, Direction::Forward);
+        let mut ops = vec![];
+        for entry in self.db.iterator_cf(self.cf(CF_OPS), mode) {
+            let (key, value) = entry?;
+            let timestamp: Clock<A> = op_key_timestamp(&key)?;
+            if matches!(to, Bound::Included(t) | Bound::Excluded(t) if timestamp.counter() > t.counter())
+            {
+                break;
+            }
+            if (from, to).contains(&timestamp) {
+                ops.push(codec::decode(&value)?);
+            }
+        }
+        ops.sort_by(|a: &OpMove<ID, TM, A>, b| a.timestamp().cmp(b.timestamp()));
+        Ok(ops)
+    }
+
+    fn remove_ops_before(&mut self, timestamp: &Clock<A>) -> Result<usize, RocksStorageError> {
+        // ops with lower counters are removed by a single range delete.
+        // Only keys are read, to count them.
+        let cf = self.cf(CF_OPS);
+        let end = timestamp.counter().to_be_bytes();
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        for entry in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, _) = entry?;
+            if key[..] >= end[..] {
+                let t: Clock<A> = op_key_timestamp(&key)?;
+                if t.counter() > timestamp.counter() {
+                    break;
+                }
+                if &t < timestamp {
+                    batch.delete_cf(cf, &key);
+                    removed += 1;
+                }
+            } else {
+                removed += 1;
+            }
+        }
+        batch.delete_range_cf(cf, &[][..], &end[..]);
+        self.write(batch)?;
+        Ok(removed)
+    }
+}
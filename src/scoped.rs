@@ -0,0 +1,175 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Debug;
+
+use super::{Clock, Resolve, State, TieBreak, TreeId, TreeMeta, TreeNode, TreeReplica};
+use crdts::Actor;
+
+/// `UnstableSubtree` is returned by `State::rm_subtree_checked` when a
+/// node in the subtree was moved by an op after the causal threshold, so
+/// may yet be moved back out of it, eg by undo and redo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnstableSubtree<ID> {
+    /// a node moved after the threshold.
+    pub child_id: ID,
+}
+
+impl<ID: Debug> fmt::Display for UnstableSubtree<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "node {:?} was moved after the causal threshold",
+            self.child_id
+        )
+    }
+}
+
+impl<ID: Debug> std::error::Error for UnstableSubtree<ID> {}
+
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// removes the subtree under parent_id, and parent_id itself if
+    /// include_parent, as `Tree::rm_subtree`, once no log entry at or
+    /// after threshold moves a node into, out of or within it.  Returns
+    /// the number of nodes removed.  useful for emptying trash.
+    /// not used by crdt algo.
+    ///
+    /// threshold should be no later than the causally stable threshold,
+    /// so that no op older than it can still arrive, eg the
+    /// threshold returned by `TreeReplica::causally_stable_threshold`.
+    pub fn rm_subtree_checked(
+        &mut self,
+        parent_id: &ID,
+        include_parent: bool,
+        threshold: &Clock<A>,
+    ) -> Result<usize, UnstableSubtree<ID>> {
+        self.rm_subtree_below(parent_id, include_parent, Some(threshold))
+    }
+
+    /// removes each node for which f returns false, with the nodes under
+    /// it, as `Tree::retain`, unless a log entry after threshold
+    /// moves a node into, out of or within its subtree, and returns the
+    /// number of nodes removed.  useful for garbage collection.
+    /// not used by crdt algo.
+    ///
+    /// See ::rm_subtree_checked() for threshold.
+    pub fn retain_checked<F>(&mut self, threshold: &Clock<A>, f: F) -> usize
+    where
+        F: FnMut(&ID, &TreeNode<ID, TM>) -> bool,
+    {
+        self.retain_below(Some(threshold), f)
+    }
+
+    // as ::rm_subtree_checked(), where a threshold of None means every log
+    // entry may yet be undone.
+    fn rm_subtree_below(
+        &mut self,
+        parent_id: &ID,
+        include_parent: bool,
+        threshold: Option<&Clock<A>>,
+    ) -> Result<usize, UnstableSubtree<ID>> {
+        if let Some(child_id) = self.unstable_in(parent_id, threshold) {
+            return Err(UnstableSubtree { child_id });
+        }
+        let before = self.tree().num_nodes();
+        self.tree_mut().rm_subtree(parent_id, include_parent);
+        Ok(before - self.tree().num_nodes())
+    }
+
+    // as ::retain_checked(), where a threshold of None means every log
+    // entry may yet be undone.
+    fn retain_below<F>(&mut self, threshold: Option<&Clock<A>>, mut f: F) -> usize
+    where
+        F: FnMut(&ID, &TreeNode<ID, TM>) -> bool,
+    {
+        let rejected: Vec<ID> = self
+            .tree()
+            .iter()
+            .filter(|(child_id, tt)| !f(child_id, tt))
+            .map(|(child_id, _)| child_id.clone())
+            .collect();
+        let mut removed = 0;
+        for child_id in rejected {
+            // may already be removed with an ancestor.
+            if self.tree().find(&child_id).is_some() {
+                removed += self
+                    .rm_subtree_below(&child_id, true, threshold)
+                    .unwrap_or(0);
+            }
+        }
+        removed
+    }
+
+    // returns the node moved by a log entry after threshold into, out of
+    // or within the subtree under parent_id, if any.
+    fn unstable_in(&self, parent_id: &ID, threshold: Option<&Clock<A>>) -> Option<ID> {
+        let mut subtree: HashSet<&ID> = self
+            .tree()
+            .iter_subtree(parent_id)
+            .map(|(child_id, _, _)| child_id)
+            .collect();
+        subtree.insert(parent_id);
+        self.log()
+            .iter()
+            .take_while(|e| match threshold {
+                Some(t) => T::cmp(e.timestamp(), t) == Ordering::Greater,
+                None => true,
+            })
+            .find(|e| {
+                subtree.contains(e.child_id())
+                    || subtree.contains(e.parent_id())
+                    || e.oldp()
+                        .as_ref()
+                        .is_some_and(|n| subtree.contains(n.parent_id()))
+            })
+            .map(|e| e.child_id().clone())
+    }
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// removes a subtree, as `State::rm_subtree_checked`, at the causally
+    /// stable threshold, rather than via ::tree_mut().
+    /// not used by crdt algo.
+    ///
+    /// Until another replica has been seen, no log entry is stable.
+    pub fn rm_subtree_checked(
+        &mut self,
+        parent_id: &ID,
+        include_parent: bool,
+    ) -> Result<usize, UnstableSubtree<ID>> {
+        let threshold = self.causally_stable_threshold().cloned();
+        self.state_mut()
+            .rm_subtree_below(parent_id, include_parent, threshold.as_ref())
+    }
+
+    /// removes nodes, as `State::retain_checked`, at the causally stable
+    /// threshold, rather than via ::tree_mut().
+    /// not used by crdt algo.
+    pub fn retain_checked<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&ID, &TreeNode<ID, TM>) -> bool,
+    {
+        let threshold = self.causally_stable_threshold().cloned();
+        self.state_mut().retain_below(threshold.as_ref(), f)
+    }
+}
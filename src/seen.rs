@@ -0,0 +1,128 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::{BTreeMap, HashMap};
+use std::iter::FromIterator;
+
+use super::Clock;
+use crdts::Actor;
+
+// `Seen` holds the timestamps of applied ops, to skip redelivered ops,
+// as runs of consecutive counters for each actor.
+//
+// An actor's counters are consecutive for as long as it generates ops
+// without seeing newer ones from peers, eg a batch from
+// TreeReplica::opmoves(), so a run usually covers many ops.  Each run
+// costs two counters, ie at worst two per op when every op from an actor
+// follows a newer op from a peer.  Runs older than the causally stable
+// threshold are dropped by ::retain_from() as the log is truncated, so
+// only ops newer than it are held, as in the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Seen<A: Actor> {
+    // for each actor, runs of counters, as first -> last.
+    runs: HashMap<A, BTreeMap<u64, u64>>,
+}
+
+impl<A: Actor> Seen<A> {
+    // returns true if timestamp has been inserted, and not dropped since.
+    pub(crate) fn contains(&self, timestamp: &Clock<A>) -> bool {
+        let counter = timestamp.counter();
+        let run = self
+            .runs
+            .get(timestamp.actor_id())
+            .and_then(|runs| runs.range(..=counter).next_back());
+        matches!(run, Some((_, last)) if counter <= *last)
+    }
+
+    // records timestamp, joining it to the runs either side, if any.
+    pub(crate) fn insert(&mut self, timestamp: &Clock<A>) {
+        let counter = timestamp.counter();
+        let runs = self.runs.entry(timestamp.actor_id().clone()).or_default();
+        let mut first = counter;
+        if let Some((&f, &last)) = runs.range(..=counter).next_back() {
+            if counter <= last {
+                return;
+            }
+            if last + 1 == counter {
+                first = f;
+            }
+        }
+        let last = counter
+            .checked_add(1)
+            .and_then(|next| runs.remove(&next))
+            .unwrap_or(counter);
+        runs.insert(first, last);
+    }
+
+    // drops timestamps older than t, as by Clock's order.
+    pub(crate) fn retain_from(&mut self, t: &Clock<A>) {
+        self.runs.retain(|actor, runs| {
+            // counters equal to t's are kept for actors not less than t's.
+            let min = match actor < t.actor_id() {
+                true => t.counter().saturating_add(1),
+                false => t.counter(),
+            };
+            let mut kept = runs.split_off(&min);
+            if let Some((_, &last)) = runs.iter().next_back() {
+                if last >= min {
+                    kept.insert(min, last);
+                }
+            }
+            *runs = kept;
+            !runs.is_empty()
+        });
+    }
+}
+
+impl<A: Actor> Default for Seen<A> {
+    fn default() -> Self {
+        Self {
+            runs: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, A: Actor + 'a> FromIterator<&'a Clock<A>> for Seen<A> {
+    fn from_iter<I: IntoIterator<Item = &'a Clock<A>>>(iter: I) -> Self {
+        let mut seen = Self::default();
+        for timestamp in iter {
+            seen.insert(timestamp);
+        }
+        seen
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::quickcheck;
+    use std::collections::HashSet;
+
+    quickcheck! {
+        fn matches_a_set(
+            inserts: Vec<(u8, u8)>,
+            floor: (u8, u8),
+            probes: Vec<(u8, u8)>
+        ) -> bool {
+            // few actors and counters, so runs join.
+            let clock = |(a, c): (u8, u8)| Clock::new(a % 4, Some(c as u64 % 32));
+            let mut seen = Seen::default();
+            let mut set = HashSet::new();
+            for c in inserts.into_iter().map(clock) {
+                seen.insert(&c);
+                set.insert(c);
+            }
+            let floor = clock(floor);
+            seen.retain_from(&floor);
+            set.retain(|c| *c >= floor);
+            probes
+                .into_iter()
+                .map(clock)
+                .chain(set.iter().cloned())
+                .all(|c| seen.contains(&c) == set.contains(&c))
+        }
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! A deterministic simulation of replicas syncing over an unreliable
+//! network.
+//!
+//! `Simulation` models a group of replicas, each broadcasting the ops it
+//! generates as `CausalOpMove`s, so that receivers buffer ops that arrive
+//! before their dependencies, and a scheduler that delivers messages after a random
+//! delay, so out of order, and may drop or duplicate them, as set by
+//! `NetworkConfig`.  Every random choice comes from a single RNG seeded by
+//! the caller, so a failing seed replays exactly.
+//!
+//! Dropped ops are recovered by anti-entropy: at quiescence, ie once no
+//! messages are in flight, each replica sends its version vector to the
+//! others, which reply with the ops it is missing.  `quiesce` repeats this
+//! until the replicas converge.  Buffering matters here: a replica that
+//! applied an actor's later op having missed an earlier one would claim it
+//! in its version vector, and never be sent it.
+//!
+//! ```text
+//! for seed in 0..100 {
+//!     let mut sim = Simulation::new(4, seed, NetworkConfig::default());
+//!     sim.run(200);
+//!     sim.assert_converged();
+//! }
+//! ```
+//!
+//! Requires the `testing` feature.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use super::test_utils::{TestActor, TestId, TestMeta, TestReplica, ROOT};
+use super::{CausalOpMove, VersionVector};
+
+/// `NetworkConfig` sets how the simulated network mistreats messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConfig {
+    /// least time, in ticks, a message takes to arrive.
+    pub min_delay: u64,
+    /// most time, in ticks, a message takes to arrive.  Messages sent
+    /// within this window of each other may arrive out of order.
+    pub max_delay: u64,
+    /// probability that a message is lost.
+    pub drop_rate: f64,
+    /// probability that a message is delivered twice.
+    pub duplicate_rate: f64,
+}
+
+impl Default for NetworkConfig {
+    /// a network that delays, reorders, drops and duplicates messages.
+    fn default() -> Self {
+        Self {
+            min_delay: 1,
+            max_delay: 10,
+            drop_rate: 0.1,
+            duplicate_rate: 0.1,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// returns a network that delivers every message, in order, after a
+    /// single tick.
+    pub fn reliable() -> Self {
+        Self {
+            min_delay: 1,
+            max_delay: 1,
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+        }
+    }
+}
+
+/// Counts of what happened to messages during a simulation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    /// messages sent.
+    pub sent: u64,
+    /// messages delivered, including duplicates.
+    pub delivered: u64,
+    /// messages lost.
+    pub dropped: u64,
+    /// extra copies of messages delivered.
+    pub duplicated: u64,
+    /// anti-entropy rounds run.
+    pub rounds: u64,
+}
+
+// a message between replicas.
+#[derive(Debug, Clone)]
+enum Payload {
+    // ops for the receiver to apply once their dependencies are met.
+    Ops(Vec<CausalOpMove<TestId, TestMeta, TestActor>>),
+    // the sender's version vector; the receiver replies with the ops the
+    // sender is missing.
+    Hello(VersionVector<TestActor>),
+}
+
+#[derive(Debug)]
+struct Message {
+    deliver_at: u64,
+    seq: u64,
+    from: usize,
+    to: usize,
+    payload: Payload,
+}
+
+// messages are delivered in order of delivery time, then of sending, so
+// delivery is deterministic.
+impl Ord for Message {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.deliver_at, self.seq).cmp(&(other.deliver_at, other.seq))
+    }
+}
+
+impl PartialOrd for Message {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Message {}
+
+/// `Simulation` runs a group of replicas over a simulated network.  See
+/// the module docs.
+#[derive(Debug)]
+pub struct Simulation {
+    replicas: Vec<TestReplica>,
+    config: NetworkConfig,
+    rng: SmallRng,
+    in_flight: BinaryHeap<Reverse<Message>>,
+    now: u64,
+    seq: u64,
+    next_id: TestId,
+    stats: NetworkStats,
+}
+
+impl Simulation {
+    /// creates a simulation of `replicas` replicas, with actor IDs 0 up
+    /// to replicas - 1, whose random choices are determined by seed.
+    ///
+    /// Panics if replicas is 0.
+    pub fn new(replicas: usize, seed: u64, config: NetworkConfig) -> Self {
+        assert!(replicas > 0, "a simulation needs at least one replica");
+        Self {
+            replicas: (0..replicas as u64).map(TestReplica::new).collect(),
+            config,
+            rng: SmallRng::seed_from_u64(seed),
+            in_flight: BinaryHeap::new(),
+            now: 0,
+            seq: 0,
+            next_id: ROOT + 1,
+            stats: NetworkStats::default(),
+        }
+    }
+
+    /// returns the replicas.
+    pub fn replicas(&self) -> &[TestReplica] {
+        &self.replicas
+    }
+
+    /// returns counts of what happened to messages so far.
+    pub fn stats(&self) -> &NetworkStats {
+        &self.stats
+    }
+
+    /// returns the current simulated time, in ticks.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// returns the number of messages in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// has replica generate and apply an op moving child_id under
+    /// parent_id, and broadcast it to the others.
+    pub fn local_op(
+        &mut self,
+        replica: usize,
+        parent_id: TestId,
+        meta: TestMeta,
+        child_id: TestId,
+    ) {
+        let r = &mut self.replicas[replica];
+        let op = r.causal_opmove(parent_id, meta, child_id);
+        r.apply_op(op.op().clone());
+        for to in 0..self.replicas.len() {
+            if to != replica {
+                self.send(replica, to, Payload::Ops(vec![op.clone()]));
+            }
+        }
+    }
+
+    /// has a random replica move a random node, new or in its tree, under
+    /// the root or a node in its tree.
+    pub fn random_op(&mut self) {
+        let replica = self.rng.gen_range(0, self.replicas.len());
+        let mut nodes: Vec<TestId> = self.replicas[replica]
+            .tree()
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+        nodes.sort_unstable();
+        let parent_id = match self.rng.gen_range(0, nodes.len() + 1) {
+            0 => ROOT,
+            i => nodes[i - 1],
+        };
+        let child_id = if !nodes.is_empty() && self.rng.gen_bool(0.5) {
+            nodes[self.rng.gen_range(0, nodes.len())]
+        } else {
+            self.next_id += 1;
+            self.next_id - 1
+        };
+        let meta = self.rng.gen_range(b'a', b'z' + 1) as char;
+        self.local_op(replica, parent_id, meta, child_id);
+    }
+
+    /// delivers the next message, if any, advancing time to its arrival.
+    /// Returns false if no messages are in flight.
+    pub fn step(&mut self) -> bool {
+        let msg = match self.in_flight.pop() {
+            Some(Reverse(msg)) => msg,
+            None => return false,
+        };
+        self.now = self.now.max(msg.deliver_at);
+        self.stats.delivered += 1;
+        let to = &mut self.replicas[msg.to];
+        match msg.payload {
+            Payload::Ops(ops) => {
+                for op in ops {
+                    to.apply_causal_op(op);
+                }
+            }
+            Payload::Hello(seen) => {
+                // the missing ops, applied oldest first, depend only on
+                // ops the sender had seen or earlier ones in the reply.
+                // Ops truncated from the log can not be resent.
+                if let Some(ops) = to.missing_ops(&seen) {
+                    if !ops.is_empty() {
+                        let ops = ops
+                            .into_iter()
+                            .map(|op| CausalOpMove::new(op, seen.clone()))
+                            .collect();
+                        self.send(msg.to, msg.from, Payload::Ops(ops));
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// delivers messages until none are in flight.
+    pub fn run_until_quiet(&mut self) {
+        while self.step() {}
+    }
+
+    /// has each replica send its version vector to each other replica, so
+    /// they reply with the ops it is missing.
+    pub fn anti_entropy(&mut self) {
+        self.stats.rounds += 1;
+        for from in 0..self.replicas.len() {
+            let seen = self.replicas[from].version_vector().clone();
+            for to in 0..self.replicas.len() {
+                if to != from {
+                    self.send(from, to, Payload::Hello(seen.clone()));
+                }
+            }
+        }
+    }
+
+    /// delivers messages and runs anti-entropy rounds until the replicas
+    /// converge, or max_rounds rounds have run.  Returns true if they
+    /// converged.
+    pub fn quiesce(&mut self, max_rounds: u64) -> bool {
+        for _ in 0..max_rounds {
+            self.run_until_quiet();
+            if self.converged() {
+                return true;
+            }
+            self.anti_entropy();
+        }
+        self.run_until_quiet();
+        self.converged()
+    }
+
+    /// generates ops random ops, delivering a random number of messages
+    /// after each, then quiesces.  Returns true if the replicas converged.
+    pub fn run(&mut self, ops: usize) -> bool {
+        for _ in 0..ops {
+            self.random_op();
+            for _ in 0..self.rng.gen_range(0, 4) {
+                self.step();
+            }
+        }
+        self.quiesce(100)
+    }
+
+    /// returns true if all replicas have seen the same ops and hold the
+    /// same tree.
+    pub fn converged(&self) -> bool {
+        let first = &self.replicas[0];
+        self.replicas[1..]
+            .iter()
+            .all(|r| r.version_vector() == first.version_vector() && r.tree() == first.tree())
+    }
+
+    /// panics, describing the replicas, unless they have converged.
+    pub fn assert_converged(&self) {
+        assert!(
+            self.converged(),
+            "replicas diverged at tick {}, stats {:?}:\n{:#?}",
+            self.now,
+            self.stats,
+            self.replicas
+                .iter()
+                .map(|r| (r.version_vector(), r.tree()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    // sends payload from one replica to another, subject to the network.
+    fn send(&mut self, from: usize, to: usize, payload: Payload) {
+        self.stats.sent += 1;
+        if self.rng.gen_bool(self.config.drop_rate) {
+            self.stats.dropped += 1;
+            return;
+        }
+        let copies = if self.rng.gen_bool(self.config.duplicate_rate) {
+            self.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let delay = self
+                .rng
+                .gen_range(self.config.min_delay, self.config.max_delay + 1);
+            self.seq += 1;
+            self.in_flight.push(Reverse(Message {
+                deliver_at: self.now + delay,
+                seq: self.seq,
+                from,
+                to,
+                payload: payload.clone(),
+            }));
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::codec::{self, CodecError};
+use super::{Clock, OpMove, Snapshot, Storage, TreeId, TreeMeta};
+use crdts::Actor;
+
+/// Errors returned by `SledStorage`.
+#[derive(Debug)]
+pub enum SledStorageError {
+    /// the database returned an error.
+    Sled(sled::Error),
+    /// a stored value could not be encoded or decoded.
+    Codec(CodecError),
+}
+
+impl fmt::Display for SledStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sled(e) => write!(f, "sled error: {}", e),
+            Self::Codec(e) => write!(f, "codec error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SledStorageError {}
+
+impl From<sled::Error> for SledStorageError {
+    fn from(e: sled::Error) -> Self {
+        Self::Sled(e)
+    }
+}
+
+impl From<CodecError> for SledStorageError {
+    fn from(e: CodecError) -> Self {
+        Self::Codec(e)
+    }
+}
+
+/// `SledStorage` is a `Storage` backed by an embedded sled database.
+///
+/// Ops are stored in the `ops` tree, keyed by (counter, actor) so that
+/// key order follows timestamp order for range scans.  Snapshots are
+/// stored in the `snapshots` tree, keyed by checkpoint id.  Values are
+/// encoded with `codec`.
+///
+/// If `sync` is set, every write is flushed to disk before returning,
+/// so that an op is durable before it is applied.  Otherwise writes are
+/// flushed periodically by sled, and on `flush`.
+///
+/// Requires the `sled-storage` feature.
+pub struct SledStorage {
+    ops: sled::Tree,
+    snapshots: sled::Tree,
+    sync: bool,
+}
+
+impl SledStorage {
+    /// creates a `SledStorage` using trees in db.
+    pub fn new(db: &sled::Db, sync: bool) -> Result<Self, SledStorageError> {
+        Ok(Self {
+            ops: db.open_tree("ops")?,
+            snapshots: db.open_tree("snapshots")?,
+            sync,
+        })
+    }
+
+    /// flushes all writes to disk.
+    pub fn flush(&self) -> Result<(), SledStorageError> {
+        self.ops.flush()?;
+        self.snapshots.flush()?;
+        Ok(())
+    }
+
+    // flushes tree if sync is set.
+    fn sync(&self, tree: &sled::Tree) -> Result<(), SledStorageError> {
+        if self.sync {
+            tree.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// returns the key for timestamp.
+fn op_key<A: Actor + Serialize>(timestamp: &Clock<A>) -> Result<Vec<u8>, SledStorageError> {
+    let mut key = timestamp.counter().to_be_bytes().to_vec();
+    bincode::serialize_into(&mut key, timestamp.actor_id())
+        .map_err(|e| CodecError::Payload(e.to_string()))?;
+    Ok(key)
+}
+
+// returns the first key with a counter greater than timestamp's, or None.
+fn key_after_counter<A: Actor>(timestamp: &Clock<A>) -> Option<Vec<u8>> {
+    timestamp
+        .counter()
+        .checked_add(1)
+        .map(|c| c.to_be_bytes().to_vec())
+}
+
+impl<ID, TM, A> Storage<ID, TM, A> for SledStorage
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    type Error = SledStorageError;
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot<ID, TM, A>) -> Result<(), SledStorageError> {
+        let value = codec::encode(snapshot)?;
+        self.snapshots.insert(snapshot.id().to_be_bytes(), value)?;
+        self.sync(&self.snapshots)
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot<ID, TM, A>>, SledStorageError> {
+        match self.snapshots.last()? {
+            Some((_, value)) => Ok(Some(codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn append_op(&mut self, op: &OpMove<ID, TM, A>) -> Result<(), SledStorageError> {
+        self.ops
+            .insert(op_key(op.timestamp())?, codec::encode(op)?)?;
+        self.sync(&self.ops)
+    }
+
+    fn scan_ops(
+        &self,
+        from: Bound<&Clock<A>>,
+        to: Bound<&Clock<A>>,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, SledStorageError> {
+        // scan whole counters, as actor encodings need not sort like
+        // actors, then filter on exact timestamps.
+        let start = match from {
+            Bound::Included(t) | Bound::Excluded(t) => {
+                Bound::Included(t.counter().to_be_bytes().to_vec())
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match to {
+            Bound::Included(t) | Bound::Excluded(t) => match key_after_counter(t) {
+                Some(key) => Bound::Excluded(key),
+                None => Bound::Unbounded,
+            },
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let mut ops = vec![];
+        for entry in self.ops.range::<Vec<u8>, _>((start, end)) {
+            let (_, value) = entry?;
+            let op: OpMove<ID, TM, A> = codec::decode(&value)?;
+            if (from, to).contains(op.timestamp()) {
+                ops.push(op);
+            }
+        }
+        ops.sort_by(|a, b| a.timestamp().cmp(b.timestamp()));
+        Ok(ops)
+    }
+
+    fn remove_ops_before(&mut self, timestamp: &Clock<A>) -> Result<usize, SledStorageError> {
+        let end = match key_after_counter(timestamp) {
+            Some(key) => Bound::Excluded(key),
+            None => Bound::Unbounded,
+        };
+        let mut batch = sled::Batch::default();
+        let mut removed = 0;
+        for entry in self.ops.range::<Vec<u8>, _>((Bound::Unbounded, end)) {
+            let (key, value) = entry?;
+            let op: OpMove<ID, TM, A> = codec::decode(&value)?;
+            if op.timestamp() < timestamp {
+                batch.remove(key);
+                removed += 1;
+            }
+        }
+        self.ops.apply_batch(batch)?;
+        self.sync(&self.ops)?;
+        Ok(removed)
+    }
+}
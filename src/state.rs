@@ -6,8 +6,19 @@
 
 use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, Ordering, PartialEq};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 
-use super::{Clock, LogOpMove, OpMove, Tree, TreeId, TreeMeta, TreeNode};
+#[cfg(feature = "compression")]
+use super::codec::{self, CodecError};
+use super::logopmove::Placement;
+use super::tiebreak::Fnv1a;
+use super::{
+    ActorOrder, Clock, ConflictEvent, Kleppmann, LogOpMove, OpMove, Quotas, Resolve, TieBreak,
+    Tree, TreeId, TreeIntoIter, TreeIter, TreeMeta, TreeNode,
+};
 use crdts::{Actor, CmRDT};
 use log::warn;
 
@@ -29,22 +40,71 @@ use log::warn;
 /// and distributed filesystems" [1] by Martin Klepmann, et al.
 ///
 /// [1] https://martin.kleppmann.com/papers/move-op.pdf
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct State<ID: TreeId, TM: TreeMeta, A: Actor> {
+///
+/// Ops with equal counters are ordered by the `TieBreak` strategy T,
+/// which by default compares actors.  See `tiebreak`.
+///
+/// Moves conflicting with the tree are resolved by the `Resolve`
+/// strategy R, which by default follows the paper.  See `resolve`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct State<
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor,
+    T: TieBreak<A> = ActorOrder,
+    R: Resolve<ID, TM, A> = Kleppmann,
+> {
     // a list of `LogMove` in descending timestamp order.
     log_op_list: Vec<LogOpMove<ID, TM, A>>,
 
     // a tree structure, ie a set of (parent, meta, child) triples
     // that represent the current state of the tree.
     tree: Tree<ID, TM>,
+
+    // the timestamp the log was last truncated or compacted before, if
+    // ever.  older ops can no longer be applied in order.
+    truncated_before: Option<Clock<A>>,
+
+    // the timestamp of the oldest entry collapsed by compaction, if any.
+    // the tree between it and truncated_before can not be rebuilt.
+    compacted_from: Option<Clock<A>>,
+
+    #[serde(skip)]
+    tie_break: PhantomData<T>,
+
+    #[serde(skip)]
+    resolve: PhantomData<R>,
+
+    // limits on the tree's shape, a setting like T and R.
+    #[serde(skip)]
+    quotas: Quotas,
+
+    // the (root, parent of the root), a setting like quotas.  a default
+    // fn spares ID a Default bound.
+    #[serde(skip, default = "Option::default")]
+    root: Option<(ID, ID)>,
+
+    // the top-level parents admitted in strict mode, if enabled.  a
+    // setting like quotas.
+    #[serde(skip, default = "Option::default")]
+    strict: Option<HashSet<ID>>,
 }
 
-impl<ID: TreeId, TM: TreeMeta, A: Actor> State<ID, TM, A> {
+impl<ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>>
+    State<ID, TM, A, T, R>
+{
     /// create a new State
     pub fn new() -> Self {
         Self {
             log_op_list: Vec::<LogOpMove<ID, TM, A>>::default(),
             tree: Tree::<ID, TM>::new(),
+            truncated_before: None,
+            compacted_from: None,
+            tie_break: PhantomData,
+            resolve: PhantomData,
+            quotas: Quotas::default(),
+            root: None,
+            strict: None,
         }
     }
 
@@ -60,48 +120,330 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> State<ID, TM, A> {
     /// not be mutated directly.
     ///
     /// See the demo_move_to_trash in examples/demo.rs for a
-    /// use-case, only after log truncation has been performed.    
+    /// use-case, only after log truncation has been performed, and
+    /// ::rm_subtree_checked() and ::retain_checked() for safer ways.
     #[inline]
     pub fn tree_mut(&mut self) -> &mut Tree<ID, TM> {
         &mut self.tree
     }
 
+    /// returns an iterator over all nodes in the tree, in arbitrary order.
+    #[inline]
+    pub fn iter(&self) -> TreeIter<'_, ID, TM> {
+        self.tree.iter()
+    }
+
+    /// sets limits on the tree's shape, checked as each later op is
+    /// applied.  A move that would exceed them is ignored.  See `Quotas`.
+    ///
+    /// Every replica must use the same quotas.  They are not serialized,
+    /// so must be set again on a deserialized state.
+    pub fn set_quotas(&mut self, quotas: Quotas) {
+        self.quotas = quotas;
+    }
+
+    /// returns the quotas.
+    #[inline]
+    pub fn quotas(&self) -> &Quotas {
+        &self.quotas
+    }
+
+    /// designates root_id as the root of the tree, a node under
+    /// root_parent, which is not itself a node.  Checked as each later op
+    /// is applied: a move of the root, of another node under root_parent,
+    /// or of root_parent itself is ignored, so the root stays alone at the
+    /// top of the tree even if a peer sends crafted ops.  A move leaving
+    /// the root in place, eg to update its metadata, is applied.
+    ///
+    /// Every replica must use the same root.  It is not serialized, so
+    /// must be set again on a deserialized state.
+    pub fn set_root(&mut self, root_id: ID, root_parent: ID) {
+        self.root = Some((root_id, root_parent));
+    }
+
+    /// returns the root's ID, if set by ::set_root() and created, ie a node.
+    pub fn root(&self) -> Option<&ID> {
+        self.root
+            .as_ref()
+            .map(|(root_id, _)| root_id)
+            .filter(|root_id| self.tree.find(root_id).is_some())
+    }
+
+    /// returns the root's parent, if set by ::set_root().
+    #[inline]
+    pub fn root_parent(&self) -> Option<&ID> {
+        self.root.as_ref().map(|(_, root_parent)| root_parent)
+    }
+
+    /// enables strict mode, checked as each later op is applied, or
+    /// disables it with None, as by default.
+    ///
+    /// In strict mode a move under a parent that is neither a node nor one
+    /// of top_level, eg the root and trash IDs, is ignored, rather than
+    /// starting a disconnected subtree, eg when ops of independent trees
+    /// are merged.  The root's parent set by ::set_root() is always
+    /// admitted.  An op ignored as its parent's op had not arrived takes
+    /// effect when it does, if that op is older, as later ops are redone.
+    ///
+    /// Every replica must use the same setting.  It is not serialized, so
+    /// must be set again on a deserialized state.
+    pub fn set_strict(&mut self, top_level: Option<Vec<ID>>) {
+        self.strict = top_level.map(|ids| ids.into_iter().collect());
+    }
+
+    /// returns true if strict mode is enabled.  See ::set_strict().
+    #[inline]
+    pub fn is_strict(&self) -> bool {
+        self.strict.is_some()
+    }
+
+    // returns true if a node may be moved under parent_id, ie strict mode
+    // is disabled or parent_id is a node or admitted top-level parent.
+    pub(crate) fn admits_parent(&self, parent_id: &ID) -> bool {
+        match &self.strict {
+            None => true,
+            Some(top_level) => {
+                self.tree.find(parent_id).is_some()
+                    || top_level.contains(parent_id)
+                    || self.root_parent() == Some(parent_id)
+            }
+        }
+    }
+
+    // returns the (root, parent of the root) set by ::set_root().
+    pub(crate) fn root_setting(&self) -> Option<&(ID, ID)> {
+        self.root.as_ref()
+    }
+
+    // returns true if the log entry at index took effect, ie replaced its
+    // node's parent and metadata, with its own or eg merged ones, rather
+    // than being ignored.
+    pub(crate) fn entry_placed(&self, index: usize) -> bool {
+        matches!(
+            self.log_op_list.get(index).and_then(|e| e.placement()),
+            Some(p) if p.took_effect()
+        )
+    }
+
+    // shares nodes between the tree and log entries again, as they were
+    // when the ops were applied, by each entry's placement.  sharing is
+    // lost by deserializing, and undo and conflict detection compare nodes
+    // by pointer.  entries without a placement are left unshared.
+    pub(crate) fn share_nodes(&mut self) {
+        // the node each child had after the entry being visited, walking
+        // from oldest to newest.
+        let mut after: HashMap<ID, TreeNode<ID, TM>> = HashMap::new();
+        for entry in self.log_op_list.iter_mut().rev() {
+            if entry.oldp().as_ref().is_some() {
+                if let Some(node) = after.get(entry.child_id()) {
+                    entry.set_oldp(Some(node.clone()));
+                }
+            }
+            let node = match (entry.placement(), entry.oldp().as_ref()) {
+                (Some(Placement::Placed), _) => entry.node().clone(),
+                (Some(Placement::Ignored), Some(oldp)) => oldp.clone(),
+                // a replaced node, eg with merged metadata, is not shared
+                // with the entry.  the deserialized copy in the next newer
+                // entry's oldp or in the tree is kept.
+                _ => {
+                    after.remove(entry.child_id());
+                    continue;
+                }
+            };
+            after.insert(entry.child_id().clone(), node);
+        }
+        for (child_id, node) in after {
+            self.tree.share_node(&child_id, node);
+        }
+    }
+
     /// returns log reference
     #[inline]
     pub fn log(&self) -> &Vec<LogOpMove<ID, TM, A>> {
         &self.log_op_list
     }
 
+    /// returns the newest timestamp the log was truncated before by
+    /// ::truncate_log_before(), or compacted before by
+    /// ::compact_log_before(), if any.  Ops older than this can no longer
+    /// be applied in order, and are taken as applied by `TreeReplica`.
+    #[inline]
+    pub fn truncated_before(&self) -> Option<&Clock<A>> {
+        self.truncated_before.as_ref()
+    }
+
+    // sets the timestamp the log was truncated before, eg as recorded with
+    // a state rebuilt from its parts.
+    pub(crate) fn set_truncated_before(&mut self, timestamp: Option<Clock<A>>) {
+        self.truncated_before = timestamp;
+    }
+
+    /// returns the timestamp of the oldest entry collapsed by
+    /// ::compact_log_before(), if any.  See ::tree_at().
+    #[inline]
+    pub fn compacted_from(&self) -> Option<&Clock<A>> {
+        self.compacted_from.as_ref()
+    }
+
+    // sets the timestamp of the oldest collapsed entry, eg as recorded
+    // with a state rebuilt from its parts.
+    pub(crate) fn set_compacted_from(&mut self, timestamp: Option<Clock<A>>) {
+        self.compacted_from = timestamp;
+    }
+
+    // returns true if the tree at timestamp was lost by compaction, ie it
+    // is between the oldest collapsed entry and the compaction point.
+    pub(crate) fn is_compacted(&self, timestamp: &Clock<A>) -> bool {
+        match (&self.compacted_from, &self.truncated_before) {
+            (Some(from), Some(before)) => from <= timestamp && timestamp < before,
+            _ => false,
+        }
+    }
+
+    /// returns a hash of the tree, as `Tree::digest`, and of the newest
+    /// log entry's timestamp.  O(1).
+    ///
+    /// States that have applied the same ops have the same digest, so
+    /// comparing digests is a quick convergence check, eg in tests or
+    /// monitoring.  Compare states with == to be certain.
+    ///
+    /// Older log entries are not included, so states whose logs were
+    /// truncated differently may still have the same digest.
+    pub fn digest(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        hasher.write_u64(self.tree.digest());
+        self.log_op_list
+            .first()
+            .map(|e| e.timestamp())
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// returns the tree as it was after applying the ops with timestamps
+    /// up to and including timestamp, by undoing newer log entries on a
+    /// copy of the tree.  self is unchanged.
+    ///
+    /// Entries truncated from the log can not be undone, so for a
+    /// timestamp older than the oldest entry the result is the tree before
+    /// the oldest entry was applied.  Likewise, for a timestamp within a
+    /// range compacted by ::compact_log_before() the result is the tree
+    /// before the oldest collapsed entry.  See ::compacted_from().
+    pub fn tree_at(&self, timestamp: &Clock<A>) -> Tree<ID, TM> {
+        let newer = |log: &&LogOpMove<ID, TM, A>| match &self.compacted_from {
+            Some(from) if self.is_compacted(timestamp) => {
+                T::cmp(log.timestamp(), from) != Ordering::Less
+            }
+            _ => T::cmp(log.timestamp(), timestamp) == Ordering::Greater,
+        };
+        let mut tree = self.tree.clone();
+        for log in self.log_op_list.iter().take_while(newer) {
+            match log.oldp().as_ref() {
+                Some(oldp) => tree.move_triple(log.child_id(), oldp.clone()),
+                None => tree.remove_triple(log.child_id()),
+            }
+        }
+        tree
+    }
+
     /// add_log_entry
     pub fn add_log_entry(&mut self, entry: LogOpMove<ID, TM, A>) {
         // add at beginning of array
         self.log_op_list.insert(0, entry);
     }
 
-    /// removes log entries before a given timestamp.
+    /// removes log entries before a given timestamp, and returns them,
+    /// newest first, eg for archiving.
     /// not part of crdt-tree algo.
-    pub fn truncate_log_before(&mut self, timestamp: &Clock<A>) -> bool {
+    pub fn truncate_log_before(&mut self, timestamp: &Clock<A>) -> Vec<LogOpMove<ID, TM, A>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "truncate_log_before",
+            counter = timestamp.counter(),
+            log_len = self.log_op_list.len()
+        )
+        .entered();
+
         // newest entries are at start of list, so to find
         // oldest entries we iterate from the end towards start.
-        let len = self.log_op_list.len();
-        let mut last_idx: usize = len - 1;
-        for (i, v) in self.log_op_list.iter().rev().enumerate() {
-            if v.timestamp() < timestamp {
-                last_idx = len - 1 - i;
-            } else {
-                break;
-            }
+        let num_older = self
+            .log_op_list
+            .iter()
+            .rev()
+            .take_while(|v| v.timestamp() < timestamp)
+            .count();
+        if !matches!(&self.truncated_before, Some(t) if t >= timestamp) {
+            self.truncated_before = Some(timestamp.clone());
         }
+        self.log_op_list
+            .split_off(self.log_op_list.len() - num_older)
+    }
 
-        loop {
-            let idx = self.log_op_list.len() - 1;
-            if idx < last_idx {
-                break;
+    /// collapses log entries before a given timestamp that move the same
+    /// child into the newest of them, and returns the entries removed,
+    /// newest first.  not part of crdt-tree algo.
+    ///
+    /// Entries before the causally stable threshold are never undone when
+    /// applying ops, so collapsing them preserves convergence, while the
+    /// log keeps a single entry per child.  The entry kept undoes to the
+    /// child's parent before the oldest entry collapsed, so ::tree_at()
+    /// is unchanged outside the compacted range, and ops older than
+    /// timestamp can no longer be applied in order, as after
+    /// ::truncate_log_before().
+    pub fn compact_log_before(&mut self, timestamp: &Clock<A>) -> Vec<LogOpMove<ID, TM, A>> {
+        let start = self
+            .log_op_list
+            .iter()
+            .position(|e| e.timestamp() < timestamp)
+            .unwrap_or(self.log_op_list.len());
+
+        // index of the newest entry for each child, and the oldp of the
+        // oldest entry collapsed into it.
+        let mut kept: HashMap<&ID, usize> = HashMap::new();
+        let mut oldps = HashMap::new();
+        let mut collapsed = vec![false; self.log_op_list.len()];
+        for (i, entry) in self.log_op_list.iter().enumerate().skip(start) {
+            match kept.get(entry.child_id()) {
+                Some(&k) => {
+                    collapsed[i] = true;
+                    oldps.insert(k, entry.oldp().as_ref().cloned());
+                }
+                None => {
+                    kept.insert(entry.child_id(), i);
+                }
+            }
+        }
+        for (k, oldp) in oldps {
+            let entry = &mut self.log_op_list[k];
+            // the kept entry now stands for the moves collapsed into it, so
+            // it took effect unless the child ends where it started.
+            let unmoved = match (entry.oldp().as_ref(), oldp.as_ref()) {
+                (Some(a), Some(b)) => TreeNode::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            };
+            if entry.placement() == Some(Placement::Ignored) && !unmoved {
+                entry.set_placement(Some(Placement::Replaced));
             }
-            self.log_op_list.remove(idx);
+            entry.set_oldp(oldp);
         }
 
-        last_idx + 1 < len
+        let mut removed = vec![];
+        let log = std::mem::take(&mut self.log_op_list);
+        for (entry, collapsed) in log.into_iter().zip(collapsed) {
+            if collapsed {
+                removed.push(entry);
+            } else {
+                self.log_op_list.push(entry);
+            }
+        }
+        if let Some(oldest) = removed.last() {
+            if !matches!(&self.compacted_from, Some(t) if t <= oldest.timestamp()) {
+                self.compacted_from = Some(oldest.timestamp().clone());
+            }
+            if !matches!(&self.truncated_before, Some(t) if t >= timestamp) {
+                self.truncated_before = Some(timestamp.clone());
+            }
+        }
+        removed
     }
 
     /// The do_op function performs the actual work of applying
@@ -112,39 +454,151 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> State<ID, TM, A> {
     /// consisting of a LogMove operation (which will be added to the log) and
     /// an updated tree.
     pub fn do_op(&mut self, op: OpMove<ID, TM, A>) -> LogOpMove<ID, TM, A> {
+        self.do_log_op(LogOpMove::new(op, None))
+    }
+
+    // performs the move recorded in log, filling in its oldp.
+    //
+    // The log entry's node, holding the op's parent and metadata, is
+    // shared with the tree rather than copied.
+    fn do_log_op(&mut self, mut log: LogOpMove<ID, TM, A>) -> LogOpMove<ID, TM, A> {
         // When a replica applies a `Move` op to its tree, it also records
         // a corresponding `LogMove` op in its log.  The t, p, m, and c
         // fields are taken directly from the `Move` record, while the `oldp`
         // field is filled in based on the state of the tree before the move.
         // If c did not exist in the tree, `oldp` is set to None.  Otherwise
         // `oldp` records the previous parent and metadata of c.
-        let oldp = self.tree.find(op.child_id()).cloned();
+        log.set_oldp(self.tree.find(log.child_id()).cloned());
+        log.set_placement(Some(Placement::Ignored));
 
         // ensures no cycles are introduced.  If the node c
         // is being moved, and c is an ancestor of the new parent
         // newp, then the tree is returned unmodified, ie the operation
         // is ignored.
         // Similarly, the operation is also ignored if c == newp
-        if op.child_id() == op.parent_id() || self.tree.is_ancestor(op.parent_id(), op.child_id()) {
-            return LogOpMove::new(op, oldp);
+        //
+        // Unless the strategy R moves c elsewhere instead, in which case
+        // the tree holds a node of its own, not shared with the log, as
+        // for merged metadata below.
+        let mut node = log.node().clone();
+        if self.introduces_cycle(log.parent_id(), log.child_id()) {
+            let elsewhere =
+                R::on_cycle(&self.tree, &log).filter(|p| !self.introduces_cycle(p, log.child_id()));
+            match elsewhere {
+                Some(parent_id) => {
+                    node = TreeNode::new(parent_id, log.metadata().clone());
+                }
+                None => {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(
+                        counter = log.timestamp().counter(),
+                        "move ignored, it would introduce a cycle"
+                    );
+                    return log;
+                }
+            }
+        }
+
+        // a conditional op is ignored if c's parent is not the expected
+        // one, eg as a concurrent op has moved it.
+        if let Some(expected) = log.expected_parent_id() {
+            if log.oldp().as_ref().map(|n| n.parent_id()) != Some(expected) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(
+                    counter = log.timestamp().counter(),
+                    "move ignored, its expected parent does not match"
+                );
+                return log;
+            }
+        }
+
+        if !self.keeps_root(log.child_id(), node.parent_id()) {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                counter = log.timestamp().counter(),
+                "move ignored, it would displace the root"
+            );
+            return log;
+        }
+
+        if !self.admits_parent(node.parent_id()) {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                counter = log.timestamp().counter(),
+                "move ignored, its parent is not in the tree"
+            );
+            return log;
+        }
+
+        if !self
+            .quotas
+            .admits(&self.tree, log.child_id(), node.parent_id())
+        {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                counter = log.timestamp().counter(),
+                "move ignored, it would exceed the quotas"
+            );
+            return log;
+        }
+
+        if let Some(current) = log.oldp().as_ref() {
+            if !R::replaces(&self.tree, &log, current) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(
+                    counter = log.timestamp().counter(),
+                    "move ignored, declined by the resolve strategy"
+                );
+                return log;
+            }
+        }
+
+        // a node left under the same parent may have its metadata merged
+        // by the strategy R, rather than replaced.
+        if let Some(current) = log.oldp().as_ref() {
+            if current.parent_id() == node.parent_id() {
+                if let Some(metadata) = R::merge_metadata(current, &log) {
+                    node = TreeNode::new(node.parent_id().clone(), metadata);
+                }
+            }
         }
 
         // Otherwise, the tree is updated by removing c from
         // its existing parent, if any, and adding the new
         // parent-child relationship (newp, m, c) to the tree.
-        self.tree.rm_child(op.child_id());
-        let tt = TreeNode::new(op.parent_id().to_owned(), op.metadata().to_owned());
-        self.tree.add_node(op.child_id().to_owned(), tt);
-        LogOpMove::new(op, oldp)
+        let placement = match TreeNode::ptr_eq(&node, log.node()) {
+            true => Placement::Placed,
+            false => Placement::Replaced,
+        };
+        log.set_placement(Some(placement));
+        self.tree.move_triple(log.child_id(), node);
+        log
+    }
+
+    // returns true if moving child_id under parent_id leaves the root, if
+    // set, alone under its parent.  see ::set_root().
+    fn keeps_root(&self, child_id: &ID, parent_id: &ID) -> bool {
+        match &self.root {
+            None => true,
+            Some((root_id, root_parent)) => {
+                child_id != root_parent && (child_id == root_id) == (parent_id == root_parent)
+            }
+        }
+    }
+
+    // returns true if moving child_id under parent_id would introduce a
+    // cycle.
+    fn introduces_cycle(&self, parent_id: &ID, child_id: &ID) -> bool {
+        child_id == parent_id || self.tree.is_ancestor(parent_id, child_id)
     }
 
     /// undo_op
     pub fn undo_op(&mut self, log: &LogOpMove<ID, TM, A>) {
-        self.tree.rm_child(log.child_id());
-
-        if let Some(oldp) = log.oldp() {
-            let tn = TreeNode::new(oldp.parent_id().to_owned(), oldp.metadata().to_owned());
-            self.tree.add_node(log.child_id().to_owned(), tn);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(counter = log.timestamp().counter(), "undo");
+        match log.oldp().as_ref() {
+            Some(oldp) => self.tree.move_triple(log.child_id(), oldp.clone()),
+            None => self.tree.remove_triple(log.child_id()),
         }
     }
 
@@ -152,8 +606,9 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> State<ID, TM, A> {
     /// again and recomputes the `LogMove` record (which
     /// might have changed due to the effect of the new operation)
     pub fn redo_op(&mut self, log: LogOpMove<ID, TM, A>) {
-        let op = OpMove::from(log);
-        let logop2 = self.do_op(op);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(counter = log.timestamp().counter(), "redo");
+        let logop2 = self.do_log_op(log);
 
         self.add_log_entry(logop2);
     }
@@ -168,69 +623,338 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> State<ID, TM, A> {
     /// type class, and they can therefore be compared with the
     /// < operator during a linear (or total) order.
     pub fn apply_op(&mut self, op1: OpMove<ID, TM, A>) {
-        if self.log_op_list.is_empty() {
-            let op2 = self.do_op(op1);
-            self.log_op_list = vec![op2];
-        } else {
-            match op1.timestamp().cmp(self.log_op_list[0].timestamp()) {
-                Ordering::Equal => {
-                    // This case should never happen in normal operation
-                    // because it is requirement/invariant that all
-                    // timestamps are unique.  However, uniqueness is not
-                    // strictly enforced in this impl.
-                    // The crdt paper does not even check for this case.
-                    // We just treat it as a no-op.
-                    warn!("op with timestamp equal to previous op ignored. (not applied).  Every op must have a unique timestamp.");
-                }
-                Ordering::Less => {
-                    let logop = self.log_op_list.remove(0); // take from beginning of array
-                    self.undo_op(&logop);
-                    self.apply_op(op1);
-                    self.redo_op(logop);
+        self.apply_op_at(op1, None);
+    }
+
+    /// applies op1, like ::apply_op(), and returns the conflicts it caused,
+    /// in the order they occurred.
+    ///
+    /// Besides op1 being ignored, or overriding or being overridden by
+    /// another actor's op on the same node, applying op1 may change the
+    /// effect of later ops, which are undone and redone.  See
+    /// `ConflictEvent`.
+    pub fn apply_op_with_conflicts(&mut self, op1: OpMove<ID, TM, A>) -> Vec<ConflictEvent<ID, A>> {
+        let mut events = vec![];
+        self.apply_op_at(op1, Some(&mut events));
+        events
+    }
+
+    // applies op1, like ::apply_op(), pushing the conflicts it caused to
+    // events, if given.  returns the index of op1's log entry, or None if
+    // op1 was not logged, as an entry had an equal timestamp.
+    pub(crate) fn apply_op_at(
+        &mut self,
+        op1: OpMove<ID, TM, A>,
+        events: Option<&mut Vec<ConflictEvent<ID, A>>>,
+    ) -> Option<usize> {
+        // the undo depth is the number of later ops undone and redone.
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "state_apply_op",
+            counter = op1.timestamp().counter(),
+            undo_depth = self
+                .log_op_list
+                .iter()
+                .take_while(|e| T::cmp(op1.timestamp(), e.timestamp()) == Ordering::Less)
+                .count()
+        )
+        .entered();
+
+        self.apply_op_undoing(op1, events).0
+    }
+
+    // applies op1, undoing and redoing later ops, recursively, and returns
+    // the index of op1's log entry, as ::apply_op_at(), and the node op1
+    // placed in the tree, or None if op1 was ignored.
+    //
+    // conflicts are pushed to events, if given.
+    fn apply_op_undoing(
+        &mut self,
+        op1: OpMove<ID, TM, A>,
+        mut events: Option<&mut Vec<ConflictEvent<ID, A>>>,
+    ) -> (Option<usize>, Option<TreeNode<ID, TM>>) {
+        let newer = match self.log_op_list.first() {
+            Some(last) => T::cmp(op1.timestamp(), last.timestamp()),
+            None => Ordering::Greater,
+        };
+        match newer {
+            Ordering::Equal => {
+                // This case should never happen in normal operation
+                // because it is requirement/invariant that all
+                // timestamps are unique.  However, uniqueness is not
+                // strictly enforced in this impl.
+                // The crdt paper does not even check for this case.
+                // We just treat it as a no-op.
+                warn!("op with timestamp equal to previous op ignored. (not applied).  Every op must have a unique timestamp.");
+                (None, None)
+            }
+            Ordering::Less => {
+                let op1_time = events.as_ref().map(|_| op1.timestamp().clone());
+                let logop = self.log_op_list.remove(0); // take from beginning of array
+                let was_applied = self.has_placed(&logop);
+                self.undo_op(&logop);
+                let (index, placed) = self.apply_op_undoing(op1, events.as_deref_mut());
+
+                #[cfg(feature = "tracing")]
+                tracing::trace!(counter = logop.timestamp().counter(), "redo");
+                let logop = self.do_log_op(logop);
+                if let (Some(events), Some(op1_time)) = (events, op1_time) {
+                    self.report_redo(&logop, was_applied, placed.as_ref(), op1_time, events);
                 }
-                Ordering::Greater => {
-                    let op2 = self.do_op(op1);
-                    self.add_log_entry(op2);
+                self.add_log_entry(logop);
+                (index.map(|i| i + 1), placed)
+            }
+            Ordering::Greater => {
+                let op2 = self.do_op(op1);
+                if let Some(events) = events {
+                    self.report_do(&op2, events);
                 }
+                let placed = self.placed_node(&op2).cloned();
+                self.add_log_entry(op2);
+                (Some(0), placed)
             }
         }
     }
 
     /// applies a list of operations and consume them. (no cloning)
     pub fn apply_ops_into(&mut self, ops: Vec<OpMove<ID, TM, A>>) {
+        self.apply_ops_iter(ops)
+    }
+
+    /// applies a list of operations reference, cloning each op.
+    pub fn apply_ops(&mut self, ops: &[OpMove<ID, TM, A>]) {
+        self.apply_ops_iter(ops.iter().cloned())
+    }
+
+    /// applies operations as they are yielded, eg by a decoder or a
+    /// channel, without collecting them first.
+    pub fn apply_ops_iter<I>(&mut self, ops: I)
+    where
+        I: IntoIterator<Item = OpMove<ID, TM, A>>,
+    {
         for op in ops {
             self.apply_op(op);
         }
     }
 
-    /// applies a list of operations reference, cloning each op.
-    pub fn apply_ops(&mut self, ops: &[OpMove<ID, TM, A>]) {
-        self.apply_ops_into(ops.to_vec())
+    /// applies a batch of ops sorted oldest first, as ::apply_ops() would,
+    /// but undoing the log entries newer than the oldest op once, then
+    /// redoing them interleaved with the batch, in a single pass.
+    ///
+    /// Applying a large, delayed batch one op at a time undoes and redoes
+    /// the newer entries for each op.  Here the work is linear in the
+    /// batch and the entries undone.
+    ///
+    /// If ops is not sorted, it is sorted first.
+    pub fn merge_sorted_ops(&mut self, mut ops: Vec<OpMove<ID, TM, A>>) {
+        if !ops
+            .windows(2)
+            .all(|w| T::cmp(w[0].timestamp(), w[1].timestamp()) != Ordering::Greater)
+        {
+            ops.sort_by(|a, b| T::cmp(a.timestamp(), b.timestamp()));
+        }
+        let oldest = match ops.first() {
+            Some(op) => op.timestamp().clone(),
+            None => return,
+        };
+
+        // the entries newer than the oldest op, undone newest first.
+        let depth = self
+            .log_op_list
+            .iter()
+            .take_while(|e| T::cmp(&oldest, e.timestamp()) == Ordering::Less)
+            .count();
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "state_merge_sorted_ops",
+            ops = ops.len(),
+            undo_depth = depth
+        )
+        .entered();
+
+        let undone: Vec<_> = self.log_op_list.drain(..depth).collect();
+        for logop in &undone {
+            self.undo_op(logop);
+        }
+
+        // the merged entries, oldest first, prepended to the log at the end.
+        let mut merged: Vec<LogOpMove<ID, TM, A>> = Vec::with_capacity(undone.len() + ops.len());
+        let mut undone = undone.into_iter().rev().peekable();
+        let mut ops = ops.into_iter().peekable();
+        loop {
+            let redo = match (undone.peek(), ops.peek()) {
+                (None, None) => break,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(logop), Some(op)) => {
+                    T::cmp(logop.timestamp(), op.timestamp()) != Ordering::Greater
+                }
+            };
+            if redo {
+                let logop = undone.next().unwrap();
+                #[cfg(feature = "tracing")]
+                tracing::trace!(counter = logop.timestamp().counter(), "redo");
+                merged.push(self.do_log_op(logop));
+                continue;
+            }
+
+            let op = ops.next().unwrap();
+            let last = merged.last().or_else(|| self.log_op_list.first());
+            if matches!(last, Some(e) if T::cmp(op.timestamp(), e.timestamp()) == Ordering::Equal) {
+                warn!("op with timestamp equal to previous op ignored. (not applied).  Every op must have a unique timestamp.");
+                continue;
+            }
+            merged.push(self.do_op(op));
+        }
+
+        merged.reverse();
+        self.log_op_list.splice(0..0, merged);
     }
 }
 
-impl<ID: TreeId, A: Actor, TM: TreeMeta> Default for State<ID, TM, A> {
+impl<ID: TreeId, A: Actor, TM: TreeMeta, T: TieBreak<A>, R: Resolve<ID, TM, A>> Default
+    for State<ID, TM, A, T, R>
+{
     fn default() -> Self {
         Self::new()
     }
 }
 
+// writes a clock compactly, as (actor, counter).
+pub(crate) fn fmt_clock<A: Actor + fmt::Debug>(
+    f: &mut fmt::Formatter<'_>,
+    clock: &Clock<A>,
+) -> fmt::Result {
+    write!(f, "({:?}, {})", clock.actor_id(), clock.counter())
+}
+
+/// Displays a summary of the state, rather than its contents: the size
+/// of the tree, and the length of the log, its timestamp range and the
+/// number of entries by each actor.
+///
+/// Takes time linear in the length of the log.
+impl<ID, TM, A, T, R> fmt::Display for State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tree: {} nodes, max depth {}; log: {} entries",
+            self.tree.num_nodes(),
+            self.tree.max_depth(),
+            self.log_op_list.len()
+        )?;
+        if let (Some(newest), Some(oldest)) = (self.log_op_list.first(), self.log_op_list.last()) {
+            let mut by_actor: BTreeMap<&A, usize> = BTreeMap::new();
+            for entry in &self.log_op_list {
+                *by_actor.entry(entry.timestamp().actor_id()).or_insert(0) += 1;
+            }
+            write!(f, ", ")?;
+            fmt_clock(f, oldest.timestamp())?;
+            write!(f, " to ")?;
+            fmt_clock(f, newest.timestamp())?;
+            write!(f, ", by actor {:?}", by_actor)?;
+        }
+        Ok(())
+    }
+}
+
+// zstd level used by to_bytes_compressed.  zstd's default.
+#[cfg(feature = "compression")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+#[cfg(feature = "compression")]
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId + Serialize + serde::de::DeserializeOwned,
+    TM: TreeMeta + Serialize + serde::de::DeserializeOwned,
+    A: Actor + Serialize + serde::de::DeserializeOwned,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// returns the state encoded with `codec::encode` and compressed with
+    /// zstd, for persisting or sending full states.
+    ///
+    /// Requires the `compression` feature.
+    pub fn to_bytes_compressed(&self) -> Result<Vec<u8>, CodecError> {
+        let compression = |e: std::io::Error| CodecError::Compression(e.to_string());
+        let mut encoder = zstd::Encoder::new(vec![], COMPRESSION_LEVEL).map_err(compression)?;
+        codec::encode_into(&mut encoder, self)?;
+        encoder.finish().map_err(compression)
+    }
+
+    /// returns a state from bytes, as returned by `to_bytes_compressed`.
+    ///
+    /// Requires the `compression` feature.
+    pub fn from_bytes_compressed(bytes: &[u8]) -> Result<Self, CodecError> {
+        let decoder =
+            zstd::Decoder::new(bytes).map_err(|e| CodecError::Compression(e.to_string()))?;
+        codec::decode_from(decoder)
+    }
+}
+
 // to make clippy happy.
 type LogOpList<ID, TM, A> = Vec<LogOpMove<ID, TM, A>>;
 
-impl<ID: TreeId, A: Actor, TM: TreeMeta> From<(Vec<LogOpMove<ID, TM, A>>, Tree<ID, TM>)>
-    for State<ID, TM, A>
+impl<ID: TreeId, A: Actor, TM: TreeMeta, T: TieBreak<A>, R: Resolve<ID, TM, A>>
+    From<(Vec<LogOpMove<ID, TM, A>>, Tree<ID, TM>)> for State<ID, TM, A, T, R>
 {
     /// creates State from tuple `(Vec<LogOpMove>, Tree)`
     fn from(e: (LogOpList<ID, TM, A>, Tree<ID, TM>)) -> Self {
-        Self {
+        let mut state = Self {
             log_op_list: e.0,
             tree: e.1,
-        }
+            truncated_before: None,
+            compacted_from: None,
+            tie_break: PhantomData,
+            resolve: PhantomData,
+            quotas: Quotas::default(),
+            root: None,
+            strict: None,
+        };
+        state.share_nodes();
+        state
     }
 }
 
-impl<ID: TreeId, TM: TreeMeta, A: Actor> CmRDT for State<ID, TM, A> {
+// the serialized fields of State.  the settings are not serialized.
+#[derive(Deserialize)]
+struct StateData<ID: TreeId, TM: TreeMeta, A: Actor> {
+    log_op_list: LogOpList<ID, TM, A>,
+    tree: Tree<ID, TM>,
+    // absent from states serialized before it was recorded.
+    #[serde(default = "Option::default")]
+    truncated_before: Option<Clock<A>>,
+    #[serde(default = "Option::default")]
+    compacted_from: Option<Clock<A>>,
+}
+
+impl<'de, ID, TM, A, T, R> Deserialize<'de> for State<ID, TM, A, T, R>
+where
+    ID: TreeId + Deserialize<'de>,
+    TM: TreeMeta + Deserialize<'de>,
+    A: Actor + Deserialize<'de>,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = StateData::deserialize(deserializer)?;
+        let mut state = Self::from((data.log_op_list, data.tree));
+        state.truncated_before = data.truncated_before;
+        state.compacted_from = data.compacted_from;
+        Ok(state)
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>> CmRDT
+    for State<ID, TM, A, T, R>
+{
     type Op = OpMove<ID, TM, A>;
 
     /// Apply an operation to a `State` instance.
@@ -241,13 +965,26 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor> CmRDT for State<ID, TM, A> {
 
 /// Implement `IntoIterator` for `State`.  This is useful for
 /// walking all Nodes in a tree without knowing a starting point.
-impl<ID: TreeId, TM: TreeMeta, A: Actor> IntoIterator for State<ID, TM, A> {
+impl<ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>> IntoIterator
+    for State<ID, TM, A, T, R>
+{
     type Item = (ID, TreeNode<ID, TM>);
-    type IntoIter = std::collections::hash_map::IntoIter<ID, TreeNode<ID, TM>>;
+    type IntoIter = TreeIntoIter<ID, TM>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.tree.into_iter()
     }
 }
 
+impl<'a, ID: TreeId, TM: TreeMeta, A: Actor, T: TieBreak<A>, R: Resolve<ID, TM, A>> IntoIterator
+    for &'a State<ID, TM, A, T, R>
+{
+    type Item = (&'a ID, &'a TreeNode<ID, TM>);
+    type IntoIter = TreeIter<'a, ID, TM>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 // See <root>/tests/tree.rs for tests
@@ -0,0 +1,117 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::ops::Bound;
+
+use super::{Clock, OpMove, Snapshot, TreeId, TreeMeta};
+use crdts::Actor;
+
+/// `Storage` is implemented by backends that durably persist a replica's
+/// ops and snapshots.  See `StoredReplica`.
+///
+/// Ops are keyed by timestamp, which is unique per op.  Snapshots are
+/// keyed by checkpoint id.
+pub trait Storage<ID: TreeId, TM: TreeMeta, A: Actor> {
+    /// error returned by storage operations.
+    type Error: std::error::Error;
+
+    /// stores a snapshot.
+    fn put_snapshot(&mut self, snapshot: &Snapshot<ID, TM, A>) -> Result<(), Self::Error>;
+
+    /// returns the snapshot with the highest checkpoint id, if any.
+    fn latest_snapshot(&self) -> Result<Option<Snapshot<ID, TM, A>>, Self::Error>;
+
+    /// stores an op.  An op with the same timestamp is replaced.
+    fn append_op(&mut self, op: &OpMove<ID, TM, A>) -> Result<(), Self::Error>;
+
+    /// returns stored ops with timestamps between from and to, oldest first.
+    fn scan_ops(
+        &self,
+        from: Bound<&Clock<A>>,
+        to: Bound<&Clock<A>>,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, Self::Error>;
+
+    /// removes stored ops older than timestamp, returning how many were
+    /// removed.
+    fn remove_ops_before(&mut self, timestamp: &Clock<A>) -> Result<usize, Self::Error>;
+}
+
+/// `MemoryStorage` is a `Storage` that keeps everything in memory.
+///
+/// It is not durable, and is intended for tests and for applications
+/// that persist by other means.
+#[derive(Debug, Clone)]
+pub struct MemoryStorage<ID: TreeId, TM: TreeMeta, A: Actor> {
+    snapshots: BTreeMap<u64, Snapshot<ID, TM, A>>,
+    ops: BTreeMap<Clock<A>, OpMove<ID, TM, A>>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> MemoryStorage<ID, TM, A> {
+    /// returns new, empty MemoryStorage
+    pub fn new() -> Self {
+        Self {
+            snapshots: BTreeMap::new(),
+            ops: BTreeMap::new(),
+        }
+    }
+
+    /// returns number of stored ops
+    #[inline]
+    pub fn num_ops(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// returns number of stored snapshots
+    #[inline]
+    pub fn num_snapshots(&self) -> usize {
+        self.snapshots.len()
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Default for MemoryStorage<ID, TM, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Storage<ID, TM, A> for MemoryStorage<ID, TM, A> {
+    type Error = Infallible;
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot<ID, TM, A>) -> Result<(), Infallible> {
+        self.snapshots.insert(snapshot.id(), snapshot.clone());
+        Ok(())
+    }
+
+    fn latest_snapshot(&self) -> Result<Option<Snapshot<ID, TM, A>>, Infallible> {
+        Ok(self.snapshots.values().next_back().cloned())
+    }
+
+    fn append_op(&mut self, op: &OpMove<ID, TM, A>) -> Result<(), Infallible> {
+        self.ops.insert(op.timestamp().clone(), op.clone());
+        Ok(())
+    }
+
+    fn scan_ops(
+        &self,
+        from: Bound<&Clock<A>>,
+        to: Bound<&Clock<A>>,
+    ) -> Result<Vec<OpMove<ID, TM, A>>, Infallible> {
+        Ok(self
+            .ops
+            .range((from, to))
+            .map(|(_, op)| op.clone())
+            .collect())
+    }
+
+    fn remove_ops_before(&mut self, timestamp: &Clock<A>) -> Result<usize, Infallible> {
+        let kept = self.ops.split_off(timestamp);
+        let removed = self.ops.len();
+        self.ops = kept;
+        Ok(removed)
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::ops::Bound;
+
+use super::{Clock, OpMove, Storage, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// `StoredReplica` is a `TreeReplica` that persists every op to a
+/// `Storage` backend before applying it.
+///
+/// Snapshots are written by `checkpoint`, either explicitly or after
+/// every `checkpoint_interval` ops.  A checkpoint also removes stored ops
+/// that are reflected in the snapshot and can no longer be undone.
+///
+/// `open` restores the replica from the latest snapshot plus the stored
+/// ops applied after it.
+pub struct StoredReplica<ID: TreeId, TM: TreeMeta, A: Actor, S: Storage<ID, TM, A>> {
+    replica: TreeReplica<ID, TM, A>,
+    storage: S,
+    checkpoint_id: u64,
+    checkpoint_interval: Option<usize>,
+    ops_since_checkpoint: usize,
+}
+
+impl<ID, TM, A, S> StoredReplica<ID, TM, A, S>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + Debug,
+    S: Storage<ID, TM, A>,
+{
+    /// opens a replica stored in storage.
+    ///
+    /// If storage holds no snapshot, the replica is created for actor
+    /// and all stored ops are applied.  Otherwise actor is ignored and the
+    /// replica is restored from the latest snapshot.
+    pub fn open(actor: A, storage: S) -> Result<Self, S::Error> {
+        let (replica, checkpoint_id, ops) = match storage.latest_snapshot()? {
+            Some(snapshot) => {
+                let checkpoint_id = snapshot.id();
+                // ops older than the threshold are reflected in the tree, and
+                // those in the snapshot's log are already applied.
+                let applied: HashSet<&Clock<A>> =
+                    snapshot.log().iter().map(|e| e.timestamp()).collect();
+                let from = match snapshot.causally_stable_threshold() {
+                    Some(t) => Bound::Included(t),
+                    None => Bound::Unbounded,
+                };
+                let ops: Vec<OpMove<ID, TM, A>> = storage
+                    .scan_ops(from, Bound::Unbounded)?
+                    .into_iter()
+                    .filter(|op| !applied.contains(op.timestamp()))
+                    .collect();
+                (TreeReplica::restore(snapshot, vec![]), checkpoint_id, ops)
+            }
+            None => (
+                TreeReplica::new(actor),
+                0,
+                storage.scan_ops(Bound::Unbounded, Bound::Unbounded)?,
+            ),
+        };
+        let mut stored = Self {
+            replica,
+            storage,
+            checkpoint_id,
+            checkpoint_interval: None,
+            ops_since_checkpoint: 0,
+        };
+        stored.replica.apply_ops(ops);
+        Ok(stored)
+    }
+
+    /// sets the number of ops after which a checkpoint is taken
+    /// automatically.  None disables automatic checkpoints.
+    pub fn with_checkpoint_interval(mut self, interval: Option<usize>) -> Self {
+        self.checkpoint_interval = interval;
+        self
+    }
+
+    /// returns the underlying replica
+    #[inline]
+    pub fn replica(&self) -> &TreeReplica<ID, TM, A> {
+        &self.replica
+    }
+
+    /// returns the storage backend
+    #[inline]
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// returns the replica and storage, consuming self.
+    pub fn into_parts(self) -> (TreeReplica<ID, TM, A>, S) {
+        (self.replica, self.storage)
+    }
+
+    /// returns id of the latest checkpoint, or 0 if there is none.
+    #[inline]
+    pub fn checkpoint_id(&self) -> u64 {
+        self.checkpoint_id
+    }
+
+    /// persists op, then applies it.
+    pub fn apply_op(&mut self, op: OpMove<ID, TM, A>) -> Result<(), S::Error> {
+        self.storage.append_op(&op)?;
+        self.replica.apply_op(op);
+        self.ops_since_checkpoint += 1;
+        match self.checkpoint_interval {
+            Some(n) if self.ops_since_checkpoint >= n => self.checkpoint(),
+            _ => Ok(()),
+        }
+    }
+
+    /// persists and applies a list of ops, in order.
+    pub fn apply_ops(&mut self, ops: Vec<OpMove<ID, TM, A>>) -> Result<(), S::Error> {
+        for op in ops {
+            self.apply_op(op)?;
+        }
+        Ok(())
+    }
+
+    /// persists a snapshot of the replica, and removes stored ops that
+    /// are causally stable.
+    pub fn checkpoint(&mut self) -> Result<(), S::Error> {
+        let snapshot = self.replica.snapshot(self.checkpoint_id + 1);
+        self.storage.put_snapshot(&snapshot)?;
+        self.checkpoint_id = snapshot.id();
+        self.ops_since_checkpoint = 0;
+        if let Some(t) = snapshot.causally_stable_threshold() {
+            self.storage.remove_ops_before(t)?;
+        }
+        Ok(())
+    }
+}
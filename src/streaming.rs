@@ -0,0 +1,216 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Streams of ops and snapshots, read and applied a message at a time.
+//!
+//! A stream is a sequence of `codec` messages.  An op stream holds one
+//! `OpMove` message per op, and ends at the end of the reader.  A snapshot
+//! stream holds a header, then one message per tree node, then one per log
+//! entry, so neither the encoded snapshot nor a list of ops need be held
+//! in memory before they are applied.
+//!
+//! Requires the `codec` feature.
+
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::codec::{self, CodecError, Wire};
+use super::{
+    Clock, LogOpMove, OpMove, Resolve, Snapshot, TieBreak, Tree, TreeId, TreeMeta, TreeNode,
+    TreeReplica, VersionVector,
+};
+use crdts::Actor;
+
+// the first message of a snapshot stream.
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader<ID, A: Actor> {
+    id: u64,
+    time: Clock<A>,
+    latest_time_by_replica: VersionVector<A>,
+    num_nodes: u64,
+    num_entries: u64,
+    detached: Vec<ID>,
+    truncated_before: Option<Clock<A>>,
+}
+
+impl<ID, A> Wire for SnapshotHeader<ID, A>
+where
+    ID: Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 7;
+}
+
+// a tree node in a snapshot stream.
+#[derive(Serialize, Deserialize)]
+struct NodeMessage<ID: TreeId, TM: TreeMeta> {
+    child_id: ID,
+    node: TreeNode<ID, TM>,
+}
+
+impl<ID, TM> Wire for NodeMessage<ID, TM>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+{
+    const KIND: u8 = 8;
+}
+
+/// writes ops to writer as an op stream, and returns the number written.
+pub fn write_ops<W, I, ID, TM, A>(mut writer: W, ops: I) -> Result<usize, CodecError>
+where
+    W: Write,
+    I: IntoIterator<Item = OpMove<ID, TM, A>>,
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    let mut written = 0;
+    for op in ops {
+        codec::encode_into(&mut writer, &op)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// `OpStream` is an iterator over the ops of an op stream, as written by
+/// `write_ops`, decoding each as it is read.
+///
+/// Iteration ends at the end of the reader, or after the first error.
+pub struct OpStream<R, ID: TreeId, TM: TreeMeta, A: Actor> {
+    reader: R,
+    failed: bool,
+    phantom: PhantomData<OpMove<ID, TM, A>>,
+}
+
+impl<R: BufRead, ID: TreeId, TM: TreeMeta, A: Actor> OpStream<R, ID, TM, A> {
+    /// creates a stream reading ops from reader, eg a `BufReader` of a
+    /// file or socket.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            failed: false,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<R, ID, TM, A> Iterator for OpStream<R, ID, TM, A>
+where
+    R: BufRead,
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    type Item = Result<OpMove<ID, TM, A>, CodecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(CodecError::Payload(e.to_string())));
+            }
+        }
+        let op = codec::decode_from(&mut self.reader);
+        self.failed = op.is_err();
+        Some(op)
+    }
+}
+
+/// writes snapshot to writer as a snapshot stream.
+pub fn write_snapshot<W, ID, TM, A>(
+    mut writer: W,
+    snapshot: &Snapshot<ID, TM, A>,
+) -> Result<(), CodecError>
+where
+    W: Write,
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    let tree = snapshot.tree();
+    let header = SnapshotHeader {
+        id: snapshot.id(),
+        time: snapshot.time().clone(),
+        latest_time_by_replica: snapshot.latest_time_by_replica().clone(),
+        num_nodes: tree.num_nodes() as u64,
+        num_entries: snapshot.log().len() as u64,
+        detached: tree.detached_ids(),
+        truncated_before: snapshot.truncated_before().cloned(),
+    };
+    codec::encode_into(&mut writer, &header)?;
+    for (child_id, node) in tree.iter() {
+        let message = NodeMessage {
+            child_id: child_id.clone(),
+            node: node.clone(),
+        };
+        codec::encode_into(&mut writer, &message)?;
+    }
+    for entry in snapshot.log() {
+        codec::encode_into(&mut writer, entry)?;
+    }
+    Ok(())
+}
+
+/// reads a snapshot stream, as written by `write_snapshot`, building the
+/// snapshot as each message is decoded.
+pub fn read_snapshot<R, ID, TM, A>(mut reader: R) -> Result<Snapshot<ID, TM, A>, CodecError>
+where
+    R: BufRead,
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + Serialize + DeserializeOwned,
+{
+    let header: SnapshotHeader<ID, A> = codec::decode_from(&mut reader)?;
+    let mut tree = Tree::new();
+    for _ in 0..header.num_nodes {
+        let message: NodeMessage<ID, TM> = codec::decode_from(&mut reader)?;
+        tree.add_node(message.child_id, message.node);
+    }
+    tree.mark_detached(header.detached);
+    let mut log = vec![];
+    for _ in 0..header.num_entries {
+        let entry: LogOpMove<ID, TM, A> = codec::decode_from(&mut reader)?;
+        log.push(entry);
+    }
+    Ok(Snapshot::new(
+        header.id,
+        tree,
+        log,
+        header.truncated_before,
+        header.time,
+        header.latest_time_by_replica,
+    ))
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId + Serialize + DeserializeOwned,
+    TM: TreeMeta + Serialize + DeserializeOwned,
+    A: Actor + std::fmt::Debug + Serialize + DeserializeOwned,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// applies the ops of an op stream, as written by `write_ops`, as each
+    /// is decoded, and returns the number read.
+    ///
+    /// On error, the ops read before it remain applied.
+    pub fn apply_op_stream<B: BufRead>(&mut self, reader: B) -> Result<usize, CodecError> {
+        let mut read = 0;
+        for op in OpStream::new(reader) {
+            self.apply_op(op?);
+            read += 1;
+        }
+        Ok(read)
+    }
+}
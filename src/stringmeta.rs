@@ -0,0 +1,102 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+
+/// `StringMeta` is metadata holding a name, eg of a file or directory, for
+/// trees that need nothing more.
+///
+/// `StringMeta` derefs to `str`, and is formatted and serialized as the
+/// string itself.
+///
+/// ```text
+/// let mut r: U64TreeReplica<StringMeta> = TreeReplica::new(1);
+/// r.apply_op(r.opmove(0, "home".into(), 1));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StringMeta(String);
+
+impl StringMeta {
+    /// creates metadata holding name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// returns the name.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// returns the name, consuming self.
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for StringMeta {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for StringMeta {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for StringMeta {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for StringMeta {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for StringMeta {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl From<StringMeta> for String {
+    fn from(meta: StringMeta) -> Self {
+        meta.0
+    }
+}
+
+impl PartialEq<str> for StringMeta {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for StringMeta {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for StringMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
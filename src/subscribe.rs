@@ -0,0 +1,129 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use log::warn;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use super::{OpMove, TreeId, TreeMeta};
+use crdts::Actor;
+
+// number of ops buffered for each subscriber.  a subscriber that falls
+// further behind misses ops.
+const CAPACITY: usize = 1024;
+
+/// `ApplyOutcome` is what became of an op passed to
+/// `TreeReplica::apply_op`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApplyOutcome {
+    /// the op was applied to the tree state.
+    Applied,
+    /// the op had already been applied, and was skipped.
+    Duplicate,
+    /// the op was rejected by the drift guard or the log limit, or as its
+    /// actor was tombstoned by `TreeReplica::forget_actor`.
+    Rejected,
+}
+
+/// `AppliedOp` is an op passed to `TreeReplica::apply_op`, with its
+/// outcome.  See `TreeReplica::subscribe`.
+///
+/// Requires the `tokio` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedOp<ID: TreeId, TM: TreeMeta, A: Actor> {
+    op: OpMove<ID, TM, A>,
+    outcome: ApplyOutcome,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> AppliedOp<ID, TM, A> {
+    /// returns the op
+    #[inline]
+    pub fn op(&self) -> &OpMove<ID, TM, A> {
+        &self.op
+    }
+
+    /// returns the op's outcome
+    #[inline]
+    pub fn outcome(&self) -> ApplyOutcome {
+        self.outcome
+    }
+
+    /// returns the op, consuming self.
+    #[inline]
+    pub fn into_op(self) -> OpMove<ID, TM, A> {
+        self.op
+    }
+}
+
+// Subscribers holds the channel to a replica's subscribers, if any.
+//
+// It is a local setting of the replica, so a clone of the replica starts
+// without subscribers, and replicas compare equal regardless.
+#[derive(Debug)]
+pub(crate) struct Subscribers<ID: TreeId, TM: TreeMeta, A: Actor> {
+    sender: Option<broadcast::Sender<AppliedOp<ID, TM, A>>>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Subscribers<ID, TM, A> {
+    // returns a stream of ops notified from now on.
+    pub(crate) fn subscribe(&mut self) -> impl Stream<Item = AppliedOp<ID, TM, A>> + Unpin
+    where
+        ID: Send + 'static,
+        TM: Send + 'static,
+        A: Send + 'static,
+    {
+        let receiver = match &self.sender {
+            Some(sender) => sender.subscribe(),
+            None => {
+                let (sender, receiver) = broadcast::channel(CAPACITY);
+                self.sender = Some(sender);
+                receiver
+            }
+        };
+        BroadcastStream::new(receiver).filter_map(|op| match op {
+            Ok(op) => Some(op),
+            Err(e) => {
+                warn!("subscriber fell behind, ops dropped: {}", e);
+                None
+            }
+        })
+    }
+
+    // sends op and its outcome to any subscribers.  op is cloned only if
+    // there are subscribers.
+    pub(crate) fn notify(&self, op: &OpMove<ID, TM, A>, outcome: ApplyOutcome) {
+        if let Some(sender) = &self.sender {
+            if sender.receiver_count() > 0 {
+                // fails only if every receiver was dropped meanwhile.
+                let _ = sender.send(AppliedOp {
+                    op: op.clone(),
+                    outcome,
+                });
+            }
+        }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Default for Subscribers<ID, TM, A> {
+    fn default() -> Self {
+        Self { sender: None }
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Clone for Subscribers<ID, TM, A> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> PartialEq for Subscribers<ID, TM, A> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> Eq for Subscribers<ID, TM, A> {}
@@ -0,0 +1,172 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Clock, OpMove, Resolve, State, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+impl<ID, TM, A, T, R> State<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + Default,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// returns ops creating id, if it is a node, and every node under it,
+    /// with their current parents and metadata.  Each node's op precedes
+    /// the ops of its children.
+    ///
+    /// The ops carry placeholder timestamps, of the default actor, so
+    /// must be re-stamped before being applied, eg by
+    /// `TreeReplica::import_subtree`.
+    pub fn export_subtree(&self, id: &ID) -> Vec<OpMove<ID, TM, A>> {
+        let mut time = Clock::new(A::default(), None);
+        let mut ops = vec![];
+        self.tree().walk(id, |tree, child_id, _| {
+            if let Some(node) = tree.find(child_id) {
+                ops.push(OpMove::new(
+                    time.tick(),
+                    node.parent_id().clone(),
+                    node.metadata().clone(),
+                    child_id.clone(),
+                ));
+            }
+        });
+        ops
+    }
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + std::fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// applies ops exported by `State::export_subtree`, eg from another
+    /// tree, re-stamped as ops of this replica, and returns them, for
+    /// sending to peers.
+    ///
+    /// The top of the subtree, ie each op whose parent is not created by
+    /// another of the ops, is placed under parent_id.
+    pub fn import_subtree<B: Actor>(
+        &mut self,
+        ops: Vec<OpMove<ID, TM, B>>,
+        parent_id: ID,
+    ) -> Vec<OpMove<ID, TM, A>> {
+        let created: HashSet<ID> = ops.iter().map(|op| op.child_id().clone()).collect();
+        let moves = ops
+            .into_iter()
+            .map(|op| {
+                let parent = if created.contains(op.parent_id()) {
+                    op.parent_id().clone()
+                } else {
+                    parent_id.clone()
+                };
+                (parent, op.metadata().clone(), op.child_id().clone())
+            })
+            .collect();
+        let ops = self.opmoves(moves);
+        self.apply_ops_byref(&ops);
+        ops
+    }
+
+    /// copies the subtree rooted at id, ie id and every node under it,
+    /// under parent_id, and returns the generated ops, for sending to
+    /// peers.  Structure and metadata are preserved.
+    ///
+    /// new_id is called with the ID of each copied node, in the order
+    /// nodes are created, parents first, and returns the copy's ID, which
+    /// must not already be in the tree, eg a fresh UUID.
+    ///
+    /// id may be copied under itself, or any of its descendants, as only
+    /// nodes present before the copy are copied.  Returns no ops if id is
+    /// not a node.
+    pub fn copy_subtree<F>(
+        &mut self,
+        id: &ID,
+        parent_id: ID,
+        mut new_id: F,
+    ) -> Vec<OpMove<ID, TM, A>>
+    where
+        F: FnMut(&ID) -> ID,
+    {
+        if self.tree().find(id).is_none() {
+            return vec![];
+        }
+        let mut copies: HashMap<ID, ID> = HashMap::new();
+        let mut moves = vec![];
+        self.tree().walk(id, |tree, child_id, depth| {
+            if let Some(node) = tree.find(child_id) {
+                let parent = if depth == 0 {
+                    parent_id.clone()
+                } else {
+                    copies[node.parent_id()].clone()
+                };
+                let copy = new_id(child_id);
+                copies.insert(child_id.clone(), copy.clone());
+                moves.push((parent, node.metadata().clone(), copy));
+            }
+        });
+        let ops = self.opmoves(moves);
+        self.apply_ops_byref(&ops);
+        ops
+    }
+
+    /// grafts the subtree of other, a state of another tree, rooted at
+    /// other_id under parent_id, and returns the generated ops, for sending
+    /// to peers.  Structure and metadata are preserved.  Use it to merge
+    /// independent trees, eg workspaces.
+    ///
+    /// other_id is grafted under parent_id if it is a node of other, else
+    /// its children are, eg those of other's root.  Only the current tree
+    /// is grafted, not other's log.
+    ///
+    /// map_id is called with each grafted ID that collides with this tree,
+    /// ie is parent_id, a node or the parent of one, in the order nodes
+    /// are created, parents first.  It returns the ID to use instead, which
+    /// must not collide either, eg a fresh UUID.  Other IDs are kept.
+    pub fn graft<B, T2, R2, F>(
+        &mut self,
+        other: &State<ID, TM, B, T2, R2>,
+        other_id: &ID,
+        parent_id: ID,
+        mut map_id: F,
+    ) -> Vec<OpMove<ID, TM, A>>
+    where
+        B: Actor,
+        T2: TieBreak<B>,
+        R2: Resolve<ID, TM, B>,
+        F: FnMut(&ID) -> ID,
+    {
+        let tree = self.tree();
+        let collides =
+            |id: &ID| id == &parent_id || tree.find(id).is_some() || tree.num_children(id) > 0;
+        let mut grafted: HashMap<ID, ID> = HashMap::new();
+        let mut moves = vec![];
+        other.tree().walk(other_id, |other_tree, child_id, _| {
+            if let Some(node) = other_tree.find(child_id) {
+                let parent = match grafted.get(node.parent_id()) {
+                    Some(id) => id.clone(),
+                    None => parent_id.clone(),
+                };
+                let id = if collides(child_id) {
+                    map_id(child_id)
+                } else {
+                    child_id.clone()
+                };
+                grafted.insert(child_id.clone(), id.clone());
+                moves.push((parent, node.metadata().clone(), id));
+            }
+        });
+        let ops = self.opmoves(moves);
+        self.apply_ops_byref(&ops);
+        ops
+    }
+}
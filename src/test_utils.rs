@@ -0,0 +1,207 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! proptest strategies generating realistic op histories, for property
+//! testing layers built on this crate.
+//!
+//! Histories come from simulating a group of replicas.  At each step, one
+//! replica either moves a node, new or existing, under the root or under
+//! a node in its tree, or catches up with another replica's ops.  So ops
+//! carry the Lamport timestamps replicas really generate: ops made without
+//! sight of each other are concurrent, and the rest are causally ordered.
+//!
+//! ```text
+//! proptest! {
+//!     fn converges(history in op_history(3, 0..50)) {
+//!         let mut a = TestReplica::new(0);
+//!         a.apply_ops(history.ops().to_vec());
+//!         ...
+//!     }
+//! }
+//! ```
+//!
+//! Node 0 is the root, and never itself a node in a tree.
+//!
+//! Requires the `testing` feature.
+
+use std::collections::VecDeque;
+
+use proptest::collection::{vec, SizeRange};
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use super::{OpMove, TreeReplica};
+
+/// node ID type of generated ops.
+pub type TestId = u64;
+/// metadata type of generated ops.
+pub type TestMeta = char;
+/// actor ID type of generated ops.
+pub type TestActor = u64;
+/// an op generated by a strategy in this module.
+pub type TestOp = OpMove<TestId, TestMeta, TestActor>;
+/// a replica generated by a strategy in this module.
+pub type TestReplica = TreeReplica<TestId, TestMeta, TestActor>;
+
+/// the root node ID.
+pub const ROOT: TestId = 0;
+
+/// `OpHistory` is the ops generated by a group of simulated replicas, in
+/// the order generated.
+#[derive(Debug, Clone)]
+pub struct OpHistory {
+    ops: Vec<TestOp>,
+    replicas: Vec<TestReplica>,
+}
+
+impl OpHistory {
+    /// returns the ops, in the order generated.
+    pub fn ops(&self) -> &[TestOp] {
+        &self.ops
+    }
+
+    /// returns the ops generated by actor, in the order generated.
+    pub fn ops_by(&self, actor: TestActor) -> Vec<TestOp> {
+        self.ops
+            .iter()
+            .filter(|op| *op.timestamp().actor_id() == actor)
+            .cloned()
+            .collect()
+    }
+
+    /// returns the simulated replicas, as left at the end of the history.
+    /// Replica i has actor ID i.  Each has applied its own ops, but may
+    /// not have caught up with others.
+    pub fn replicas(&self) -> &[TestReplica] {
+        &self.replicas
+    }
+}
+
+// a step of the simulation.
+#[derive(Debug, Clone)]
+enum Step {
+    // actor moves child, or a new node if None, under parent.
+    Move {
+        actor: Index,
+        parent: Index,
+        child: Option<Index>,
+        meta: TestMeta,
+    },
+    // to applies the ops from `from` that it is missing.
+    Sync {
+        from: Index,
+        to: Index,
+    },
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        3 => (
+            any::<Index>(),
+            any::<Index>(),
+            proptest::option::of(any::<Index>()),
+            proptest::char::range('a', 'z'),
+        )
+            .prop_map(|(actor, parent, child, meta)| Step::Move {
+                actor,
+                parent,
+                child,
+                meta,
+            }),
+        1 => (any::<Index>(), any::<Index>()).prop_map(|(from, to)| Step::Sync { from, to }),
+    ]
+}
+
+// runs steps with a replica per actor.
+fn simulate(actors: usize, steps: Vec<Step>) -> OpHistory {
+    let mut replicas: Vec<TestReplica> = (0..actors as TestActor).map(TestReplica::new).collect();
+    let mut ops = vec![];
+    let mut next_id = ROOT + 1;
+    for step in steps {
+        match step {
+            Step::Move {
+                actor,
+                parent,
+                child,
+                meta,
+            } => {
+                let r = &mut replicas[actor.index(actors)];
+                let mut nodes: Vec<TestId> = r.tree().iter().map(|(id, _)| *id).collect();
+                nodes.sort_unstable();
+                let parent_id = match parent.index(nodes.len() + 1) {
+                    0 => ROOT,
+                    i => nodes[i - 1],
+                };
+                let child_id = match child {
+                    Some(child) if !nodes.is_empty() => nodes[child.index(nodes.len())],
+                    _ => {
+                        next_id += 1;
+                        next_id - 1
+                    }
+                };
+                let op = r.opmove(parent_id, meta, child_id);
+                r.apply_op(op.clone());
+                ops.push(op);
+            }
+            Step::Sync { from, to } => {
+                let (from, to) = (from.index(actors), to.index(actors));
+                if let Some(missing) = replicas[from].missing_ops(replicas[to].version_vector()) {
+                    replicas[to].apply_ops(missing);
+                }
+            }
+        }
+    }
+    OpHistory { ops, replicas }
+}
+
+/// returns a strategy generating histories of `actors` replicas, with a
+/// number of steps in steps.
+///
+/// Panics if actors is 0.
+pub fn op_history(actors: usize, steps: impl Into<SizeRange>) -> impl Strategy<Value = OpHistory> {
+    assert!(actors > 0, "op_history needs at least one actor");
+    vec(step(), steps).prop_map(move |steps| simulate(actors, steps))
+}
+
+/// returns a strategy generating the ops of a history of `actors`
+/// replicas, in the order generated.  See `op_history`.
+pub fn ops(actors: usize, steps: impl Into<SizeRange>) -> impl Strategy<Value = Vec<TestOp>> {
+    op_history(actors, steps).prop_map(|history| history.ops)
+}
+
+/// returns a strategy generating the replicas at the end of a history of
+/// `actors` replicas.  See `OpHistory::replicas`.
+pub fn replicas(
+    actors: usize,
+    steps: impl Into<SizeRange>,
+) -> impl Strategy<Value = Vec<TestReplica>> {
+    op_history(actors, steps).prop_map(|history| history.replicas)
+}
+
+/// returns a strategy generating interleavings of ops: orders in which a
+/// peer might receive them, keeping the ops of each actor in their order
+/// in ops.
+pub fn interleaving(ops: Vec<TestOp>) -> impl Strategy<Value = Vec<TestOp>> {
+    let len = ops.len();
+    vec(any::<Index>(), len).prop_map(move |picks| {
+        let mut queues: Vec<VecDeque<TestOp>> = vec![];
+        for op in &ops {
+            let actor = *op.timestamp().actor_id() as usize;
+            if queues.len() <= actor {
+                queues.resize(actor + 1, VecDeque::new());
+            }
+            queues[actor].push_back(op.clone());
+        }
+        let mut interleaved = Vec::with_capacity(len);
+        for pick in picks {
+            let mut pending: Vec<&mut VecDeque<TestOp>> =
+                queues.iter_mut().filter(|q| !q.is_empty()).collect();
+            let i = pick.index(pending.len());
+            interleaved.extend(pending[i].pop_front());
+        }
+        interleaved
+    })
+}
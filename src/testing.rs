@@ -0,0 +1,109 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! quickcheck `Arbitrary` impls, for property tests here and downstream.
+//!
+//! `State` and `Tree` are generated by applying arbitrary ops, so they
+//! hold only states reachable in normal operation, and a `State` shrinks
+//! by shrinking the list of ops in its log.  Types holding shared nodes
+//! also require IDs and metadata to be `Sync`, as quickcheck requires
+//! `Send`.
+//!
+//! Requires the `testing` feature.
+
+use crdts::quickcheck::{Arbitrary, Gen};
+use crdts::Actor;
+
+use super::{Clock, LogOpMove, OpMove, Resolve, State, TieBreak, Tree, TreeId, TreeMeta, TreeNode};
+
+// Generate arbitrary (random) clocks.
+impl<A: Actor + Arbitrary> Arbitrary for Clock<A> {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        Self::new(A::arbitrary(g), Some(u64::arbitrary(g)))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let mut shrunk_clocks = Vec::new();
+        if self.counter() > 0 {
+            shrunk_clocks.push(Self::new(self.actor_id().clone(), Some(self.counter() - 1)));
+        }
+        Box::new(shrunk_clocks.into_iter())
+    }
+}
+
+impl<ID: TreeId + Arbitrary, A: Actor + Arbitrary, TM: TreeMeta + Arbitrary> Arbitrary
+    for OpMove<ID, TM, A>
+{
+    /// generates an arbitrary (random) OpMove
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        Self::new(
+            Clock::arbitrary(g),
+            ID::arbitrary(g),
+            TM::arbitrary(g),
+            ID::arbitrary(g),
+        )
+    }
+}
+
+impl<ID, TM> Arbitrary for TreeNode<ID, TM>
+where
+    ID: TreeId + Arbitrary + Sync,
+    TM: TreeMeta + Arbitrary + Sync,
+{
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        Self::new(ID::arbitrary(g), TM::arbitrary(g))
+    }
+}
+
+impl<ID, TM, A> Arbitrary for LogOpMove<ID, TM, A>
+where
+    ID: TreeId + Arbitrary + Sync,
+    TM: TreeMeta + Arbitrary + Sync,
+    A: Actor + Arbitrary,
+{
+    /// generates an arbitrary log entry.  Note that oldp is independent
+    /// of the op, so the entry need not be one a `State` would log.
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        Self::new(OpMove::arbitrary(g), Option::arbitrary(g))
+    }
+}
+
+impl<ID, TM, A, T, R> Arbitrary for State<ID, TM, A, T, R>
+where
+    ID: TreeId + Arbitrary + Sync,
+    TM: TreeMeta + Arbitrary + Sync,
+    A: Actor + Arbitrary,
+    T: TieBreak<A> + Send + 'static,
+    R: Resolve<ID, TM, A> + Send + 'static,
+{
+    /// generates a state by applying arbitrary ops.
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let mut state = Self::new();
+        state.apply_ops_into(Vec::arbitrary(g));
+        state
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let ops: Vec<OpMove<ID, TM, A>> =
+            self.log().iter().rev().cloned().map(Into::into).collect();
+        Box::new(ops.shrink().map(|ops| {
+            let mut state = Self::new();
+            state.apply_ops_into(ops);
+            state
+        }))
+    }
+}
+
+impl<ID, TM> Arbitrary for Tree<ID, TM>
+where
+    ID: TreeId + Arbitrary + Sync,
+    TM: TreeMeta + Arbitrary + Sync,
+{
+    /// generates a tree by applying arbitrary ops.
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        State::<ID, TM, u8>::arbitrary(g).tree().clone()
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Strategies for ordering ops whose timestamps have equal counters.
+//!
+//! Ops are applied in timestamp order, and timestamps are ordered by
+//! counter.  Concurrent ops frequently share a counter, and the op that
+//! sorts last wins.  By default, the op with the greater actor wins, which
+//! consistently privileges the same replicas.
+//!
+//! A `State` or `TreeReplica` may be given another strategy as its last
+//! type parameter, eg
+//!
+//! ```text
+//! let mut r: TreeReplica<u64, String, u8, HashOrder> = TreeReplica::new(1);
+//! ```
+//!
+//! Every replica of a tree must use the same strategy, else they may
+//! not converge.
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use super::Clock;
+use crdts::Actor;
+
+/// `TieBreak` orders timestamps that have equal counters.
+pub trait TieBreak<A: Actor>: Debug + Clone + Default + PartialEq + Eq {
+    /// orders a and b, which have equal counters and different actors.
+    ///
+    /// This must be a total order over actors at each counter.  It must
+    /// not return `Ordering::Equal`, but if it does the actors are
+    /// compared instead.
+    fn tie_break(a: &Clock<A>, b: &Clock<A>) -> Ordering;
+
+    /// orders timestamps by counter, then by ::tie_break().
+    fn cmp(a: &Clock<A>, b: &Clock<A>) -> Ordering {
+        a.counter().cmp(&b.counter()).then_with(|| {
+            if a.actor_id() == b.actor_id() {
+                return Ordering::Equal;
+            }
+            Self::tie_break(a, b).then_with(|| a.actor_id().cmp(b.actor_id()))
+        })
+    }
+}
+
+/// `ActorOrder` orders by actor, the same as `Clock`'s `Ord`.  This is
+/// the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActorOrder;
+
+impl<A: Actor> TieBreak<A> for ActorOrder {
+    #[inline]
+    fn tie_break(a: &Clock<A>, b: &Clock<A>) -> Ordering {
+        a.actor_id().cmp(b.actor_id())
+    }
+}
+
+/// `HashOrder` orders by a hash of the whole timestamp, so which actor
+/// wins varies from one counter to the next.
+///
+/// The hash is FNV-1a over the timestamp's `Hash` impl, which is stable
+/// across Rust versions.  Note that std's `Hash` impls for integers are
+/// byte-order dependent, so all replicas must share the same endianness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashOrder;
+
+impl HashOrder {
+    // returns the hash used for ordering.
+    fn hash<A: Actor>(timestamp: &Clock<A>) -> u64 {
+        let mut hasher = Fnv1a::default();
+        timestamp.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<A: Actor> TieBreak<A> for HashOrder {
+    #[inline]
+    fn tie_break(a: &Clock<A>, b: &Clock<A>) -> Ordering {
+        Self::hash(a).cmp(&Self::hash(b))
+    }
+}
+
+// 64 bit FNV-1a.  std's DefaultHasher may change between releases, so is
+// unsuitable for ordering ops across replicas.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -4,13 +4,94 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
+use serde::de::Deserializer;
+use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
-use std::cmp::{Eq, PartialEq};
-use std::collections::{HashMap, HashSet};
+use std::cmp::{Eq, Ordering, PartialEq};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::fmt::Debug;
 
-use super::{TreeId, TreeMeta, TreeNode};
+use super::collections::{self, Map, Seq, SeqIntoIter, SeqIter, Set};
+use super::interner::{Handle, Interner};
+use super::nodeindex::{MetaIndex, NameIndex, NodeIndex, NodeIndexes};
+use super::tiebreak::Fnv1a;
+use super::{InvariantViolation, TreeId, TreeMeta, TreeNode};
+use std::hash::{Hash, Hasher};
+
+/// Returned by the callback passed to `Tree::try_walk` to control
+/// how the walk proceeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+    /// visit the children of the current node, then continue.
+    Continue,
+    /// skip the children of the current node, then continue.
+    Prune,
+    /// stop walking immediately.
+    Stop,
+}
+
+/// The path between two nodes, via their lowest common ancestor.  See
+/// `Tree::relative_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelativePath<ID> {
+    /// the number of steps up from the first node to the common ancestor
+    pub up: usize,
+    /// the nodes below the common ancestor down to the second node, top
+    /// first
+    pub down: Vec<ID>,
+}
+
+// order in which Tree::traverse visits nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Traversal {
+    DepthFirst,
+    BreadthFirst,
+}
+
+// to make clippy happy.
+type SiblingOrder<'a, ID> = &'a mut dyn FnMut(&ID, &ID) -> Ordering;
+
+// the order of children, roots and orphans returned by a tree.
+type ChildOrder<ID> = fn(&ID, &ID) -> Ordering;
+
+// hashes metadata into a tree's digest.
+type MetaHash<TM> = fn(&TM) -> u64;
+
+// hashes metadata that implements Hash.
+fn hash_meta<TM: Hash>(meta: &TM) -> u64 {
+    let mut hasher = Fnv1a::default();
+    meta.hash(&mut hasher);
+    hasher.finish()
+}
+
+// a multiset of heights, so that the greatest is known without scanning
+// them all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Heights(BTreeMap<usize, usize>); // height => count.
+
+impl Heights {
+    fn insert(&mut self, height: usize) {
+        *self.0.entry(height).or_insert(0) += 1;
+    }
+
+    fn remove(&mut self, height: usize) {
+        if let Some(cnt) = self.0.get_mut(&height) {
+            *cnt -= 1;
+            if *cnt == 0 {
+                self.0.remove(&height);
+            }
+        }
+    }
+
+    fn max(&self) -> Option<usize> {
+        self.0.keys().next_back().copied()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
 
 /// Implements `Tree`, a set of triples representing current tree structure.
 ///
@@ -34,34 +115,308 @@ use super::{TreeId, TreeMeta, TreeNode};
 /// the new parent-child relationship.
 /// ----
 /// [1] https://martin.kleppmann.com/papers/move-op.pdf
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Internally, each ID is interned once and the triples and indexes refer
+/// to nodes by a small handle, so large IDs such as hashes or UUIDs are
+/// not copied into every map and set.  The public API is in terms of ID.
+///
+/// With the `persistent` feature, the internal maps are persistent maps
+/// that share structure between clones, so cloning a tree, eg to
+/// serialize it in the background, is O(1).  Each clone is then copied
+/// piecemeal, only as nodes are modified.
+#[derive(Clone)]
 pub struct Tree<ID: TreeId, TM: TreeMeta> {
-    triples: HashMap<ID, TreeNode<ID, TM>>, // tree_nodes, indexed by child_id.
-    children: HashMap<ID, HashSet<ID>>,     // parent_id => [child_id].  index/optimization.
+    ids: Interner<ID>,                   // ID <=> handle.
+    nodes: Seq<Option<Node<ID, TM>>>,    // tree_nodes, indexed by child handle.
+    num_nodes: usize,                    // number of Some entries in nodes.
+    children: Map<Handle, Set<Handle>>,  // parent => [child].  index/optimization.
+    heights: Map<Handle, Heights>,       // parent => heights of its children.  index/optimization.
+    root_heights: Heights,               // heights of the roots.  index/optimization.
+    sizes: Map<Handle, usize>,           // parent => num descendants.  index/optimization.
+    roots: Set<Handle>,                  // top-level parents.  index/optimization.
+    detached: Set<Handle>,               // removed parents with children.  index/optimization.
+    indexes: NodeIndexes<ID, TM>,        // optional secondary indexes.
+    child_order: Option<ChildOrder<ID>>, // optional order of returned children.
+    digest: u64,                         // xor of the triples' hashes.
+    meta_hash: Option<MetaHash<TM>>,     // hashes metadata into digest, if set.
+}
+
+// a tree_node, plus the handle of its parent.
+#[derive(Clone)]
+struct Node<ID: TreeId, TM: TreeMeta> {
+    parent: Handle,
+    node: TreeNode<ID, TM>, // shared with log entries.
 }
 
 impl<ID: TreeId, TM: TreeMeta> Tree<ID, TM> {
     /// create a new Tree instance
     pub fn new() -> Self {
         Self {
-            triples: HashMap::<ID, TreeNode<ID, TM>>::new(), // tree_nodes, indexed by child_id.
-            children: HashMap::<ID, HashSet<ID>>::new(), // parent_id => [child_id].  index/optimization.
+            ids: Interner::default(),
+            nodes: Seq::new(),
+            num_nodes: 0,
+            children: Map::new(),
+            heights: Map::new(),
+            root_heights: Heights::default(),
+            sizes: Map::new(),
+            roots: Set::new(),
+            detached: Set::new(),
+            indexes: NodeIndexes::default(),
+            child_order: None,
+            digest: 0,
+            meta_hash: None,
+        }
+    }
+
+    // returns the node with handle h, if any.
+    #[inline]
+    fn node(&self, h: Handle) -> Option<&Node<ID, TM>> {
+        self.nodes.get(h as usize).and_then(|n| n.as_ref())
+    }
+
+    // returns the handle of child_id, if it is a node in the tree.
+    #[inline]
+    fn node_handle(&self, child_id: &ID) -> Option<Handle> {
+        self.ids.get(child_id).filter(|&h| self.node(h).is_some())
+    }
+
+    // returns IDs for a list of handles.
+    fn ids_of<'a, I: IntoIterator<Item = &'a Handle>>(&self, handles: I) -> Vec<ID> {
+        handles
+            .into_iter()
+            .map(|&h| self.ids.id(h).clone())
+            .collect()
+    }
+
+    // sorts ids by the child order, if any.
+    fn in_child_order(&self, mut ids: Vec<ID>) -> Vec<ID> {
+        if let Some(order) = self.child_order {
+            ids.sort_by(order);
+        }
+        ids
+    }
+
+    /// sets the order of the IDs returned by children(), roots() and
+    /// orphans(), and so of walks and Display output.  With None, the
+    /// default, the order is arbitrary, and may differ between runs and
+    /// replicas.
+    /// not used by crdt algo.
+    ///
+    /// Sorting costs O(k log k) for k children on each call.
+    ///
+    /// The order is not serialized, so must be set again after
+    /// deserializing a tree.
+    pub fn set_child_order(&mut self, order: Option<fn(&ID, &ID) -> Ordering>) {
+        self.child_order = order;
+    }
+
+    /// orders the IDs returned by children(), roots() and orphans() by
+    /// ID, as set_child_order(), so that walks and Display output are
+    /// identical across runs and replicas.
+    /// not used by crdt algo.
+    pub fn sort_children_by_id(&mut self)
+    where
+        ID: Ord,
+    {
+        self.set_child_order(Some(ID::cmp));
+    }
+
+    /// returns the order set by set_child_order(), if any.
+    /// not used by crdt algo.
+    pub fn child_order(&self) -> Option<fn(&ID, &ID) -> Ordering> {
+        self.child_order
+    }
+
+    // returns the hash of a triple, as xored into the digest.
+    fn triple_hash(&self, child_id: &ID, tt: &TreeNode<ID, TM>) -> u64 {
+        let mut hasher = Fnv1a::default();
+        child_id.hash(&mut hasher);
+        tt.parent_id().hash(&mut hasher);
+        if let Some(meta_hash) = self.meta_hash {
+            hasher.write_u64(meta_hash(tt.metadata()));
+        }
+        hasher.finish()
+    }
+
+    /// returns a hash of the tree's triples, which is kept up to date as
+    /// nodes are added, moved and removed, so costs O(1).
+    /// not used by crdt algo.
+    ///
+    /// Trees with the same triples have the same digest, whatever order
+    /// their ops were applied in, so digests are a quick check of whether
+    /// replicas have converged.  Trees with different triples almost
+    /// always have different digests; compare the trees with == to be
+    /// certain.
+    ///
+    /// Metadata is only included after hash_metadata().  Orphans are not
+    /// distinguished from other nodes.
+    pub fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// includes each node's metadata in ::digest(), recomputing it.
+    /// not used by crdt algo.
+    ///
+    /// Like indexes, this is not serialized, so must be enabled again
+    /// after deserializing a tree.
+    pub fn hash_metadata(&mut self)
+    where
+        TM: Hash,
+    {
+        self.meta_hash = Some(hash_meta::<TM>);
+        self.rehash();
+    }
+
+    // recomputes the digest from the triples.
+    fn rehash(&mut self) {
+        self.digest = self.iter().fold(0, |digest, (child_id, tt)| {
+            digest ^ self.triple_hash(child_id, tt)
+        });
+    }
+
+    // releases handle h once it is neither a node nor a parent.
+    fn release_unused(&mut self, h: Handle) {
+        if self.node(h).is_none() && !self.children.contains_key(&h) {
+            self.ids.release(h);
         }
     }
 
     /// helper for removing a triple based on child_id
+    ///
+    /// Any children of child_id are left behind as orphans.
     pub fn rm_child(&mut self, child_id: &ID) {
-        let result = self.triples.get(child_id);
-        if let Some(t) = result {
-            if let Some(map) = self.children.get_mut(t.parent_id()) {
-                map.remove(child_id);
-                // cleanup parent entry if empty.
-                if map.is_empty() {
-                    self.children.remove(t.parent_id());
+        if let Some(c) = self.node_handle(child_id) {
+            self.remove_triple(child_id);
+            if self.children.contains_key(&c) {
+                self.detached.insert(c);
+            }
+        }
+    }
+
+    // removes a triple based on child_id, without tracking orphans.
+    //
+    // used by the crdt algo, for which a child of a removed node is no
+    // different than a child of a top-level parent, eg when a move
+    // into a new parent is applied before the parent's creation.
+    pub(crate) fn remove_triple(&mut self, child_id: &ID) {
+        if let Some(c) = self.node_handle(child_id) {
+            let parent = self.detach(child_id, c, true);
+            self.release_unused(c);
+            self.release_unused(parent);
+        }
+    }
+
+    // moves child_id to the node tt, as ::remove_triple() then
+    // ::add_node(), adding it if it is not in the tree.
+    //
+    // used by the crdt algo.  subtree sizes only change between the old
+    // and new parents and their lowest common ancestor, so only those are
+    // updated, and moving a node within or near its parent is cheap
+    // however deep the tree.
+    pub(crate) fn move_triple(&mut self, child_id: &ID, tt: TreeNode<ID, TM>) {
+        let c = match self.node_handle(child_id) {
+            Some(c) => c,
+            None => return self.add_node(child_id.clone(), tt),
+        };
+        let old = self.node(c).expect("node exists").parent;
+        let new = self.ids.get(tt.parent_id());
+        let lca = new.and_then(|new| self.meeting_point(old, new));
+        self.shift_sizes(c, old, lca, false);
+        if let Some(new) = new {
+            self.shift_sizes(c, new, lca, true);
+        }
+        // the handles are kept until child_id is added again, as sizes
+        // above lca were left as they were.
+        self.detach(child_id, c, false);
+        self.attach(child_id.clone(), tt, new.is_none());
+        self.release_unused(old);
+    }
+
+    // removes child_id, whose handle is c, from the tree and returns its
+    // parent's handle, without releasing either.  subtree sizes are
+    // updated if sized.
+    fn detach(&mut self, child_id: &ID, c: Handle, sized: bool) -> Handle {
+        let parent = self.node(c).expect("node exists").parent;
+        let height = self.height_of(c);
+        self.update_height(parent, Some(height), None, c);
+        if let Some(set) = self.children.get_mut(&parent) {
+            set.remove(&c);
+            // cleanup parent entry if empty.
+            if set.is_empty() {
+                self.children.remove(&parent);
+                self.remove_root(parent);
+                self.detached.remove(&parent);
+            }
+        }
+
+        // child_id and its descendants no longer count towards its ancestors.
+        if sized {
+            self.shift_sizes(c, parent, None, false);
+        }
+
+        let n = self.nodes[c as usize].take().expect("node exists");
+        self.num_nodes -= 1;
+        self.digest ^= self.triple_hash(child_id, &n.node);
+        self.indexes.remove(child_id, &n.node);
+        // any children of child_id are now top-level nodes.
+        if self.children.contains_key(&c) {
+            self.insert_root(c);
+        }
+        parent
+    }
+
+    // adds the size of c's subtree, c included, to from and its ancestors
+    // below until, or subtracts it if not add.
+    fn shift_sizes(&mut self, c: Handle, from: Handle, until: Option<Handle>, add: bool) {
+        let moved = self.sizes.get(&c).copied().unwrap_or(0) + 1;
+        let mut ancestor = from;
+        while Some(ancestor) != until {
+            if add {
+                *self.sizes.entry(ancestor).or_insert(0) += moved;
+            } else if let Some(size) = self.sizes.get_mut(&ancestor) {
+                *size -= moved;
+                if *size == 0 {
+                    self.sizes.remove(&ancestor);
+                }
+            }
+            match self.node(ancestor) {
+                // guards against a cycle introduced via direct add_node().
+                Some(n) if ancestor != c => ancestor = n.parent,
+                _ => break,
+            }
+        }
+    }
+
+    // returns the lowest common ancestor of handles a and b, either
+    // included, or None if they have different top-level parents.
+    //
+    // both are walked up in turn, so the cost is bounded by their
+    // distance to the ancestor rather than by their depth.
+    fn meeting_point(&self, a: Handle, b: Handle) -> Option<Handle> {
+        let (mut seen_a, mut seen_b) = (HashSet::new(), HashSet::new());
+        let (mut a, mut b) = (Some(a), Some(b));
+        while a.is_some() || b.is_some() {
+            if let Some(h) = a {
+                if seen_b.contains(&h) {
+                    return Some(h);
                 }
+                // guards against a cycle introduced via direct add_node().
+                a = match seen_a.insert(h) {
+                    true => self.node(h).map(|n| n.parent),
+                    false => None,
+                };
+            }
+            if let Some(h) = b {
+                if seen_a.contains(&h) {
+                    return Some(h);
+                }
+                b = match seen_b.insert(h) {
+                    true => self.node(h).map(|n| n.parent),
+                    false => None,
+                };
             }
-            self.triples.remove(child_id);
         }
+        None
     }
 
     /// removes a subtree.  useful for emptying trash.
@@ -76,50 +431,699 @@ impl<ID: TreeId, TM: TreeMeta> Tree<ID, TM> {
         }
     }
 
+    /// removes each node for which f returns false, with the nodes under
+    /// it, and returns the number of nodes removed.  useful for garbage
+    /// collection.
+    /// not used by crdt algo.
+    ///
+    /// Like rm_subtree(), this is not recorded in the op log.  See
+    /// `State::retain_checked` for removing only nodes that no op in the
+    /// log may move again.
+    pub fn retain<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&ID, &TreeNode<ID, TM>) -> bool,
+    {
+        let rejected: Vec<ID> = self
+            .iter()
+            .filter(|(child_id, tt)| !f(child_id, tt))
+            .map(|(child_id, _)| child_id.clone())
+            .collect();
+        let before = self.num_nodes;
+        for child_id in rejected {
+            // may already be removed with an ancestor.
+            if self.find(&child_id).is_some() {
+                self.rm_subtree(&child_id, true);
+            }
+        }
+        before - self.num_nodes
+    }
+
     /// adds a node to the tree
+    ///
+    /// tt is shared with any clones of it held by the caller, eg a log
+    /// entry, rather than copied.
     pub fn add_node(&mut self, child_id: ID, tt: TreeNode<ID, TM>) {
-        if let Some(n) = self.children.get_mut(tt.parent_id()) {
-            n.insert(child_id.to_owned());
-        } else {
-            let mut h: HashSet<ID> = HashSet::new();
-            h.insert(child_id.to_owned());
-            self.children.insert(tt.parent_id().to_owned(), h);
+        self.attach(child_id, tt, true);
+    }
+
+    // adds a node to the tree, updating subtree sizes if sized.
+    fn attach(&mut self, child_id: ID, tt: TreeNode<ID, TM>, sized: bool) {
+        let c = self.ids.intern(&child_id);
+        let parent = self.ids.intern(tt.parent_id());
+        self.children.entry(parent).or_default().insert(c);
+
+        // child_id and its descendants now count towards its ancestors.
+        if sized {
+            self.shift_sizes(c, parent, None, true);
+        }
+
+        if self.node(parent).is_none() {
+            self.insert_root(parent);
+        }
+        self.remove_root(c);
+        self.detached.remove(&c);
+
+        let height = self.height_of(c);
+        self.update_height(parent, None, Some(height), c);
+        self.indexes.insert(&child_id, &tt);
+        while self.nodes.len() < self.ids.capacity() {
+            collections::push(&mut self.nodes, None);
+        }
+        self.digest ^= self.triple_hash(&child_id, &tt);
+        let replaced = self.nodes[c as usize]
+            .as_ref()
+            .map(|old| self.triple_hash(&child_id, &old.node));
+        if let Some(hash) = replaced {
+            self.digest ^= hash;
+        }
+        let slot = &mut self.nodes[c as usize];
+        if slot.is_none() {
+            self.num_nodes += 1;
+        }
+        *slot = Some(Node { parent, node: tt });
+    }
+
+    // returns the height of handle h, ie the number of levels of nodes
+    // below it, or 0 if it has no children.
+    #[inline]
+    fn height_of(&self, h: Handle) -> usize {
+        self.heights
+            .get(&h)
+            .and_then(|heights| heights.max())
+            .map_or(0, |max| max + 1)
+    }
+
+    // replaces a child's height, old, with new in the heights of parent,
+    // and so on up its ancestors for as long as their heights change.
+    // Subtree heights do not depend on where the subtree is, so moving a
+    // node only updates the heights above its old and new parents.
+    fn update_height(
+        &mut self,
+        mut parent: Handle,
+        mut old: Option<usize>,
+        mut new: Option<usize>,
+        moved: Handle,
+    ) {
+        loop {
+            let before = self.height_of(parent);
+            let heights = self.heights.entry(parent).or_default();
+            if let Some(h) = old {
+                heights.remove(h);
+            }
+            if let Some(h) = new {
+                heights.insert(h);
+            }
+            if heights.is_empty() {
+                self.heights.remove(&parent);
+            }
+            let after = self.height_of(parent);
+            if before == after {
+                break;
+            }
+            if self.roots.contains(&parent) {
+                self.root_heights.remove(before);
+                self.root_heights.insert(after);
+            }
+            match self.node(parent) {
+                // guards against a cycle introduced via direct add_node().
+                Some(n) if parent != moved => {
+                    old = Some(before);
+                    new = Some(after);
+                    parent = n.parent;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    // adds h to the roots, if it is not already one.
+    fn insert_root(&mut self, h: Handle) {
+        if !self.roots.contains(&h) {
+            self.roots.insert(h);
+            self.root_heights.insert(self.height_of(h));
         }
-        self.triples.insert(child_id, tt);
+    }
+
+    // removes h from the roots, if it is one.
+    fn remove_root(&mut self, h: Handle) {
+        if self.roots.contains(&h) {
+            self.roots.remove(&h);
+            self.root_heights.remove(self.height_of(h));
+        }
+    }
+
+    /// returns the number of descendants of parent_id (not including
+    /// parent_id itself), or 0 if it has none.
+    /// not used by crdt algo.
+    ///
+    /// Sizes are indexed.  Moving a node updates the sizes between its old
+    /// and new parents and their lowest common ancestor, which are the
+    /// sizes that change, so moves near the node are cheap.
+    #[inline]
+    pub fn subtree_size(&self, parent_id: &ID) -> usize {
+        self.ids
+            .get(parent_id)
+            .and_then(|h| self.sizes.get(&h).copied())
+            .unwrap_or(0)
+    }
+
+    /// returns the depth of child_id, ie its number of ancestors, or
+    /// None if child_id is not in the tree.
+    ///
+    /// A node whose parent is not itself a node (eg the conventional
+    /// root) has depth 1.
+    /// not used by crdt algo.
+    ///
+    /// Depths are not indexed, as a move would change the depth of every
+    /// node under the moved node, so this costs O(depth).
+    pub fn depth(&self, child_id: &ID) -> Option<usize> {
+        self.node_handle(child_id).and_then(|h| self.depth_of(h))
+    }
+
+    /// returns top-level parent IDs, ie IDs that are the parent of
+    /// some node but are not themselves a node in the tree.
+    ///
+    /// Typically these are the conventional root and trash IDs.
+    /// not used by crdt algo.
+    pub fn roots(&self) -> Vec<ID> {
+        self.in_child_order(self.ids_of(&self.roots))
+    }
+
+    /// returns nodes whose parent node has been removed from the tree
+    /// by calling rm_child() on it while it still had children.
+    ///
+    /// Only direct mutation of the tree can produce orphans.
+    /// not used by crdt algo.
+    pub fn orphans(&self) -> Vec<ID> {
+        let orphans = self
+            .detached
+            .iter()
+            .filter_map(|parent| self.children.get(parent))
+            .flat_map(|list| self.ids_of(list))
+            .collect();
+        self.in_child_order(orphans)
+    }
+
+    /// enables a secondary index of nodes by a key extracted from their
+    /// metadata, replacing any existing index with the same key type.
+    /// not used by crdt algo.
+    ///
+    /// key may return None for nodes that should not be indexed.  The
+    /// index is maintained as nodes are added, moved and removed, so
+    /// that find_by_meta() need not walk the tree.
+    ///
+    /// Indexes are not serialized, so must be enabled again after
+    /// deserializing a tree.
+    pub fn index_metadata<K>(&mut self, key: fn(&TM) -> Option<K>)
+    where
+        ID: Send + Sync + 'static,
+        TM: 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        let mut index = MetaIndex::new(key);
+        for (child_id, tt) in self.iter() {
+            index.insert(child_id, tt);
+        }
+        self.indexes.set(index);
+    }
+
+    /// returns nodes whose metadata key (as extracted by the function
+    /// passed to index_metadata) equals key.
+    /// not used by crdt algo.
+    ///
+    /// returns an empty list if no index with key type K is enabled.
+    pub fn find_by_meta<K>(&self, key: &K) -> Vec<ID>
+    where
+        ID: Send + Sync + 'static,
+        TM: 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.indexes
+            .get::<MetaIndex<ID, TM, K>>()
+            .and_then(|index| index.find(key))
+            .map(|list| list.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// enables an index of nodes by (parent_id, name), where name is
+    /// extracted from their metadata, replacing any existing index with
+    /// the same name type.
+    /// not used by crdt algo.
+    ///
+    /// This provides filesystem-style directory entry lookup via
+    /// child_by_name().  name may return None for unnamed nodes.
+    ///
+    /// Indexes are not serialized, so must be enabled again after
+    /// deserializing a tree.
+    pub fn index_names<N>(&mut self, name: fn(&TM) -> Option<N>)
+    where
+        ID: Send + Sync + 'static,
+        TM: 'static,
+        N: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        let mut index = NameIndex::new(name);
+        for (child_id, tt) in self.iter() {
+            index.insert(child_id, tt);
+        }
+        self.indexes.set(index);
+    }
+
+    /// returns the child of parent_id with the given name, as extracted
+    /// by the function passed to index_names().
+    /// not used by crdt algo.
+    ///
+    /// If several children share the name, an arbitrary one of them is
+    /// returned.  See children_by_name().
+    ///
+    /// returns None if no index with name type N is enabled.
+    pub fn child_by_name<N>(&self, parent_id: &ID, name: &N) -> Option<ID>
+    where
+        ID: Send + Sync + 'static,
+        TM: 'static,
+        N: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.indexes
+            .get::<NameIndex<ID, TM, N>>()
+            .and_then(|index| index.find(parent_id.clone(), name.clone()))
+            .and_then(|list| list.iter().next().cloned())
+    }
+
+    /// returns all children of parent_id with the given name, as
+    /// extracted by the function passed to index_names().
+    /// not used by crdt algo.
+    ///
+    /// returns an empty list if no index with name type N is enabled.
+    pub fn children_by_name<N>(&self, parent_id: &ID, name: &N) -> Vec<ID>
+    where
+        ID: Send + Sync + 'static,
+        TM: 'static,
+        N: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.indexes
+            .get::<NameIndex<ID, TM, N>>()
+            .and_then(|index| index.find(parent_id.clone(), name.clone()))
+            .map(|list| list.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// returns the depth of the deepest node in the tree, or 0 if the
+    /// tree is empty.
+    /// not used by crdt algo.
+    #[inline]
+    pub fn max_depth(&self) -> usize {
+        self.root_heights.max().unwrap_or(0)
+    }
+
+    // returns the number of levels of nodes below child_id, or 0 if it
+    // has none.  O(1).
+    #[inline]
+    pub(crate) fn height(&self, child_id: &ID) -> usize {
+        self.ids.get(child_id).map_or(0, |h| self.height_of(h))
     }
 
     /// returns matching node, or None.
     pub fn find(&self, child_id: &ID) -> Option<&TreeNode<ID, TM>> {
-        self.triples.get(child_id)
+        self.ids
+            .get(child_id)
+            .and_then(|h| self.node(h))
+            .map(|n| &n.node)
+    }
+
+    /// returns the parent of child_id, or None if it is not a node.
+    #[inline]
+    pub fn parent(&self, child_id: &ID) -> Option<&ID> {
+        self.find(child_id).map(|n| n.parent_id())
     }
 
-    /// returns children (IDs) of a given parent node.
+    /// returns the metadata of child_id, or None if it is not a node.
+    #[inline]
+    pub fn metadata(&self, child_id: &ID) -> Option<&TM> {
+        self.find(child_id).map(|n| n.metadata())
+    }
+
+    // replaces the node for child_id with an equal node shared with a log
+    // entry.  a node with another parent, eg from an inconsistent log, is
+    // not shared.
+    pub(crate) fn share_node(&mut self, child_id: &ID, node: TreeNode<ID, TM>) {
+        if let Some(h) = self.node_handle(child_id) {
+            if let Some(n) = &mut self.nodes[h as usize] {
+                if n.node.parent_id() == node.parent_id() {
+                    n.node = node;
+                }
+            }
+        }
+    }
+
+    /// returns children (IDs) of a given parent node, in the order set
+    /// by set_child_order(), if any.
     /// useful for walking tree.
     /// not used by crdt algo.
     pub fn children(&self, parent_id: &ID) -> Vec<ID> {
-        if let Some(list) = self.children.get(parent_id) {
-            list.iter().cloned().collect()
-        } else {
-            Vec::<ID>::default()
+        match self.ids.get(parent_id).and_then(|h| self.children.get(&h)) {
+            Some(list) => self.in_child_order(self.ids_of(list)),
+            None => Vec::<ID>::default(),
         }
     }
 
+    /// returns the number of children of a given parent node.
+    /// not used by crdt algo.
+    pub fn num_children(&self, parent_id: &ID) -> usize {
+        self.ids
+            .get(parent_id)
+            .and_then(|h| self.children.get(&h))
+            .map_or(0, |list| list.len())
+    }
+
     /// walks tree and calls FnMut f for each node.
     /// not used by crdt algo.
     ///
     /// walk uses a non-recursive algorithm, so calling
     /// it on a deep tree will not cause stack overflow.
+    ///
+    /// f receives the depth of each node relative to parent_id,
+    /// which is visited first at depth 0.  Up to version 0.0.16, f
+    /// received the number of nodes still waiting to be visited
+    /// instead, which is not a depth.
     pub fn walk<F>(&self, parent_id: &ID, mut f: F)
     where
         F: FnMut(&Self, &ID, usize),
     {
-        let mut stack: Vec<ID> = Vec::new();
-        stack.push(parent_id.clone());
-        while !stack.is_empty() {
-            if let Some(next) = stack.pop() {
-                f(self, &next, stack.len());
-                for child in self.children(&next) {
-                    stack.push(child)
+        self.try_walk(parent_id, |tree, id, depth| {
+            f(tree, id, depth);
+            WalkControl::Continue
+        });
+    }
+
+    /// walks tree and calls FnMut f for each node, until f
+    /// returns `WalkControl::Stop`.
+    /// not used by crdt algo.
+    ///
+    /// f may also return `WalkControl::Prune` to skip the
+    /// descendants of the current node.
+    ///
+    /// returns false if the walk was stopped early, else true.
+    pub fn try_walk<F>(&self, parent_id: &ID, f: F) -> bool
+    where
+        F: FnMut(&Self, &ID, usize) -> WalkControl,
+    {
+        self.traverse(parent_id, Traversal::DepthFirst, None, f)
+    }
+
+    /// walks tree depth-first (pre-order), calling FnMut f for each node.
+    /// not used by crdt algo.
+    ///
+    /// Siblings are visited in the order set by set_child_order(), else
+    /// in arbitrary order.  See also `walk_dfs_by`.  f controls the walk as in `try_walk`.
+    pub fn walk_dfs<F>(&self, parent_id: &ID, f: F) -> bool
+    where
+        F: FnMut(&Self, &ID, usize) -> WalkControl,
+    {
+        self.traverse(parent_id, Traversal::DepthFirst, None, f)
+    }
+
+    /// walks tree breadth-first (level by level), calling FnMut f for each node.
+    /// not used by crdt algo.
+    ///
+    /// Siblings are visited in the order set by set_child_order(), else
+    /// in arbitrary order.  See also `walk_bfs_by`.  f controls the walk as in `try_walk`.
+    pub fn walk_bfs<F>(&self, parent_id: &ID, f: F) -> bool
+    where
+        F: FnMut(&Self, &ID, usize) -> WalkControl,
+    {
+        self.traverse(parent_id, Traversal::BreadthFirst, None, f)
+    }
+
+    /// walks tree depth-first (pre-order), visiting siblings in the
+    /// order given by compare.
+    /// not used by crdt algo.
+    ///
+    /// Since the order does not depend on internal hashing, the walk is
+    /// reproducible across runs and replicas. eg, to order by ID:
+    ///
+    /// ```text
+    /// tree.walk_dfs_by(&root, |a, b| a.cmp(b), |tree, id, depth| { ... });
+    /// ```
+    pub fn walk_dfs_by<C, F>(&self, parent_id: &ID, mut compare: C, f: F) -> bool
+    where
+        C: FnMut(&ID, &ID) -> Ordering,
+        F: FnMut(&Self, &ID, usize) -> WalkControl,
+    {
+        self.traverse(parent_id, Traversal::DepthFirst, Some(&mut compare), f)
+    }
+
+    /// walks tree breadth-first (level by level), visiting siblings in
+    /// the order given by compare.
+    /// not used by crdt algo.
+    ///
+    /// See `walk_dfs_by`.
+    pub fn walk_bfs_by<C, F>(&self, parent_id: &ID, mut compare: C, f: F) -> bool
+    where
+        C: FnMut(&ID, &ID) -> Ordering,
+        F: FnMut(&Self, &ID, usize) -> WalkControl,
+    {
+        self.traverse(parent_id, Traversal::BreadthFirst, Some(&mut compare), f)
+    }
+
+    // shared implementation of the walk_* methods.
+    //
+    // non-recursive, so a deep tree will not cause stack overflow.
+    fn traverse<F>(
+        &self,
+        parent_id: &ID,
+        traversal: Traversal,
+        mut compare: Option<SiblingOrder<ID>>,
+        mut f: F,
+    ) -> bool
+    where
+        F: FnMut(&Self, &ID, usize) -> WalkControl,
+    {
+        let mut queue: VecDeque<(ID, usize)> = VecDeque::new();
+        queue.push_back((parent_id.clone(), 0));
+
+        loop {
+            let next = match traversal {
+                Traversal::DepthFirst => queue.pop_back(),
+                Traversal::BreadthFirst => queue.pop_front(),
+            };
+            let (id, depth) = match next {
+                Some(n) => n,
+                None => return true,
+            };
+            match f(self, &id, depth) {
+                WalkControl::Continue => {
+                    let mut children = self.children(&id);
+                    let ordered = match compare.as_mut() {
+                        Some(cmp) => {
+                            children.sort_by(|a, b| cmp(a, b));
+                            true
+                        }
+                        None => self.child_order.is_some(),
+                    };
+                    // a stack pops last-in first, so push in reverse.
+                    if ordered && traversal == Traversal::DepthFirst {
+                        children.reverse();
+                    }
+                    queue.extend(children.into_iter().map(|c| (c, depth + 1)));
+                }
+                WalkControl::Prune => {}
+                WalkControl::Stop => return false,
+            }
+        }
+    }
+
+    /// walks the subtree under parent_id in parallel, mapping each node
+    /// with f and combining the results with reduce.
+    /// not used by crdt algo.
+    ///
+    /// The top of the subtree is walked breadth-first until there are
+    /// enough independent subtrees to keep all rayon threads busy, then each
+    /// of those is walked on its own thread.  Nodes are therefore visited in
+    /// no particular order, so reduce should be associative and commutative.
+    ///
+    /// Like walk, this does not recurse, so a deep tree will not cause
+    /// stack overflow.
+    #[cfg(feature = "rayon")]
+    pub fn par_walk<T, F, R>(&self, parent_id: &ID, f: F, reduce: R) -> T
+    where
+        ID: Send + Sync,
+        TM: Send + Sync,
+        T: Send,
+        F: Fn(&Self, &ID, usize) -> T + Sync,
+        R: Fn(T, T) -> T + Sync,
+    {
+        use rayon::prelude::*;
+
+        let target = rayon::current_num_threads() * 4;
+
+        let mut result = f(self, parent_id, 0);
+        let mut frontier: Vec<(ID, usize)> = self
+            .children(parent_id)
+            .into_iter()
+            .map(|c| (c, 1))
+            .collect();
+
+        while !frontier.is_empty() && frontier.len() < target {
+            let mut next_level = Vec::new();
+            for (id, depth) in frontier {
+                result = reduce(result, f(self, &id, depth));
+                next_level.extend(self.children(&id).into_iter().map(|c| (c, depth + 1)));
+            }
+            frontier = next_level;
+        }
+
+        let subtrees = frontier
+            .into_par_iter()
+            .map(|(id, depth)| {
+                let mut acc = f(self, &id, depth);
+                let mut stack: Vec<(ID, usize)> = self
+                    .children(&id)
+                    .into_iter()
+                    .map(|c| (c, depth + 1))
+                    .collect();
+                while let Some((next, d)) = stack.pop() {
+                    acc = reduce(acc, f(self, &next, d));
+                    stack.extend(self.children(&next).into_iter().map(|c| (c, d + 1)));
+                }
+                acc
+            })
+            .reduce_with(&reduce);
+
+        match subtrees {
+            Some(t) => reduce(result, t),
+            None => result,
+        }
+    }
+
+    /// checks that the tree is acyclic and that all indexes are
+    /// consistent with the (parent, meta, child) triples.
+    ///
+    /// This always holds for a tree that has only been modified by
+    /// the crdt algo, but may not hold after deserializing a tree from
+    /// untrusted storage or after direct mutation.  Cost is O(n).
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation<ID>> {
+        // no node may be its own ancestor.  Each node is walked up only
+        // until reaching a node already known to be acyclic.
+        let mut acyclic: HashSet<Handle> = HashSet::new();
+        for c in self.handles() {
+            let mut path: HashSet<Handle> = HashSet::new();
+            let mut next = c;
+            while let Some(n) = self.node(next) {
+                if acyclic.contains(&next) {
+                    break;
+                }
+                if !path.insert(next) {
+                    return Err(InvariantViolation::Cycle(self.ids.id(next).clone()));
+                }
+                next = n.parent;
+            }
+            acyclic.extend(path);
+        }
+
+        // the children index must list exactly the triples.
+        for c in self.handles() {
+            let n = self.node(c).expect("node exists");
+            let listed = self
+                .children
+                .get(&n.parent)
+                .map(|list| list.contains(&c))
+                .unwrap_or(false);
+            if !listed {
+                return Err(InvariantViolation::MissingChild {
+                    parent_id: n.node.parent_id().clone(),
+                    child_id: self.ids.id(c).clone(),
+                });
+            }
+        }
+        for (&parent, list) in self.children.iter() {
+            for &c in list {
+                match self.node(c) {
+                    Some(n) if n.parent == parent => {}
+                    _ => {
+                        return Err(InvariantViolation::StaleChild {
+                            parent_id: self.ids.id(parent).clone(),
+                            child_id: self.ids.id(c).clone(),
+                        })
+                    }
+                }
+            }
+        }
+
+        // derived indexes must match those of a freshly built tree.  The
+        // rebuilt tree interns IDs afresh, so indexes are compared by ID.
+        let mut rebuilt = self.clone();
+        rebuilt.repair();
+        if rebuilt.root_heights != self.root_heights
+            || rebuilt.heights_by_id() != self.heights_by_id()
+        {
+            return Err(InvariantViolation::IndexMismatch("height"));
+        }
+        if rebuilt.by_id(&rebuilt.sizes) != self.by_id(&self.sizes) {
+            return Err(InvariantViolation::IndexMismatch("subtree size"));
+        }
+        if rebuilt.roots().into_iter().collect::<HashSet<ID>>()
+            != self.roots().into_iter().collect::<HashSet<ID>>()
+        {
+            return Err(InvariantViolation::IndexMismatch("roots"));
+        }
+        Ok(())
+    }
+
+    // returns handles of all nodes.
+    fn handles(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.is_some())
+            .map(|(h, _)| h as Handle)
+    }
+
+    // returns the height of each parent, keyed by ID.
+    fn heights_by_id(&self) -> HashMap<&ID, usize> {
+        self.heights
+            .keys()
+            .map(|&h| (self.ids.id(h), self.height_of(h)))
+            .collect()
+    }
+
+    // returns a handle-keyed index keyed by ID instead.
+    fn by_id(&self, index: &Map<Handle, usize>) -> HashMap<&ID, usize> {
+        index.iter().map(|(&h, &v)| (self.ids.id(h), v)).collect()
+    }
+
+    /// rebuilds the children index and all other indexes from the
+    /// (parent, meta, child) triples, which are taken as authoritative.
+    ///
+    /// Cycles in the triples are not repaired.
+    ///
+    /// IDs are interned afresh, which also reclaims memory left behind by
+    /// IDs that are no longer in the tree.
+    pub fn repair(&mut self) {
+        let detached = self.ids_of(&self.detached);
+        let mut indexes = std::mem::take(&mut self.indexes);
+        indexes.clear();
+        let old = std::mem::take(self);
+        self.indexes = indexes;
+        self.child_order = old.child_order;
+        self.meta_hash = old.meta_hash;
+        for (child_id, tt) in old {
+            self.add_node(child_id, tt);
+        }
+        self.mark_detached(detached);
+    }
+
+    // returns the removed parents that still have children.  see
+    // ::mark_detached().
+    pub(crate) fn detached_ids(&self) -> Vec<ID> {
+        self.ids_of(&self.detached)
+    }
+
+    // marks parents as detached, ie removed while they have children.
+    // parents that are nodes or have no children are ignored.
+    pub(crate) fn mark_detached(&mut self, detached: Vec<ID>) {
+        for id in detached {
+            if let Some(h) = self.ids.get(&id) {
+                if self.roots.contains(&h) {
+                    self.detached.insert(h);
                 }
             }
         }
@@ -144,20 +1148,376 @@ impl<ID: TreeId, TM: TreeMeta> Tree<ID, TM> {
     /// is 2 ancestor of 8?  yes.
     /// is 2 ancestor of 5?   no.
     /// ```
+    ///
+    /// A descendant is at most the height of ancestor_id below it, and
+    /// heights are indexed, so at most that many parent pointers are
+    /// followed.  The crdt algo asks whether a moved node is an ancestor
+    /// of its new parent, so the cost is bounded by the height of the
+    /// moved subtree, however deep the tree, and is O(1) when moving a
+    /// node without children.
     pub fn is_ancestor(&self, child_id: &ID, ancestor_id: &ID) -> bool {
-        let mut target_id = child_id;
-        while let Some(n) = self.find(target_id) {
-            if n.parent_id() == ancestor_id {
-                return true;
+        let (child, ancestor) = match (self.ids.get(child_id), self.ids.get(ancestor_id)) {
+            (Some(c), Some(a)) => (c, a),
+            _ => return false,
+        };
+        let mut target = child;
+        for _ in 0..self.height_of(ancestor) {
+            match self.node(target) {
+                Some(n) if n.parent == ancestor => return true,
+                Some(n) => target = n.parent,
+                None => return false,
             }
-            target_id = n.parent_id();
         }
         false
     }
 
+    // returns the depth of handle h, with top-level parents at depth 0.
+    fn depth_of(&self, mut h: Handle) -> Option<usize> {
+        if self.node(h).is_none() && !self.children.contains_key(&h) {
+            return None;
+        }
+        let mut depth = 0;
+        while let Some(n) = self.node(h) {
+            depth += 1;
+            h = n.parent;
+        }
+        Some(depth)
+    }
+
+    // returns the lowest common ancestor of handles a and b, and their
+    // depths.
+    fn lca_handle(&self, a: &ID, b: &ID) -> Option<(Handle, usize, usize)> {
+        let (mut a, mut b) = (self.ids.get(a)?, self.ids.get(b)?);
+        let (depth_a, depth_b) = (self.depth_of(a)?, self.depth_of(b)?);
+        for _ in depth_b..depth_a {
+            a = self.node(a)?.parent;
+        }
+        for _ in depth_a..depth_b {
+            b = self.node(b)?.parent;
+        }
+        while a != b {
+            a = self.node(a)?.parent;
+            b = self.node(b)?.parent;
+        }
+        Some((a, depth_a, depth_b))
+    }
+
+    /// returns child_id followed by its ancestors, up to and including
+    /// its top-level parent, eg for a breadcrumb.
+    /// not used by crdt algo.
+    ///
+    /// returns just child_id for a top-level parent, or an empty list if
+    /// child_id is not in the tree.
+    pub fn path_to_root(&self, child_id: &ID) -> Vec<ID> {
+        let mut h = match self.ids.get(child_id) {
+            Some(h) => h,
+            None => return vec![],
+        };
+        let depth = match self.depth_of(h) {
+            Some(d) => d,
+            None => return vec![],
+        };
+        let mut path = Vec::with_capacity(depth + 1);
+        path.push(child_id.clone());
+        while let Some(n) = self.node(h) {
+            h = n.parent;
+            path.push(self.ids.id(h).clone());
+        }
+        path
+    }
+
+    /// returns the lowest common ancestor of a and b, ie the deepest node
+    /// or top-level parent that both are in.  A node is in itself, so if
+    /// a is an ancestor of b, a is returned.
+    /// not used by crdt algo.
+    ///
+    /// returns None if a and b are under different top-level parents, or
+    /// either is not in the tree.
+    pub fn lca(&self, a: &ID, b: &ID) -> Option<ID> {
+        self.lca_handle(a, b)
+            .map(|(h, _, _)| self.ids.id(h).clone())
+    }
+
+    /// returns the path from a to b, as the steps up from a to their
+    /// lowest common ancestor, then the nodes down to b, like a relative
+    /// filesystem path.  See ::lca().
+    /// not used by crdt algo.
+    ///
+    /// returns None if a and b have no common ancestor.
+    pub fn relative_path(&self, a: &ID, b: &ID) -> Option<RelativePath<ID>> {
+        let (lca, depth_a, depth_b) = self.lca_handle(a, b)?;
+        let depth_lca = self.depth_of(lca)?;
+        let mut down = Vec::with_capacity(depth_b - depth_lca);
+        let mut h = self.ids.get(b)?;
+        while h != lca {
+            down.push(self.ids.id(h).clone());
+            h = self.node(h)?.parent;
+        }
+        down.reverse();
+        Some(RelativePath {
+            up: depth_a - depth_lca,
+            down,
+        })
+    }
+
     /// Total number of nodes (triples) in the tree
     pub fn num_nodes(&self) -> usize {
-        self.triples.len()
+        self.num_nodes
+    }
+
+    /// returns an iterator over all nodes in the tree, in arbitrary order.
+    pub fn iter(&self) -> TreeIter<'_, ID, TM> {
+        TreeIter {
+            ids: &self.ids,
+            nodes: self.nodes.iter().enumerate(),
+            remaining: self.num_nodes,
+        }
+    }
+
+    /// returns an iterator over parent_id, if it is a node, and the nodes
+    /// under it, depth-first (pre-order), with the depth of each relative
+    /// to parent_id.  Nodes are visited lazily, like ::walk(), so the
+    /// usual iterator adapters may be used to stop early.
+    /// not used by crdt algo.
+    ///
+    /// Siblings are visited in the order set by set_child_order(), else
+    /// in arbitrary order.
+    pub fn iter_subtree(&self, parent_id: &ID) -> SubtreeIter<'_, ID, TM> {
+        let (stack, remaining) = match self.ids.get(parent_id) {
+            Some(h) => {
+                let own = usize::from(self.node(h).is_some());
+                (vec![(h, 0)], self.subtree_size(parent_id) + own)
+            }
+            None => (vec![], 0),
+        };
+        SubtreeIter {
+            tree: self,
+            stack,
+            remaining,
+        }
+    }
+
+    /// returns an iterator over all nodes in the tree, in arbitrary order,
+    /// with mutable access to each node's metadata.
+    ///
+    /// The tree structure (parent/child relationships) cannot be modified
+    /// this way.
+    ///
+    /// Warning: metadata changed this way is not recorded in the op log,
+    /// so it is not replicated and may be overwritten by undo/redo.
+    /// Nor does it update metadata indexes, or the digest if it includes
+    /// metadata; call repair() afterwards if any are enabled.
+    ///
+    /// Each node visited is first copied if it is shared with the log.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&ID, &mut TM)> {
+        let ids = &self.ids;
+        self.nodes.iter_mut().enumerate().filter_map(move |(h, n)| {
+            n.as_mut()
+                .map(|n| (ids.id(h as Handle), n.node.metadata_mut()))
+        })
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> Default for Tree<ID, TM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Trees are equal if they have the same triples and orphans, regardless
+/// of how their IDs are interned.
+impl<ID: TreeId, TM: TreeMeta + PartialEq> PartialEq for Tree<ID, TM> {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_nodes == other.num_nodes
+            && self
+                .iter()
+                .all(|(child_id, n)| other.find(child_id) == Some(n))
+            && self.detached.len() == other.detached.len()
+            && self.detached.iter().all(|&h| {
+                matches!(other.ids.get(self.ids.id(h)), Some(o) if other.detached.contains(&o))
+            })
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta + Eq> Eq for Tree<ID, TM> {}
+
+impl<ID: TreeId + Debug, TM: TreeMeta + Debug> Debug for Tree<ID, TM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tree")
+            .field("triples", &DebugTriples(self))
+            .field("detached", &self.ids_of(&self.detached))
+            .finish()
+    }
+}
+
+// formats a tree's triples as a map of child_id => tree_node.
+struct DebugTriples<'a, ID: TreeId, TM: TreeMeta>(&'a Tree<ID, TM>);
+
+impl<'a, ID: TreeId + Debug, TM: TreeMeta + Debug> Debug for DebugTriples<'a, ID, TM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.0.iter()).finish()
+    }
+}
+
+// serialized form of a tree: its triples and orphaned parents.  The
+// indexes are rebuilt on deserialization.
+#[derive(Serialize)]
+struct TreeRef<'a, ID: TreeId, TM: TreeMeta> {
+    triples: SerTriples<'a, ID, TM>,
+    detached: Vec<&'a ID>,
+}
+
+// serializes a tree's triples as a map of child_id => tree_node.
+struct SerTriples<'a, ID: TreeId, TM: TreeMeta>(&'a Tree<ID, TM>);
+
+impl<'a, ID, TM> Serialize for SerTriples<'a, ID, TM>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter())
+    }
+}
+
+// trees serialized before IDs were interned have no detached list, and
+// their children index is ignored.  a default fn spares ID a Default
+// bound.
+#[derive(Deserialize)]
+struct TreeData<ID: TreeId, TM: TreeMeta> {
+    triples: HashMap<ID, TreeNode<ID, TM>>,
+    #[serde(default = "Vec::new")]
+    detached: Vec<ID>,
+}
+
+impl<ID, TM> Serialize for Tree<ID, TM>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TreeRef {
+            triples: SerTriples(self),
+            detached: self.detached.iter().map(|&h| self.ids.id(h)).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, ID, TM> Deserialize<'de> for Tree<ID, TM>
+where
+    ID: TreeId + Deserialize<'de>,
+    TM: TreeMeta + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TreeData::deserialize(deserializer)?;
+        let mut tree = Self::new();
+        for (child_id, tt) in data.triples {
+            tree.add_node(child_id, tt);
+        }
+        tree.mark_detached(data.detached);
+        Ok(tree)
+    }
+}
+
+/// An iterator over the nodes of a `Tree`.  See `Tree::iter`.
+pub struct TreeIter<'a, ID: TreeId, TM: TreeMeta> {
+    ids: &'a Interner<ID>,
+    nodes: std::iter::Enumerate<SeqIter<'a, Option<Node<ID, TM>>>>,
+    remaining: usize,
+}
+
+impl<'a, ID: TreeId, TM: TreeMeta> Iterator for TreeIter<'a, ID, TM> {
+    type Item = (&'a ID, &'a TreeNode<ID, TM>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (h, n) in &mut self.nodes {
+            if let Some(n) = n {
+                self.remaining -= 1;
+                return Some((self.ids.id(h as Handle), &n.node));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, ID: TreeId, TM: TreeMeta> ExactSizeIterator for TreeIter<'a, ID, TM> {}
+
+/// An iterator over the nodes of a subtree, with their depths.  See
+/// `Tree::iter_subtree`.
+pub struct SubtreeIter<'a, ID: TreeId, TM: TreeMeta> {
+    tree: &'a Tree<ID, TM>,
+    stack: Vec<(Handle, usize)>, // nodes to visit, with their depths.
+    remaining: usize,
+}
+
+impl<'a, ID: TreeId, TM: TreeMeta> Iterator for SubtreeIter<'a, ID, TM> {
+    type Item = (&'a ID, &'a TreeNode<ID, TM>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree;
+        while let Some((h, depth)) = self.stack.pop() {
+            if let Some(list) = tree.children.get(&h) {
+                let start = self.stack.len();
+                self.stack.extend(list.iter().map(|&c| (c, depth + 1)));
+                // a stack pops last-in first, so push in reverse.
+                if let Some(order) = tree.child_order {
+                    self.stack[start..].sort_by(|a, b| order(tree.ids.id(b.0), tree.ids.id(a.0)));
+                }
+            }
+            // the top of the subtree may be a top-level parent.
+            if let Some(n) = tree.node(h) {
+                self.remaining -= 1;
+                return Some((tree.ids.id(h), &n.node, depth));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, ID: TreeId, TM: TreeMeta> ExactSizeIterator for SubtreeIter<'a, ID, TM> {}
+
+/// An owning iterator over the nodes of a `Tree`.  See `Tree::into_iter`.
+pub struct TreeIntoIter<ID: TreeId, TM: TreeMeta> {
+    ids: SeqIntoIter<Option<ID>>,
+    nodes: SeqIntoIter<Option<Node<ID, TM>>>,
+    remaining: usize,
+}
+
+impl<ID: TreeId, TM: TreeMeta> Iterator for TreeIntoIter<ID, TM> {
+    type Item = (ID, TreeNode<ID, TM>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (id, n) in (&mut self.ids).zip(&mut self.nodes) {
+            if let (Some(id), Some(n)) = (id, n) {
+                self.remaining -= 1;
+                return Some((id, n.node));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta> ExactSizeIterator for TreeIntoIter<ID, TM> {}
+
+impl<'a, ID: TreeId, TM: TreeMeta> IntoIterator for &'a Tree<ID, TM> {
+    type Item = (&'a ID, &'a TreeNode<ID, TM>);
+    type IntoIter = TreeIter<'a, ID, TM>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
@@ -165,10 +1525,14 @@ impl<ID: TreeId, TM: TreeMeta> Tree<ID, TM> {
 /// walking all Nodes in tree without knowing a starting point.
 impl<ID: TreeId, TM: TreeMeta> IntoIterator for Tree<ID, TM> {
     type Item = (ID, TreeNode<ID, TM>);
-    type IntoIter = std::collections::hash_map::IntoIter<ID, TreeNode<ID, TM>>;
+    type IntoIter = TreeIntoIter<ID, TM>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.triples.into_iter()
+        TreeIntoIter {
+            remaining: self.num_nodes,
+            ids: self.ids.into_ids().into_iter(),
+            nodes: self.nodes.into_iter(),
+        }
     }
 }
 
@@ -204,25 +1568,11 @@ impl<ID: TreeId + Debug, TM: TreeMeta + Debug> Tree<ID, TM> {
 
     // print a tree.
     fn print_tree(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut r: fmt::Result = Ok(());
-
-        let mut seen: HashSet<ID> = Default::default();
-
-        // We iterate through all triples to find the top-level nodes,
-        // i.e. those without any parent (or metadata), then print sub-tree
-        // for each one.
-        // PERF: This is a slow way to find top-level nodes.  We could
-        //       consider keeping a list of them as tree is modified
-        for treenode in self.triples.values() {
-            let p = treenode.parent_id();
-            if self.triples.get(p).is_none() && !seen.contains(p) {
-                seen.insert(p.clone());
-                r = self.print_treenode(f, p, 0);
-                if r.is_err() {
-                    break;
-                }
-            }
+        // print sub-tree for each top-level node, ie those without
+        // any parent (or metadata).
+        for root in self.roots() {
+            self.print_treenode(f, &root, 0)?;
         }
-        r
+        Ok(())
     }
 }
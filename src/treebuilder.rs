@@ -0,0 +1,188 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use super::fs::{components, FsError, FsMeta};
+use super::{OpMove, Resolve, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// `NodeDef` defines a node, with its children, for `TreeBuilder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeDef<ID, TM> {
+    id: ID,
+    metadata: TM,
+    children: Vec<NodeDef<ID, TM>>,
+}
+
+impl<ID, TM> NodeDef<ID, TM> {
+    /// defines node id, with metadata and no children.
+    pub fn new(id: ID, metadata: TM) -> Self {
+        Self {
+            id,
+            metadata,
+            children: vec![],
+        }
+    }
+
+    /// returns the definition with child added.
+    pub fn with_child(mut self, child: NodeDef<ID, TM>) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// returns the definition with children added.
+    pub fn with_children<I>(mut self, children: I) -> Self
+    where
+        I: IntoIterator<Item = NodeDef<ID, TM>>,
+    {
+        self.children.extend(children);
+        self
+    }
+}
+
+/// `TreeBuilder` collects the nodes of an initial tree, and generates the
+/// ops creating them as one batch, parents before children.
+///
+/// ```text
+/// let ops = TreeBuilder::new(root_id)
+///     .with_nodes(vec![NodeDef::new(1, "home".into()).with_child(NodeDef::new(2, "bob".into()))])
+///     .with_paths(vec![("/etc/hosts", "hosts".into())], |path| hash(path))?
+///     .build(&replica);
+/// replica.apply_ops(ops);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreeBuilder<ID, TM> {
+    root_id: ID,
+    // (parent_id, metadata, child_id) of each node, parents first.
+    moves: Vec<(ID, TM, ID)>,
+    // index into moves of each node added by path.
+    paths: HashMap<String, usize>,
+}
+
+impl<ID: TreeId, TM: TreeMeta> TreeBuilder<ID, TM> {
+    /// creates a builder for nodes under root_id, which need not exist as
+    /// a node.
+    pub fn new(root_id: ID) -> Self {
+        Self {
+            root_id,
+            moves: vec![],
+            paths: HashMap::new(),
+        }
+    }
+
+    /// adds nodes, with their descendants, under the root.
+    pub fn with_nodes<I>(mut self, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = NodeDef<ID, TM>>,
+    {
+        let root_id = self.root_id.clone();
+        self.add_nodes(root_id, nodes);
+        self
+    }
+
+    /// adds nodes, with their descendants, under parent_id, which may be
+    /// a node added earlier.
+    pub fn with_nodes_under<I>(mut self, parent_id: ID, nodes: I) -> Self
+    where
+        I: IntoIterator<Item = NodeDef<ID, TM>>,
+    {
+        self.add_nodes(parent_id, nodes);
+        self
+    }
+
+    /// returns the number of nodes added.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// returns true if no nodes were added.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// returns (parent_id, metadata, child_id) of each node, parents
+    /// first, as accepted by `TreeReplica::opmoves`.
+    pub fn into_moves(self) -> Vec<(ID, TM, ID)> {
+        self.moves
+    }
+
+    /// generates the ops creating the nodes on replica.  The ops are not
+    /// applied.
+    pub fn build<A, T, R>(self, replica: &TreeReplica<ID, TM, A, T, R>) -> Vec<OpMove<ID, TM, A>>
+    where
+        A: Actor + Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        replica.opmoves(self.moves)
+    }
+
+    fn add_nodes<I>(&mut self, parent_id: ID, nodes: I)
+    where
+        I: IntoIterator<Item = NodeDef<ID, TM>>,
+    {
+        // depth first, so that a node is followed by its descendants.
+        let mut stack: Vec<(ID, NodeDef<ID, TM>)> =
+            nodes.into_iter().map(|n| (parent_id.clone(), n)).collect();
+        stack.reverse();
+        while let Some((parent_id, node)) = stack.pop() {
+            let NodeDef {
+                id,
+                metadata,
+                children,
+            } = node;
+            stack.extend(children.into_iter().rev().map(|c| (id.clone(), c)));
+            self.moves.push((parent_id, metadata, id));
+        }
+    }
+}
+
+impl<ID: TreeId, TM: FsMeta> TreeBuilder<ID, TM> {
+    /// adds a node at each path, relative to the root, eg `/etc/hosts`,
+    /// with its metadata, creating missing intermediate directories.
+    ///
+    /// id_for is called with each new node's path, eg `/etc`, and returns
+    /// its ID.  Intermediate directories get metadata from
+    /// `FsMeta::from_name`; a later path naming one replaces it, as does a
+    /// repeated path.  Returns `FsError::InvalidPath` for the root path.
+    pub fn with_paths<I, P, F>(mut self, paths: I, mut id_for: F) -> Result<Self, FsError>
+    where
+        I: IntoIterator<Item = (P, TM)>,
+        P: AsRef<str>,
+        F: FnMut(&str) -> ID,
+    {
+        for (path, metadata) in paths {
+            let path = path.as_ref();
+            let names: Vec<&str> = components(path).collect();
+            if names.is_empty() {
+                return Err(FsError::InvalidPath(path.to_string()));
+            }
+            let mut parent_id = self.root_id.clone();
+            let mut node_path = String::new();
+            for name in &names {
+                node_path.push('/');
+                node_path.push_str(name);
+                let index = match self.paths.get(&node_path) {
+                    Some(&index) => index,
+                    None => {
+                        let id = id_for(&node_path);
+                        self.moves.push((parent_id, TM::from_name(name), id));
+                        self.paths.insert(node_path.clone(), self.moves.len() - 1);
+                        self.moves.len() - 1
+                    }
+                };
+                parent_id = self.moves[index].2.clone();
+            }
+            let index = self.paths[&node_path];
+            self.moves[index].1 = metadata;
+        }
+        Ok(self)
+    }
+}
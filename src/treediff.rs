@@ -0,0 +1,58 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{OpMove, Resolve, TieBreak, Tree, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// returns the moves, as `(parent_id, metadata, child_id)`, that
+/// transform current into target: one for each node of target that is
+/// missing from current, or has a different parent or metadata there.
+///
+/// The moves are ordered by the depth of their node in target, shallowest
+/// first, so that applying them in order never moves a node under one of
+/// its own descendants, which would be ignored.
+///
+/// Moves can not remove nodes, so nodes of current missing from target
+/// are left alone.  To delete them, eg by moving them to a trash node,
+/// include them in target under the trash node.
+pub fn diff<ID, TM>(current: &Tree<ID, TM>, target: &Tree<ID, TM>) -> Vec<(ID, TM, ID)>
+where
+    ID: TreeId,
+    TM: TreeMeta + PartialEq,
+{
+    let mut moves: Vec<(usize, (ID, TM, ID))> = target
+        .iter()
+        .filter(|(id, node)| current.find(id) != Some(*node))
+        .map(|(id, node)| {
+            let depth = target.depth(id).unwrap_or(usize::MAX);
+            let m = (
+                node.parent_id().clone(),
+                node.metadata().clone(),
+                id.clone(),
+            );
+            (depth, m)
+        })
+        .collect();
+    moves.sort_by_key(|(depth, _)| *depth);
+    moves.into_iter().map(|(_, m)| m).collect()
+}
+
+impl<ID, TM, A, T, R> TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta + PartialEq,
+    A: Actor + std::fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    /// generates and applies the ops that transform the replica's tree
+    /// into target, and returns them, for sending to peers.  See `diff`.
+    pub fn converge_to(&mut self, target: &Tree<ID, TM>) -> Vec<OpMove<ID, TM, A>> {
+        let ops = self.opmoves(diff(self.tree(), target));
+        self.apply_ops_byref(&ops);
+        ops
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Nested JSON representation of a `Tree`.
+//!
+//! Requires the `serde_json` feature.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{Tree, TreeId, TreeMeta, TreeNode};
+
+/// `JsonNode` is a node in the nested JSON representation of a `Tree`.
+///
+/// Top-level entries are the tree's roots, ie parents that are not
+/// themselves nodes in the tree, and have no metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = "ID: Deserialize<'de>, TM: Deserialize<'de>"))]
+pub struct JsonNode<ID, TM> {
+    /// the node's ID
+    pub id: ID,
+    /// the node's metadata.  None for roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<TM>,
+    /// the node's children
+    #[serde(default)]
+    pub children: Vec<JsonNode<ID, TM>>,
+}
+
+impl<ID: TreeId, TM: TreeMeta> Tree<ID, TM> {
+    /// returns the tree as nested `JsonNode`s, one per root.
+    ///
+    /// The order of roots and of siblings is unspecified.
+    pub fn to_json_nodes(&self) -> Vec<JsonNode<ID, TM>> {
+        self.roots()
+            .into_iter()
+            .map(|root| JsonNode {
+                children: self.json_children(&root),
+                id: root,
+                meta: None,
+            })
+            .collect()
+    }
+
+    /// creates a tree from nested `JsonNode`s, as returned by `to_json_nodes`.
+    ///
+    /// Metadata of top-level nodes is ignored.
+    pub fn from_json_nodes(roots: Vec<JsonNode<ID, TM>>) -> Self {
+        let mut tree = Self::new();
+        let mut stack: Vec<(ID, JsonNode<ID, TM>)> = vec![];
+        for root in roots {
+            for child in root.children {
+                stack.push((root.id.clone(), child));
+            }
+        }
+        while let Some((parent_id, node)) = stack.pop() {
+            for child in node.children {
+                stack.push((node.id.clone(), child));
+            }
+            if let Some(meta) = node.meta {
+                tree.add_node(node.id, TreeNode::new(parent_id, meta));
+            }
+        }
+        tree
+    }
+
+    /// returns a nested JSON representation of the tree, with metadata
+    /// inline.  eg:
+    ///
+    /// ```json
+    /// [{"id": 0, "children": [{"id": 1, "meta": "home", "children": []}]}]
+    /// ```
+    pub fn to_json(&self) -> serde_json::Result<String>
+    where
+        ID: Serialize,
+        TM: Serialize,
+    {
+        serde_json::to_string_pretty(&self.to_json_nodes())
+    }
+
+    /// creates a tree from JSON, as returned by `to_json`.
+    pub fn from_json(json: &str) -> serde_json::Result<Self>
+    where
+        ID: DeserializeOwned,
+        TM: DeserializeOwned,
+    {
+        Ok(Self::from_json_nodes(serde_json::from_str(json)?))
+    }
+
+    // returns the children of parent_id as JsonNodes, recursively.
+    fn json_children(&self, parent_id: &ID) -> Vec<JsonNode<ID, TM>> {
+        // collect descendants in pre-order, so that building them in
+        // reverse visits every node's children before the node itself.
+        let mut order: Vec<(ID, &TreeNode<ID, TM>)> = vec![];
+        let mut stack: Vec<ID> = self.children(parent_id);
+        while let Some(child_id) = stack.pop() {
+            if let Some(node) = self.find(&child_id) {
+                stack.extend(self.children(&child_id));
+                order.push((child_id, node));
+            }
+        }
+        let mut built: HashMap<ID, Vec<JsonNode<ID, TM>>> = HashMap::new();
+        for (id, node) in order.into_iter().rev() {
+            let json = JsonNode {
+                children: built.remove(&id).unwrap_or_default(),
+                id,
+                meta: Some(node.metadata().clone()),
+            };
+            built
+                .entry(node.parent_id().clone())
+                .or_default()
+                .push(json);
+        }
+        built.remove(parent_id).unwrap_or_default()
+    }
+}
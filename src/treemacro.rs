@@ -0,0 +1,47 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// generates the ops building a tree, written as nested children with
+/// metadata, on a replica.
+///
+/// Returns the ops from `TreeReplica::opmoves`, parents before children,
+/// which are not applied:
+///
+/// ```text
+/// let ops = crdt_tree::tree!(r1, 0 => {
+///     1: "home" => {
+///         2: "bob",
+///         3: "alice" => { 4: "docs" },
+///     },
+///     5: "tmp",
+/// });
+/// r1.apply_ops(ops);
+/// ```
+///
+/// Each node is `child_id: metadata`, optionally followed by `=> { .. }`
+/// with its children.  IDs are single tokens, eg literals or variables;
+/// wrap other expressions in parentheses.  A child's ID is evaluated again
+/// for each of its children.
+#[macro_export]
+macro_rules! tree {
+    (@moves $moves:ident, $parent:tt, ) => {};
+    (@moves $moves:ident, $parent:tt,
+        $child:tt : $meta:expr => { $($children:tt)* } $(, $($rest:tt)*)?
+    ) => {
+        $moves.push(($parent, $meta, $child));
+        $crate::tree!(@moves $moves, $child, $($children)*);
+        $crate::tree!(@moves $moves, $parent, $($($rest)*)?);
+    };
+    (@moves $moves:ident, $parent:tt, $child:tt : $meta:expr $(, $($rest:tt)*)?) => {
+        $moves.push(($parent, $meta, $child));
+        $crate::tree!(@moves $moves, $parent, $($($rest)*)?);
+    };
+    ($replica:expr, $root:tt => { $($children:tt)* }) => {{
+        let mut moves = ::std::vec::Vec::new();
+        $crate::tree!(@moves moves, $root, $($children)*);
+        $replica.opmoves(moves)
+    }};
+}
@@ -4,7 +4,27 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
+use crdts::CvRDT;
+
 /// `TreeMeta` represent the app-defined data that an application stores in each node
 /// of the tree.
 pub trait TreeMeta: Clone {}
 impl<TM: Clone> TreeMeta for TM {}
+
+/// `MergeMeta` is metadata that is itself a CRDT, eg a `crdts::LWWReg` or
+/// `crdts::Map`, so that concurrent values may be merged rather than one
+/// replacing the other.  See `resolve::MergeMetadata`.
+///
+/// Every state-based CRDT of the `crdts` crate is `MergeMeta`, as are
+/// records declared by `meta_record!`.
+pub trait MergeMeta: TreeMeta {
+    /// merges other into self.  With `resolve::MergeMetadata`, other is
+    /// the metadata of a later op.
+    fn merge(&mut self, other: Self);
+}
+
+impl<TM: TreeMeta + CvRDT> MergeMeta for TM {
+    fn merge(&mut self, other: Self) {
+        CvRDT::merge(self, other)
+    }
+}
@@ -4,8 +4,10 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::{Eq, PartialEq};
+use std::fmt;
+use std::sync::Arc;
 
 use super::{TreeId, TreeMeta};
 
@@ -14,8 +16,17 @@ use super::{TreeId, TreeMeta};
 /// Logically, each `TreeNode` consists of a triple `(parent_id, metadata, child_id)`.
 /// However, in this implementation, the `child_id` is stored as the
 /// key in `Tree::triples HashMap<ID, TreeNode>`
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TreeNode<ID: TreeId, TM: TreeMeta> {
+///
+/// The parent and metadata are held behind an `Arc`, so that cloning a
+/// node is cheap and the clone shares them.  This is how the tree and
+/// the log entries that refer to a node share a single copy.
+#[derive(Clone)]
+pub struct TreeNode<ID: TreeId, TM: TreeMeta>(Arc<NodeData<ID, TM>>);
+
+// the parent and metadata of a TreeNode.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "TreeNode")]
+struct NodeData<ID, TM> {
     parent_id: ID,
     metadata: TM,
 }
@@ -27,19 +38,87 @@ impl<ID: TreeId, TM: TreeMeta> TreeNode<ID, TM> {
 
     /// creates a new `TreeNode` instance
     pub fn new(parent_id: ID, metadata: TM) -> Self {
-        Self {
+        Self(Arc::new(NodeData {
             parent_id,
             metadata,
-        }
+        }))
     }
 
     /// returns `parent_id` reference
     pub fn parent_id(&self) -> &ID {
-        &self.parent_id
+        &self.0.parent_id
     }
 
     /// returns metadata reference
     pub fn metadata(&self) -> &TM {
-        &self.metadata
+        &self.0.metadata
+    }
+
+    /// returns (`parent_id`, metadata) references
+    pub fn parts(&self) -> (&ID, &TM) {
+        (&self.0.parent_id, &self.0.metadata)
+    }
+
+    /// returns mutable metadata reference.  The node is first copied if
+    /// it is shared.
+    pub(crate) fn metadata_mut(&mut self) -> &mut TM {
+        &mut Arc::make_mut(&mut self.0).metadata
+    }
+
+    // returns (parent_id, metadata), consuming self.  They are copied only
+    // if the node is still shared.
+    pub(crate) fn into_parts(self) -> (ID, TM) {
+        let data = Arc::try_unwrap(self.0).unwrap_or_else(|data| (*data).clone());
+        (data.parent_id, data.metadata)
+    }
+
+    // returns the address of the shared parent and metadata, eg to count
+    // distinct nodes.
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *const () {
+        Arc::as_ptr(&self.0) as *const ()
+    }
+
+    // returns true if a and b are the same node, rather than equal ones.
+    #[inline]
+    pub(crate) fn ptr_eq(a: &Self, b: &Self) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta + PartialEq> PartialEq for TreeNode<ID, TM> {
+    fn eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other) || self.0 == other.0
+    }
+}
+
+impl<ID: TreeId, TM: TreeMeta + Eq> Eq for TreeNode<ID, TM> {}
+
+impl<ID: TreeId + fmt::Debug, TM: TreeMeta + fmt::Debug> fmt::Debug for TreeNode<ID, TM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeNode")
+            .field("parent_id", &self.0.parent_id)
+            .field("metadata", &self.0.metadata)
+            .finish()
+    }
+}
+
+impl<ID, TM> Serialize for TreeNode<ID, TM>
+where
+    ID: TreeId + Serialize,
+    TM: TreeMeta + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, ID, TM> Deserialize<'de> for TreeNode<ID, TM>
+where
+    ID: TreeId + Deserialize<'de>,
+    TM: TreeMeta + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NodeData::deserialize(deserializer).map(|data| Self(Arc::new(data)))
     }
 }
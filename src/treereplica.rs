@@ -9,10 +9,39 @@ extern crate crdts;
 use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, PartialEq};
 
-use super::{Clock, LogOpMove, OpMove, State, Tree, TreeId, TreeMeta};
+use super::replicaexport::ImportError;
+use super::seen::Seen;
+use super::state::fmt_clock;
+use super::wallclock::now_millis;
+use super::{
+    conflict::OnConflict, ActorOrder, ApplyReport, Barrier, CausalContext, CausalOpMove,
+    ChangeEvent, Clock, ConflictHandler, DriftGuard, DriftPolicy, Kleppmann, LogLimit,
+    LogLimitPolicy, LogOpMove, OpMove, Outbox, Quotas, ReplicaExport, Resolve, Segment, Snapshot,
+    State, Storage, TieBreak, Tree, TreeId, TreeMeta, TreeNode, TreeSnapshot, VersionVector,
+    EXPORT_VERSION,
+};
+#[cfg(feature = "tokio")]
+use super::{
+    subscribe::{ApplyOutcome, Subscribers},
+    AppliedOp,
+};
 use crdts::Actor;
-use log::debug;
-use std::collections::HashMap;
+use log::{debug, warn};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "tokio")]
+use tokio_stream::Stream;
+
+// what became of an op passed to ::apply_op().
+enum OpOutcome {
+    // applied after undoing steps newer entries, and placed unless
+    // ignored by the tree.
+    Applied { steps: usize, placed: bool },
+    Duplicate,
+    Tombstoned,
+    Drifted,
+    OverLimit,
+}
 
 /// `TreeReplica` holds tree `State` plus lamport timestamp (actor + counter)
 ///
@@ -25,21 +54,133 @@ use std::collections::HashMap;
 ///
 /// `State` is a lower-level interface to the Tree CRDT and is not tied to any
 /// actor/peer.
+///
+/// T is the `TieBreak` strategy used to order ops with equal counters, and
+/// R the `Resolve` strategy for moves conflicting with the tree.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TreeReplica<ID: TreeId, TM: TreeMeta, A: Actor> {
-    state: State<ID, TM, A>, // Tree state
-    time: Clock<A>,          // Lamport Clock for this replica/tree.
+#[serde(bound(
+    serialize = "ID: Serialize, TM: Serialize, A: Serialize",
+    deserialize = "ID: Deserialize<'de>, TM: Deserialize<'de>, A: Deserialize<'de>"
+))]
+pub struct TreeReplica<
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor,
+    T: TieBreak<A> = ActorOrder,
+    R: Resolve<ID, TM, A> = Kleppmann,
+> {
+    state: State<ID, TM, A, T, R>, // Tree state
+    time: Clock<A>,                // Lamport Clock for this replica/tree.
+
+    latest_time_by_replica: VersionVector<A>,
 
-    latest_time_by_replica: HashMap<A, Clock<A>>,
+    // local settings, not persisted.
+    #[serde(skip)]
+    wall_clock: bool, // stamp generated ops with wall-clock time.
+    #[serde(skip)]
+    provenance: Option<Vec<u8>>, // stamp generated ops with provenance.
+    #[serde(skip)]
+    drift_guard: Option<DriftGuard>,
+    #[serde(skip)]
+    drifted: Vec<Clock<A>>, // timestamps of ops caught by drift_guard.
+    #[serde(skip)]
+    tombstones: HashSet<A>, // forgotten actors whose ops are rejected.
+    #[serde(skip)]
+    log_limit: Option<LogLimit>,
+    #[serde(skip)]
+    spilled: Vec<LogOpMove<ID, TM, A>>, // entries removed by log_limit.
+    #[serde(skip)]
+    pending: Vec<CausalOpMove<ID, TM, A>>, // causal ops awaiting dependencies.
+
+    // timestamps of applied ops, to skip redelivered ops.  ops older than
+    // State::truncated_before() were truncated from the log, and are taken
+    // as seen.
+    #[serde(skip)]
+    seen: Seen<A>,
+    #[serde(skip)]
+    outbox: Outbox<ID, TM, A>, // local ops awaiting acknowledgement.
+
+    // timestamps of local ops that ::undo() and ::redo() may reverse,
+    // most recent last.
+    #[serde(skip)]
+    undo_stack: Vec<Clock<A>>,
+    #[serde(skip)]
+    redo_stack: Vec<Clock<A>>,
+    #[serde(skip)]
+    on_conflict: OnConflict<ID, A>,
+    #[cfg(feature = "tokio")]
+    #[serde(skip)]
+    subscribers: Subscribers<ID, TM, A>,
 }
 
-impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A> {
+impl<
+        ID: TreeId,
+        TM: TreeMeta,
+        A: Actor + std::fmt::Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    > TreeReplica<ID, TM, A, T, R>
+{
     /// returns new TreeReplica
     pub fn new(id: A) -> Self {
+        Self::from_parts(
+            State::new(),
+            Clock::<A>::new(id, None),
+            VersionVector::new(),
+        )
+    }
+
+    /// resumes a replica with actor id from state, eg as persisted before
+    /// a restart, and the latest timestamp seen from each replica, as
+    /// returned by ::version_vector().
+    ///
+    /// The replica's clock is advanced past every timestamp in
+    /// latest_times and the log, so ops it generates are newer than any
+    /// it has seen.  Ops older than state's log was truncated before, if
+    /// ever, are taken as already applied.  See `State::truncated_before`.
+    pub fn from_state(
+        id: A,
+        state: State<ID, TM, A, T, R>,
+        latest_times: VersionVector<A>,
+    ) -> Self {
+        let mut latest_time_by_replica = latest_times;
+        for entry in state.log() {
+            latest_time_by_replica.observe(entry.timestamp());
+        }
+        let mut time = Clock::<A>::new(id, None);
+        for latest in latest_time_by_replica.clocks() {
+            time = time.merge(latest);
+        }
+        Self::from_parts(state, time, latest_time_by_replica)
+    }
+
+    // returns a replica with default local settings, which has seen the
+    // ops in state's log and those its log was truncated before.
+    fn from_parts(
+        state: State<ID, TM, A, T, R>,
+        time: Clock<A>,
+        latest_time_by_replica: VersionVector<A>,
+    ) -> Self {
+        let seen = state.log().iter().map(|e| e.timestamp()).collect();
         Self {
-            state: State::new(),
-            time: Clock::<A>::new(id, None),
-            latest_time_by_replica: HashMap::<A, Clock<A>>::new(),
+            state,
+            time,
+            latest_time_by_replica,
+            wall_clock: false,
+            provenance: None,
+            drift_guard: None,
+            drifted: Vec::new(),
+            tombstones: HashSet::new(),
+            log_limit: None,
+            spilled: Vec::new(),
+            pending: Vec::new(),
+            seen,
+            outbox: Outbox::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            on_conflict: OnConflict::default(),
+            #[cfg(feature = "tokio")]
+            subscribers: Subscribers::default(),
         }
     }
 
@@ -53,7 +194,7 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A
     ///
     /// To generate multiple ops before calling ::apply_op(), use ::opmoves() instead.
     pub fn opmove(&self, parent_id: ID, metadata: TM, child_id: ID) -> OpMove<ID, TM, A> {
-        OpMove::new(self.time.inc(), parent_id, metadata, child_id)
+        self.stamp(OpMove::new(self.time.inc(), parent_id, metadata, child_id))
     }
 
     /// Generates a list of OpMove from a list of tuples (child_id, metadata, parent_id)
@@ -67,11 +208,124 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A
         let mut opmoves = vec![];
 
         for op in ops {
-            opmoves.push(OpMove::new(time.tick(), op.0, op.1, op.2));
+            opmoves.push(self.stamp(OpMove::new(time.tick(), op.0, op.1, op.2)));
         }
         opmoves
     }
 
+    // returns op with the wall-clock time and provenance, if enabled.
+    fn stamp(&self, mut op: OpMove<ID, TM, A>) -> OpMove<ID, TM, A> {
+        if self.wall_clock {
+            op.set_wall_time(Some(now_millis()));
+        }
+        op.set_provenance(self.provenance.clone());
+        op
+    }
+
+    /// sets whether ops generated by ::opmove() and ::opmoves() are
+    /// stamped with the local wall-clock time.  Off by default.
+    pub fn set_wall_clock(&mut self, enabled: bool) {
+        self.wall_clock = enabled;
+    }
+
+    /// sets the provenance that ops generated by ::opmove() and
+    /// ::opmoves() are stamped with, eg this device's ID, or None.  See
+    /// `OpMove::with_provenance`.
+    pub fn set_provenance(&mut self, provenance: Option<Vec<u8>>) {
+        self.provenance = provenance;
+    }
+
+    /// sets a guard against applied ops that claim a wall-clock time too
+    /// far in the future, or None to remove it.
+    ///
+    /// Ops without a wall-clock time always pass.  The timestamps of ops
+    /// caught by the guard are returned by ::drifted_ops().
+    pub fn set_drift_guard(&mut self, guard: Option<DriftGuard>) {
+        self.drift_guard = guard;
+    }
+
+    /// sets a cap on the number of entries held in the log, or None to
+    /// remove it.  See `LogLimit`.
+    ///
+    /// Once the log is full, it is first truncated to the causally stable
+    /// threshold, as by ::truncate_log(), and the limit's policy applies
+    /// only if that does not make room.
+    pub fn set_log_limit(&mut self, limit: Option<LogLimit>) {
+        self.log_limit = limit;
+        self.enforce_log_limit();
+    }
+
+    /// removes and returns the log entries removed by a log limit with
+    /// `LogLimitPolicy::Spill`, oldest first, eg to store them with
+    /// `Storage::append_op`.
+    pub fn take_spilled(&mut self) -> Vec<LogOpMove<ID, TM, A>> {
+        std::mem::take(&mut self.spilled)
+    }
+
+    /// sets a handler called with each conflict caused by an applied op,
+    /// or None to remove it.  See `ConflictEvent`.
+    ///
+    /// Conflicts are only detected while a handler is set.  The handler is
+    /// not cloned or persisted with the replica.
+    pub fn set_conflict_handler(&mut self, handler: Option<ConflictHandler<ID, A>>) {
+        self.on_conflict.handler = handler;
+    }
+
+    /// sets limits on the tree's shape.  See `State::set_quotas`.
+    pub fn set_quotas(&mut self, quotas: Quotas) {
+        self.state.set_quotas(quotas);
+    }
+
+    /// designates root_id as the root of the tree, under root_parent.  See
+    /// `State::set_root`.
+    pub fn set_root(&mut self, root_id: ID, root_parent: ID) {
+        self.state.set_root(root_id, root_parent);
+    }
+
+    /// returns the root's ID, if set by ::set_root() and created, ie a node.
+    #[inline]
+    pub fn root(&self) -> Option<&ID> {
+        self.state.root()
+    }
+
+    /// enables strict mode, admitting moves only under nodes and the
+    /// top_level parents, or disables it with None.  See
+    /// `State::set_strict`.
+    pub fn set_strict(&mut self, top_level: Option<Vec<ID>>) {
+        self.state.set_strict(top_level);
+    }
+
+    /// creates the root set by ::set_root(), with metadata, and returns its
+    /// ID.  If the root exists, its metadata is replaced.
+    ///
+    /// Replicas creating the root concurrently converge on one root, with
+    /// the metadata of the latest op.  The op is added to the outbox, for
+    /// sending to peers.
+    ///
+    /// Panics if no root is set.
+    pub fn create_root(&mut self, metadata: TM) -> ID {
+        let (root_id, root_parent) = self
+            .state
+            .root_setting()
+            .cloned()
+            .expect("no root is set, see set_root()");
+        let op = self.opmove(root_parent, metadata, root_id.clone());
+        self.apply_op(op);
+        root_id
+    }
+
+    /// returns timestamps of ops caught by the drift guard, oldest first.
+    #[inline]
+    pub fn drifted_ops(&self) -> &[Clock<A>] {
+        &self.drifted
+    }
+
+    /// returns timestamps of ops caught by the drift guard, and clears
+    /// them.
+    pub fn take_drifted_ops(&mut self) -> Vec<Clock<A>> {
+        std::mem::take(&mut self.drifted)
+    }
+
     /// Returns actor ID for this replica
     #[inline]
     pub fn id(&self) -> &A {
@@ -86,7 +340,7 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A
 
     /// Returns Tree State reference
     #[inline]
-    pub fn state(&self) -> &State<ID, TM, A> {
+    pub fn state(&self) -> &State<ID, TM, A, T, R> {
         &self.state
     }
 
@@ -102,49 +356,313 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A
     /// not be mutated directly.
     ///
     /// See the demo_move_to_trash in examples/demo.rs for a
-    /// use-case, only after log truncation has been performed.
+    /// use-case, only after log truncation has been performed, and
+    /// ::rm_subtree_checked() and ::retain_checked() for safer ways.
     #[inline]
     pub fn tree_mut(&mut self) -> &mut Tree<ID, TM> {
         self.state.tree_mut()
     }
 
+    /// Returns the parent of child_id, as `Tree::parent`
+    #[inline]
+    pub fn parent(&self, child_id: &ID) -> Option<&ID> {
+        self.tree().parent(child_id)
+    }
+
+    /// Returns the metadata of child_id, as `Tree::metadata`
+    #[inline]
+    pub fn metadata(&self, child_id: &ID) -> Option<&TM> {
+        self.tree().metadata(child_id)
+    }
+
+    // returns the state, for maintenance that does not go through ops.
+    pub(crate) fn state_mut(&mut self) -> &mut State<ID, TM, A, T, R> {
+        &mut self.state
+    }
+
     /// Applies single operation to `State` and updates our time clock
     ///
     /// Also records latest timestamp for each replica if
     /// track_causally_stable_threshold flag is set.
+    ///
+    /// If a drift guard is set, an op claiming a wall-clock time too far
+    /// in the future is flagged, or rejected and not applied.
+    ///
+    /// An op that has already been applied, eg when redelivered by a
+    /// gossip transport, is skipped without touching `State`.
     pub fn apply_op(&mut self, op: OpMove<ID, TM, A>) {
+        self.apply_op_outcome(op);
+    }
+
+    // applies op as ::apply_op(), returning what became of it.
+    fn apply_op_outcome(&mut self, op: OpMove<ID, TM, A>) -> OpOutcome {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "apply_op",
+            actor = ?op.timestamp().actor_id(),
+            counter = op.timestamp().counter()
+        )
+        .entered();
+
+        if self.has_seen(op.timestamp()) {
+            debug!("op {:?} already applied, skipping op", op.timestamp());
+            #[cfg(feature = "tokio")]
+            self.subscribers.notify(&op, ApplyOutcome::Duplicate);
+            return OpOutcome::Duplicate;
+        }
+
+        if self.tombstones.contains(op.timestamp().actor_id()) {
+            warn!(
+                "op {:?} is from a tombstoned actor, dropping op!",
+                op.timestamp()
+            );
+            #[cfg(feature = "tokio")]
+            self.subscribers.notify(&op, ApplyOutcome::Rejected);
+            return OpOutcome::Tombstoned;
+        }
+
+        if let (Some(guard), Some(wall_time)) = (&self.drift_guard, op.wall_time()) {
+            if guard.exceeds(wall_time, now_millis()) {
+                self.drifted.push(op.timestamp().clone());
+                if guard.policy() == DriftPolicy::Reject {
+                    warn!(
+                        "op {:?} claims wall-clock time {} beyond max drift, dropping op!",
+                        op.timestamp(),
+                        wall_time
+                    );
+                    #[cfg(feature = "tokio")]
+                    self.subscribers.notify(&op, ApplyOutcome::Rejected);
+                    return OpOutcome::Drifted;
+                }
+            }
+        }
+
+        if !self.make_room(Some(op.timestamp())) {
+            warn!(
+                "op {:?} exceeds the log limit, dropping op!",
+                op.timestamp()
+            );
+            #[cfg(feature = "tokio")]
+            self.subscribers.notify(&op, ApplyOutcome::Rejected);
+            return OpOutcome::OverLimit;
+        }
+
+        self.seen.insert(op.timestamp());
+        if op.timestamp().actor_id() == self.id() {
+            self.outbox.push(op.clone());
+            self.undo_stack.push(op.timestamp().clone());
+            self.redo_stack.clear();
+        }
         self.time = self.time.merge(op.timestamp());
 
         // store latest timestamp for this actor.
         // This is only needed for calculation of causally_stable_threshold.
-        let id = op.timestamp().actor_id();
-        match self.latest_time_by_replica.get(id) {
-            Some(latest) if (op.timestamp() <= latest) => {
+        if let Some(latest) = self
+            .latest_time_by_replica
+            .latest(op.timestamp().actor_id())
+        {
+            if op.timestamp() <= latest {
                 debug!(
                     "Clock not increased, current timestamp {:?}, provided is {:?}, dropping op!",
                     latest,
                     op.timestamp()
                 );
             }
-            _ => {
-                self.latest_time_by_replica
-                    .insert(op.timestamp().actor_id().clone(), op.timestamp().clone());
+        }
+        self.latest_time_by_replica.observe(op.timestamp());
+
+        #[cfg(feature = "tokio")]
+        self.subscribers.notify(&op, ApplyOutcome::Applied);
+        // newer entries are undone and redone around the op.
+        let steps = self
+            .state
+            .log()
+            .iter()
+            .take_while(|e| T::cmp(e.timestamp(), op.timestamp()) == Ordering::Greater)
+            .count();
+        let index = match &mut self.on_conflict.handler {
+            Some(handler) => {
+                let mut events = vec![];
+                let index = self.state.apply_op_at(op, Some(&mut events));
+                for event in events {
+                    handler(event);
+                }
+                index
+            }
+            None => self.state.apply_op_at(op, None),
+        };
+        // the op is not logged if an entry has an equal timestamp.
+        let placed = matches!(index, Some(i) if self.state.entry_placed(i));
+        self.enforce_log_limit();
+        OpOutcome::Applied { steps, placed }
+    }
+
+    // makes room in the log for ops with the given timestamps, truncating
+    // it to the causally stable threshold, as it would be once they are
+    // applied, if needed.  returns false if there is still no room, and
+    // the log limit rejects new ops.
+    pub(crate) fn make_room<'a, I>(&mut self, timestamps: I) -> bool
+    where
+        I: IntoIterator<Item = &'a Clock<A>>,
+        A: 'a,
+    {
+        let timestamps: Vec<_> = timestamps.into_iter().collect();
+        let n = timestamps.len();
+        match self.log_limit {
+            Some(limit) if limit.policy() == LogLimitPolicy::Reject => {
+                if self.state.log().len() + n <= limit.max_len() {
+                    return true;
+                }
+                let mut latest = self.latest_time_by_replica.clone();
+                for t in timestamps {
+                    latest.observe(t);
+                }
+                if let Some(t) = latest.clocks().min().cloned() {
+                    self.truncate_log_to(t);
+                }
+                self.state.log().len() + n <= limit.max_len()
             }
+            _ => true,
+        }
+    }
+
+    // removes entries from a log over the log limit, as its policy allows.
+    fn enforce_log_limit(&mut self) {
+        let limit = match self.log_limit {
+            Some(limit) if self.state.log().len() > limit.max_len() => limit,
+            _ => return,
         };
+        let mut removed = self.truncate_log();
+        let len = self.state.log().len();
+        if len > limit.max_len() && limit.policy() != LogLimitPolicy::Reject {
+            warn!(
+                "log length {} exceeds the log limit, truncating entries not causally stable!",
+                len
+            );
+            let oldest_kept = match limit.max_len() {
+                0 => self.time.inc(),
+                n => self.state.log()[n - 1].timestamp().clone(),
+            };
+            let mut unstable = self.state.truncate_log_before(&oldest_kept);
+            unstable.append(&mut removed);
+            removed = unstable;
+        }
+        if limit.policy() == LogLimitPolicy::Spill {
+            self.spilled.extend(removed.into_iter().rev());
+        }
+    }
 
-        self.state.apply_op(op);
+    // returns true if the drift guard would reject op, were it applied
+    // now.
+    pub(crate) fn drift_rejects(&self, op: &OpMove<ID, TM, A>) -> bool {
+        match (&self.drift_guard, op.wall_time()) {
+            (Some(guard), Some(wall_time)) => {
+                guard.policy() == DriftPolicy::Reject && guard.exceeds(wall_time, now_millis())
+            }
+            _ => false,
+        }
+    }
+
+    /// returns a stream of every op passed to ::apply_op() from now on,
+    /// with its outcome.
+    ///
+    /// Each subscriber buffers up to 1024 ops.  A subscriber that falls
+    /// further behind misses the oldest ops, and a warning is logged.
+    /// Subscribers are not cloned with the replica.
+    ///
+    /// Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn subscribe(&mut self) -> impl Stream<Item = AppliedOp<ID, TM, A>> + Unpin
+    where
+        ID: Send + 'static,
+        TM: Send + 'static,
+        A: Send + 'static,
+    {
+        self.subscribers.subscribe()
+    }
+
+    /// returns the outbox of ops generated by this replica.
+    #[inline]
+    pub fn outbox(&self) -> &Outbox<ID, TM, A> {
+        &self.outbox
+    }
+
+    /// returns the outbox of ops generated by this replica, for
+    /// registering peers and recording acknowledgements.
+    ///
+    /// Ops generated by this replica are added to the outbox when applied.
+    /// The outbox is not persisted with the replica.
+    #[inline]
+    pub fn outbox_mut(&mut self) -> &mut Outbox<ID, TM, A> {
+        &mut self.outbox
+    }
+
+    /// returns ops generated by this replica not yet acknowledged by
+    /// peer, oldest first, for retransmission.  See `Outbox`.
+    pub fn unacked_ops(&self, peer: &A) -> Vec<&OpMove<ID, TM, A>> {
+        self.outbox.unacked_ops(peer)
+    }
+
+    /// returns true if the op with timestamp has already been applied.
+    ///
+    /// Ops older than the threshold at the last ::truncate_log() are taken
+    /// as applied.  Ops applied before the replica was deserialized are
+    /// not remembered, though `State` still ignores exact duplicates.
+    ///
+    /// Newer ops are remembered as runs of consecutive counters for each
+    /// actor, which costs 16 bytes per run, and at worst per op, until
+    /// the log is truncated.
+    pub fn has_seen(&self, timestamp: &Clock<A>) -> bool {
+        matches!(self.state.truncated_before(), Some(floor) if timestamp < floor)
+            || self.seen.contains(timestamp)
     }
 
     /// Applies list of operations
     pub fn apply_ops(&mut self, ops: Vec<OpMove<ID, TM, A>>) {
+        self.apply_ops_iter(ops)
+    }
+
+    /// applies operations as they are yielded, eg by a decoder or a
+    /// channel, without collecting them first.
+    pub fn apply_ops_iter<I>(&mut self, ops: I)
+    where
+        I: IntoIterator<Item = OpMove<ID, TM, A>>,
+    {
         for op in ops {
             self.apply_op(op);
         }
     }
 
+    /// applies ops as ::apply_ops(), and returns a summary of what became
+    /// of them, eg for a sync daemon to log per batch.  See `ApplyReport`.
+    pub fn apply_ops_report(&mut self, ops: Vec<OpMove<ID, TM, A>>) -> ApplyReport {
+        let mut report = ApplyReport {
+            counter_before: self.time.counter(),
+            ..ApplyReport::default()
+        };
+        for op in ops {
+            match self.apply_op_outcome(op) {
+                OpOutcome::Applied { steps, placed } => {
+                    if placed {
+                        report.applied += 1;
+                    } else {
+                        report.no_effect += 1;
+                    }
+                    report.undo_redo_steps += steps;
+                }
+                OpOutcome::Duplicate => report.duplicates += 1,
+                OpOutcome::Tombstoned => report.tombstoned += 1,
+                OpOutcome::Drifted => report.drifted += 1,
+                OpOutcome::OverLimit => report.over_limit += 1,
+            }
+        }
+        report.counter_after = self.time.counter();
+        report
+    }
+
     /// Applies list of operations without taking ownership
     pub fn apply_ops_byref(&mut self, ops: &[OpMove<ID, TM, A>]) {
-        self.apply_ops(ops.to_vec())
+        self.apply_ops_iter(ops.iter().cloned())
     }
 
     /// applies op from a log.  useful for log replay.
@@ -154,9 +672,343 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A
 
     /// applies ops from a log.  useful for log replay.
     pub fn apply_log_ops(&mut self, log_ops: Vec<LogOpMove<ID, TM, A>>) {
-        for log_op in log_ops {
-            self.apply_log_op(log_op);
+        self.apply_ops_iter(log_ops.into_iter().map(OpMove::from))
+    }
+
+    /// returns the latest timestamp seen from each replica.
+    #[inline]
+    pub fn version_vector(&self) -> &VersionVector<A> {
+        &self.latest_time_by_replica
+    }
+
+    /// returns an iterator over (actor, latest timestamp) for each replica
+    /// an op has been seen from, eg to display peers' sync status.
+    ///
+    /// The minimum is the causally stable threshold.
+    pub fn latest_times(&self) -> impl Iterator<Item = (&A, &Clock<A>)> {
+        self.latest_time_by_replica
+            .clocks()
+            .map(|c| (c.actor_id(), c))
+    }
+
+    /// returns the latest timestamp seen from actor, or None if no op
+    /// from actor has been seen.
+    #[inline]
+    pub fn latest_time_of(&self, actor: &A) -> Option<&Clock<A>> {
+        self.latest_time_by_replica.latest(actor)
+    }
+
+    /// returns the latest counter seen from each replica.
+    pub fn causal_context(&self) -> CausalContext<A> {
+        self.latest_time_by_replica.clone()
+    }
+
+    /// Generates an OpMove, as ::opmove(), together with this replica's
+    /// causal context.  See `CausalOpMove`.
+    pub fn causal_opmove(
+        &self,
+        parent_id: ID,
+        metadata: TM,
+        child_id: ID,
+    ) -> CausalOpMove<ID, TM, A> {
+        CausalOpMove::new(
+            self.opmove(parent_id, metadata, child_id),
+            self.causal_context(),
+        )
+    }
+
+    /// Applies a causal op once every op in its context has been applied.
+    ///
+    /// Until then, the op is buffered, and it is applied by a later call
+    /// that supplies its missing dependencies.  Returns the number of ops
+    /// applied by this call, including buffered ops.
+    ///
+    /// Buffered ops are not persisted with the replica, except by
+    /// ::export().
+    pub fn apply_causal_op(&mut self, op: CausalOpMove<ID, TM, A>) -> usize {
+        self.pending.push(op);
+        let mut applied = 0;
+        loop {
+            let seen = self.causal_context();
+            match self.pending.iter().position(|op| op.is_ready(&seen)) {
+                Some(i) => {
+                    let op = self.pending.remove(i);
+                    self.apply_op(op.into_op());
+                    applied += 1;
+                }
+                None => return applied,
+            }
+        }
+    }
+
+    /// returns causal ops buffered by ::apply_causal_op(), awaiting
+    /// their dependencies.
+    #[inline]
+    pub fn pending_causal_ops(&self) -> &[CausalOpMove<ID, TM, A>] {
+        &self.pending
+    }
+
+    /// returns the ops in the log not yet applied by a peer whose version
+    /// vector is seen, oldest first.  Sync layers send these to the peer.
+    ///
+    /// Returns None if ops the peer may be missing were truncated from the
+    /// log by ::truncate_log(), in which case the peer must be sent a
+    /// snapshot instead.
+    pub fn missing_ops(&self, seen: &VersionVector<A>) -> Option<Vec<OpMove<ID, TM, A>>> {
+        if let Some(floor) = self.state.truncated_before() {
+            // truncated ops from each actor have counters up to the floor's.
+            let behind = self
+                .latest_time_by_replica
+                .iter()
+                .any(|(a, c)| seen.get(a) < c.min(floor.counter()));
+            if behind {
+                return None;
+            }
+        }
+        let ops = self
+            .state
+            .log()
+            .iter()
+            .rev()
+            .filter(|e| !seen.contains(e.timestamp()))
+            .map(|e| e.clone().into())
+            .collect();
+        Some(ops)
+    }
+
+    /// returns the net effect on each node of the ops applied after
+    /// since, computed from the log.
+    ///
+    /// Each changed node is reported once, in the order it was first
+    /// changed.  Nodes whose ops were all ignored, eg as they would have
+    /// introduced a cycle, are not reported.  A node moved and then moved
+    /// back is reported as `Moved`, with equal parents.
+    ///
+    /// Changes made by ops already truncated from the log are not
+    /// included, so clients further behind must resync, eg from a
+    /// snapshot.
+    pub fn changes_since(&self, since: &Clock<A>) -> Vec<ChangeEvent<ID, TM>> {
+        // the log is newest first, so the last entry for each node holds
+        // the node as it was before the first change.
+        let mut before = HashMap::new();
+        let newer = self
+            .state
+            .log()
+            .iter()
+            .take_while(|e| T::cmp(e.timestamp(), since) == Ordering::Greater);
+        for (i, entry) in newer.enumerate() {
+            before.insert(entry.child_id(), (i, entry.oldp().as_ref()));
+        }
+        let mut before: Vec<_> = before.into_iter().collect();
+        before.sort_by_key(|(_, (i, _))| std::cmp::Reverse(*i));
+
+        let tree = self.state.tree();
+        before
+            .into_iter()
+            .filter_map(|(id, (_, old))| match (old, tree.find(id)) {
+                (None, Some(new)) => Some(ChangeEvent::Created {
+                    id: id.clone(),
+                    parent_id: new.parent_id().clone(),
+                    metadata: new.metadata().clone(),
+                }),
+                // nodes are shared with the log, and shared again when
+                // deserialized, so an unchanged node is the same allocation.
+                (Some(old), Some(new)) if !TreeNode::ptr_eq(old, new) => Some(ChangeEvent::Moved {
+                    id: id.clone(),
+                    old_parent_id: old.parent_id().clone(),
+                    parent_id: new.parent_id().clone(),
+                    metadata: new.metadata().clone(),
+                }),
+                (Some(old), None) => Some(ChangeEvent::Deleted {
+                    id: id.clone(),
+                    old_parent_id: old.parent_id().clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// undoes the last op generated by this replica, by generating and
+    /// applying an op with a fresh timestamp that moves the node back to
+    /// its previous parent, with its previous metadata.  Returns the op,
+    /// for sending to peers.
+    ///
+    /// Returns None, generating no op, if:
+    ///  - the last local op created its node, as ops can not remove nodes.
+    ///  - the last local op was ignored, eg as it would have introduced a
+    ///    cycle, so there is nothing to undo.
+    ///  - the node has since been moved by a newer op, eg a concurrent op
+    ///    from a peer that won, which undo must not overwrite.
+    ///  - the last local op was truncated from the log, or compacted.
+    ///
+    /// As the generated op is itself a local op, calling this again undoes
+    /// the undo.
+    pub fn undo_last_local(&mut self) -> Option<OpMove<ID, TM, A>> {
+        let entry = self
+            .state
+            .log()
+            .iter()
+            .find(|e| e.timestamp().actor_id() == self.id())?;
+        let (parent_id, metadata, child_id) = self.inverse(entry)?;
+        let op = self.opmove(parent_id, metadata, child_id);
+        self.apply_op(op.clone());
+        Some(op)
+    }
+
+    /// undoes the most recent local op not yet undone, like
+    /// ::undo_last_local(), and returns the generated op, for sending to
+    /// peers.  The undone op may then be reapplied by ::redo().
+    ///
+    /// Local ops that can not be undone, as they created their node, were
+    /// ignored, or have since been overridden by a newer op, are skipped
+    /// and dropped from the undo stack.  Returns None if no local op can be
+    /// undone.
+    ///
+    /// Applying a newly generated local op clears the redo stack.  Ops
+    /// truncated from the log by ::truncate_log() can not be undone.  The
+    /// stacks are not persisted with the replica, except by ::export().
+    pub fn undo(&mut self) -> Option<OpMove<ID, TM, A>> {
+        let op = self.pop_inverse(false)?;
+        let timestamp = op.timestamp().clone();
+        self.apply_undo_op(op.clone());
+        self.redo_stack.push(timestamp);
+        Some(op)
+    }
+
+    /// reapplies the op most recently undone by ::undo(), by generating an
+    /// op that reverses the undo, and returns it, for sending to peers.
+    /// The reapplied op may be undone again.
+    ///
+    /// Returns None if there is nothing to redo, or the undone ops have
+    /// since been overridden by newer ops.
+    pub fn redo(&mut self) -> Option<OpMove<ID, TM, A>> {
+        let op = self.pop_inverse(true)?;
+        let timestamp = op.timestamp().clone();
+        self.apply_undo_op(op.clone());
+        self.undo_stack.push(timestamp);
+        Some(op)
+    }
+
+    /// returns true if ::undo() would generate an op.
+    pub fn can_undo(&self) -> bool {
+        self.can_reverse(&self.undo_stack)
+    }
+
+    /// returns true if ::redo() would generate an op.
+    pub fn can_redo(&self) -> bool {
+        self.can_reverse(&self.redo_stack)
+    }
+
+    // returns the move, as (parent_id, metadata, child_id), reversing the
+    // logged op entry, or None if it can not be reversed.
+    fn inverse(&self, entry: &LogOpMove<ID, TM, A>) -> Option<(ID, TM, ID)> {
+        // a compacted entry's oldp skips the moves collapsed into it.
+        if self.state.is_compacted(entry.timestamp()) {
+            return None;
+        }
+        let oldp = entry.oldp().as_ref()?;
+        // the tree shares the node of the op that placed it, so the op is
+        // in effect only if its node is still there.
+        match self.state.tree().find(entry.child_id()) {
+            Some(node) if TreeNode::ptr_eq(node, entry.node()) => Some((
+                oldp.parent_id().clone(),
+                oldp.metadata().clone(),
+                entry.child_id().clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn find_entry(&self, timestamp: &Clock<A>) -> Option<&LogOpMove<ID, TM, A>> {
+        self.state.log().iter().find(|e| e.timestamp() == timestamp)
+    }
+
+    fn can_reverse(&self, stack: &[Clock<A>]) -> bool {
+        stack
+            .iter()
+            .filter_map(|t| self.find_entry(t))
+            .any(|e| self.inverse(e).is_some())
+    }
+
+    // pops timestamps from the undo stack, or the redo stack if redo,
+    // until one whose op can be reversed, and returns an op reversing it.
+    fn pop_inverse(&mut self, redo: bool) -> Option<OpMove<ID, TM, A>> {
+        loop {
+            let stack = if redo {
+                &mut self.redo_stack
+            } else {
+                &mut self.undo_stack
+            };
+            let timestamp = stack.pop()?;
+            let inverse = self.find_entry(&timestamp).and_then(|e| self.inverse(e));
+            if let Some((parent_id, metadata, child_id)) = inverse {
+                return Some(self.opmove(parent_id, metadata, child_id));
+            }
+        }
+    }
+
+    // applies an op generated by ::undo() or ::redo(), leaving the stacks
+    // for the caller to update.
+    fn apply_undo_op(&mut self, op: OpMove<ID, TM, A>) {
+        let undo_stack = std::mem::take(&mut self.undo_stack);
+        let redo_stack = std::mem::take(&mut self.redo_stack);
+        self.apply_op(op);
+        self.undo_stack = undo_stack;
+        self.redo_stack = redo_stack;
+    }
+
+    /// returns a barrier announcing that this replica will only generate
+    /// ops newer than those it has applied, for sending to peers.  See
+    /// `Barrier`.
+    ///
+    /// Peers that apply it may truncate ops up to this replica's clock,
+    /// without waiting for its next op, eg while it is idle.
+    pub fn barrier(&self) -> Barrier<A> {
+        Barrier::new(Clock::new(self.id().clone(), Some(self.time.counter())))
+    }
+
+    /// records a barrier from a peer, advancing the causally stable
+    /// threshold as an op from the peer would.  Returns false if the
+    /// barrier is no newer than the peer's latest timestamp, or the peer
+    /// was tombstoned.
+    pub fn apply_barrier(&mut self, barrier: &Barrier<A>) -> bool {
+        if self.tombstones.contains(barrier.actor()) {
+            return false;
+        }
+        self.merge_time(barrier.clock());
+        self.latest_time_by_replica.observe(barrier.clock())
+    }
+
+    // advances the clock to at least clock's counter, so that generated
+    // ops are newer.
+    pub(crate) fn merge_time(&mut self, clock: &Clock<A>) {
+        self.time = self.time.merge(clock);
+    }
+
+    /// stops tracking actor for the causally stable threshold, eg once
+    /// its device is decommissioned, so that it no longer blocks
+    /// ::truncate_log().  Returns the latest timestamp seen from actor, if
+    /// any.
+    ///
+    /// If tombstone is true, ops from actor are rejected from now on.
+    /// Otherwise, an op from actor is tracked again once applied.
+    ///
+    /// Warning: an op from actor older than the new threshold would be
+    /// applied after the log has been truncated past it, so replicas would
+    /// diverge.  Only forget an actor once its ops have all been applied,
+    /// or tombstone it.  Tombstones are not persisted with the replica,
+    /// except by ::export().
+    pub fn forget_actor(&mut self, actor: &A, tombstone: bool) -> Option<Clock<A>> {
+        if tombstone {
+            self.tombstones.insert(actor.clone());
         }
+        self.latest_time_by_replica.remove(actor)
+    }
+
+    /// returns true if actor was tombstoned by ::forget_actor().
+    #[inline]
+    pub fn is_tombstoned(&self, actor: &A) -> bool {
+        self.tombstones.contains(actor)
     }
 
     /// returns the causally stable threshold
@@ -164,18 +1016,214 @@ impl<ID: TreeId, TM: TreeMeta, A: Actor + std::fmt::Debug> TreeReplica<ID, TM, A
         // The minimum of latest timestamp from each replica
         // is the causally stable threshold.
 
-        let mut v: Vec<&Clock<A>> = self.latest_time_by_replica.values().collect();
-        v.sort();
-        v.reverse(); // reverse, so last is lowest.
-        v.pop()
+        self.latest_time_by_replica.clocks().min()
+    }
+
+    /// truncates log entries older than the causally stable threshold,
+    /// and returns them, newest first, eg for archiving.
+    pub fn truncate_log(&mut self) -> Vec<LogOpMove<ID, TM, A>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("truncate_log", actor = ?self.id()).entered();
+
+        match self.causally_stable_threshold().cloned() {
+            Some(t) => self.truncate_log_to(t),
+            None => Vec::new(),
+        }
+    }
+
+    /// collapses log entries older than the causally stable threshold
+    /// that move the same child into the newest of them, and returns the
+    /// number removed.  See `State::compact_log_before`.
+    ///
+    /// Unlike ::truncate_log(), a stable entry for each child is kept, eg
+    /// for ::tree_at() and audit.  Entries within the compacted range, see
+    /// `State::compacted_from`, can no longer be undone.
+    pub fn compact_log(&mut self) -> usize {
+        let t = match self.causally_stable_threshold() {
+            Some(t) => t.clone(),
+            None => return 0,
+        };
+        let removed = self.state.compact_log_before(&t);
+        if !removed.is_empty() {
+            self.seen.retain_from(&t);
+            let state = &self.state;
+            self.undo_stack.retain(|s| !state.is_compacted(s));
+            self.redo_stack.retain(|s| !state.is_compacted(s));
+        }
+        removed.len()
+    }
+
+    // truncates log entries older than t, which must be causally stable.
+    pub(crate) fn truncate_log_to(&mut self, t: Clock<A>) -> Vec<LogOpMove<ID, TM, A>> {
+        let truncated = self.state.truncate_log_before(&t);
+        self.seen.retain_from(&t);
+        // truncated ops can no longer be undone.
+        self.undo_stack.retain(|s| *s >= t);
+        self.redo_stack.retain(|s| *s >= t);
+        truncated
+    }
+
+    /// truncates the log like ::truncate_log(), but first moves the
+    /// truncated entries into storage, as ops, so that the full history
+    /// remains available, eg for audit.  Returns the number archived.
+    ///
+    /// On error, the log is left untouched, though some ops may already
+    /// have been stored.  Archived ops may be read back with
+    /// `Storage::scan_ops`.
+    pub fn truncate_to_archive<S: Storage<ID, TM, A>>(
+        &mut self,
+        storage: &mut S,
+    ) -> Result<usize, S::Error> {
+        let threshold = match self.causally_stable_threshold() {
+            Some(t) => t.clone(),
+            None => return Ok(0),
+        };
+        for entry in self
+            .state
+            .log()
+            .iter()
+            .rev()
+            .take_while(|e| e.timestamp() < &threshold)
+        {
+            storage.append_op(&entry.clone().op_into())?;
+        }
+        Ok(self.truncate_log().len())
+    }
+
+    /// returns a snapshot of the tree and clocks, with the given
+    /// checkpoint id.
+    ///
+    /// Only log entries that are not causally stable are included.  See
+    /// `Checkpointer`.
+    pub fn snapshot(&self, checkpoint_id: u64) -> Snapshot<ID, TM, A> {
+        let threshold = self.causally_stable_threshold();
+        let log: Vec<_> = self
+            .state
+            .log()
+            .iter()
+            .take_while(|e| !matches!(threshold, Some(t) if e.timestamp() < t))
+            .cloned()
+            .collect();
+        // the snapshot's log is truncated before the threshold only if
+        // entries were left out.
+        let truncated_before = match log.len() < self.state.log().len() {
+            true => threshold.max(self.state.truncated_before()),
+            false => self.state.truncated_before(),
+        };
+        Snapshot::new(
+            checkpoint_id,
+            self.tree().clone(),
+            log,
+            truncated_before.cloned(),
+            self.time.clone(),
+            self.latest_time_by_replica.clone(),
+        )
+    }
+
+    /// returns a read-only view of the tree as it is now.
+    ///
+    /// The view remains valid, and unchanged, while ops continue to be
+    /// applied to the replica.  See `TreeSnapshot`.
+    pub fn read_snapshot(&self) -> TreeSnapshot<ID, TM, A> {
+        TreeSnapshot::new(self.tree().clone(), self.time.clone())
+    }
+
+    /// returns everything needed to resume this replica elsewhere, eg
+    /// from a backup or on another device.  See `ReplicaExport`.
+    ///
+    /// Unlike a snapshot, the whole log is kept, so local ops may still
+    /// be undone after ::import().
+    pub fn export(&self) -> ReplicaExport<ID, TM, A> {
+        let mut tombstones: Vec<A> = self.tombstones.iter().cloned().collect();
+        tombstones.sort();
+        ReplicaExport {
+            version: EXPORT_VERSION,
+            tree: self.tree().clone(),
+            log: self.state.log().clone(),
+            time: self.time.clone(),
+            latest_time_by_replica: self.latest_time_by_replica.clone(),
+            truncated_before: self.state.truncated_before().cloned(),
+            compacted_from: self.state.compacted_from().cloned(),
+            pending: self.pending.clone(),
+            undo_stack: self.undo_stack.clone(),
+            redo_stack: self.redo_stack.clone(),
+            wall_clock: self.wall_clock,
+            provenance: self.provenance.clone(),
+            drift_guard: self.drift_guard,
+            tombstones,
+            log_limit: self.log_limit,
+            quotas: *self.state.quotas(),
+        }
+    }
+
+    /// resumes a replica exported by ::export().
+    ///
+    /// Returns `ImportError::UnsupportedVersion` if the export was made
+    /// with another format version.
+    pub fn import(export: ReplicaExport<ID, TM, A>) -> Result<Self, ImportError> {
+        if export.version != EXPORT_VERSION {
+            return Err(ImportError::UnsupportedVersion(export.version));
+        }
+        let mut state = State::from((export.log, export.tree));
+        state.set_truncated_before(export.truncated_before);
+        state.set_compacted_from(export.compacted_from);
+        let mut replica = Self::from_parts(state, export.time, export.latest_time_by_replica);
+        replica.set_quotas(export.quotas);
+        replica.pending = export.pending;
+        replica.undo_stack = export.undo_stack;
+        replica.redo_stack = export.redo_stack;
+        replica.wall_clock = export.wall_clock;
+        replica.provenance = export.provenance;
+        replica.drift_guard = export.drift_guard;
+        replica.tombstones = export.tombstones.into_iter().collect();
+        replica.log_limit = export.log_limit;
+        Ok(replica)
     }
 
-    /// truncates log
-    pub fn truncate_log(&mut self) -> bool {
-        let result = self.causally_stable_threshold();
-        match result.cloned() {
-            Some(t) => self.state.truncate_log_before(&t),
-            None => false,
+    /// restores a replica from a snapshot and the segments recorded
+    /// after it.
+    ///
+    /// Segments are applied in order of their sequence numbers.  Those
+    /// belonging to other checkpoints are ignored.
+    pub fn restore<I>(snapshot: Snapshot<ID, TM, A>, segments: I) -> Self
+    where
+        I: IntoIterator<Item = Segment<ID, TM, A>>,
+    {
+        let checkpoint_id = snapshot.id();
+        let (tree, log, truncated_before, time, latest_time_by_replica) = snapshot.into_parts();
+        let mut state = State::from((log, tree));
+        state.set_truncated_before(truncated_before);
+        let mut replica = Self::from_parts(state, time, latest_time_by_replica);
+        let mut segments: Vec<Segment<ID, TM, A>> = segments
+            .into_iter()
+            .filter(|s| s.checkpoint_id() == checkpoint_id)
+            .collect();
+        segments.sort_by_key(|s| s.seq());
+        for segment in segments {
+            replica.apply_ops(segment.into_ops());
+        }
+        replica
+    }
+}
+
+/// Displays a summary of the replica: its actor, clock and causally
+/// stable threshold, followed by the summary of its state.  See `State`.
+impl<ID, TM, A, T, R> std::fmt::Display for TreeReplica<ID, TM, A, T, R>
+where
+    ID: TreeId,
+    TM: TreeMeta,
+    A: Actor + std::fmt::Debug,
+    T: TieBreak<A>,
+    R: Resolve<ID, TM, A>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "replica {:?} at ", self.id())?;
+        fmt_clock(f, self.time())?;
+        write!(f, ", causally stable threshold ")?;
+        match self.causally_stable_threshold() {
+            Some(cst) => fmt_clock(f, cst)?,
+            None => write!(f, "none")?,
         }
+        write!(f, "; {}", self.state)
     }
 }
@@ -0,0 +1,52 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{Clock, Tree, TreeId, TreeMeta};
+use crdts::Actor;
+
+/// `TreeSnapshot` is a read-only view of a replica's tree at a point in
+/// time.  See `TreeReplica::read_snapshot`.
+///
+/// A snapshot is unaffected by ops applied to the replica afterwards, so
+/// it can be read, eg by a UI or a background indexer, without holding a
+/// borrow of the replica.
+///
+/// With the `persistent` feature, taking a snapshot is O(1), as the
+/// snapshot shares structure with the replica's tree.  Otherwise the
+/// tree is copied.
+#[derive(Debug, Clone)]
+pub struct TreeSnapshot<ID: TreeId, TM: TreeMeta, A: Actor> {
+    tree: Tree<ID, TM>,
+    time: Clock<A>,
+}
+
+impl<ID: TreeId, TM: TreeMeta, A: Actor> TreeSnapshot<ID, TM, A> {
+    // creates a snapshot.  see TreeReplica::read_snapshot.
+    pub(crate) fn new(tree: Tree<ID, TM>, time: Clock<A>) -> Self {
+        Self { tree, time }
+    }
+
+    /// returns the tree
+    #[inline]
+    pub fn tree(&self) -> &Tree<ID, TM> {
+        &self.tree
+    }
+
+    /// returns the replica's lamport time when the snapshot was taken.
+    ///
+    /// Every op applied to the replica before the snapshot was taken has a
+    /// timestamp no greater than this.
+    #[inline]
+    pub fn time(&self) -> &Clock<A> {
+        &self.time
+    }
+
+    /// returns the tree, consuming self.
+    #[inline]
+    pub fn into_tree(self) -> Tree<ID, TM> {
+        self.tree
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use super::{OpMove, Resolve, State, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+/// `UniqueNames` is an opt-in policy that keeps the names of siblings
+/// unique, as a filesystem requires.
+///
+/// The crdt algo happily accepts two concurrent ops that each create a
+/// child of the same parent with the same name.  After merging, both
+/// children exist.  This policy detects such duplicates and generates
+/// ops that rename all but one of them.
+///
+/// The child that keeps its name is the one with the lowest ID.  Each
+/// other child is renamed via the provided rename function, which
+/// receives the child's ID, eg to append a suffix derived from it.  Both
+/// are found from the tree alone, so do not depend on how far each
+/// replica has truncated its log.
+///
+/// Every replica therefore renames the same children to the same names,
+/// so it is harmless if several replicas generate rename ops for the
+/// same conflict.
+#[derive(Clone, Copy)]
+pub struct UniqueNames<ID, TM, N> {
+    name: fn(&TM) -> Option<N>,
+    rename: fn(&TM, &ID) -> TM,
+}
+
+impl<ID: TreeId + Ord, TM: TreeMeta, N: Eq + Hash> UniqueNames<ID, TM, N> {
+    /// creates a new `UniqueNames` policy.
+    ///
+    /// name extracts a node's name from its metadata, or None if the
+    /// node is unnamed.  rename returns the metadata for a node that
+    /// lost a conflict, given its metadata and ID.
+    pub fn new(name: fn(&TM) -> Option<N>, rename: fn(&TM, &ID) -> TM) -> Self {
+        Self { name, rename }
+    }
+
+    /// returns each set of siblings that share a name, as a list of
+    /// `(parent_id, [child_id])` where the first child is the one that
+    /// keeps its name.
+    pub fn conflicts<A, T, R>(&self, state: &State<ID, TM, A, T, R>) -> Vec<(ID, Vec<ID>)>
+    where
+        A: Actor,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let mut by_name: HashMap<(&ID, N), Vec<ID>> = HashMap::new();
+        for (child_id, node) in state.tree().iter() {
+            if let Some(n) = (self.name)(node.metadata()) {
+                by_name
+                    .entry((node.parent_id(), n))
+                    .or_default()
+                    .push(child_id.clone());
+            }
+        }
+
+        let mut conflicts: Vec<(ID, Vec<ID>)> = by_name
+            .into_iter()
+            .filter(|(_, children)| children.len() > 1)
+            .map(|((parent_id, _), mut children)| {
+                children.sort();
+                (parent_id.clone(), children)
+            })
+            .collect();
+        conflicts.sort();
+        conflicts
+    }
+
+    /// generates ops that resolve all current name conflicts in the
+    /// replica's tree.
+    ///
+    /// The ops are not applied.  The caller should apply them locally
+    /// and send them to other replicas, like any other locally
+    /// generated op.
+    pub fn resolve<A, T, R>(&self, replica: &TreeReplica<ID, TM, A, T, R>) -> Vec<OpMove<ID, TM, A>>
+    where
+        A: Actor + Debug,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let tree = replica.tree();
+        let mut renames = vec![];
+        for (parent_id, children) in self.conflicts(replica.state()) {
+            for child_id in children.into_iter().skip(1) {
+                if let Some(node) = tree.find(&child_id) {
+                    let metadata = (self.rename)(node.metadata(), &child_id);
+                    renames.push((parent_id.clone(), metadata, child_id));
+                }
+            }
+        }
+        replica.opmoves(renames)
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::Clock;
+use crdts::Actor;
+
+/// `VersionVector` holds the latest timestamp seen from each replica.
+///
+/// A replica's version vector summarizes the ops it has applied, so sync
+/// layers can exchange them to find out which ops a peer is missing, or
+/// compare them to tell whether two replicas have diverged.  See
+/// `TreeReplica::version_vector`.
+///
+/// Serialized as a map from actor to timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VersionVector<A: Actor> {
+    clocks: BTreeMap<A, Clock<A>>,
+}
+
+impl<A: Actor> VersionVector<A> {
+    /// creates an empty version vector
+    pub fn new() -> Self {
+        Self {
+            clocks: BTreeMap::new(),
+        }
+    }
+
+    /// returns the latest counter seen from actor, or 0 if none.
+    #[inline]
+    pub fn get(&self, actor: &A) -> u64 {
+        self.clocks.get(actor).map_or(0, Clock::counter)
+    }
+
+    /// returns the latest timestamp seen from actor, if any.
+    #[inline]
+    pub fn latest(&self, actor: &A) -> Option<&Clock<A>> {
+        self.clocks.get(actor)
+    }
+
+    /// records that the op with timestamp has been seen.
+    ///
+    /// Returns false, leaving self unchanged, if a timestamp from the
+    /// same actor at least as great had already been seen.
+    pub fn observe(&mut self, timestamp: &Clock<A>) -> bool {
+        match self.clocks.get(timestamp.actor_id()) {
+            Some(latest) if timestamp <= latest => false,
+            _ => {
+                self.clocks
+                    .insert(timestamp.actor_id().clone(), timestamp.clone());
+                true
+            }
+        }
+    }
+
+    /// returns true if the op with timestamp had been seen, ie is
+    /// included in this version vector.
+    #[inline]
+    pub fn contains(&self, timestamp: &Clock<A>) -> bool {
+        self.get(timestamp.actor_id()) >= timestamp.counter()
+    }
+
+    /// returns true if every op included in other is included in self.
+    pub fn includes(&self, other: &Self) -> bool {
+        other
+            .clocks
+            .values()
+            .all(|c| self.get(c.actor_id()) >= c.counter())
+    }
+
+    /// returns true if self includes every op included in other, and at
+    /// least one more.
+    #[inline]
+    pub fn dominates(&self, other: &Self) -> bool {
+        self.includes(other) && !other.includes(self)
+    }
+
+    /// returns true if each of self and other includes an op that the
+    /// other does not, ie the replicas have diverged.
+    #[inline]
+    pub fn concurrent(&self, other: &Self) -> bool {
+        !self.includes(other) && !other.includes(self)
+    }
+
+    /// merges other into self, so that self includes every op included in
+    /// either.
+    pub fn merge(&mut self, other: &Self) {
+        for timestamp in other.clocks.values() {
+            self.observe(timestamp);
+        }
+    }
+
+    /// removes actor, returning the latest timestamp seen from it, if any.
+    pub fn remove(&mut self, actor: &A) -> Option<Clock<A>> {
+        self.clocks.remove(actor)
+    }
+
+    /// returns an iterator over (actor, latest counter).
+    pub fn iter(&self) -> impl Iterator<Item = (&A, u64)> {
+        self.clocks.iter().map(|(a, c)| (a, c.counter()))
+    }
+
+    /// returns an iterator over the latest timestamp from each actor.
+    pub fn clocks(&self) -> impl Iterator<Item = &Clock<A>> {
+        self.clocks.values()
+    }
+
+    /// returns the number of actors seen.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.clocks.len()
+    }
+
+    /// returns true if no ops have been seen.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.clocks.is_empty()
+    }
+}
+
+impl<A: Actor> Default for VersionVector<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, A: Actor + 'a> std::iter::FromIterator<&'a Clock<A>> for VersionVector<A> {
+    fn from_iter<I: IntoIterator<Item = &'a Clock<A>>>(iter: I) -> Self {
+        let mut vv = Self::new();
+        for timestamp in iter {
+            vv.observe(timestamp);
+        }
+        vv
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! Write-ahead log of ops.
+//!
+//! A `Wal` is an append-only file of ops.  Each op is appended, and
+//! optionally synced to disk, before it is applied to the replica, so that
+//! an op is never lost once applied.  At startup, `recover` replays the
+//! file into a replica.
+//!
+//! Each record is laid out as:
+//!
+//! ```text
+//! +-----------+-------------+-------------------------------+
+//! | len (u32) | crc32 (u32) | op, as encoded by codec       |
+//! +-----------+-------------+-------------------------------+
+//! ```
+//!
+//! with integers in little-endian order, and the checksum computed over
+//! the encoded op.
+//!
+//! Requires the `wal` feature.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::codec::{self, CodecError};
+use super::{OpMove, Resolve, TieBreak, TreeId, TreeMeta, TreeReplica};
+use crdts::Actor;
+
+// length of the len and crc32 fields preceding each op.
+const RECORD_HEADER_LEN: usize = 8;
+
+/// largest op accepted in a record, in bytes.  Guards against allocating
+/// huge buffers for a corrupt length field.
+pub const MAX_RECORD_LEN: u32 = 64 * 1024 * 1024;
+
+/// Errors returned by `Wal`.
+#[derive(Debug)]
+pub enum WalError {
+    /// the file could not be read or written.
+    Io(io::Error),
+    /// an op could not be encoded or decoded.
+    Codec(CodecError),
+    /// the log ends with an incomplete record, eg after a crash during
+    /// an append.  The log can be truncated to offset to discard it.
+    TruncatedTail {
+        /// offset of the record, from the start of the file.
+        offset: u64,
+    },
+    /// the record at offset does not match its checksum.
+    BadChecksum {
+        /// offset of the record, from the start of the file.
+        offset: u64,
+    },
+    /// the record at offset has a length exceeding `MAX_RECORD_LEN`, eg
+    /// as its length field is corrupt.
+    RecordTooLarge {
+        /// offset of the record, from the start of the file.
+        offset: u64,
+        /// the record's length field.
+        len: u32,
+    },
+}
+
+impl fmt::Display for WalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "wal i/o error: {}", e),
+            Self::Codec(e) => write!(f, "wal codec error: {}", e),
+            Self::TruncatedTail { offset } => {
+                write!(f, "wal ends with incomplete record at offset {}", offset)
+            }
+            Self::BadChecksum { offset } => {
+                write!(f, "wal record at offset {} fails checksum", offset)
+            }
+            Self::RecordTooLarge { offset, len } => {
+                write!(
+                    f,
+                    "wal record at offset {} too large: {} bytes",
+                    offset, len
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for WalError {}
+
+impl From<io::Error> for WalError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<CodecError> for WalError {
+    fn from(e: CodecError) -> Self {
+        Self::Codec(e)
+    }
+}
+
+/// `Wal` is an append-only log of ops, stored in a file.
+#[derive(Debug)]
+pub struct Wal {
+    file: File,
+    path: PathBuf,
+    sync: bool,
+}
+
+impl Wal {
+    /// opens the log at path, creating it if it does not exist.
+    ///
+    /// If sync is set, each append is synced to disk before returning.
+    pub fn open<P: AsRef<Path>>(path: P, sync: bool) -> Result<Self, WalError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { file, path, sync })
+    }
+
+    /// returns path of the log file
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// appends op to the log.
+    pub fn append<ID, TM, A>(&mut self, op: &OpMove<ID, TM, A>) -> Result<(), WalError>
+    where
+        ID: TreeId + Serialize + DeserializeOwned,
+        TM: TreeMeta + Serialize + DeserializeOwned,
+        A: Actor + Serialize + DeserializeOwned,
+    {
+        let payload = codec::encode(op)?;
+        let len = u32::try_from(payload.len())
+            .ok()
+            .filter(|&len| len <= MAX_RECORD_LEN)
+            .ok_or_else(|| CodecError::Payload("op too large for wal record".to_string()))?;
+        // the record is written with a single call, so that a crash leaves
+        // at most one partial record, at the end.
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+        self.file.write_all(&record)?;
+        if self.sync {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// syncs appended ops to disk.  Only needed if sync is not set.
+    pub fn sync(&self) -> Result<(), WalError> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// appends op to the log, then applies it to replica.
+    pub fn apply_op<ID, TM, A, T, R>(
+        &mut self,
+        replica: &mut TreeReplica<ID, TM, A, T, R>,
+        op: OpMove<ID, TM, A>,
+    ) -> Result<(), WalError>
+    where
+        ID: TreeId + Serialize + DeserializeOwned,
+        TM: TreeMeta + Serialize + DeserializeOwned,
+        A: Actor + fmt::Debug + Serialize + DeserializeOwned,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        self.append(&op)?;
+        replica.apply_op(op);
+        Ok(())
+    }
+
+    /// appends a list of ops to the log, then applies them to replica.
+    ///
+    /// If sync is set, the log is synced once, after all ops are appended.
+    pub fn apply_ops<ID, TM, A, T, R>(
+        &mut self,
+        replica: &mut TreeReplica<ID, TM, A, T, R>,
+        ops: Vec<OpMove<ID, TM, A>>,
+    ) -> Result<(), WalError>
+    where
+        ID: TreeId + Serialize + DeserializeOwned,
+        TM: TreeMeta + Serialize + DeserializeOwned,
+        A: Actor + fmt::Debug + Serialize + DeserializeOwned,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let sync = std::mem::replace(&mut self.sync, false);
+        let appended = ops.iter().try_for_each(|op| self.append(op));
+        self.sync = sync;
+        appended?;
+        if sync {
+            self.sync()?;
+        }
+        replica.apply_ops(ops);
+        Ok(())
+    }
+
+    /// returns all ops in the log, in the order they were appended.
+    pub fn read_ops<ID, TM, A>(&self) -> Result<Vec<OpMove<ID, TM, A>>, WalError>
+    where
+        ID: TreeId + Serialize + DeserializeOwned,
+        TM: TreeMeta + Serialize + DeserializeOwned,
+        A: Actor + Serialize + DeserializeOwned,
+    {
+        let file = File::open(&self.path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut ops = vec![];
+        let mut offset = 0u64;
+        loop {
+            let mut head = [0u8; RECORD_HEADER_LEN];
+            match read_full(&mut reader, &mut head)? {
+                0 => break,
+                RECORD_HEADER_LEN => {}
+                _ => return Err(WalError::TruncatedTail { offset }),
+            }
+            let len = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+            if len > MAX_RECORD_LEN {
+                return Err(WalError::RecordTooLarge { offset, len });
+            }
+            // a record running past the end of the file is incomplete.
+            let len = len as usize;
+            if offset + (RECORD_HEADER_LEN + len) as u64 > file_len {
+                return Err(WalError::TruncatedTail { offset });
+            }
+            let crc = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+            let mut payload = vec![0u8; len];
+            if read_full(&mut reader, &mut payload)? != len {
+                return Err(WalError::TruncatedTail { offset });
+            }
+            if crc32fast::hash(&payload) != crc {
+                return Err(WalError::BadChecksum { offset });
+            }
+            ops.push(codec::decode(&payload)?);
+            offset += (RECORD_HEADER_LEN + len) as u64;
+        }
+        Ok(ops)
+    }
+
+    /// replays the log into replica, returning the number of ops applied.
+    ///
+    /// Ops still in replica's log are ignored by it, so the log can be
+    /// replayed into a replica restored from a snapshot taken after some
+    /// of the logged ops.
+    pub fn recover<ID, TM, A, T, R>(
+        &self,
+        replica: &mut TreeReplica<ID, TM, A, T, R>,
+    ) -> Result<usize, WalError>
+    where
+        ID: TreeId + Serialize + DeserializeOwned,
+        TM: TreeMeta + Serialize + DeserializeOwned,
+        A: Actor + fmt::Debug + Serialize + DeserializeOwned,
+        T: TieBreak<A>,
+        R: Resolve<ID, TM, A>,
+    {
+        let ops = self.read_ops()?;
+        let num_ops = ops.len();
+        replica.apply_ops(ops);
+        Ok(num_ops)
+    }
+
+    /// empties the log, eg once its ops are persisted in a snapshot.
+    pub fn reset(&mut self) -> Result<(), WalError> {
+        self.truncate(0)
+    }
+
+    /// discards records from offset on, eg an incomplete record reported
+    /// by `WalError::TruncatedTail`.
+    pub fn truncate(&mut self, offset: u64) -> Result<(), WalError> {
+        self.file.set_len(offset)?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+// reads into buf until it is full or the reader is exhausted, returning
+// the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
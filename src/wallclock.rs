@@ -0,0 +1,69 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What a `DriftGuard` does with an op whose wall-clock time is too far
+/// in the future.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DriftPolicy {
+    /// the op is applied, and its timestamp recorded.
+    Flag,
+    /// the op is not applied, and its timestamp recorded.
+    ///
+    /// Warning: a rejected op is never applied, so replicas that do not
+    /// reject it will diverge from this one.
+    Reject,
+}
+
+/// `DriftGuard` checks the wall-clock time claimed by remote ops against
+/// the local clock.  See `TreeReplica::set_drift_guard`.
+///
+/// Wall-clock time plays no part in ordering ops, which is by Lamport
+/// timestamp only.  An op claiming a time far in the future indicates a
+/// replica with a broken clock, or a forged op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriftGuard {
+    max_drift: Duration,
+    policy: DriftPolicy,
+}
+
+impl DriftGuard {
+    /// creates a guard for ops claiming a time more than max_drift ahead
+    /// of the local clock.
+    pub fn new(max_drift: Duration, policy: DriftPolicy) -> Self {
+        Self { max_drift, policy }
+    }
+
+    /// returns the maximum drift
+    #[inline]
+    pub fn max_drift(&self) -> Duration {
+        self.max_drift
+    }
+
+    /// returns the policy
+    #[inline]
+    pub fn policy(&self) -> DriftPolicy {
+        self.policy
+    }
+
+    /// returns true if wall_time, in milliseconds since the UNIX epoch, is
+    /// more than max_drift ahead of now.
+    pub fn exceeds(&self, wall_time: u64, now: u64) -> bool {
+        let max_drift = u64::try_from(self.max_drift.as_millis()).unwrap_or(u64::MAX);
+        wall_time.saturating_sub(now) > max_drift
+    }
+}
+
+// returns the local wall-clock time, in milliseconds since the UNIX epoch.
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or(0)
+}
@@ -0,0 +1,138 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+//! wasm-bindgen bindings, for using a replica from JavaScript.
+//!
+//! `WasmReplica` is exported to JS as `TreeReplica`.  Node IDs and actor
+//! IDs are strings, eg UUIDs from `crypto.randomUUID()`, and metadata is
+//! any JSON value.  Ops, metadata, version vectors and trees cross the
+//! boundary as JSON text, in the same format serde_json produces on a
+//! Rust backend, so ops can be relayed between browser and server
+//! replicas unchanged.
+//!
+//! ```text
+//! const replica = new TreeReplica(crypto.randomUUID());
+//! const op = replica.moveNode("root", JSON.stringify({ name: "a" }), id);
+//! socket.send(`[${op}]`);
+//! socket.onmessage = (e) => replica.applyOps(e.data);
+//! ```
+//!
+//! Build with `wasm-pack build -- --features wasm`.
+//!
+//! Requires the `wasm` feature.
+
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use super::{OpMove, TreeReplica, VersionVector};
+
+type Replica = TreeReplica<String, Value, String>;
+type Op = OpMove<String, Value, String>;
+
+/// `WasmReplica` is a `TreeReplica` with string IDs and JSON metadata,
+/// for use from JavaScript.  See the module docs.
+#[wasm_bindgen(js_name = TreeReplica)]
+#[derive(Debug)]
+pub struct WasmReplica {
+    replica: Replica,
+}
+
+#[wasm_bindgen(js_class = TreeReplica)]
+impl WasmReplica {
+    /// creates a replica for the actor actor_id.
+    #[wasm_bindgen(constructor)]
+    pub fn new(actor_id: String) -> Self {
+        Self {
+            replica: Replica::new(actor_id),
+        }
+    }
+
+    /// returns the replica's actor ID.
+    #[wasm_bindgen(js_name = actorId)]
+    pub fn actor_id(&self) -> String {
+        self.replica.id().clone()
+    }
+
+    /// moves child_id under parent_id, with metadata, a JSON value, and
+    /// returns the op as JSON, for sending to peers.
+    #[wasm_bindgen(js_name = moveNode)]
+    pub fn move_node(
+        &mut self,
+        parent_id: String,
+        metadata: &str,
+        child_id: String,
+    ) -> Result<String, JsError> {
+        let metadata = serde_json::from_str(metadata)?;
+        let op = self.replica.opmove(parent_id, metadata, child_id);
+        self.replica.apply_op(op.clone());
+        Ok(serde_json::to_string(&op)?)
+    }
+
+    /// applies an op, as JSON, generated by a peer.
+    #[wasm_bindgen(js_name = applyOp)]
+    pub fn apply_op(&mut self, op: &str) -> Result<(), JsError> {
+        let op: Op = serde_json::from_str(op)?;
+        self.replica.apply_op(op);
+        Ok(())
+    }
+
+    /// applies ops, a JSON array, generated by peers.
+    #[wasm_bindgen(js_name = applyOps)]
+    pub fn apply_ops(&mut self, ops: &str) -> Result<(), JsError> {
+        let ops: Vec<Op> = serde_json::from_str(ops)?;
+        self.replica.apply_ops(ops);
+        Ok(())
+    }
+
+    /// returns the latest timestamp seen from each replica, as JSON.
+    #[wasm_bindgen(js_name = versionVector)]
+    pub fn version_vector(&self) -> Result<String, JsError> {
+        Ok(serde_json::to_string(self.replica.version_vector())?)
+    }
+
+    /// returns the ops, as a JSON array, not yet applied by a peer whose
+    /// version vector, as JSON, is seen.
+    ///
+    /// Returns undefined if the ops were truncated from the log, in which
+    /// case the peer needs a snapshot.
+    #[wasm_bindgen(js_name = missingOps)]
+    pub fn missing_ops(&self, seen: &str) -> Result<Option<String>, JsError> {
+        let seen: VersionVector<String> = serde_json::from_str(seen)?;
+        match self.replica.missing_ops(&seen) {
+            Some(ops) => Ok(Some(serde_json::to_string(&ops)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// returns the parent of node id, or undefined if id is not in the
+    /// tree.
+    pub fn parent(&self, id: String) -> Option<String> {
+        self.replica
+            .tree()
+            .find(&id)
+            .map(|node| node.parent_id().clone())
+    }
+
+    /// returns the metadata of node id, as JSON, or undefined if id is not
+    /// in the tree.
+    pub fn metadata(&self, id: String) -> Result<Option<String>, JsError> {
+        match self.replica.tree().find(&id) {
+            Some(node) => Ok(Some(serde_json::to_string(node.metadata())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// returns the IDs of the children of parent_id.
+    pub fn children(&self, parent_id: String) -> Vec<String> {
+        self.replica.tree().children(&parent_id)
+    }
+
+    /// returns the tree as nested JSON.  See `Tree::to_json_nodes`.
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        Ok(serde_json::to_string(&self.replica.tree().to_json_nodes())?)
+    }
+}
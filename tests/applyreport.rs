@@ -0,0 +1,128 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree apply reports
+use crdt_tree::{
+    ActorOrder, ApplyReport, Clock, HashOrder, MergeMetadata, OpMove, TieBreak, TreeReplica,
+};
+use std::cmp::Ordering;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// Tests that a batch's report counts each op's outcome, the log entries
+// undone and redone, and the clock advance.
+#[test]
+fn apply_ops_report() {
+    let r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let r3 = TypeReplica::new(3);
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (2, "b", 3)]);
+    assert_eq!(
+        r2.apply_ops_report(ops.clone()),
+        ApplyReport {
+            applied: 3,
+            counter_after: 3,
+            ..ApplyReport::default()
+        }
+    );
+
+    // a cycle, and concurrent ops older than r2's latest, which are
+    // applied after undoing 3 and then 2 newer entries.
+    let cycle = r2.opmove(3, "a", 2);
+    let concurrent = r3.opmoves(vec![(1, "c", 4), (0, "d", 5)]);
+    r2.forget_actor(&4, true);
+    let r4 = TypeReplica::new(4);
+    let batch = vec![
+        ops[0].clone(),
+        cycle,
+        concurrent[0].clone(),
+        r4.opmove(0, "e", 6),
+        concurrent[1].clone(),
+    ];
+    let report = r2.apply_ops_report(batch);
+    assert_eq!(report.applied, 2);
+    assert_eq!(report.no_effect, 1);
+    assert_eq!(report.duplicates, 1);
+    assert_eq!(report.tombstoned, 1);
+    assert_eq!(report.ignored(), 2);
+    assert_eq!(report.total(), 5);
+    assert_eq!(report.undo_redo_steps, 5);
+    assert_eq!(report.counter_before, 3);
+    assert_eq!(report.clock_advance(), 1);
+    assert!(r2.tree().find(&4).is_some());
+    assert!(r2.tree().find(&6).is_none());
+}
+
+// Tests that undone and redone entries are counted in the replica's
+// TieBreak order.
+#[test]
+fn apply_ops_report_tie_break() {
+    // a counter at which HashOrder lets actor 1 win, unlike ActorOrder.
+    let counter = (1..)
+        .find(|&c| {
+            HashOrder::tie_break(&Clock::new(1u8, Some(c)), &Clock::new(2u8, Some(c)))
+                == Ordering::Greater
+        })
+        .unwrap();
+    let newer = OpMove::new(Clock::new(1, Some(counter)), 0, "a", 1);
+    let older = OpMove::new(Clock::new(2, Some(counter)), 0, "b", 2);
+
+    let mut r: TreeReplica<TypeId, TypeMeta, TypeActor, HashOrder> = TreeReplica::new(3);
+    let report = r.apply_ops_report(vec![newer.clone(), older.clone()]);
+    assert_eq!(report.applied, 2);
+    assert_eq!(report.undo_redo_steps, 1);
+
+    let mut r = TypeReplica::new(3);
+    let report = r.apply_ops_report(vec![newer, older]);
+    assert_eq!(report.applied, 2);
+    assert_eq!(report.undo_redo_steps, 0);
+}
+
+crdt_tree::meta_record! {
+    /// file metadata, for the tests.
+    struct FileMeta {
+        mode: u32,
+    }
+}
+
+// Tests that moves whose metadata is merged count as applied.
+#[test]
+fn apply_ops_report_merged_metadata() {
+    let mode = |mode| FileMeta { mode: Some(mode) };
+    let mut r: TreeReplica<TypeId, FileMeta, TypeActor, ActorOrder, MergeMetadata> =
+        TreeReplica::new(1);
+    let ops = r.opmoves(vec![(0, mode(1), 1), (0, mode(2), 1), (1, mode(3), 0)]);
+    let report = r.apply_ops_report(ops);
+    assert_eq!(report.applied, 2);
+    assert_eq!(report.no_effect, 1);
+    assert_eq!(r.tree().find(&1).unwrap().metadata(), &mode(2));
+}
+
+// Tests that ops are counted by their effect on a deserialized replica,
+// including ops older than its log entries.
+#[test]
+fn apply_ops_report_after_deserializing() {
+    type StringReplica = TreeReplica<TypeId, String, TypeActor>;
+    let mut r1 = StringReplica::new(1);
+    let ops = r1.opmoves(vec![(0, "root".to_string(), 1), (1, "a".to_string(), 2)]);
+    r1.apply_ops_byref(&ops);
+    let op = r1.opmove(1, "b".to_string(), 3);
+    r1.apply_op(op);
+
+    let json = serde_json::to_string(&r1).unwrap();
+    let mut resumed: StringReplica = serde_json::from_str(&json).unwrap();
+    // a concurrent op older than r1's newest entry, and a cycle.
+    let older = OpMove::new(Clock::new(2, Some(2)), 1, "c".to_string(), 4);
+    let cycle = OpMove::new(Clock::new(2, Some(4)), 2, "root".to_string(), 1);
+    let report = resumed.apply_ops_report(vec![older, cycle]);
+    assert_eq!(report.applied, 1);
+    assert_eq!(report.no_effect, 1);
+    assert_eq!(report.undo_redo_steps, 1);
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree audit log
+use crdt_tree::{AuditFilter, Clock, OpMove, State};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+
+fn op(
+    actor: TypeActor,
+    counter: u64,
+    parent: TypeId,
+    child: TypeId,
+) -> OpMove<TypeId, TypeMeta, TypeActor> {
+    OpMove::new(Clock::new(actor, Some(counter)), parent, "m", child).with_wall_time(counter * 100)
+}
+
+fn setup() -> State<TypeId, TypeMeta, TypeActor> {
+    let mut state = State::new();
+    state.apply_ops_into(vec![
+        op(1, 1, 0, 1),
+        op(2, 2, 1, 2),
+        // ignored, as it would make 1 a child of its own child.
+        op(1, 3, 2, 1),
+        op(2, 4, 0, 2),
+    ]);
+    state
+}
+
+#[test]
+fn audit_log_records() {
+    let state = setup();
+    let records = state.audit_log(&AuditFilter::new());
+    let moves: Vec<_> = records
+        .iter()
+        .map(|r| (r.counter, r.child_id, r.old_parent_id, r.new_parent_id))
+        .collect();
+    assert_eq!(
+        moves,
+        vec![
+            (1, 1, None, Some(0)),
+            (2, 2, None, Some(1)),
+            (3, 1, Some(0), Some(0)),
+            (4, 2, Some(1), Some(0)),
+        ]
+    );
+    assert_eq!(records[2].parent_id, 2);
+    assert_eq!(records[2].wall_time, Some(300));
+}
+
+#[test]
+fn audit_log_filters() {
+    let state = setup();
+    let counters = |filter: AuditFilter<TypeActor>| -> Vec<u64> {
+        state.audit_log(&filter).iter().map(|r| r.counter).collect()
+    };
+    assert_eq!(counters(AuditFilter::new().with_actor(2)), vec![2, 4]);
+    assert_eq!(
+        counters(AuditFilter::new().with_wall_time(150..350)),
+        vec![2, 3]
+    );
+    assert_eq!(
+        counters(AuditFilter::new().with_actor(1).with_wall_time(150..350)),
+        vec![3]
+    );
+
+    // ops without a wall-clock time are excluded by a time range.
+    let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+    state.apply_op(OpMove::new(Clock::new(1, Some(1)), 0, "m", 1));
+    assert_eq!(state.audit_log(&AuditFilter::new()).len(), 1);
+    assert!(state
+        .audit_log(&AuditFilter::new().with_wall_time(0..u64::MAX))
+        .is_empty());
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn export_audit_log_json_lines() {
+    use crdt_tree::AuditRecord;
+
+    let state = setup();
+    let mut out = vec![];
+    let n = state
+        .export_audit_log(&AuditFilter::new().with_actor(2), &mut out)
+        .unwrap();
+    assert_eq!(n, 2);
+
+    let text = String::from_utf8(out).unwrap();
+    let records: Vec<AuditRecord<TypeId, TypeActor>> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records, state.audit_log(&AuditFilter::new().with_actor(2)));
+    assert!(text
+        .lines()
+        .next()
+        .unwrap()
+        .contains("\"old_parent_id\":null"));
+}
@@ -0,0 +1,58 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree causal barriers
+use crdt_tree::{Barrier, Clock, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// Tests that a barrier from an idle replica lets its peer truncate.
+#[test]
+fn barrier_advances_threshold() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let op = r2.opmove(0, "root", 1);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    // r2 is idle while r1 works, so nothing is stable.
+    let ops = r1.opmoves(vec![(1, "a", 2), (1, "b", 3), (1, "c", 4)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    assert!(r1.truncate_log().is_empty());
+
+    let barrier = r2.barrier();
+    assert_eq!(barrier.actor(), &2);
+    assert_eq!(barrier.clock(), &Clock::new(2, Some(4)));
+    assert!(r1.apply_barrier(&barrier));
+    assert!(!r1.apply_barrier(&barrier));
+    assert_eq!(r1.latest_time_of(&2), Some(barrier.clock()));
+    assert_eq!(r1.truncate_log().len(), 3);
+
+    // r2's next op is newer than its barrier.
+    let op = r2.opmove(1, "d", 5);
+    assert!(op.timestamp() > barrier.clock());
+    r1.apply_op(op);
+    assert!(r1.tree().find(&5).is_some());
+}
+
+// Tests that barriers from tombstoned actors are ignored, and that a
+// barrier advances the receiver's clock.
+#[test]
+fn barrier_from_tombstoned_actor() {
+    let mut r1 = TypeReplica::new(1);
+    assert!(r1.apply_barrier(&Barrier::new(Clock::new(3, Some(7)))));
+    assert_eq!(r1.time().counter(), 7);
+    assert!(r1.opmove(0, "a", 1).timestamp().counter() > 7);
+
+    r1.forget_actor(&3, true);
+    assert!(!r1.apply_barrier(&Barrier::new(Clock::new(3, Some(9)))));
+    assert_eq!(r1.latest_time_of(&3), None);
+}
@@ -0,0 +1,137 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree causal ops
+use crdt_tree::{CausalContext, Clock, TreeReplica, VersionVector};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// Tests that causal ops tell concurrent ops from causally ordered ones.
+#[test]
+fn concurrent_vs_causal() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+
+    let a = r1.causal_opmove(0, "a", 1);
+    r1.apply_op(a.op().clone());
+    r2.apply_op(a.op().clone());
+
+    // b and c are generated concurrently, after both saw a.
+    let b = r1.causal_opmove(1, "b", 2);
+    let c = r2.causal_opmove(1, "c", 3);
+    assert!(a.happened_before(&b));
+    assert!(a.happened_before(&c));
+    assert!(!b.happened_before(&a));
+    assert!(b.is_concurrent(&c));
+    assert!(!a.is_concurrent(&b));
+    assert!(!a.is_concurrent(&a));
+
+    // d is generated after r2 saw b, so follows both.
+    r2.apply_op(b.op().clone());
+    r2.apply_op(c.op().clone());
+    let d = r2.causal_opmove(2, "d", 4);
+    assert!(b.happened_before(&d));
+    assert!(c.happened_before(&d));
+    assert_eq!(d.context().get(&1), b.op().timestamp().counter());
+    assert_eq!(d.context().get(&9), 0);
+}
+
+// Tests that ops are buffered until their dependencies are applied.
+#[test]
+fn buffer_missing_deps() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let mut r3 = TypeReplica::new(3);
+
+    let a = r1.causal_opmove(0, "a", 1);
+    r1.apply_op(a.op().clone());
+    let b = r1.causal_opmove(1, "b", 2);
+    r1.apply_op(b.op().clone());
+    r2.apply_causal_op(a.clone());
+    r2.apply_causal_op(b.clone());
+    let c = r2.causal_opmove(2, "c", 3);
+    r2.apply_op(c.op().clone());
+
+    // r3 receives c, then b, then a.
+    assert_eq!(r3.apply_causal_op(c.clone()), 0);
+    assert_eq!(r3.apply_causal_op(b.clone()), 0);
+    assert_eq!(r3.pending_causal_ops().len(), 2);
+    assert_eq!(r3.tree().num_nodes(), 0);
+    assert_eq!(r3.apply_causal_op(a), 3);
+    assert!(r3.pending_causal_ops().is_empty());
+    assert_eq!(r3.tree(), r2.tree());
+    assert_eq!(r3.causal_context(), r2.causal_context());
+    assert!(r3.causal_context().includes(c.context()));
+}
+
+// Tests CausalContext directly.
+#[test]
+fn causal_context() {
+    let t1 = Clock::<TypeActor>::new(1, Some(5));
+    let t2 = Clock::<TypeActor>::new(2, Some(3));
+    let context: CausalContext<TypeActor> = vec![&t1, &t2].into_iter().collect();
+    assert!(context.contains(&t1));
+    assert!(context.contains(&Clock::new(1, Some(4))));
+    assert!(!context.contains(&Clock::new(1, Some(6))));
+    assert!(!context.contains(&Clock::new(3, Some(1))));
+
+    let mut other = CausalContext::new();
+    other.observe(&t1);
+    assert!(context.includes(&other));
+    assert!(!other.includes(&context));
+    other.observe(&Clock::new(1, Some(2)));
+    assert_eq!(other.get(&1), 5);
+    assert_eq!(other.iter().collect::<Vec<_>>(), vec![(&1, 5)]);
+}
+
+// Tests VersionVector comparison and merging.
+#[test]
+fn version_vector() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    assert!(r1.version_vector().is_empty());
+
+    let a = r1.opmove(0, "a", 1);
+    r1.apply_op(a.clone());
+    r2.apply_op(a);
+    assert_eq!(r1.version_vector(), r2.version_vector());
+    assert!(!r1.version_vector().dominates(r2.version_vector()));
+    assert!(!r1.version_vector().concurrent(r2.version_vector()));
+
+    let b = r1.opmove(1, "b", 2);
+    r1.apply_op(b.clone());
+    assert!(r1.version_vector().dominates(r2.version_vector()));
+    assert!(!r2.version_vector().dominates(r1.version_vector()));
+
+    let c = r2.opmove(1, "c", 3);
+    r2.apply_op(c.clone());
+    let (v1, v2) = (r1.version_vector(), r2.version_vector());
+    assert!(v1.concurrent(v2));
+    assert!(!v1.dominates(v2));
+
+    let mut merged = v1.clone();
+    merged.merge(v2);
+    assert!(merged.dominates(v1) && merged.dominates(v2));
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged.latest(&2), Some(c.timestamp()));
+
+    // once both have seen every op, the vectors are equal.
+    r1.apply_op(c);
+    r2.apply_op(b);
+    assert_eq!(r1.version_vector(), &merged);
+    assert_eq!(r2.version_vector(), &merged);
+
+    // serializes as a map of actor to timestamp.
+    let json = serde_json::to_string(&merged).unwrap();
+    assert_eq!(
+        serde_json::from_str::<VersionVector<TypeActor>>(&json).unwrap(),
+        merged
+    );
+}
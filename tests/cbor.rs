@@ -0,0 +1,74 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree CBOR encoding
+#[cfg(feature = "cbor")]
+mod cbor {
+    use crdt_tree::cbor;
+    use crdt_tree::{OpMove, State, TreeReplica};
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+    type TypeState = State<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns ops creating a few nodes.
+    fn new_ops() -> Vec<TypeOp> {
+        let r = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(1);
+        r.opmoves((1..20).map(|i| (i / 3, format!("node{}", i), i)).collect())
+    }
+
+    // Tests that values round-trip through CBOR.
+    #[test]
+    fn round_trip() {
+        let ops = new_ops();
+        let bytes = cbor::to_vec(&ops).unwrap();
+        assert_eq!(cbor::from_slice::<Vec<TypeOp>>(&bytes).unwrap(), ops);
+
+        let bytes = cbor::to_canonical_vec(&ops).unwrap();
+        assert_eq!(cbor::from_slice::<Vec<TypeOp>>(&bytes).unwrap(), ops);
+
+        let mut state = TypeState::new();
+        state.apply_ops(&ops);
+        let bytes = cbor::to_vec(&state).unwrap();
+        assert_eq!(cbor::from_slice::<TypeState>(&bytes).unwrap(), state);
+
+        let bytes = cbor::state_to_canonical_vec(&state).unwrap();
+        assert_eq!(
+            cbor::state_from_slice::<TypeId, TypeMeta, TypeActor>(&bytes).unwrap(),
+            state
+        );
+
+        assert!(cbor::from_slice::<TypeState>(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    // Tests that equal states, built in different orders, have identical
+    // canonical encodings.
+    #[test]
+    fn canonical_encoding_is_deterministic() {
+        let ops = new_ops();
+        let mut reversed = ops.clone();
+        reversed.reverse();
+
+        let mut s1 = TypeState::new();
+        s1.apply_ops(&ops);
+        let mut s2 = TypeState::new();
+        s2.apply_ops(&reversed);
+        assert_eq!(s1, s2);
+
+        assert_eq!(
+            cbor::state_to_canonical_vec(&s1).unwrap(),
+            cbor::state_to_canonical_vec(&s2).unwrap()
+        );
+        let nodes1: std::collections::HashMap<_, _> = s1.tree().iter().collect();
+        let nodes2: std::collections::HashMap<_, _> = s2.tree().iter().collect();
+        assert_eq!(
+            cbor::to_canonical_vec(&nodes1).unwrap(),
+            cbor::to_canonical_vec(&nodes2).unwrap()
+        );
+    }
+}
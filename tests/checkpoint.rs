@@ -0,0 +1,130 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree checkpointing
+use crdt_tree::{Checkpointer, Clock, OpMove, Segment, Snapshot, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = String;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+// helper: returns ops from r creating nodes first..last under parent.
+fn new_ops(r: &TypeReplica, parent: TypeId, first: TypeId, last: TypeId) -> Vec<TypeOp> {
+    r.opmoves(
+        (first..=last)
+            .map(|i| (parent, format!("node{}", i), i))
+            .collect(),
+    )
+}
+
+// Tests that a replica restored from its latest snapshot and segments
+// matches the original, and keeps converging with a peer.
+#[test]
+fn restore_from_snapshot_and_segments() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let mut checkpointer = Checkpointer::new(10, 0);
+
+    // persisted snapshot and segments.
+    let mut snapshot: Option<Snapshot<TypeId, TypeMeta, TypeActor>> = None;
+    let mut segments: Vec<Segment<TypeId, TypeMeta, TypeActor>> = vec![];
+
+    for batch in 0..7 {
+        let first = batch * 4 + 1;
+        let ops = new_ops(&r1, 0, first, first + 3);
+        r1.apply_ops_byref(&ops);
+        r2.apply_ops_byref(&ops);
+        let remote = new_ops(&r2, first, 1000 + batch, 1000 + batch);
+        r1.apply_ops_byref(&remote);
+        r2.apply_ops_byref(&remote);
+
+        segments.push(checkpointer.record(ops));
+        segments.push(checkpointer.record(remote));
+        if checkpointer.is_due() {
+            snapshot = Some(checkpointer.checkpoint(&r1));
+            segments.clear();
+        }
+    }
+    let snapshot = snapshot.unwrap();
+    assert_eq!(snapshot.id(), 3);
+    assert_eq!(checkpointer.checkpoint_id(), 3);
+    assert!(!segments.is_empty());
+    // ops older than both replicas' latest times are not in the snapshot.
+    assert!(snapshot.log().len() < r1.state().log().len());
+
+    // segments are applied in order, and stale segments are ignored.
+    let mut stale = Checkpointer::new(10, 1);
+    let mut shuffled = segments.clone();
+    shuffled.reverse();
+    shuffled.push(stale.record(new_ops(&r1, 0, 500, 505)));
+
+    let mut restored = TypeReplica::restore(snapshot, shuffled);
+    assert_eq!(restored.tree(), r1.tree());
+    assert_eq!(restored.time(), r1.time());
+    assert_eq!(
+        restored.causally_stable_threshold(),
+        r1.causally_stable_threshold()
+    );
+
+    // a concurrent op from r2, older than r1's latest ops, is undone and
+    // redone identically on both.
+    let concurrent = r2.opmove(1000, "moved".to_string(), 1);
+    r1.apply_op(concurrent.clone());
+    restored.apply_op(concurrent);
+    assert_eq!(restored.tree(), r1.tree());
+}
+
+// Tests that a replica restored from a snapshot that left no log entries
+// out applies old ops from actors it has not seen, as the original does.
+#[test]
+fn restore_applies_old_ops_of_new_actors() {
+    let mut r1 = TypeReplica::new(1);
+    let r2 = TypeReplica::new(2);
+    // r2's op is at the causally stable threshold, so is kept.
+    let remote = new_ops(&r2, 0, 1, 1);
+    r1.apply_ops_byref(&remote);
+    let ops = new_ops(&r1, 1, 2, 2);
+    r1.apply_ops_byref(&ops);
+
+    let snapshot = Checkpointer::new(10, 0).checkpoint(&r1);
+    assert_eq!(snapshot.log().len(), r1.state().log().len());
+    assert_eq!(snapshot.truncated_before(), None);
+    let mut restored = TypeReplica::restore(snapshot, vec![]);
+
+    let old = TypeOp::new(Clock::new(0, Some(1)), 1, "x".to_string(), 9);
+    assert!(!restored.has_seen(old.timestamp()));
+    r1.apply_op(old.clone());
+    restored.apply_op(old);
+    assert_eq!(restored.tree(), r1.tree());
+    assert!(restored.tree().find(&9).is_some());
+}
+
+// Tests that a read snapshot is unchanged by ops applied to the replica
+// after it is taken, including from another thread.
+#[test]
+fn read_snapshot_is_stable() {
+    let mut r1 = TypeReplica::new(1);
+    r1.apply_ops(new_ops(&r1, 0, 1, 10));
+    let snapshot = r1.read_snapshot();
+    assert_eq!(snapshot.time(), r1.time());
+
+    let reader = {
+        let snapshot = snapshot.clone();
+        std::thread::spawn(move || snapshot.tree().subtree_size(&0))
+    };
+    r1.apply_ops(new_ops(&r1, 1, 11, 20));
+    r1.apply_op(r1.opmove(5, "moved".to_string(), 1));
+
+    assert_eq!(reader.join().unwrap(), 10);
+    assert_eq!(snapshot.tree().num_nodes(), 10);
+    assert_eq!(snapshot.tree().find(&1).unwrap().parent_id(), &0);
+    assert!(snapshot.time() < r1.time());
+    assert_eq!(r1.tree().num_nodes(), 20);
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &5);
+}
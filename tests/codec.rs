@@ -0,0 +1,140 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree wire codec
+#[cfg(feature = "codec")]
+mod codec {
+    use crdt_tree::codec::{self, CodecError, FORMAT_VERSION, MIN_FORMAT_VERSION};
+    use crdt_tree::{Barrier, CausalOpMove, LogOpMove, OpMove, State, TreeReplica};
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns a replica with a few nodes.
+    fn new_replica() -> TreeReplica<TypeId, TypeMeta, TypeActor> {
+        let mut r = TreeReplica::new(1);
+        let ops = r.opmoves(vec![
+            (0, "home".to_string(), 1),
+            (1, "bob".to_string(), 2),
+            (1, "alice".to_string(), 3),
+        ]);
+        r.apply_ops(ops);
+        r
+    }
+
+    // Tests that each wire type round-trips through encode and decode.
+    #[test]
+    fn round_trip() {
+        let r = new_replica();
+        let op = r.state().log()[0].clone().op_into();
+
+        let bytes = codec::encode(&op).unwrap();
+        assert_eq!(codec::header(&bytes).unwrap().0, FORMAT_VERSION);
+        assert_eq!(codec::decode::<TypeOp>(&bytes).unwrap(), op);
+
+        let ops = vec![op.clone(), op];
+        let bytes = codec::encode(&ops).unwrap();
+        assert_eq!(codec::decode::<Vec<TypeOp>>(&bytes).unwrap(), ops);
+
+        let causal = r.causal_opmove(1, "causal".to_string(), 9);
+        let bytes = codec::encode(&causal).unwrap();
+        let decoded: CausalOpMove<TypeId, TypeMeta, TypeActor> = codec::decode(&bytes).unwrap();
+        assert_eq!(decoded, causal);
+
+        let entry = r.state().log()[1].clone();
+        let bytes = codec::encode(&entry).unwrap();
+        let decoded: LogOpMove<TypeId, TypeMeta, TypeActor> = codec::decode(&bytes).unwrap();
+        assert_eq!(decoded, entry);
+
+        let bytes = codec::encode(r.state()).unwrap();
+        let decoded: State<TypeId, TypeMeta, TypeActor> = codec::decode(&bytes).unwrap();
+        assert_eq!(&decoded, r.state());
+
+        let barrier = r.barrier();
+        let bytes = codec::encode(&barrier).unwrap();
+        assert_eq!(
+            codec::decode::<Barrier<TypeActor>>(&bytes).unwrap(),
+            barrier
+        );
+    }
+
+    // Tests that malformed or mismatched messages are rejected.
+    #[test]
+    fn rejects_bad_messages() {
+        let r = new_replica();
+        let mut bytes = codec::encode(r.state()).unwrap();
+
+        assert_eq!(
+            codec::decode::<TypeOp>(&bytes),
+            Err(CodecError::WrongKind {
+                expected: 1,
+                found: 4
+            })
+        );
+        assert_eq!(
+            codec::decode::<TypeOp>(&bytes[..3]),
+            Err(CodecError::Truncated)
+        );
+        assert_eq!(
+            codec::decode::<State<TypeId, TypeMeta, TypeActor>>(&bytes[..10]),
+            Err(CodecError::Truncated)
+        );
+
+        bytes[2] = FORMAT_VERSION + 1;
+        assert_eq!(
+            codec::decode::<State<TypeId, TypeMeta, TypeActor>>(&bytes),
+            Err(CodecError::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+        bytes[0] = b'X';
+        assert_eq!(
+            codec::decode::<State<TypeId, TypeMeta, TypeActor>>(&bytes),
+            Err(CodecError::BadMagic)
+        );
+    }
+
+    // Tests that messages of versions older than the oldest decodable one
+    // are rejected.
+    #[test]
+    fn rejects_old_versions() {
+        let r = new_replica();
+        let mut bytes = codec::encode(r.state()).unwrap();
+        bytes[2] = MIN_FORMAT_VERSION - 1;
+        assert_eq!(
+            codec::decode::<State<TypeId, TypeMeta, TypeActor>>(&bytes),
+            Err(CodecError::UnsupportedVersion(MIN_FORMAT_VERSION - 1))
+        );
+    }
+
+    // Tests that states round-trip through compressed bytes, and that
+    // repeated metadata compresses well.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_state() {
+        let r = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(1);
+        let ops = r.opmoves(
+            (1..2000)
+                .map(|i| (i / 10, format!("metadata-{}", i % 5), i))
+                .collect(),
+        );
+        let mut state = State::new();
+        state.apply_ops(&ops);
+
+        let compressed = state.to_bytes_compressed().unwrap();
+        let uncompressed = codec::encode(&state).unwrap();
+        assert!(compressed.len() * 4 < uncompressed.len());
+
+        let decoded: State<TypeId, TypeMeta, TypeActor> =
+            State::from_bytes_compressed(&compressed).unwrap();
+        assert_eq!(decoded, state);
+
+        assert!(matches!(
+            State::<TypeId, TypeMeta, TypeActor>::from_bytes_compressed(&uncompressed),
+            Err(CodecError::Compression(_)) | Err(CodecError::Payload(_))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree conditional moves
+use crdt_tree::{OpMove, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// returns replicas 1 and 2 holding folders 1, 2 and 3, and file 4 in 1.
+fn setup() -> (TypeReplica, TypeReplica) {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "a", 1), (0, "b", 2), (0, "c", 3), (1, "f", 4)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops(ops);
+    (r1, r2)
+}
+
+#[test]
+fn conditional_move_applies_when_expected() {
+    let (mut r1, _) = setup();
+    let op = r1.opmove(2, "f", 4).with_expected_parent(1);
+    assert_eq!(op.expected_parent_id(), Some(&1));
+    r1.apply_op(op);
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &2);
+}
+
+#[test]
+fn conditional_move_ignored_when_not_expected() {
+    let (mut r1, _) = setup();
+    let op = r1.opmove(2, "f", 4).with_expected_parent(3);
+    r1.apply_op(op);
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &1);
+    assert_eq!(r1.state().log_stats().no_ops(), 1);
+
+    // nor does it create a missing node.
+    let op = r1.opmove(2, "g", 5).with_expected_parent(1);
+    r1.apply_op(op);
+    assert_eq!(r1.tree().find(&5), None);
+}
+
+#[test]
+fn conditional_move_is_deterministic() {
+    // both replicas move file 4 out of folder 1 concurrently.  Replica 2's
+    // op is ordered first, so replica 1's conditional op is ignored on
+    // both replicas, whatever the order of delivery.
+    let (mut r1, mut r2) = setup();
+    let other = r1.opmove(0, "d", 5);
+    r1.apply_op(other.clone());
+    let theirs = r2.opmove(3, "f", 4);
+    let mine = r1.opmove(2, "f", 4).with_expected_parent(1);
+    assert!(theirs.timestamp() < mine.timestamp());
+
+    r1.apply_op(mine.clone());
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &2);
+    r1.apply_op(theirs.clone());
+    r2.apply_op(theirs);
+    r2.apply_op(mine);
+    r2.apply_op(other);
+
+    assert_eq!(r1.tree(), r2.tree());
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &3);
+}
+
+#[test]
+fn conditional_move_survives_log_round_trip() {
+    let (mut r1, _) = setup();
+    let op = r1.opmove(2, "f", 4).with_expected_parent(1);
+    r1.apply_op(op.clone());
+    let logged: OpMove<TypeId, TypeMeta, TypeActor> = r1.state().log()[0].clone().into();
+    assert_eq!(logged, op);
+    assert_eq!(r1.state().log()[0].expected_parent_id(), Some(&1));
+}
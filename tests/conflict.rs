@@ -0,0 +1,97 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree conflict detection
+use crdt_tree::{Clock, Conflict, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// returns replicas 1 and 2 holding folders 1, 2 and 3, and file 4 in 1.
+fn setup() -> (TypeReplica, TypeReplica) {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "a", 1), (0, "b", 2), (0, "c", 3), (1, "f", 4)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops(ops);
+    (r1, r2)
+}
+
+#[test]
+fn concurrent_moves_of_same_node() {
+    let (mut r1, mut r2) = setup();
+    let since = r1.time().clone();
+    let mine = r1.opmove(2, "f", 4);
+    let theirs = r2.opmove(3, "f", 4);
+    r1.apply_ops(vec![mine.clone(), theirs.clone()]);
+    r2.apply_ops(vec![theirs.clone(), mine.clone()]);
+
+    let expected = vec![Conflict::Overridden {
+        child_id: 4,
+        op: mine.timestamp().clone(),
+        by: theirs.timestamp().clone(),
+    }];
+    assert_eq!(r1.state().conflicts_since(&since), expected);
+    assert_eq!(r2.state().conflicts_since(&since), expected);
+
+    // the conflict is not after the winning op.
+    assert!(r1.state().conflicts_since(theirs.timestamp()).is_empty());
+}
+
+#[test]
+fn moves_by_same_actor_do_not_conflict() {
+    let (mut r1, _) = setup();
+    let since = r1.time().clone();
+    let ops = r1.opmoves(vec![(2, "f", 4), (3, "f", 4)]);
+    r1.apply_ops(ops);
+    assert!(r1.state().conflicts_since(&since).is_empty());
+}
+
+#[test]
+fn concurrent_moves_creating_cycle() {
+    // each replica moves one folder into the other.
+    let (mut r1, mut r2) = setup();
+    let since = r1.time().clone();
+    let mine = r1.opmove(2, "a", 1);
+    let theirs = r2.opmove(1, "b", 2);
+    r1.apply_ops(vec![mine.clone(), theirs.clone()]);
+    r2.apply_ops(vec![theirs.clone(), mine]);
+
+    let expected = vec![Conflict::Cycle {
+        child_id: 2,
+        op: theirs.timestamp().clone(),
+    }];
+    assert_eq!(r1.state().conflicts_since(&since), expected);
+    assert_eq!(r2.state().conflicts_since(&since), expected);
+}
+
+#[test]
+fn failed_precondition() {
+    let (mut r1, mut r2) = setup();
+    let since = r1.time().clone();
+    let theirs = r2.opmove(3, "f", 4);
+    r2.apply_op(theirs.clone());
+    r1.apply_op(theirs);
+    let mine = r1.opmove(2, "f", 4).with_expected_parent(1);
+    r1.apply_op(mine.clone());
+
+    assert_eq!(
+        r1.state().conflicts_since(&since),
+        vec![Conflict::PreconditionFailed {
+            child_id: 4,
+            op: mine.timestamp().clone(),
+        }]
+    );
+}
+
+#[test]
+fn uncontended_ops_do_not_conflict() {
+    let (r1, _) = setup();
+    assert!(r1.state().conflicts_since(&Clock::new(0, None)).is_empty());
+}
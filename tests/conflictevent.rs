@@ -0,0 +1,194 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree conflict events
+use crdt_tree::{Clock, ConflictEvent, ConflictKind, OpMove, TreeReplica};
+use std::sync::{Arc, Mutex};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeEvent = ConflictEvent<TypeId, TypeActor>;
+
+// returns a replica whose conflicts are collected in the returned list.
+fn setup(id: TypeActor) -> (TypeReplica, Arc<Mutex<Vec<TypeEvent>>>) {
+    let mut r = TypeReplica::new(id);
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    r.set_conflict_handler(Some(Box::new(move |e| sink.lock().unwrap().push(e))));
+    (r, events)
+}
+
+fn take(events: &Arc<Mutex<Vec<TypeEvent>>>) -> Vec<(ConflictKind, TypeId, u64, Option<u64>)> {
+    events
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|e| {
+            (
+                e.kind(),
+                *e.child_id(),
+                e.op().counter(),
+                e.by().map(|c| c.counter()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn cycle_and_precondition_reported() {
+    let (mut r1, events) = setup(1);
+    let ops = r1.opmoves(vec![(0, "a", 1), (1, "b", 2), (2, "c", 3)]);
+    r1.apply_ops(ops);
+    assert!(take(&events).is_empty());
+
+    // 1 under 3, which op 3 placed under 2.
+    let op = r1.opmove(3, "a", 1);
+    r1.apply_op(op);
+    assert_eq!(take(&events), vec![(ConflictKind::Cycle, 1, 4, Some(3))]);
+
+    // 2 is under 1, as op 2 placed it.
+    let op = r1.opmove(0, "b", 2).with_expected_parent(5);
+    r1.apply_op(op);
+    assert_eq!(
+        take(&events),
+        vec![(ConflictKind::PreconditionFailed, 2, 5, Some(2))]
+    );
+}
+
+#[test]
+fn lww_loss_reported_both_ways() {
+    let (mut r1, events) = setup(1);
+    let mut r2 = TypeReplica::new(2);
+    let op = r1.opmove(0, "a", 1);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    // concurrent renames, r2's wins as it is later.
+    let mine = r1.opmove(0, "mine", 1);
+    let theirs = OpMove::new(Clock::new(2, Some(3)), 0, "theirs", 1);
+    r1.apply_op(mine);
+    assert!(take(&events).is_empty());
+    r1.apply_op(theirs);
+    assert_eq!(
+        take(&events),
+        vec![(ConflictKind::Overridden, 1, 2, Some(3))]
+    );
+
+    // an older op of another actor, overridden by r1's later op.
+    let (mut r3, events) = setup(1);
+    let op = r3.opmove(0, "a", 1);
+    r3.apply_op(op);
+    let op = r3.opmove(0, "mine", 1);
+    r3.apply_op(op);
+    r3.apply_op(OpMove::new(Clock::new(2, Some(1)), 0, "old", 1));
+    assert_eq!(
+        take(&events),
+        vec![
+            (ConflictKind::Overridden, 1, 1, Some(1)),
+            (ConflictKind::Overridden, 1, 1, Some(2)),
+        ]
+    );
+}
+
+#[test]
+fn redo_changes_reported() {
+    let (mut r1, events) = setup(1);
+    let ops = r1.opmoves(vec![(0, "a", 1), (0, "b", 2)]);
+    r1.apply_ops(ops);
+
+    // 1 under 2, then an older concurrent op moving 2 under 1 arrives,
+    // which makes the later move a cycle.
+    let op = r1.opmove(2, "a", 1);
+    r1.apply_op(op);
+    r1.apply_op(OpMove::new(Clock::new(2, Some(2)), 1, "b", 2));
+    assert_eq!(
+        take(&events),
+        vec![
+            (ConflictKind::Overridden, 2, 2, Some(2)),
+            (ConflictKind::Cycle, 1, 3, Some(2)),
+        ]
+    );
+
+    // a conditional move ignored, until an older op moves 5 as expected.
+    let (mut r2, events) = setup(2);
+    let ops = r2.opmoves(vec![(0, "x", 4), (0, "y", 5)]);
+    r2.apply_ops(ops);
+    let op = r2.opmove(0, "z", 5).with_expected_parent(4);
+    r2.apply_op(op);
+    assert_eq!(
+        take(&events),
+        vec![(ConflictKind::PreconditionFailed, 5, 3, Some(2))]
+    );
+    r2.apply_op(OpMove::new(Clock::new(1, Some(3)), 4, "w", 5));
+    assert_eq!(
+        take(&events),
+        vec![
+            (ConflictKind::Overridden, 5, 2, Some(3)),
+            (ConflictKind::Reinstated, 5, 3, Some(3)),
+            (ConflictKind::Overridden, 5, 3, Some(3)),
+        ]
+    );
+    assert_eq!(r2.tree().find(&5).unwrap().metadata(), &"z");
+}
+
+#[test]
+fn handler_is_local() {
+    let (mut r1, events) = setup(1);
+    let ops = r1.opmoves(vec![(0, "a", 1), (1, "b", 2)]);
+    r1.apply_ops(ops);
+    let mut r2 = r1.clone();
+    let op = r2.opmove(2, "a", 1);
+    r2.apply_op(op.clone());
+    assert!(take(&events).is_empty());
+    assert_eq!(r1, r1.clone());
+
+    r1.set_conflict_handler(None);
+    r1.apply_op(op);
+    assert!(take(&events).is_empty());
+}
+
+#[test]
+fn conflicts_reported_after_deserializing() {
+    type StringReplica = TreeReplica<TypeId, String, TypeActor>;
+    let mut r1 = StringReplica::new(1);
+    let ops = r1.opmoves(vec![
+        (0, "a".to_string(), 1),
+        (1, "b".to_string(), 2),
+        (2, "c".to_string(), 3),
+    ]);
+    r1.apply_ops(ops);
+    let json = serde_json::to_string(&r1).unwrap();
+    let mut r1: StringReplica = serde_json::from_str(&json).unwrap();
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    r1.set_conflict_handler(Some(Box::new(move |e| sink.lock().unwrap().push(e))));
+    let take = || {
+        events
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|e: TypeEvent| {
+                (
+                    e.kind(),
+                    *e.child_id(),
+                    e.op().counter(),
+                    e.by().map(|c| c.counter()),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // the culprit of a cycle and the op overridden are found by the
+    // nodes they placed, as before deserializing.
+    let op = r1.opmove(3, "a".to_string(), 1);
+    r1.apply_op(op);
+    assert_eq!(take(), vec![(ConflictKind::Cycle, 1, 4, Some(3))]);
+    r1.apply_op(OpMove::new(Clock::new(2, Some(5)), 1, "d".to_string(), 3));
+    assert_eq!(take(), vec![(ConflictKind::Overridden, 3, 3, Some(5))]);
+}
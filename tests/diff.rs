@@ -0,0 +1,89 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree diff
+use crdt_tree::{diff, Tree, TreeNode, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// returns a tree holding the given (parent_id, metadata, child_id) triples.
+fn tree(triples: &[(TypeId, TypeMeta, TypeId)]) -> Tree<TypeId, TypeMeta> {
+    let mut tree = Tree::new();
+    for (parent_id, meta, child_id) in triples {
+        tree.add_node(*child_id, TreeNode::new(*parent_id, *meta));
+    }
+    tree
+}
+
+#[test]
+fn diff_identical_is_empty() {
+    let t = tree(&[(0, "a", 1), (1, "b", 2)]);
+    assert!(diff(&t, &t.clone()).is_empty());
+}
+
+#[test]
+fn diff_is_minimal() {
+    let current = tree(&[(0, "a", 1), (1, "b", 2), (1, "c", 3), (0, "d", 4)]);
+    let target = tree(&[
+        (0, "a", 1),
+        (1, "renamed", 2),
+        (4, "c", 3),
+        (0, "d", 4),
+        (3, "e", 5),
+    ]);
+
+    let mut moves = diff(&current, &target);
+    moves.sort_by_key(|m| m.2);
+    assert_eq!(moves, vec![(1, "renamed", 2), (4, "c", 3), (3, "e", 5)]);
+}
+
+#[test]
+fn diff_orders_moves_to_avoid_cycles() {
+    // 2 and 3 swap places, which fails if 3 moves under 2 first.
+    let current = tree(&[(0, "a", 1), (1, "b", 2), (2, "c", 3)]);
+    let target = tree(&[(0, "a", 1), (1, "c", 3), (3, "b", 2)]);
+
+    let mut r1 = TypeReplica::new(1);
+    let ops = r1.opmoves(vec![(0, "a", 1), (1, "b", 2), (2, "c", 3)]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree(), &current);
+
+    let ops = r1.converge_to(&target);
+    assert_eq!(ops.len(), 2);
+    assert_eq!(ops[0].child_id(), &3);
+    assert_eq!(r1.tree(), &target);
+}
+
+#[test]
+fn converge_to_propagates() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "a", 1), (1, "b", 2), (1, "c", 3), (0, "d", 4)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops(ops);
+
+    // nodes missing from target are left alone.
+    let target = tree(&[
+        (0, "d", 4),
+        (4, "a", 1),
+        (1, "b", 2),
+        (1, "x", 5),
+        (5, "y", 6),
+    ]);
+    let ops = r1.converge_to(&target);
+    for (id, node) in target.iter() {
+        assert_eq!(r1.tree().find(id), Some(node));
+    }
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &1);
+    assert!(r1.converge_to(&target).is_empty());
+
+    r2.apply_ops(ops);
+    assert_eq!(r1.tree(), r2.tree());
+}
@@ -0,0 +1,153 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree metadata encryption
+#[cfg(feature = "encryption")]
+mod encryption {
+    use crdt_tree::encryption::{
+        EncryptedOpMove, EncryptedStorage, EncryptedStorageError, EncryptionError, MetaKey, Sealed,
+        SecretKey,
+    };
+    use crdt_tree::{MemoryStorage, State, Storage, StoredReplica, TreeReplica};
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeState = State<TypeId, TypeMeta, TypeActor>;
+    type TypeStorage = EncryptedStorage<MemoryStorage<TypeId, Sealed, TypeActor>>;
+
+    // helper: returns a replica with a few nodes.
+    fn new_replica() -> TreeReplica<TypeId, TypeMeta, TypeActor> {
+        let mut r = TreeReplica::new(1);
+        let ops = r.opmoves(vec![
+            (0, "home".to_string(), 1),
+            (1, "bob".to_string(), 2),
+            (1, "alice".to_string(), 3),
+            (1, "robert".to_string(), 2),
+        ]);
+        r.apply_ops(ops);
+        r
+    }
+
+    // Tests that ops, states and snapshots round-trip, with the structure
+    // left in the clear.
+    #[test]
+    fn seal_and_open() {
+        let key = MetaKey::new([7; 32]);
+        let r = new_replica();
+
+        let op = r.state().log()[0].clone().op_into();
+        let sealed = key.seal_op(&op).unwrap();
+        assert_eq!(sealed.child_id(), op.child_id());
+        assert_eq!(sealed.parent_id(), op.parent_id());
+        assert_eq!(key.open_op(&sealed).unwrap(), op);
+
+        let sealed = key.seal_state(r.state()).unwrap();
+        assert_eq!(sealed.log().len(), r.state().log().len());
+        assert_eq!(sealed.tree().num_nodes(), r.tree().num_nodes());
+        let mut children = sealed.tree().children(&1);
+        children.sort_unstable();
+        assert_eq!(children, vec![2, 3]);
+        let opened: TypeState = key.open_state(&sealed).unwrap();
+        assert_eq!(&opened, r.state());
+
+        let snapshot = r.snapshot(3);
+        let sealed = key.seal_snapshot(&snapshot).unwrap();
+        assert_eq!(sealed.id(), 3);
+        assert_eq!(key.open_snapshot(&sealed).unwrap(), snapshot);
+    }
+
+    // Tests that a wrong key, another node or tampering is detected.
+    #[test]
+    fn open_rejects_bad_ciphertext() {
+        let key = MetaKey::new([7; 32]);
+        let sealed = key.seal(&1u64, &"secret".to_string()).unwrap();
+        assert_eq!(key.open::<_, String>(&1u64, &sealed).unwrap(), "secret");
+        // nonces are random.
+        assert_ne!(key.seal(&1u64, &"secret".to_string()).unwrap(), sealed);
+
+        assert_eq!(
+            MetaKey::new([8; 32]).open::<_, String>(&1u64, &sealed),
+            Err(EncryptionError::Decrypt)
+        );
+        assert_eq!(
+            key.open::<_, String>(&2u64, &sealed),
+            Err(EncryptionError::Decrypt)
+        );
+        let mut bytes = bincode::serialize(&sealed).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let tampered: Sealed = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(
+            key.open::<_, String>(&1u64, &tampered),
+            Err(EncryptionError::Decrypt)
+        );
+    }
+
+    // Tests that a stored replica works through encrypted storage, which
+    // only holds sealed metadata.
+    #[test]
+    fn encrypted_storage() {
+        let storage = TypeStorage::new(MemoryStorage::new(), MetaKey::new([1; 32]));
+        let mut stored = StoredReplica::open(1, storage)
+            .unwrap()
+            .with_checkpoint_interval(Some(3));
+        let ops = stored
+            .replica()
+            .opmoves((1..=5).map(|i| (0, format!("n{}", i), i)).collect());
+        stored.apply_ops(ops).unwrap();
+
+        let (replica, storage) = stored.into_parts();
+        let inner = storage.inner();
+        assert_eq!(inner.num_snapshots(), 1);
+        let snapshot = inner.latest_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.tree().num_nodes(), 3);
+
+        let reopened = StoredReplica::open(1, storage).unwrap();
+        assert_eq!(reopened.replica().tree(), replica.tree());
+
+        // opened with the wrong key.
+        let storage = TypeStorage::new(reopened.into_parts().1.into_inner(), MetaKey::new([2; 32]));
+        assert!(matches!(
+            StoredReplica::<TypeId, TypeMeta, TypeActor, _>::open(1, storage),
+            Err(EncryptedStorageError::Encryption(EncryptionError::Decrypt))
+        ));
+    }
+
+    // Tests that recipients can open and apply an encrypted op, and that
+    // a relay can apply it without reading the metadata.
+    #[test]
+    fn encrypted_op_end_to_end() {
+        let alice = SecretKey::from([1; 32]);
+        let bob = SecretKey::from([2; 32]);
+        let eve = SecretKey::from([3; 32]);
+        let r1 = new_replica();
+        let mut r2 = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+        let mut relay = TreeReplica::<TypeId, Sealed, TypeActor>::new(3);
+
+        let op = r1.opmove(0, "secret".to_string(), 9);
+        let sealed = EncryptedOpMove::seal(&op, &[alice.public_key(), bob.public_key()]).unwrap();
+        assert_eq!(sealed.recipients().len(), 2);
+        assert_eq!(sealed.timestamp(), op.timestamp());
+        assert_eq!(sealed.sealed_op().child_id(), &9);
+
+        // through the wire.
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(!json.contains("secret"));
+        let sealed: EncryptedOpMove<TypeId, TypeActor> = serde_json::from_str(&json).unwrap();
+
+        relay.apply_op(sealed.sealed_op().clone());
+        assert_eq!(relay.tree().find(&9).unwrap().parent_id(), &0);
+
+        assert_eq!(sealed.open::<TypeMeta>(&alice).unwrap(), op);
+        r2.apply_encrypted_op(&sealed, &bob).unwrap();
+        assert_eq!(r2.tree().find(&9).unwrap().metadata(), "secret");
+        assert_eq!(
+            r2.apply_encrypted_op(&sealed, &eve),
+            Err(EncryptionError::NotRecipient)
+        );
+    }
+}
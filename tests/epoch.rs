@@ -0,0 +1,83 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree epoch-based log truncation
+use crdt_tree::{Epochs, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// Tests that entries before an epoch are dropped once every member has
+// acknowledged it, and not before.
+#[test]
+fn epoch_truncates_when_acknowledged() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let mut e1 = Epochs::new(vec![1, 2]);
+    let mut e2 = Epochs::new(vec![1, 2]);
+
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+
+    let ack1 = e1.start(&mut r1);
+    assert_eq!(ack1.epoch().number(), 1);
+    assert_eq!(ack1.epoch().boundary(), r1.time());
+    assert!(!e1.is_complete());
+
+    // r1's op generated after starting the epoch is kept.
+    let op = r1.opmove(1, "c", 4);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    assert!(e2.apply_ack(&mut r2, &ack1).is_empty());
+    let ack2 = e2.acknowledge(&mut r2, ack1.epoch()).unwrap();
+    assert!(e2.acknowledge(&mut r2, ack1.epoch()).is_none());
+    assert!(e2.is_complete());
+    assert_eq!(r2.state().log().len(), 2);
+    assert!(r2.opmove(1, "d", 5).timestamp() > ack1.epoch().boundary());
+
+    assert_eq!(e1.apply_ack(&mut r1, &ack2).len(), 2);
+    assert!(e1.is_complete());
+    assert_eq!(r1.state().log().len(), 2);
+    assert_eq!(r1.state().log()[0].child_id(), &4);
+    assert_eq!(r1.tree(), r2.tree());
+}
+
+// Tests that of epochs started concurrently, the one with the newest
+// boundary prevails, and that acks from non-members are ignored.
+#[test]
+fn concurrent_epochs() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let mut e1 = Epochs::new(vec![1, 2]);
+    let mut e2 = Epochs::new(vec![1, 2]);
+    let ops = r2.opmoves(vec![(0, "root", 1), (1, "a", 2)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+
+    let ack1 = e1.start(&mut r1);
+    let ack2 = e2.start(&mut r2);
+    assert!(ack2.epoch() > ack1.epoch());
+
+    assert!(e2.acknowledge(&mut r2, ack1.epoch()).is_none());
+    let ack = e1.acknowledge(&mut r1, ack2.epoch()).unwrap();
+    assert_eq!(e1.current(), Some(ack2.epoch()));
+
+    // r1's ack of its own, losing epoch no longer counts.
+    assert!(e2.apply_ack(&mut r2, &ack1).is_empty());
+    assert!(!e2.is_complete());
+    assert_eq!(e2.apply_ack(&mut r2, &ack).len(), 1);
+    assert_eq!(e1.apply_ack(&mut r1, &ack2).len(), 1);
+
+    let mut e3 = Epochs::new(vec![3]);
+    let mut r3 = TypeReplica::new(3);
+    assert!(e3.apply_ack(&mut r3, &ack1).is_empty());
+    assert!(!e3.is_complete());
+}
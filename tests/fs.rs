@@ -0,0 +1,174 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree filesystem facade
+use crdt_tree::fs::{FileSystem, FsError};
+use crdt_tree::TreeReplica;
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeFs = FileSystem<TypeId, String, TypeActor>;
+
+const ROOT: TypeId = 1;
+const TRASH: TypeId = 2;
+
+// helper: generate a new random id
+fn new_id() -> TypeId {
+    rand::random::<TypeId>()
+}
+
+// helper: creates a new filesystem for actor
+fn new_fs(actor: TypeActor) -> TypeFs {
+    FileSystem::new(TreeReplica::new(actor), ROOT, TRASH, new_id)
+}
+
+#[test]
+fn mkdir_ls_mv_rm() {
+    let mut fs = new_fs(1);
+
+    fs.mkdir_all("/home/bob/projects").unwrap();
+    fs.mkdir("/home/alice").unwrap();
+    fs.mkdir("/etc").unwrap();
+    assert_eq!(fs.ls("/").unwrap(), vec!["etc", "home"]);
+    assert_eq!(fs.ls("/home").unwrap(), vec!["alice", "bob"]);
+
+    assert_eq!(
+        fs.mkdir("/home/alice"),
+        Err(FsError::AlreadyExists("/home/alice".to_string()))
+    );
+    assert_eq!(
+        fs.mkdir("/usr/local"),
+        Err(FsError::NotFound("/usr".to_string()))
+    );
+    assert!(fs.mkdir_all("/home/bob").unwrap().is_empty());
+
+    let projects = fs.lookup("/home/bob/projects").unwrap();
+    fs.mv("/home/bob/projects", "/home/alice/work").unwrap();
+    assert_eq!(fs.lookup("/home/alice/work"), Some(projects));
+    assert_eq!(fs.lookup("/home/bob/projects"), None);
+
+    fs.rename("/home/alice", "carol").unwrap();
+    assert_eq!(fs.ls("/home").unwrap(), vec!["bob", "carol"]);
+    assert_eq!(fs.lookup("/home/carol/work"), Some(projects));
+
+    assert_eq!(
+        fs.mv("/home", "/home/carol/home"),
+        Err(FsError::MoveIntoSelf("/home/carol/home".to_string()))
+    );
+    assert!(matches!(fs.rm("/"), Err(FsError::InvalidPath(_))));
+
+    fs.rm("/home/carol").unwrap();
+    assert_eq!(fs.ls("/home").unwrap(), vec!["bob"]);
+    assert_eq!(fs.replica().tree().children(&TRASH).len(), 1);
+}
+
+// Tests that ops generated by one filesystem replicate to another.
+#[test]
+fn replicate_ops() {
+    let mut fs1 = new_fs(1);
+    let mut fs2 = new_fs(2);
+
+    let mut ops = fs1.mkdir_all("/a/b").unwrap();
+    ops.extend(fs1.mkdir("/c").unwrap());
+    ops.extend(fs1.mv("/a/b", "/c/b").unwrap());
+    fs2.replica_mut().apply_ops(ops);
+
+    assert_eq!(fs2.ls("/c").unwrap(), vec!["b"]);
+    assert_eq!(fs1.replica().state(), fs2.replica().state());
+}
+
+// Tests operations that address nodes by parent ID and name.
+#[test]
+fn id_based_ops() {
+    let mut fs = new_fs(1);
+
+    let (home, _) = fs.mkdir_at(&ROOT, "home").unwrap();
+    let (bob, _) = fs.mkdir_at(&home, "bob").unwrap();
+    fs.mkdir_at(&home, "alice").unwrap();
+    assert_eq!(fs.child(&home, "bob"), Some(bob));
+    assert!(matches!(
+        fs.mkdir_at(&home, "bob"),
+        Err(FsError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        fs.mkdir_at(&home, "a/b"),
+        Err(FsError::InvalidPath(_))
+    ));
+
+    let names: Vec<String> = fs.entries(&home).into_iter().map(|e| e.0).collect();
+    assert_eq!(names, vec!["alice", "bob"]);
+
+    fs.rename_at(&home, "bob", &ROOT, "robert").unwrap();
+    assert_eq!(fs.lookup("/robert"), Some(bob));
+    assert!(matches!(
+        fs.rename_at(&ROOT, "home", &home, "x"),
+        Err(FsError::MoveIntoSelf(_))
+    ));
+
+    fs.remove_at(&ROOT, "robert").unwrap();
+    assert_eq!(fs.lookup("/robert"), None);
+    assert!(matches!(
+        fs.remove_at(&ROOT, "robert"),
+        Err(FsError::NotFound(_))
+    ));
+}
+
+// helper: returns a new, empty directory under the system temp dir.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("crdt_tree_{}_{}", name, new_id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// helper: derives an ID from path, so that imports generate stable IDs.
+fn path_id(path: &str) -> TypeId {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Tests importing an on-disk directory tree and exporting it again.
+#[test]
+fn import_export_dir() {
+    let src = temp_dir("src");
+    std::fs::create_dir_all(src.join("a/b/c")).unwrap();
+    std::fs::create_dir_all(src.join("d")).unwrap();
+    std::fs::write(src.join("a/file.txt"), "ignored").unwrap();
+
+    let mut fs1 = new_fs(1);
+    let mut fs2 = new_fs(2);
+    fs1.mkdir("/import").unwrap();
+    fs2.mkdir_all("/import/a").unwrap();
+
+    assert_eq!(fs1.import_dir(&src, "/import", path_id).unwrap().len(), 4);
+    assert!(fs1.import_dir(&src, "/import", path_id).unwrap().is_empty());
+    assert_eq!(fs1.ls("/import/a").unwrap(), vec!["b"]);
+    assert_eq!(fs1.lookup("/import/a/b/c"), Some(path_id("/import/a/b/c")));
+
+    // fs2 already has /import/a, so only b, c and d are created.
+    assert_eq!(fs2.import_dir(&src, "/import", path_id).unwrap().len(), 3);
+    assert_eq!(fs2.lookup("/import/d"), fs1.lookup("/import/d"));
+
+    let dest = temp_dir("dest");
+    fs1.export_dir("/import", &dest).unwrap();
+    assert!(dest.join("a/b/c").is_dir());
+    assert!(dest.join("d").is_dir());
+    assert!(!dest.join("a/file.txt").exists());
+
+    assert_eq!(
+        fs1.import_dir(&src.join("missing"), "/import", path_id)
+            .map_err(|e| matches!(e, FsError::Io(_))),
+        Err(true)
+    );
+
+    std::fs::remove_dir_all(src).unwrap();
+    std::fs::remove_dir_all(dest).unwrap();
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree arbitrary impls
+#[cfg(feature = "arbitrary")]
+mod fuzzing {
+    use arbitrary::{Arbitrary, Unstructured};
+    use crdt_tree::{Clock, OpMove, State};
+
+    type TypeId = u8;
+    type TypeActor = u8;
+    type TypeMeta = u8;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns n pseudo-random bytes, as a fuzzer might supply.
+    fn input(seed: u64, n: usize) -> Vec<u8> {
+        let mut x = seed;
+        (0..n)
+            .map(|_| {
+                x = x
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (x >> 56) as u8
+            })
+            .collect()
+    }
+
+    // Tests that batches of arbitrary ops apply to a valid tree, and
+    // survive a serde round trip.
+    #[test]
+    fn op_batches() {
+        for seed in 0..50 {
+            let bytes = input(seed, 4096);
+            let mut u = Unstructured::new(&bytes);
+            let ops = Vec::<TypeOp>::arbitrary(&mut u).unwrap();
+
+            let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+            state.apply_ops(&ops);
+            assert!(state.tree().check_invariants().is_ok());
+
+            let json = serde_json::to_string(&ops).unwrap();
+            assert_eq!(serde_json::from_str::<Vec<TypeOp>>(&json).unwrap(), ops);
+        }
+    }
+
+    // Tests that clocks use the input's bytes, so inputs of zeros give
+    // zero clocks.
+    #[test]
+    fn clock_from_zeros() {
+        let mut u = Unstructured::new(&[0u8; 16]);
+        let clock = Clock::<TypeActor>::arbitrary(&mut u).unwrap();
+        assert_eq!(clock, Clock::new(0, None));
+    }
+}
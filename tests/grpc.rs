@@ -0,0 +1,67 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree gRPC sync service
+#[cfg(feature = "grpc")]
+mod grpc {
+    use crdt_tree::grpc::{SyncClient, SyncError, SyncServer};
+    use crdt_tree::{ReplicaHandle, TreeReplica};
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+    // helper: serves handle's replica on a local port, returning its url.
+    async fn serve(handle: ReplicaHandle<TypeId, TypeMeta, TypeActor>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(SyncServer::new(handle))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        url
+    }
+
+    // Tests that two replicas converge by syncing over gRPC.
+    #[tokio::test]
+    async fn sync_replicas() {
+        let server = ReplicaHandle::spawn(TypeReplica::new(1));
+        let client = ReplicaHandle::spawn(TypeReplica::new(2));
+        server.move_node(0, "root".into(), 1).await.unwrap();
+        server.move_node(1, "a".into(), 2).await.unwrap();
+        client.move_node(0, "other".into(), 3).await.unwrap();
+
+        let mut sync = SyncClient::connect(serve(server.clone()).await)
+            .await
+            .unwrap();
+        assert_eq!(sync.sync(&client).await.unwrap(), (2, 1));
+        assert_eq!(sync.sync(&client).await.unwrap(), (0, 0));
+
+        let tree = |r: &TypeReplica| r.tree().clone();
+        assert_eq!(
+            server.query(tree).await.unwrap(),
+            client.query(tree).await.unwrap()
+        );
+        let vv = client.query(|r| r.version_vector().clone()).await.unwrap();
+        assert_eq!(sync.exchange_version_vectors(&vv).await.unwrap(), vv);
+        let state = sync.get_snapshot().await.unwrap();
+        assert_eq!(state.tree().num_nodes(), 3);
+
+        // once the server truncates its log, a new replica needs a
+        // snapshot.
+        server.update(|r| r.truncate_log()).await.unwrap();
+        let fresh = ReplicaHandle::spawn(TypeReplica::new(3));
+        assert!(matches!(
+            sync.sync(&fresh).await,
+            Err(SyncError::SnapshotRequired)
+        ));
+    }
+}
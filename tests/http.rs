@@ -0,0 +1,92 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree HTTP/JSON endpoints
+#[cfg(feature = "http")]
+mod http {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::Router;
+    use crdt_tree::http::router;
+    use crdt_tree::{OpMove, ReplicaHandle, State, TreeReplica, VersionVector};
+    use tower::ServiceExt;
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+    // helper: sends a request to app, returning the status and body.
+    async fn call(app: &Router, method: &str, uri: &str, body: String) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    // helper: percent-encodes s for use in a query string.
+    fn encode(s: &str) -> String {
+        s.bytes()
+            .map(|b| match b {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => (b as char).to_string(),
+                _ => format!("%{:02X}", b),
+            })
+            .collect()
+    }
+
+    // Tests that ops pushed and pulled as JSON bring two replicas
+    // together, and that the snapshot is served.
+    #[tokio::test]
+    async fn push_and_pull() {
+        let handle = ReplicaHandle::spawn(TypeReplica::new(1));
+        handle.move_node(0, "root".into(), 1).await.unwrap();
+        let app = router(handle.clone());
+
+        let mut r2 = TypeReplica::new(2);
+        let (status, body) = call(&app, "GET", "/ops", String::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let ops: Vec<TypeOp> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(ops.len(), 1);
+        r2.apply_ops(ops);
+
+        let op = r2.opmove(1, "a".into(), 2);
+        r2.apply_op(op.clone());
+        let body = serde_json::to_string(&vec![op]).unwrap();
+        let (status, _) = call(&app, "POST", "/ops", body).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        // nothing new since r2's version vector.
+        let since = encode(&serde_json::to_string(r2.version_vector()).unwrap());
+        let uri = format!("/ops?since={}", since);
+        let (status, body) = call(&app, "GET", &uri, String::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(serde_json::from_slice::<Vec<TypeOp>>(&body)
+            .unwrap()
+            .is_empty());
+
+        let (status, body) = call(&app, "GET", "/snapshot", String::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let state: State<TypeId, TypeMeta, TypeActor> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(state.tree(), r2.tree());
+
+        let (status, _) = call(&app, "GET", "/ops?since=nope", String::new()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // once the log is truncated, a new replica needs a snapshot.
+        handle.update(|r| r.truncate_log()).await.unwrap();
+        let since = encode(&serde_json::to_string(&VersionVector::<TypeActor>::new()).unwrap());
+        let uri = format!("/ops?since={}", since);
+        let (status, _) = call(&app, "GET", &uri, String::new()).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+#[cfg(any(feature = "indextree", feature = "ego-tree"))]
+use crdt_tree::{Tree, TreeNode};
+
+// helper: returns a tree with children in id order.
+#[cfg(any(feature = "indextree", feature = "ego-tree"))]
+fn new_tree() -> Tree<u64, &'static str> {
+    let mut tree = Tree::new();
+    tree.add_node(1, TreeNode::new(0, "home"));
+    tree.add_node(4, TreeNode::new(1, "carol"));
+    tree.add_node(2, TreeNode::new(1, "alice"));
+    tree.add_node(3, TreeNode::new(1, "bob"));
+    tree.add_node(5, TreeNode::new(2, "docs"));
+    tree.sort_children_by_id();
+    tree
+}
+
+/// tests for conversion to indextree
+#[cfg(feature = "indextree")]
+mod indextree_tests {
+    use super::*;
+
+    // Tests that the arena mirrors the subtree, in child order.
+    #[test]
+    fn to_indextree() {
+        let tree = new_tree();
+        let (arena, root) = tree.to_indextree(&0);
+        assert_eq!(arena.len(), 6);
+        assert_eq!(arena[root].get(), &(0, None));
+
+        let ids: Vec<_> = root.descendants(&arena).map(|n| arena[n].get().0).collect();
+        assert_eq!(ids, vec![0, 1, 2, 5, 3, 4]);
+
+        let home = arena[root].first_child().unwrap();
+        assert_eq!(arena[home].get(), &(1, Some("home")));
+        let names: Vec<_> = home
+            .children(&arena)
+            .map(|n| arena[n].get().1.unwrap())
+            .collect();
+        assert_eq!(names, vec!["alice", "bob", "carol"]);
+
+        // a node as the root, and an unknown parent.
+        let (arena, root) = tree.to_indextree(&2);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena[root].get(), &(2, Some("alice")));
+        let (arena, _) = tree.to_indextree(&9);
+        assert_eq!(arena.len(), 1);
+    }
+}
+
+/// tests for conversion to ego-tree
+#[cfg(feature = "ego-tree")]
+mod ego_tree_tests {
+    use super::*;
+
+    // Tests that the ego tree mirrors the subtree, in child order.
+    #[test]
+    fn to_ego_tree() {
+        let tree = new_tree();
+        let ego = tree.to_ego_tree(&0);
+        assert_eq!(ego.root().value(), &(0, None));
+
+        let ids: Vec<_> = ego.root().descendants().map(|n| n.value().0).collect();
+        assert_eq!(ids, vec![0, 1, 2, 5, 3, 4]);
+
+        let home = ego.root().first_child().unwrap();
+        assert_eq!(home.value(), &(1, Some("home")));
+        let names: Vec<_> = home.children().map(|n| n.value().1.unwrap()).collect();
+        assert_eq!(names, vec!["alice", "bob", "carol"]);
+
+        let ego = tree.to_ego_tree(&2);
+        assert_eq!(ego.root().value(), &(2, Some("alice")));
+        assert_eq!(ego.nodes().count(), 2);
+    }
+}
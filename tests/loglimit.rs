@@ -0,0 +1,100 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree log limits
+use crdt_tree::{LogLimit, LogLimitPolicy, OpMove, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// helper: returns a replica with the given limit, and a peer that has
+// seen one op from it, then gone offline so no entry is causally stable.
+fn new_replicas(policy: LogLimitPolicy) -> (TypeReplica, TypeReplica) {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let op = r2.opmove(0, "root", 1);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+    r1.set_log_limit(Some(LogLimit::new(3, policy)));
+    (r1, r2)
+}
+
+// helper: generates and applies n local ops moving new nodes under root.
+fn apply_local(
+    r: &mut TypeReplica,
+    ids: std::ops::Range<TypeId>,
+) -> Vec<OpMove<u64, &'static str, u8>> {
+    let ops = r.opmoves(ids.map(|id| (1, "n", id)).collect());
+    r.apply_ops_byref(&ops);
+    ops
+}
+
+// Tests that new ops are rejected once the log is full and nothing is
+// causally stable, and accepted again once entries become stable.
+#[test]
+fn reject_when_full() {
+    let (mut r1, mut r2) = new_replicas(LogLimitPolicy::Reject);
+    let ops = apply_local(&mut r1, 2..5);
+    assert_eq!(r1.state().log().len(), 3);
+    assert!(r1.tree().find(&3).is_some());
+    assert!(r1.tree().find(&4).is_none());
+
+    // once r2 catches up, stable entries are truncated to make room.
+    r2.apply_ops_byref(&ops[..2]);
+    r1.apply_op(r2.opmove(1, "m", 9));
+    assert!(r1.tree().find(&9).is_some());
+    assert!(r1.state().log().len() <= 3);
+}
+
+// Tests that the oldest entries are discarded when the log is full, even
+// though not causally stable.
+#[test]
+fn unsafe_truncate_when_full() {
+    let (mut r1, _r2) = new_replicas(LogLimitPolicy::UnsafeTruncate);
+    let ops = apply_local(&mut r1, 2..8);
+    assert_eq!(r1.state().log().len(), 3);
+    assert_eq!(r1.tree().num_nodes(), 7);
+    assert_eq!(r1.state().log()[2].timestamp(), ops[3].timestamp());
+    assert!(r1.take_spilled().is_empty());
+
+    // discarded ops are still skipped when redelivered.
+    r1.apply_ops_byref(&ops);
+    assert_eq!(r1.state().log().len(), 3);
+}
+
+// Tests that entries removed when the log is full are kept for the
+// application, oldest first.
+#[test]
+fn spill_when_full() {
+    let (mut r1, _r2) = new_replicas(LogLimitPolicy::Spill);
+    let ops = apply_local(&mut r1, 2..8);
+    assert_eq!(r1.state().log().len(), 3);
+
+    let spilled: Vec<_> = r1
+        .take_spilled()
+        .into_iter()
+        .map(|e| e.timestamp().clone())
+        .collect();
+    assert_eq!(spilled.len(), 4);
+    assert_eq!(spilled[0].actor_id(), &2);
+    assert_eq!(
+        &spilled[1..],
+        &[
+            ops[0].timestamp().clone(),
+            ops[1].timestamp().clone(),
+            ops[2].timestamp().clone()
+        ][..]
+    );
+    assert!(r1.take_spilled().is_empty());
+
+    // lowering the limit spills straight away.
+    r1.set_log_limit(Some(LogLimit::new(1, LogLimitPolicy::Spill)));
+    assert_eq!(r1.state().log().len(), 1);
+    assert_eq!(r1.take_spilled().len(), 2);
+}
@@ -0,0 +1,106 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree log statistics
+use crdt_tree::{Clock, OpMove, State};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+
+fn op(
+    actor: TypeActor,
+    counter: u64,
+    parent: TypeId,
+    meta: TypeMeta,
+    child: TypeId,
+) -> OpMove<TypeId, TypeMeta, TypeActor> {
+    OpMove::new(Clock::new(actor, Some(counter)), parent, meta, child)
+}
+
+#[test]
+fn log_stats_empty() {
+    let state: State<TypeId, TypeMeta, TypeActor> = State::new();
+    let stats = state.log_stats();
+
+    assert_eq!(stats.entries(), 0);
+    assert!(stats.ops_by_actor().is_empty());
+    assert_eq!(stats.oldest(), None);
+    assert_eq!(stats.newest(), None);
+    assert_eq!(stats.no_ops(), 0);
+}
+
+#[test]
+fn log_stats() {
+    let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+    state.apply_ops_into(vec![
+        op(1, 1, 0, "a", 1),
+        op(2, 2, 1, "b", 2),
+        // ignored, as it would make 1 a child of its own child.
+        op(1, 3, 2, "a", 1),
+        // moves 2 where it already is.
+        op(2, 4, 1, "b", 2),
+        op(1, 5, 0, "b", 2),
+    ]);
+    let stats = state.log_stats();
+
+    assert_eq!(stats.entries(), 5);
+    assert_eq!(stats.ops_by_actor().get(&1), Some(&3));
+    assert_eq!(stats.ops_by_actor().get(&2), Some(&2));
+    assert_eq!(stats.oldest(), Some(&Clock::new(1, Some(1))));
+    assert_eq!(stats.newest(), Some(&Clock::new(1, Some(5))));
+    assert_eq!(stats.no_ops(), 2);
+    assert!(stats.estimated_bytes() > 0);
+}
+
+#[test]
+fn log_stats_counts_no_ops_after_reorder() {
+    // the cycle is only introduced once the earlier op arrives late, and
+    // the later op is redone.
+    let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+    state.apply_ops_into(vec![op(1, 1, 0, "a", 1), op(1, 3, 2, "a", 1)]);
+    assert_eq!(state.log_stats().no_ops(), 0);
+
+    state.apply_op(op(2, 2, 1, "b", 2));
+    assert_eq!(state.log_stats().no_ops(), 1);
+}
+
+#[test]
+fn log_range() {
+    let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+    state.apply_ops_into((1..=5).map(|c| op(1, c, 0, "a", c)).collect());
+
+    let from = Clock::new(1, Some(2));
+    let to = Clock::new(1, Some(4));
+    let counters: Vec<u64> = state
+        .log_range(&from, &to)
+        .map(|e| e.timestamp().counter())
+        .collect();
+    assert_eq!(counters, vec![3, 2]);
+
+    assert_eq!(state.log_range(&to, &from).count(), 0);
+    assert_eq!(state.log_range(&from, &from).len(), 0);
+    let all = Clock::new(1, Some(9));
+    assert_eq!(state.log_range(&Clock::new(1, None), &all).len(), 5);
+}
+
+#[test]
+fn log_after() {
+    let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+    assert_eq!(state.log_after(&Clock::new(1, None)).len(), 0);
+    state.apply_ops_into((1..=5).map(|c| op(1, c, 0, "a", c)).collect());
+
+    // oldest first, as sent to a peer catching up.
+    let counters: Vec<u64> = state
+        .log_after(&Clock::new(1, Some(3)))
+        .rev()
+        .map(|e| e.timestamp().counter())
+        .collect();
+    assert_eq!(counters, vec![4, 5]);
+    assert_eq!(state.log_after(&Clock::new(1, Some(5))).len(), 0);
+    assert_eq!(state.log_after(&Clock::new(1, None)).len(), 5);
+}
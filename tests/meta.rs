@@ -0,0 +1,83 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree metadata records
+use crdt_tree::{ActorOrder, MergeMeta, MergeMetadata, TreeReplica};
+
+crdt_tree::meta_record! {
+    /// file metadata, for the tests.
+    struct FileMeta {
+        name: &'static str,
+        mode: u32,
+        mtime: u64,
+    }
+}
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeReplica = TreeReplica<TypeId, FileMeta, TypeActor, ActorOrder, MergeMetadata>;
+
+fn named(name: &'static str) -> FileMeta {
+    FileMeta {
+        name: Some(name),
+        mode: Some(0o600),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn merge_overwrites_set_fields() {
+    let mut meta = named("a");
+    meta.merge(FileMeta {
+        mode: Some(0o644),
+        mtime: Some(7),
+        ..Default::default()
+    });
+    assert_eq!(meta.name, Some("a"));
+    assert_eq!(meta.mode, Some(0o644));
+    assert_eq!(meta.mtime, Some(7));
+}
+
+#[test]
+fn concurrent_rename_and_chmod_survive() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let op = r1.opmove(0, named("a"), 1);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    let rename = r1.opmove(
+        0,
+        FileMeta {
+            name: Some("b"),
+            ..Default::default()
+        },
+        1,
+    );
+    let chmod = r2.opmove(
+        0,
+        FileMeta {
+            mode: Some(0o644),
+            ..Default::default()
+        },
+        1,
+    );
+    r1.apply_op(rename.clone());
+    r1.apply_op(chmod.clone());
+    r2.apply_op(chmod);
+    r2.apply_op(rename);
+
+    assert_eq!(r1.tree(), r2.tree());
+    let meta = r1.tree().find(&1).unwrap().metadata();
+    assert_eq!(meta.name, Some("b"));
+    assert_eq!(meta.mode, Some(0o644));
+
+    // moving elsewhere replaces the whole record.
+    let op = r1.opmove(5, named("c"), 1);
+    r1.apply_op(op);
+    assert_eq!(r1.tree().find(&1).unwrap().metadata(), &named("c"));
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crdt_tree::migrate::{MigrateError, Migration};
+use crdt_tree::{MemoryStorage, State, StoredReplica, TreeReplica};
+
+type TypeActor = u8;
+type OldState = State<u64, String, TypeActor>;
+type NewState = State<String, Vec<u8>, TypeActor>;
+
+// helper: maps an old ID to a new one.
+fn map_id(id: &u64) -> Result<String, String> {
+    Ok(format!("node-{}", id))
+}
+
+// helper: maps old metadata to new metadata.
+fn map_meta(meta: &String) -> Result<Vec<u8>, String> {
+    Ok(meta.as_bytes().to_vec())
+}
+
+// helper: returns a replica with a few nodes, one moved.
+fn new_replica() -> TreeReplica<u64, String, TypeActor> {
+    let mut r = TreeReplica::new(1);
+    let ops = r.opmoves(vec![
+        (0, "home".to_string(), 1),
+        (1, "bob".to_string(), 2),
+        (1, "alice".to_string(), 3),
+        (3, "bob".to_string(), 2),
+    ]);
+    r.apply_ops(ops);
+    r
+}
+
+// Tests that a migrated state matches one built by applying the
+// migrated ops.
+#[test]
+fn migrate_state() {
+    let r = new_replica();
+    let mut migration = Migration::new(map_id, map_meta);
+
+    let ops: Vec<_> = r
+        .state()
+        .log()
+        .iter()
+        .rev()
+        .map(|e| e.clone().op_into())
+        .collect();
+    let new_ops = migration.ops(&ops).unwrap();
+    let mut expected = NewState::new();
+    expected.apply_ops(&new_ops);
+
+    let old: &OldState = r.state();
+    let migrated: NewState = migration.state(old).unwrap();
+    assert_eq!(migrated, expected);
+    let node = migrated.tree().find(&"node-2".to_string()).unwrap();
+    assert_eq!(node.parent_id(), "node-3");
+    assert_eq!(node.metadata(), b"bob");
+    let oldp = migrated.log()[0].oldp().as_ref().unwrap();
+    assert_eq!(oldp.parent_id(), "node-1");
+
+    let snapshot = r.snapshot(1);
+    let migrated = migration.snapshot(&snapshot).unwrap();
+    assert_eq!(migrated.time(), snapshot.time());
+    assert_eq!(migrated.tree(), expected.tree());
+}
+
+// Tests that a failing mapper stops the migration.
+#[test]
+fn migrate_map_error() {
+    let r = new_replica();
+    let mut migration = Migration::new(
+        |id: &u64| match id {
+            3 => Err(format!("no mapping for {}", id)),
+            _ => map_id(id),
+        },
+        map_meta,
+    );
+    let result: Result<NewState, _> = migration.state(r.state());
+    assert_eq!(result, Err("no mapping for 3".to_string()));
+}
+
+// Tests that a storage is copied with IDs and metadata mapped, and that
+// a replica opened from it matches the original.
+#[test]
+fn migrate_storage() {
+    let mut stored = StoredReplica::open(1, MemoryStorage::new())
+        .unwrap()
+        .with_checkpoint_interval(Some(3));
+    let ops = stored
+        .replica()
+        .opmoves((1..=5).map(|i| (0, format!("n{}", i), i)).collect());
+    stored.apply_ops(ops).unwrap();
+    let (replica, storage) = stored.into_parts();
+
+    let mut migration = Migration::new(map_id, map_meta);
+    let mut new_storage = MemoryStorage::<String, Vec<u8>, TypeActor>::new();
+    let copied = migration.storage(&storage, &mut new_storage).unwrap();
+    assert_eq!(copied, storage.num_ops());
+    assert_eq!(new_storage.num_snapshots(), 1);
+
+    let reopened = StoredReplica::open(1, new_storage).unwrap();
+    let expected: NewState = migration.state(replica.state()).unwrap();
+    assert_eq!(reopened.replica().tree(), expected.tree());
+
+    let err: MigrateError<String> = MigrateError::Map("bad".to_string());
+    assert_eq!(err.to_string(), "mapping failed: bad");
+}
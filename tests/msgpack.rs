@@ -0,0 +1,68 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree MessagePack encoding
+#[cfg(feature = "msgpack")]
+mod msgpack {
+    use crdt_tree::msgpack;
+    use crdt_tree::{OpMove, State, TreeReplica};
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+    type TypeState = State<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns a batch of ops creating n nodes.
+    fn new_ops(n: u64) -> Vec<TypeOp> {
+        let r = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(1);
+        r.opmoves((1..=n).map(|i| (i / 3, format!("node{}", i), i)).collect())
+    }
+
+    // Tests that op batches and states round-trip through MessagePack.
+    #[test]
+    fn round_trip() {
+        let ops = new_ops(50);
+        let bytes = msgpack::to_vec(&ops).unwrap();
+        assert_eq!(msgpack::from_slice::<Vec<TypeOp>>(&bytes).unwrap(), ops);
+        let bytes = msgpack::to_vec_named(&ops).unwrap();
+        assert_eq!(msgpack::from_slice::<Vec<TypeOp>>(&bytes).unwrap(), ops);
+
+        let mut state = TypeState::new();
+        state.apply_ops(&ops);
+        let bytes = msgpack::to_vec(&state).unwrap();
+        assert_eq!(msgpack::from_slice::<TypeState>(&bytes).unwrap(), state);
+
+        assert!(msgpack::from_slice::<TypeState>(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    // Compares encoded sizes with JSON.  Run with --nocapture to see them.
+    #[test]
+    fn size_versus_json() {
+        let ops = new_ops(1000);
+        let mut state = TypeState::new();
+        state.apply_ops(&ops);
+
+        let json_ops = serde_json::to_vec(&ops).unwrap().len();
+        let msgpack_ops = msgpack::to_vec(&ops).unwrap().len();
+        let named_ops = msgpack::to_vec_named(&ops).unwrap().len();
+        let json_state = serde_json::to_vec(&state).unwrap().len();
+        let msgpack_state = msgpack::to_vec(&state).unwrap().len();
+
+        println!(
+            "1000 ops:   json {:>7} msgpack {:>7} msgpack named {:>7}",
+            json_ops, msgpack_ops, named_ops
+        );
+        println!(
+            "1000 nodes: json {:>7} msgpack {:>7}",
+            json_state, msgpack_state
+        );
+
+        assert!(msgpack_ops < named_ops);
+        assert!(named_ops < json_ops);
+        assert!(msgpack_state < json_state);
+    }
+}
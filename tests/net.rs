@@ -0,0 +1,113 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree length-delimited framing and sync messages
+#[cfg(feature = "net")]
+mod net {
+    use crdt_tree::net::{
+        handle_message, read_frame, write_frame, NetError, SyncMessage, MAX_FRAME_LEN,
+    };
+    use crdt_tree::TreeReplica;
+    use std::io::Cursor;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+    type TypeMessage = SyncMessage<TypeId, TypeMeta, TypeActor>;
+
+    // Tests that frames round trip, and a clean end of stream is not an
+    // error but a truncated or oversized frame is.
+    #[test]
+    fn frames() {
+        let r = TypeReplica::new(1);
+        let op = r.opmove(0, "root".to_string(), 1);
+        let msgs = vec![
+            TypeMessage::Hello(r.version_vector().clone()),
+            TypeMessage::Ops(vec![op]),
+            TypeMessage::SnapshotRequired,
+        ];
+        let mut bytes = vec![];
+        for msg in &msgs {
+            write_frame(&mut bytes, msg).unwrap();
+        }
+
+        let mut reader = Cursor::new(&bytes);
+        for msg in &msgs {
+            assert_eq!(
+                read_frame::<_, TypeMessage>(&mut reader).unwrap().as_ref(),
+                Some(msg)
+            );
+        }
+        assert!(read_frame::<_, TypeMessage>(&mut reader).unwrap().is_none());
+
+        let mut reader = Cursor::new(&bytes[..bytes.len() - 1]);
+        read_frame::<_, TypeMessage>(&mut reader).unwrap();
+        read_frame::<_, TypeMessage>(&mut reader).unwrap();
+        assert!(matches!(
+            read_frame::<_, TypeMessage>(&mut reader),
+            Err(NetError::Io(_))
+        ));
+
+        let mut reader = Cursor::new((MAX_FRAME_LEN + 1).to_be_bytes());
+        assert!(matches!(
+            read_frame::<_, TypeMessage>(&mut reader),
+            Err(NetError::FrameTooLarge(len)) if len == MAX_FRAME_LEN + 1
+        ));
+    }
+
+    // Tests that two replicas converge by exchanging Hello messages over
+    // TCP, including ops generated while disconnected.
+    #[test]
+    fn catch_up_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut r1 = TypeReplica::new(1);
+        let op = r1.opmove(0, "root".to_string(), 1);
+        r1.apply_op(op);
+        let mut r2 = TypeReplica::new(2);
+        let op = r2.opmove(0, "other".to_string(), 2);
+        r2.apply_op(op);
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            write_frame(
+                &mut stream,
+                &TypeMessage::Hello(r1.version_vector().clone()),
+            )
+            .unwrap();
+            while let Some(msg) = read_frame(&mut stream).unwrap() {
+                if let Some(reply) = handle_message(&mut r1, msg) {
+                    write_frame(&mut stream, &reply).unwrap();
+                }
+            }
+            r1
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write_frame(
+            &mut stream,
+            &TypeMessage::Hello(r2.version_vector().clone()),
+        )
+        .unwrap();
+        // the server's Hello, then its answer to ours.
+        for _ in 0..2 {
+            let msg = read_frame(&mut stream).unwrap().unwrap();
+            if let Some(reply) = handle_message(&mut r2, msg) {
+                write_frame(&mut stream, &reply).unwrap();
+            }
+        }
+        drop(stream);
+
+        let r1 = server.join().unwrap();
+        assert_eq!(r1.tree(), r2.tree());
+        assert_eq!(r2.tree().num_nodes(), 2);
+        assert_eq!(r1.version_vector(), r2.version_vector());
+    }
+}
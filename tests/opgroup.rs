@@ -0,0 +1,110 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree op groups
+use crdt_tree::{Clock, DriftGuard, DriftPolicy, OpGroup, OpGroupError, OpMove, TreeReplica};
+use std::time::Duration;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+fn op(actor: TypeActor, counter: u64, child: TypeId) -> TypeOp {
+    OpMove::new(Clock::new(actor, Some(counter)), 0, "a", child)
+}
+
+#[test]
+fn opgroup_validation() {
+    let group: Result<OpGroup<TypeId, TypeMeta, TypeActor>, _> = OpGroup::new(vec![]);
+    assert_eq!(group, Err(OpGroupError::Empty));
+    assert_eq!(
+        OpGroup::new(vec![op(1, 1, 1), op(2, 2, 2)]),
+        Err(OpGroupError::MixedActors)
+    );
+    assert_eq!(
+        OpGroup::new(vec![op(1, 1, 1), op(1, 3, 2)]),
+        Err(OpGroupError::NotConsecutive)
+    );
+    assert_eq!(
+        OpGroup::new(vec![op(1, 2, 1), op(1, 1, 2)]),
+        Err(OpGroupError::NotConsecutive)
+    );
+    let group = OpGroup::new(vec![op(1, 4, 1), op(1, 5, 2)]).unwrap();
+    assert_eq!(group.ops().len(), 2);
+}
+
+#[test]
+fn opgroup_serde_validates() {
+    // owned metadata, to deserialize.
+    let op = |actor, counter, child| {
+        OpMove::new(Clock::new(actor, Some(counter)), 0, "a".to_string(), child)
+    };
+    let group: OpGroup<TypeId, String, TypeActor> =
+        OpGroup::new(vec![op(1, 4, 1), op(1, 5, 2)]).unwrap();
+    let json = serde_json::to_string(&group).unwrap();
+    assert_eq!(
+        serde_json::from_str::<OpGroup<_, _, _>>(&json).unwrap(),
+        group
+    );
+
+    let json = serde_json::to_string(&vec![op(1, 4, 1), op(2, 5, 2)]).unwrap();
+    assert!(serde_json::from_str::<OpGroup<TypeId, String, TypeActor>>(&json).is_err());
+}
+
+#[test]
+fn apply_group_all_or_nothing() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let group = r1
+        .opgroup(vec![
+            (0, "dir", 1),
+            (0, "renamed", 1),
+            (1, "f", 2),
+            (1, "g", 3),
+        ])
+        .unwrap();
+    assert!(r1.opgroup(vec![]).is_err());
+
+    assert!(r1.apply_group(group.clone()));
+    assert!(r2.apply_group(group.clone()));
+    assert_eq!(r1.tree(), r2.tree());
+    assert_eq!(r1.tree().find(&1).unwrap().metadata(), &"renamed");
+    assert_eq!(r1.tree().children(&1).len(), 2);
+    let counters: Vec<u64> = r1
+        .state()
+        .log()
+        .iter()
+        .map(|e| e.timestamp().counter())
+        .collect();
+    assert_eq!(counters, vec![4, 3, 2, 1]);
+
+    // redelivered.
+    assert!(!r2.apply_group(group));
+    assert_eq!(r2.state().log().len(), 4);
+}
+
+#[test]
+fn apply_group_rejects_whole_group() {
+    let mut r1 = TypeReplica::new(1);
+    r1.set_drift_guard(Some(DriftGuard::new(
+        Duration::from_secs(60),
+        DriftPolicy::Reject,
+    )));
+    let far_future = u64::MAX / 2;
+    let group = OpGroup::new(vec![
+        op(2, 1, 1),
+        op(2, 2, 2).with_wall_time(far_future),
+        op(2, 3, 3),
+    ])
+    .unwrap();
+
+    assert!(!r1.apply_group(group));
+    assert_eq!(r1.tree().num_nodes(), 0);
+    assert!(r1.state().log().is_empty());
+}
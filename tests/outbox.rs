@@ -0,0 +1,53 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree outbox
+use crdt_tree::TreeReplica;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// Tests that local ops are held until acknowledged by every peer.
+#[test]
+fn unacked_until_all_ack() {
+    let mut r1 = TypeReplica::new(1);
+
+    // without peers, nothing is held.
+    r1.apply_op(r1.opmove(0, "root", 1));
+    assert!(r1.outbox().is_empty());
+
+    r1.outbox_mut().add_peer(2);
+    r1.outbox_mut().add_peer(3);
+    let ops = r1.opmoves(vec![(1, "a", 2), (1, "b", 3), (1, "c", 4)]);
+    r1.apply_ops_byref(&ops);
+
+    // remote ops are not held.
+    let mut r2 = TypeReplica::new(2);
+    r2.apply_ops_byref(&ops);
+    r1.apply_op(r2.opmove(2, "d", 5));
+    assert_eq!(r1.outbox().len(), 3);
+    assert_eq!(r1.unacked_ops(&2), ops.iter().collect::<Vec<_>>());
+    assert!(r1.unacked_ops(&9).is_empty());
+
+    assert!(r1.outbox_mut().ack(&2, ops[1].timestamp()));
+    assert!(!r1.outbox_mut().ack(&9, ops[1].timestamp()));
+    assert_eq!(r1.unacked_ops(&2), vec![&ops[0], &ops[2]]);
+    assert_eq!(r1.unacked_ops(&3).len(), 3);
+
+    // acked by both peers, so dropped.
+    assert!(r1.outbox_mut().ack(&3, ops[1].timestamp()));
+    assert_eq!(r1.outbox().len(), 2);
+    assert!(!r1.outbox_mut().ack(&3, ops[1].timestamp()));
+
+    // removing a peer drops ops only it had not acked.
+    r1.outbox_mut().ack(&2, ops[0].timestamp());
+    r1.outbox_mut().remove_peer(&3);
+    assert_eq!(r1.unacked_ops(&2), vec![&ops[2]]);
+    assert_eq!(r1.outbox().peers().collect::<Vec<_>>(), vec![&2]);
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree parallel batch decoding
+#[cfg(all(feature = "codec", feature = "rayon"))]
+mod parallel {
+    use crdt_tree::codec;
+    use crdt_tree::parallel::{decode_batch, BatchError};
+    use crdt_tree::{OpMove, State, TreeReplica};
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns encoded ops from two replicas, newest first, so
+    // out of order.
+    fn encoded_batch() -> (Vec<TypeOp>, Vec<Vec<u8>>) {
+        let mut r1 = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(1);
+        let mut ops = vec![r1.opmove(0, "root".to_string(), 1)];
+        r1.apply_op(ops[0].clone());
+        for i in 2..50 {
+            let op = r1.opmove(1 + i % 3, format!("n{}", i), i);
+            r1.apply_op(op.clone());
+            ops.push(op);
+        }
+        let mut messages: Vec<Vec<u8>> = ops.iter().map(|op| codec::encode(op).unwrap()).collect();
+        messages.reverse();
+        (ops, messages)
+    }
+
+    // a validator accepting ops signed by actor 1 only.
+    fn validate(_bytes: &[u8], op: &TypeOp) -> Result<(), String> {
+        match op.timestamp().actor_id() {
+            1 => Ok(()),
+            a => Err(format!("unknown signer {}", a)),
+        }
+    }
+
+    // Tests that a batch decoded in parallel applies as the ops would one
+    // at a time.
+    #[test]
+    fn apply_encoded_batch() {
+        let (ops, messages) = encoded_batch();
+        let decoded = decode_batch(&messages, validate).unwrap();
+        assert_eq!(decoded.len(), ops.len());
+        assert_eq!(decoded.last(), ops.first());
+
+        let mut expected: State<TypeId, TypeMeta, TypeActor> = State::new();
+        expected.apply_ops(&ops);
+        let mut state: State<TypeId, TypeMeta, TypeActor> = State::new();
+        assert_eq!(
+            state.apply_encoded_batch(&messages, validate),
+            Ok(ops.len())
+        );
+        assert_eq!(state, expected);
+
+        let mut r2 = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+        let report = r2.apply_encoded_batch(&messages, validate).unwrap();
+        assert_eq!(report.total(), ops.len());
+        assert_eq!(r2.tree(), expected.tree());
+    }
+
+    // Tests that the first bad message in batch order is reported, and
+    // nothing is applied.
+    #[test]
+    fn batch_errors() {
+        let (_, mut messages) = encoded_batch();
+        let mut r2 = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+        let forged = r2.opmove(0, "forged".to_string(), 99);
+        messages.insert(30, codec::encode(&forged).unwrap());
+        let truncated = messages[40][..4].to_vec();
+        messages.insert(40, truncated);
+        messages.insert(10, codec::encode(&forged).unwrap());
+
+        let err = r2.apply_encoded_batch(&messages, validate).unwrap_err();
+        assert_eq!(
+            err,
+            BatchError::Invalid {
+                index: 10,
+                error: "unknown signer 2".to_string()
+            }
+        );
+        assert_eq!(r2.tree().num_nodes(), 0);
+
+        messages.remove(10);
+        messages.remove(30);
+        let err =
+            decode_batch::<_, TypeId, TypeMeta, TypeActor, _, _>(&messages, validate).unwrap_err();
+        assert_eq!(err.index(), 39);
+        assert!(matches!(err, BatchError::Decode { .. }));
+        assert!(err.to_string().starts_with("message 39"));
+    }
+}
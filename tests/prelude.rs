@@ -0,0 +1,42 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree prelude and type aliases
+use crdt_tree::prelude::*;
+
+// Tests that a replica can be declared with only its metadata type.
+#[test]
+fn u64_replica_with_string_meta() {
+    let mut r1: U64TreeReplica<StringMeta> = TreeReplica::new(1);
+    let mut r2: U64TreeReplica<StringMeta> = TreeReplica::new(2);
+    let ops: Vec<U64OpMove<StringMeta>> =
+        r1.opmoves(vec![(0, "home".into(), 1), (1, "bob".into(), 2)]);
+    r1.apply_ops(ops.clone());
+    r2.apply_ops(ops);
+    assert_eq!(r1.tree(), r2.tree());
+
+    let name = r1.tree().find(&2).unwrap().metadata();
+    assert_eq!(name, "bob");
+    assert_eq!(name.len(), 3);
+    assert_eq!(name.to_string(), "bob");
+    assert_eq!(serde_json::to_string(name).unwrap(), "\"bob\"");
+    let name: StringMeta = serde_json::from_str("\"alice\"").unwrap();
+    assert_eq!(name.into_string(), "alice");
+
+    let state: &U64State<StringMeta> = r1.state();
+    assert_eq!(state.log().len(), 2);
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_replica() {
+    let mut r1: UuidTreeReplica<StringMeta> = TreeReplica::new(Uuid::new_v4());
+    let (root, child) = (Uuid::new_v4(), Uuid::new_v4());
+    let op = r1.opmove(root, StringMeta::new("docs"), child);
+    r1.apply_op(op);
+    assert_eq!(r1.tree().children(&root), vec![child]);
+    assert_eq!(r1.tree().find(&child).unwrap().metadata().as_str(), "docs");
+}
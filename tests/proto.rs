@@ -0,0 +1,87 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree protobuf conversions
+#[cfg(feature = "protobuf")]
+mod proto {
+    use crdt_tree::proto::{ProtoError, ProtoOpMove, ProtoState};
+    use crdt_tree::{OpMove, State, TreeReplica};
+    use prost::Message;
+    use std::convert::TryFrom;
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+    type TypeState = State<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns a replica with a few nodes, one of them moved by a
+    // conditional op with a provenance.
+    fn new_replica() -> TreeReplica<TypeId, TypeMeta, TypeActor> {
+        let mut r = TreeReplica::new(1);
+        let mut ops = r.opmoves(vec![
+            (0, "home".to_string(), 1),
+            (1, "bob".to_string(), 2),
+            (1, "alice".to_string(), 3),
+            (2, "alice".to_string(), 3),
+        ]);
+        let moved = ops
+            .pop()
+            .unwrap()
+            .with_expected_parent(1)
+            .with_provenance(b"laptop".to_vec());
+        ops.push(moved);
+        r.apply_ops(ops);
+        r
+    }
+
+    // Tests that ops and states survive a protobuf round-trip, and have
+    // the same serde representation afterwards.
+    #[test]
+    fn round_trip() {
+        let r = new_replica();
+
+        for entry in r.state().log() {
+            let op = entry.clone().op_into();
+            let bytes = ProtoOpMove::from(&op).encode_to_vec();
+            let decoded = TypeOp::try_from(ProtoOpMove::decode(&bytes[..]).unwrap()).unwrap();
+            assert_eq!(decoded, op);
+            assert_eq!(
+                serde_json::to_value(&decoded).unwrap(),
+                serde_json::to_value(&op).unwrap()
+            );
+        }
+
+        let bytes = ProtoState::from(r.state()).encode_to_vec();
+        let decoded = TypeState::try_from(ProtoState::decode(&bytes[..]).unwrap()).unwrap();
+        assert_eq!(&decoded, r.state());
+        assert_eq!(
+            serde_json::to_value(decoded.log()).unwrap(),
+            serde_json::to_value(r.state().log()).unwrap()
+        );
+    }
+
+    // Tests that messages with missing or invalid fields are rejected.
+    #[test]
+    fn rejects_bad_messages() {
+        let r = new_replica();
+        let op = r.state().log()[0].clone().op_into();
+
+        let mut msg = ProtoOpMove::from(&op);
+        msg.child_id = vec![1, 2, 3];
+        assert_eq!(
+            TypeOp::try_from(msg),
+            Err(ProtoError::InvalidBytes("child_id"))
+        );
+
+        let mut msg = ProtoOpMove::from(&op);
+        msg.timestamp = None;
+        assert_eq!(
+            TypeOp::try_from(msg),
+            Err(ProtoError::MissingField("timestamp"))
+        );
+    }
+}
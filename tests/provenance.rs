@@ -0,0 +1,63 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree op provenance
+use crdt_tree::{AuditFilter, Clock, OpMove, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = String;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+// Tests that generated ops carry the replica's provenance, and that it
+// survives the log.
+#[test]
+fn stamp_provenance() {
+    let mut r1 = TypeReplica::new(1);
+    assert_eq!(r1.opmove(0, "a".to_string(), 1).provenance(), None);
+
+    r1.set_provenance(Some(b"phone/1.2".to_vec()));
+    let op = r1.opmove(0, "a".to_string(), 1);
+    assert_eq!(op.provenance(), Some(&b"phone/1.2"[..]));
+    assert!(r1
+        .opmoves(vec![(0, "b".to_string(), 2)])
+        .iter()
+        .all(|op| op.provenance() == Some(&b"phone/1.2"[..])));
+
+    r1.apply_op(op.clone());
+    let entry = &r1.state().log()[0];
+    assert_eq!(entry.provenance(), Some(&b"phone/1.2"[..]));
+    assert_eq!(entry.clone().op_into(), op);
+
+    let records = r1.state().audit_log(&AuditFilter::new());
+    assert_eq!(records[0].provenance, Some(b"phone/1.2".to_vec()));
+
+    r1.set_provenance(None);
+    assert_eq!(r1.opmove(0, "c".to_string(), 3).provenance(), None);
+}
+
+// Tests that provenance does not affect the tree, and that ops serialized
+// without it deserialize.
+#[test]
+fn provenance_ignored() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let op = OpMove::new(Clock::new(3, Some(1)), 0, "a".to_string(), 1);
+    r1.apply_op(op.clone().with_provenance(b"laptop".to_vec()));
+    r2.apply_op(op.with_provenance(b"tablet".to_vec()));
+    assert_eq!(r1.tree(), r2.tree());
+
+    let json = r#"{"timestamp":{"actor_id":1,"counter":1},"parent_id":0,
+        "metadata":"a","child_id":1,"wall_time":null,"expected_parent_id":null}"#;
+    let op: TypeOp = serde_json::from_str(json).unwrap();
+    assert_eq!(op.provenance(), None);
+
+    let op = op.with_provenance(vec![1, 2, 3]);
+    let json = serde_json::to_string(&op).unwrap();
+    assert_eq!(serde_json::from_str::<TypeOp>(&json).unwrap(), op);
+}
@@ -24,7 +24,7 @@ struct OperationList {
 impl Iterator for OperationList {
     type Item = OpMove<TypeId, TypeMeta, TypeActor>;
     fn next(&mut self) -> Option<OpMove<TypeId, TypeMeta, TypeActor>> {
-        self.ops.get(0).cloned()
+        self.ops.first().cloned()
     }
 }
 
@@ -57,13 +57,13 @@ impl Arbitrary for OperationList {
             }
         };
 
-        let mut clock = Clock::arbitrary(g);
+        let mut clock = Clock::new(TypeActor::arbitrary(g), Some(u64::arbitrary(g)));
         let mut nodes: Vec<TypeId> = Vec::new();
         let mut parent_id = TypeId::arbitrary(g);
 
         let mut ops: Vec<OpMove<TypeId, TypeMeta, TypeActor>> = Vec::new();
         for _ in 0..size {
-            let next_id = if nodes.len() > 5 && rand::random::<usize>() % 2 == 0 {
+            let next_id = if nodes.len() > 5 && rand::random::<bool>() {
                 nodes[rand::random::<usize>() % nodes.len()]
             } else {
                 TypeId::arbitrary(g)
@@ -105,8 +105,8 @@ fn acyclic(s: &State<TypeId, TypeMeta, TypeActor>) -> bool {
     let tree = s.tree();
 
     // Iterate all tree nodes and check if any node is an ancestor of itself.
-    for (child_id, _) in tree.clone().into_iter() {
-        if tree.is_ancestor(&child_id, &child_id) {
+    for (child_id, _) in tree.iter() {
+        if tree.is_ancestor(child_id, child_id) {
             return false;
         }
     }
@@ -120,8 +120,8 @@ fn parent_unique(s: &State<TypeId, TypeMeta, TypeActor>) -> bool {
 
     // Iterate all tree nodes and store count of each child_id, parent_id pair.
     // If any pair is found to exist more than once, the invariant is broken.
-    for (child_id, tn) in s.tree().clone().into_iter() {
-        let key = (child_id, *tn.parent_id());
+    for (child_id, tn) in s.iter() {
+        let key = (*child_id, *tn.parent_id());
         let cnt = cnts.get(&key).unwrap_or(&0) + 1;
         cnts.insert(key, cnt);
 
@@ -141,6 +141,14 @@ fn state_from_ops(oplist: &OperationList) -> State<TypeId, TypeMeta, TypeActor>
     s
 }
 
+// helper: checks that each node's top-level parent, the ancestor that is
+// not itself a node, is one of top_level.
+fn connected(s: &State<TypeId, TypeMeta, TypeActor>, top_level: &[TypeId]) -> bool {
+    s.iter().all(|(child_id, _)| {
+        matches!(s.tree().path_to_root(child_id).last(), Some(top) if top_level.contains(top))
+    })
+}
+
 // helper: checks if operation lists overlap, ie use the same actor_id.
 fn ops_overlap(o1: &OperationList, o2: &OperationList) -> bool {
     !o1.ops.is_empty()
@@ -227,7 +235,9 @@ quickcheck::quickcheck! {
         let mut r2 = state_from_ops(&o2);
         r2.apply_ops(&o1.ops);
 
-        let truth = acyclic(&r1) && acyclic(&r2);
+        let truth = acyclic(&r1) && acyclic(&r2)
+            && r1.tree().check_invariants().is_ok()
+            && r2.tree().check_invariants().is_ok();
 
         TestResult::from_bool(truth)
     }
@@ -260,6 +270,34 @@ quickcheck::quickcheck! {
         TestResult::from_bool(truth)
     }
 
+    // tests that in strict mode merged trees stay connected to the
+    // top-level parents, ie the first parent of each list.
+    fn prop_strict_connected(o1: OperationList, o2: OperationList) -> TestResult {
+
+        // discard if o1 actor is same as o2 actor
+        if ops_overlap(&o1, &o2) {
+            return TestResult::discard();
+        }
+
+        let top_level: Vec<TypeId> = o1.ops.first().into_iter()
+            .chain(o2.ops.first())
+            .map(|op| *op.parent_id())
+            .collect();
+
+        let mut r1: State<TypeId, TypeMeta, TypeActor> = State::new();
+        r1.set_strict(Some(top_level.clone()));
+        r1.apply_ops(&o1.ops);
+        r1.apply_ops(&o2.ops);
+
+        let mut r2: State<TypeId, TypeMeta, TypeActor> = State::new();
+        r2.set_strict(Some(top_level.clone()));
+        r2.apply_ops(&o2.ops);
+        r2.apply_ops(&o1.ops);
+
+        let truth = r1 == r2 && connected(&r1, &top_level);
+        TestResult::from_bool(truth)
+    }
+
     // tests that the operation log is always in descending order
     // (even after applying ops from other replica)
     fn prop_log_descending(o1: OperationList, o2: OperationList) -> TestResult {
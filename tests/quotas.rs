@@ -0,0 +1,78 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree structural quotas
+use crdt_tree::{Clock, OpMove, Quotas, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+fn setup(id: TypeActor, quotas: Quotas) -> TypeReplica {
+    let mut r = TypeReplica::new(id);
+    r.set_quotas(quotas);
+    r
+}
+
+#[test]
+fn max_nodes_and_children() {
+    let mut r1 = setup(1, Quotas::new().with_max_nodes(3).with_max_children(2));
+    let ops = r1.opmoves(vec![(0, "a", 1), (0, "b", 2), (0, "c", 3), (1, "d", 4)]);
+    r1.apply_ops(ops);
+    // 3 exceeds the children of 0.
+    assert_eq!(r1.tree().find(&3), None);
+    assert_eq!(r1.tree().num_nodes(), 3);
+    assert_eq!(r1.tree().num_children(&0), 2);
+
+    // 5 exceeds the nodes, but 4 may move and be renamed.
+    let ops = r1.opmoves(vec![(0, "e", 5), (2, "d", 4), (2, "x", 4)]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().find(&5), None);
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &2);
+    assert_eq!(r1.tree().find(&4).unwrap().metadata(), &"x");
+}
+
+#[test]
+fn max_depth_counts_moved_subtree() {
+    let mut r1 = setup(1, Quotas::new().with_max_depth(3));
+    let ops = r1.opmoves(vec![
+        (0, "a", 1),
+        (1, "b", 2),
+        (2, "c", 3),
+        (3, "d", 4),
+        (0, "e", 5),
+        (5, "f", 6),
+    ]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().find(&4), None);
+    assert_eq!(r1.tree().max_depth(), 3);
+
+    // 5 has a child, so fits under 1 but not under 2.
+    let ops = r1.opmoves(vec![(2, "e", 5), (1, "e", 5)]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().find(&5).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree().depth(&6), Some(3));
+}
+
+#[test]
+fn quotas_converge() {
+    // concurrent creations, only one of which fits.
+    let quotas = Quotas::new().with_max_children(1);
+    let mut r1 = setup(1, quotas);
+    let mut r2 = setup(2, quotas);
+    let op1 = r1.opmove(0, "a", 1);
+    let op2: OpMove<TypeId, TypeMeta, TypeActor> = OpMove::new(Clock::new(2, Some(1)), 0, "b", 2);
+    r1.apply_op(op1.clone());
+    r1.apply_op(op2.clone());
+    r2.apply_op(op2);
+    r2.apply_op(op1);
+
+    assert_eq!(r1.tree(), r2.tree());
+    assert!(r1.tree().find(&1).is_some());
+    assert_eq!(r1.tree().find(&2), None);
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for exporting and importing a whole crdt-tree replica
+use crdt_tree::{
+    ActorOrder, CausalOpMove, DriftGuard, DriftPolicy, ImportError, LogOpMove, MergeMetadata,
+    OpMove, Quotas, ReplicaExport, Resolve, Tree, TreeReplica, EXPORT_VERSION,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = String;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeExport = ReplicaExport<TypeId, TypeMeta, TypeActor>;
+
+// Tests that an imported replica carries on exactly as the exported one,
+// including its clocks, pending causal ops, undo stack and settings.
+#[test]
+fn export_import_roundtrip() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "a".into(), 1), (1, "b".into(), 2)]);
+    r1.apply_ops(ops.clone());
+    r2.apply_ops(ops);
+    let op = r2.opmove(0, "c".into(), 3);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+    r1.truncate_log();
+
+    // a causal op from r2 that r1 can not apply yet.
+    let context = r2.causal_context();
+    let missing = r2.opmove(3, "d".into(), 4);
+    r2.apply_op(missing.clone());
+    let causal = r2.causal_opmove(4, "e".into(), 5);
+    assert_eq!(r1.apply_causal_op(causal), 0);
+
+    let op = r1.opmove(1, "f".into(), 3);
+    r1.apply_op(op);
+    r1.set_wall_clock(true);
+    r1.set_provenance(Some(b"laptop".to_vec()));
+    r1.set_drift_guard(Some(DriftGuard::new(
+        Duration::from_secs(60),
+        DriftPolicy::Reject,
+    )));
+    let quotas = Quotas::default().with_max_depth(8);
+    r1.set_quotas(quotas);
+
+    let json = serde_json::to_string(&r1.export()).unwrap();
+    let export: TypeExport = serde_json::from_str(&json).unwrap();
+    assert_eq!(export.version(), EXPORT_VERSION);
+    assert_eq!(export, r1.export());
+    let mut imported = TypeReplica::import(export).unwrap();
+    assert_eq!(imported.export(), r1.export());
+
+    assert_eq!(imported.id(), &1);
+    assert_eq!(imported.time(), r1.time());
+    assert_eq!(imported.state(), r1.state());
+    assert_eq!(imported.version_vector(), r1.version_vector());
+    assert_eq!(
+        imported.causally_stable_threshold(),
+        r1.causally_stable_threshold()
+    );
+    assert_eq!(imported.pending_causal_ops(), r1.pending_causal_ops());
+    assert_eq!(imported.state().quotas(), &quotas);
+    for op in r2.state().log().iter().cloned().map(OpMove::from) {
+        assert_eq!(
+            imported.has_seen(op.timestamp()),
+            r1.has_seen(op.timestamp())
+        );
+    }
+
+    // the undo stack and settings survive.
+    assert!(r1.can_undo());
+    assert!(imported.can_undo());
+    let op = imported.opmove(0, "g".into(), 7);
+    assert!(op.wall_time().is_some());
+    assert_eq!(op.provenance(), Some(&b"laptop"[..]));
+
+    // the pending op is applied once its dependency arrives.
+    let missing = CausalOpMove::new(missing, context);
+    assert_eq!(imported.apply_causal_op(missing), 2);
+    assert_eq!(imported.tree().find(&5).unwrap().parent_id(), &4);
+}
+
+// Tests that an export made with another format version is rejected.
+#[test]
+fn import_rejects_unknown_version() {
+    let r = TypeReplica::new(1);
+    let mut json: serde_json::Value = serde_json::to_value(r.export()).unwrap();
+    json["version"] = (EXPORT_VERSION + 1).into();
+    let export: TypeExport = serde_json::from_value(json).unwrap();
+    assert_eq!(
+        TypeReplica::import(export).unwrap_err(),
+        ImportError::UnsupportedVersion(EXPORT_VERSION + 1)
+    );
+}
+
+crdt_tree::meta_record! {
+    /// file metadata, for the tests.
+    #[derive(Serialize, Deserialize)]
+    struct FileMeta {
+        mode: u32,
+    }
+}
+
+fn mode(mode: u32) -> FileMeta {
+    FileMeta { mode: Some(mode) }
+}
+
+// Tests that metadata merged by MergeMetadata survives an export, and the
+// entries are still undone and redone correctly after ::import().
+#[test]
+fn export_import_merged_metadata() {
+    type Replica = TreeReplica<TypeId, FileMeta, TypeActor, ActorOrder, MergeMetadata>;
+    let mut r1 = Replica::new(1);
+    let r2 = Replica::new(2);
+    let ops = r1.opmoves(vec![(0, mode(1), 1), (0, mode(2), 1)]);
+    let earlier = r2.opmove(3, mode(3), 4);
+    r1.apply_ops(ops);
+
+    let json = serde_json::to_string(&r1.export()).unwrap();
+    let export: ReplicaExport<TypeId, FileMeta, TypeActor> = serde_json::from_str(&json).unwrap();
+    for export in [r1.export(), export] {
+        let mut imported = Replica::import(export).unwrap();
+        assert_eq!(imported.tree().find(&1).unwrap().metadata(), &mode(2));
+        assert_eq!(imported.state(), r1.state());
+
+        // an op from the past undoes and redoes the merged entries.
+        let mut original = r1.clone();
+        original.apply_op(earlier.clone());
+        imported.apply_op(earlier.clone());
+        assert_eq!(imported.state(), original.state());
+        assert_eq!(imported.undo(), original.undo());
+        assert_eq!(imported.state(), original.state());
+    }
+}
+
+// moves a node that would introduce a cycle to LOST_AND_FOUND.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LostAndFound;
+
+const LOST_AND_FOUND: TypeId = 99;
+
+impl Resolve<TypeId, TypeMeta, TypeActor> for LostAndFound {
+    fn on_cycle(
+        _tree: &Tree<TypeId, TypeMeta>,
+        _op: &LogOpMove<TypeId, TypeMeta, TypeActor>,
+    ) -> Option<TypeId> {
+        Some(LOST_AND_FOUND)
+    }
+}
+
+// Tests that a node redirected by Resolve::on_cycle() survives an export,
+// and the entry is still undone and redone correctly after ::import().
+#[test]
+fn export_import_redirected_cycle() {
+    type Replica = TreeReplica<TypeId, TypeMeta, TypeActor, ActorOrder, LostAndFound>;
+    let mut r1 = Replica::new(1);
+    let r2 = Replica::new(2);
+    let ops = r1.opmoves(vec![
+        (0, "a".into(), 1),
+        (1, "b".into(), 2),
+        (2, "c".into(), 1),
+    ]);
+    let earlier = r2.opmove(3, "d".into(), 4);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &LOST_AND_FOUND);
+
+    let json = serde_json::to_string(&r1.export()).unwrap();
+    let export: TypeExport = serde_json::from_str(&json).unwrap();
+    for export in [r1.export(), export] {
+        let mut imported = Replica::import(export).unwrap();
+        assert_eq!(
+            imported.tree().find(&1).unwrap().parent_id(),
+            &LOST_AND_FOUND
+        );
+        assert_eq!(imported.state(), r1.state());
+
+        // an op from the past undoes and redoes the redirected entry.
+        let mut original = r1.clone();
+        original.apply_op(earlier.clone());
+        imported.apply_op(earlier.clone());
+        assert_eq!(imported.state(), original.state());
+        assert_eq!(imported.undo(), original.undo());
+        assert_eq!(imported.state(), original.state());
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree replica handle
+#[cfg(feature = "tokio")]
+mod replicahandle {
+    use crdt_tree::{HandleError, ReplicaHandle, TreeReplica};
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+    // Tests that requests from concurrent tasks are serialized, and the
+    // replica is returned on stop.
+    #[tokio::test]
+    async fn serialized_requests() {
+        let handle = ReplicaHandle::spawn(TypeReplica::new(1));
+        handle.move_node(0, "root".to_string(), 1).await.unwrap();
+
+        let tasks: Vec<_> = (2..12)
+            .map(|id| {
+                let handle = handle.clone();
+                tokio::spawn(async move { handle.move_node(1, id.to_string(), id).await })
+            })
+            .collect();
+        let mut ops = vec![];
+        for task in tasks {
+            ops.push(task.await.unwrap().unwrap());
+        }
+
+        // each op got its own timestamp.
+        ops.sort_by(|a, b| a.timestamp().cmp(b.timestamp()));
+        ops.dedup_by(|a, b| a.timestamp() == b.timestamp());
+        assert_eq!(ops.len(), 10);
+        let snapshot = handle.read_snapshot().await.unwrap();
+        assert_eq!(snapshot.tree().children(&1).len(), 10);
+
+        // ops from a peer.
+        let mut r2 = TypeReplica::new(2);
+        let op = r2.opmove(0, "other".to_string(), 20);
+        r2.apply_op(op.clone());
+        handle.apply_op(op).await.unwrap();
+        let n = handle.query(|r| r.tree().num_nodes()).await.unwrap();
+        assert_eq!(n, 12);
+        let time = handle
+            .update(|r| {
+                r.set_wall_clock(true);
+                r.time().counter()
+            })
+            .await
+            .unwrap();
+        assert_eq!(time, 11);
+
+        let other = handle.clone();
+        let replica = handle.stop().await.unwrap();
+        assert_eq!(replica.tree().num_nodes(), 12);
+        assert_eq!(other.query(|r| *r.id()).await, Err(HandleError::Stopped));
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree conflict resolution strategies
+use crdt_tree::{
+    ActorOrder, ConflictKind, LogOpMove, MergeMeta, MergeMetadata, OpMove, Resolve, Tree, TreeNode,
+    TreeReplica,
+};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+
+const LOST_AND_FOUND: TypeId = 99;
+const PRIMARY: TypeActor = 1;
+
+// moves a node that would introduce a cycle to LOST_AND_FOUND.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LostAndFound;
+
+impl Resolve<TypeId, TypeMeta, TypeActor> for LostAndFound {
+    fn on_cycle(
+        _tree: &Tree<TypeId, TypeMeta>,
+        _op: &LogOpMove<TypeId, TypeMeta, TypeActor>,
+    ) -> Option<TypeId> {
+        Some(LOST_AND_FOUND)
+    }
+}
+
+// only the primary actor may move a node once it is created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PrimaryOnly;
+
+impl Resolve<TypeId, TypeMeta, TypeActor> for PrimaryOnly {
+    fn replaces(
+        _tree: &Tree<TypeId, TypeMeta>,
+        op: &LogOpMove<TypeId, TypeMeta, TypeActor>,
+        _current: &TreeNode<TypeId, TypeMeta>,
+    ) -> bool {
+        op.timestamp().actor_id() == &PRIMARY
+    }
+}
+
+// applies ops to a new replica of actor id, in the given order.
+fn replay<R>(
+    id: TypeActor,
+    ops: &[OpMove<TypeId, TypeMeta, TypeActor>],
+) -> TreeReplica<TypeId, TypeMeta, TypeActor, ActorOrder, R>
+where
+    R: Resolve<TypeId, TypeMeta, TypeActor>,
+{
+    let mut r = TreeReplica::new(id);
+    r.apply_ops_byref(ops);
+    r
+}
+
+#[test]
+fn cycle_moved_to_lost_and_found() {
+    let mut r1: TreeReplica<TypeId, TypeMeta, TypeActor, ActorOrder, LostAndFound> =
+        TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMeta, TypeActor, ActorOrder, LostAndFound> =
+        TreeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "a", 1), (0, "b", 2)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+
+    // concurrently, 1 under 2 and 2 under 1.  r2's op is applied last.
+    let op1 = r1.opmove(2, "a", 1);
+    let op2 = r2.opmove(1, "b", 2);
+    r1.apply_op(op1.clone());
+    r1.apply_op(op2.clone());
+    r2.apply_op(op2);
+    r2.apply_op(op1);
+
+    assert_eq!(r1.tree(), r2.tree());
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &2);
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &LOST_AND_FOUND);
+    assert_eq!(r1.tree().find(&2).unwrap().metadata(), &"b");
+
+    // the default ignores the later op.
+    let log: Vec<_> = r1
+        .state()
+        .log()
+        .iter()
+        .rev()
+        .cloned()
+        .map(Into::into)
+        .collect();
+    let r3 = replay::<crdt_tree::Kleppmann>(3, &log);
+    assert_eq!(r3.tree().find(&2).unwrap().parent_id(), &0);
+}
+
+#[test]
+fn primary_actor_wins() {
+    let mut r1: TreeReplica<TypeId, TypeMeta, TypeActor, ActorOrder, PrimaryOnly> =
+        TreeReplica::new(PRIMARY);
+    let mut r2: TreeReplica<TypeId, TypeMeta, TypeActor, ActorOrder, PrimaryOnly> =
+        TreeReplica::new(2);
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    r1.set_conflict_handler(Some(Box::new(move |e| sink.lock().unwrap().push(e))));
+
+    // r2 creates 1, then moves it twice, concurrently with r1's move.
+    let ops = r2.opmoves(vec![(0, "a", 1), (0, "b", 5), (0, "c", 6)]);
+    r2.apply_ops_byref(&ops);
+    r1.apply_ops_byref(&ops);
+    let primary = r1.opmove(5, "a", 1);
+    r1.apply_op(primary.clone());
+    let first = r2.opmove(6, "a", 1);
+    r2.apply_op(first.clone());
+    let second = r2.opmove(6, "z", 1);
+    r2.apply_op(second.clone());
+    events.lock().unwrap().clear();
+    r1.apply_ops(vec![first, second]);
+    r2.apply_op(primary);
+
+    assert_eq!(r1.tree(), r2.tree());
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &5);
+    let kinds: Vec<ConflictKind> = events.lock().unwrap().iter().map(|e| e.kind()).collect();
+    assert_eq!(kinds, vec![ConflictKind::Declined, ConflictKind::Declined]);
+}
+
+// a set of tags, merged by union.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Tags(BTreeSet<&'static str>);
+
+impl Tags {
+    fn of(tags: &[&'static str]) -> Self {
+        Self(tags.iter().copied().collect())
+    }
+}
+
+impl MergeMeta for Tags {
+    fn merge(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+}
+
+#[test]
+fn concurrent_metadata_merged() {
+    type Replica = TreeReplica<TypeId, Tags, TypeActor, ActorOrder, MergeMetadata>;
+    let mut r1: Replica = TreeReplica::new(1);
+    let mut r2: Replica = TreeReplica::new(2);
+    let op = r1.opmove(0, Tags::default(), 1);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    // concurrently tagged.
+    let op1 = r1.opmove(0, Tags::of(&["a"]), 1);
+    let op2 = r2.opmove(0, Tags::of(&["b"]), 1);
+    r1.apply_op(op1.clone());
+    r1.apply_op(op2.clone());
+    r2.apply_op(op2);
+    r2.apply_op(op1);
+
+    assert_eq!(r1.tree(), r2.tree());
+    assert_eq!(
+        r1.tree().find(&1).unwrap().metadata(),
+        &Tags::of(&["a", "b"])
+    );
+
+    // moving under another parent replaces the metadata.
+    let op = r1.opmove(5, Tags::of(&["c"]), 1);
+    r1.apply_op(op);
+    assert_eq!(r1.tree().find(&1).unwrap().metadata(), &Tags::of(&["c"]));
+}
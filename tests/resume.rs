@@ -0,0 +1,108 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for resuming a crdt-tree replica from persisted state
+use crdt_tree::{Clock, OpMove, State, TreeReplica, VersionVector};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = String;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+type TypeState = State<TypeId, TypeMeta, TypeActor>;
+
+// Tests that a replica resumed from its serialized state and version
+// vector carries on as before the restart.
+#[test]
+fn from_state_resumes() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "a".into(), 1), (1, "b".into(), 2)]);
+    r1.apply_ops(ops.clone());
+    r2.apply_ops(ops);
+    let op = r2.opmove(0, "c".into(), 3);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+    r1.truncate_log();
+
+    let json = serde_json::to_string(r1.state()).unwrap();
+    let vv = serde_json::to_string(r1.version_vector()).unwrap();
+    let state: TypeState = serde_json::from_str(&json).unwrap();
+    let latest: VersionVector<TypeActor> = serde_json::from_str(&vv).unwrap();
+    let mut resumed = TypeReplica::from_state(1, state, latest);
+
+    assert_eq!(resumed.id(), &1);
+    assert_eq!(resumed.time(), r1.time());
+    assert_eq!(resumed.version_vector(), r1.version_vector());
+    assert_eq!(
+        resumed.causally_stable_threshold(),
+        r1.causally_stable_threshold()
+    );
+    // ops seen before the restart, truncated or not, are skipped.
+    for op in r2.state().log().iter().cloned().map(OpMove::from) {
+        assert!(resumed.has_seen(op.timestamp()));
+    }
+
+    // new ops are newer than any seen.
+    let op = resumed.opmove(3, "d".into(), 4);
+    assert!(op.timestamp() > r2.time());
+    resumed.apply_op(op.clone());
+    r2.apply_op(op);
+    assert_eq!(resumed.tree(), r2.tree());
+}
+
+// Tests that the clock is advanced past the log even without a version
+// vector.
+#[test]
+fn from_state_without_version_vector() {
+    let mut state = TypeState::new();
+    state.apply_op(OpMove::new(Clock::new(7, Some(41)), 0, "a".into(), 1));
+    let r1 = TypeReplica::from_state(1, state, VersionVector::new());
+    assert_eq!(r1.time().counter(), 41);
+    assert_eq!(r1.opmove(0, "b".into(), 2).timestamp().counter(), 42);
+    assert!(r1.has_seen(&Clock::new(7, Some(41))));
+    assert!(!r1.has_seen(&Clock::new(7, Some(40))));
+}
+
+// Tests that a replica resumed without its log ever being truncated
+// applies old ops from actors it has not seen, as before the restart.
+#[test]
+fn from_state_applies_old_ops_of_new_actors() {
+    let mut r1 = TypeReplica::new(1);
+    let mut r3 = TypeReplica::new(3);
+    let ops = r1.opmoves(vec![(0, "a".into(), 1), (1, "b".into(), 2)]);
+    r1.apply_ops(ops.clone());
+    r3.apply_ops(ops);
+
+    let json = serde_json::to_string(r1.state()).unwrap();
+    let state: TypeState = serde_json::from_str(&json).unwrap();
+    let mut resumed = TypeReplica::from_state(1, state, r1.version_vector().clone());
+
+    let op = OpMove::new(Clock::new(2, Some(1)), 1, "x".into(), 9);
+    assert!(!resumed.has_seen(op.timestamp()));
+    resumed.apply_op(op.clone());
+    r3.apply_op(op);
+    assert_eq!(resumed.tree(), r3.tree());
+    assert!(resumed.tree().find(&9).is_some());
+}
+
+// Tests that the timestamp the log was truncated before is persisted with
+// the state, so older ops are still taken as seen once resumed.
+#[test]
+fn from_state_keeps_truncation_point() {
+    let mut r1 = TypeReplica::new(1);
+    let ops = r1.opmoves(vec![(0, "a".into(), 1), (1, "b".into(), 2)]);
+    r1.apply_ops(ops);
+    r1.truncate_log();
+    let floor = r1.state().truncated_before().cloned();
+    assert!(floor.is_some());
+
+    let json = serde_json::to_string(r1.state()).unwrap();
+    let state: TypeState = serde_json::from_str(&json).unwrap();
+    assert_eq!(state.truncated_before().cloned(), floor);
+    let resumed = TypeReplica::from_state(1, state, r1.version_vector().clone());
+    assert!(resumed.has_seen(&Clock::new(2, Some(1))));
+}
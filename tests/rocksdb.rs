@@ -0,0 +1,135 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree rocksdb storage backend
+#[cfg(feature = "rocksdb-storage")]
+mod rocksdb_storage {
+    use crdt_tree::{RocksStorage, Storage, StoredReplica, TreeNode, TreeReplica};
+    use std::env;
+    use std::ops::Bound;
+    use std::path::{Path, PathBuf};
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeStored = StoredReplica<TypeId, TypeMeta, TypeActor, RocksStorage>;
+
+    // helper: returns a path for a new database under the system temp dir.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("crdt_tree_{}_{}", name, rand::random::<u64>()))
+    }
+
+    // helper: copies a directory tree, as a crash would leave it on disk.
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            let dest = to.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &dest);
+            } else {
+                std::fs::copy(entry.path(), dest).unwrap();
+            }
+        }
+    }
+
+    // Tests that synced ops and snapshots survive a crash, ie reopening
+    // the files as they were left on disk without a clean shutdown.
+    #[test]
+    fn recover_after_crash() {
+        let path = temp_path("rocksdb");
+        let crashed = temp_path("rocksdb_crashed");
+        let mut peer = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+
+        let mut stored = TypeStored::open(1, RocksStorage::open(&path, true).unwrap())
+            .unwrap()
+            .with_checkpoint_interval(Some(10));
+        for i in 1..=25u64 {
+            let op = if i % 3 == 0 {
+                peer.opmove(i / 4, format!("peer{}", i), i)
+            } else {
+                stored.replica().opmove(i / 4, format!("node{}", i), i)
+            };
+            peer.apply_op(op.clone());
+            stored.apply_op(op).unwrap();
+        }
+        assert_eq!(stored.checkpoint_id(), 2);
+
+        let ops = Storage::<TypeId, TypeMeta, TypeActor>::scan_ops(
+            stored.storage(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+        )
+        .unwrap();
+        assert!(!ops.is_empty());
+        assert!(ops.windows(2).all(|w| w[0].timestamp() < w[1].timestamp()));
+        let some = Storage::<TypeId, TypeMeta, TypeActor>::scan_ops(
+            stored.storage(),
+            Bound::Excluded(ops[0].timestamp()),
+            Bound::Included(ops[2].timestamp()),
+        )
+        .unwrap();
+        assert_eq!(some, ops[1..3].to_vec());
+
+        copy_dir(&path, &crashed);
+        let expected = stored.replica().clone();
+        drop(stored);
+
+        let reopened = TypeStored::open(1, RocksStorage::open(&crashed, false).unwrap()).unwrap();
+        assert_eq!(reopened.checkpoint_id(), 2);
+        assert_eq!(reopened.replica().tree(), expected.tree());
+        assert_eq!(reopened.replica().time(), expected.time());
+        assert_eq!(reopened.replica().tree(), peer.tree());
+        drop(reopened);
+
+        std::fs::remove_dir_all(path).unwrap();
+        std::fs::remove_dir_all(crashed).unwrap();
+    }
+
+    // Tests that nodes can be found in the stored tree without loading it,
+    // and that checkpoints update changed triples.
+    #[test]
+    fn find_stored_nodes() {
+        let path = temp_path("rocksdb_find");
+        let mut stored = TypeStored::open(1, RocksStorage::open(&path, false).unwrap()).unwrap();
+        let ops = stored.replica().opmoves(vec![
+            (0, "a".to_string(), 1),
+            (1, "b".to_string(), 2),
+            (1, "b".to_string(), 3),
+        ]);
+        stored.apply_ops(ops).unwrap();
+        stored.checkpoint().unwrap();
+
+        let storage = stored.storage();
+        assert_eq!(
+            storage.find_node::<TypeId, TypeMeta>(&2).unwrap(),
+            Some(TreeNode::new(1, "b".to_string()))
+        );
+        let mut found = storage
+            .find_by_meta::<TypeId, TypeMeta>(&"b".to_string())
+            .unwrap();
+        found.sort_unstable();
+        assert_eq!(found, vec![2, 3]);
+
+        let op = stored.replica().opmove(2, "c".to_string(), 3);
+        stored.apply_op(op).unwrap();
+        stored.checkpoint().unwrap();
+
+        let storage = stored.storage();
+        assert_eq!(
+            storage
+                .find_by_meta::<TypeId, TypeMeta>(&"b".to_string())
+                .unwrap(),
+            vec![2]
+        );
+        assert_eq!(
+            storage.find_node::<TypeId, TypeMeta>(&3).unwrap(),
+            Some(TreeNode::new(2, "c".to_string()))
+        );
+        drop(stored);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
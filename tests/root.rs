@@ -0,0 +1,100 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree root management
+use crdt_tree::{OpMove, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+const ROOT: TypeId = 0;
+const TOP: TypeId = u64::MAX;
+
+fn setup(id: TypeActor) -> TypeReplica {
+    let mut r = TypeReplica::new(id);
+    r.set_root(ROOT, TOP);
+    r
+}
+
+// returns the ops in r's log, oldest first.
+fn ops_of(r: &TypeReplica) -> Vec<OpMove<TypeId, TypeMeta, TypeActor>> {
+    r.state()
+        .log()
+        .iter()
+        .rev()
+        .cloned()
+        .map(Into::into)
+        .collect()
+}
+
+#[test]
+fn create_root() {
+    let mut r1 = setup(1);
+    let mut r2 = setup(2);
+    assert_eq!(r1.root(), None);
+
+    assert_eq!(r1.create_root("root"), ROOT);
+    assert_eq!(r1.root(), Some(&ROOT));
+    assert_eq!(r1.tree().find(&ROOT).unwrap().parent_id(), &TOP);
+    let ops = r1.opmoves(vec![(ROOT, "a", 1), (1, "b", 2)]);
+    r1.apply_ops(ops);
+
+    r2.apply_ops(ops_of(&r1));
+    assert_eq!(r2.root(), Some(&ROOT));
+    assert_eq!(r1.tree(), r2.tree());
+
+    // without a root, ops under 0 leave it a top-level parent.
+    let mut r3 = TypeReplica::new(3);
+    r3.apply_op(r3.opmove(ROOT, "a", 1));
+    assert_eq!(r3.root(), None);
+    assert_eq!(r3.state().root_parent(), None);
+}
+
+#[test]
+fn crafted_ops_cannot_displace_root() {
+    let mut r1 = setup(1);
+    r1.create_root("root");
+    let ops = r1.opmoves(vec![(ROOT, "a", 1), (1, "b", 2)]);
+    r1.apply_ops(ops);
+    let tree = r1.tree().clone();
+
+    let ops = r1.opmoves(vec![
+        // under a descendant, or elsewhere.
+        (2, "root", ROOT),
+        (99, "root", ROOT),
+        // another node at the top.
+        (TOP, "a", 1),
+        (TOP, "c", 3),
+        // the root's parent made a node.
+        (ROOT, "top", TOP),
+    ]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree(), &tree);
+
+    // the root's metadata may be updated.
+    r1.create_root("renamed");
+    assert_eq!(r1.tree().find(&ROOT).unwrap().metadata(), &"renamed");
+    assert_eq!(r1.tree().num_children(&TOP), 1);
+}
+
+#[test]
+fn concurrent_create_root_converges() {
+    let mut r1 = setup(1);
+    let mut r2 = setup(2);
+    r1.create_root("one");
+    r2.create_root("two");
+    let ops1 = ops_of(&r1);
+    let ops2 = ops_of(&r2);
+    r1.apply_ops(ops2);
+    r2.apply_ops(ops1);
+
+    assert_eq!(r1.tree(), r2.tree());
+    assert_eq!(r1.tree().num_children(&TOP), 1);
+    assert_eq!(r1.tree().find(&ROOT).unwrap().metadata(), &"two");
+}
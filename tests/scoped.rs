@@ -0,0 +1,92 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree scoped maintenance
+use crdt_tree::{TreeReplica, UnstableSubtree};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+const TRASH: TypeId = 9;
+
+// helper: returns two replicas with the same nodes.
+//
+// 0
+//  - root
+//    - a
+//      - b
+//    - c
+fn new_replicas() -> (TypeReplica, TypeReplica) {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let mut ops = vec![r2.opmove(0, "root", 1)];
+    r2.apply_op(ops[0].clone());
+    ops.extend(r1.opmoves(vec![(1, "a", 2), (2, "b", 3), (1, "c", 4)]));
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops[1..]);
+    (r1, r2)
+}
+
+// Tests that trash is only emptied once the move into it is causally
+// stable.
+#[test]
+fn rm_subtree_checked() {
+    let (mut r1, mut r2) = new_replicas();
+    let op = r1.opmove(TRASH, "a", 2);
+    r1.apply_op(op.clone());
+    r2.apply_op(op.clone());
+
+    // r2 may yet move a out of the trash.
+    assert_eq!(
+        r1.rm_subtree_checked(&TRASH, false),
+        Err(UnstableSubtree { child_id: 2 })
+    );
+    assert_eq!(r1.tree().num_nodes(), 4);
+
+    // the move is stable at its own timestamp.
+    let mut state = r1.state().clone();
+    assert_eq!(
+        state.rm_subtree_checked(&TRASH, false, op.timestamp()),
+        Ok(2)
+    );
+
+    let op = r2.opmove(1, "d", 5);
+    r2.apply_op(op.clone());
+    r1.apply_op(op);
+    assert_eq!(r1.rm_subtree_checked(&TRASH, false), Ok(2));
+    assert!(r1.tree().find(&2).is_none());
+    assert!(r1.tree().find(&3).is_none());
+    assert_eq!(r1.tree().num_nodes(), 3);
+}
+
+// Tests that retain removes rejected nodes with their subtrees, and that
+// the checked variant skips nodes moved after the threshold.
+#[test]
+fn retain() {
+    let (mut r1, mut r2) = new_replicas();
+    let mut tree = r1.tree().clone();
+    assert_eq!(tree.retain(|_, node| *node.metadata() != "a"), 2);
+    assert_eq!(tree.num_nodes(), 2);
+    assert!(tree.find(&4).is_some());
+
+    let op = r1.opmove(4, "b", 3);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    // b was just moved under c, so c is kept, unlike a.
+    assert_eq!(r1.retain_checked(|_, node| *node.metadata() == "root"), 0);
+    let op = r2.opmove(1, "d", 5);
+    r2.apply_op(op.clone());
+    r1.apply_op(op);
+    assert_eq!(
+        r1.retain_checked(|_, node| !["a", "c"].contains(node.metadata())),
+        3
+    );
+    assert_eq!(r1.tree().children(&1), vec![5]);
+}
@@ -0,0 +1,53 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree replicas over a simulated network
+#[cfg(feature = "testing")]
+mod simulation {
+    use crdt_tree::simulation::{NetworkConfig, Simulation};
+
+    // Tests that replicas converge over an unreliable network, for many
+    // seeds.
+    #[test]
+    fn converges_over_unreliable_network() {
+        for seed in 0..20 {
+            let mut sim = Simulation::new(4, seed, NetworkConfig::default());
+            assert!(sim.run(100), "seed {} did not converge", seed);
+            sim.assert_converged();
+            assert_eq!(sim.in_flight(), 0);
+        }
+    }
+
+    // Tests that a seed replays exactly.
+    #[test]
+    fn deterministic() {
+        let config = NetworkConfig {
+            drop_rate: 0.3,
+            duplicate_rate: 0.3,
+            ..NetworkConfig::default()
+        };
+        let mut a = Simulation::new(3, 7, config.clone());
+        let mut b = Simulation::new(3, 7, config);
+        a.run(50);
+        b.run(50);
+        assert_eq!(a.stats(), b.stats());
+        assert_eq!(a.now(), b.now());
+        assert_eq!(a.replicas()[0].tree(), b.replicas()[0].tree());
+        assert!(a.stats().dropped > 0 && a.stats().duplicated > 0);
+    }
+
+    // Tests that a reliable network needs no anti-entropy.
+    #[test]
+    fn reliable_network() {
+        let mut sim = Simulation::new(3, 1, NetworkConfig::reliable());
+        sim.local_op(0, 0, 'a', 1);
+        sim.local_op(1, 0, 'b', 1);
+        assert_eq!(sim.in_flight(), 4);
+        assert!(sim.quiesce(0));
+        assert_eq!(sim.stats().rounds, 0);
+        assert_eq!(sim.replicas()[2].tree().find(&1).unwrap().metadata(), &'b');
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree sled storage backend
+#[cfg(feature = "sled-storage")]
+mod sled_storage {
+    use crdt_tree::{SledStorage, Storage, StoredReplica, TreeReplica};
+    use std::env;
+    use std::ops::Bound;
+    use std::path::{Path, PathBuf};
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeStored = StoredReplica<TypeId, TypeMeta, TypeActor, SledStorage>;
+
+    // helper: returns a path for a new database under the system temp dir.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("crdt_tree_{}_{}", name, rand::random::<u64>()))
+    }
+
+    // helper: copies a directory tree, as a crash would leave it on disk.
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            let dest = to.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &dest);
+            } else {
+                std::fs::copy(entry.path(), dest).unwrap();
+            }
+        }
+    }
+
+    // Tests that synced ops and snapshots survive a crash, ie reopening
+    // the files as they were left on disk without a clean shutdown.
+    #[test]
+    fn recover_after_crash() {
+        let path = temp_path("sled");
+        let crashed = temp_path("sled_crashed");
+        let mut peer = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+
+        let db = sled::open(&path).unwrap();
+        let mut stored = TypeStored::open(1, SledStorage::new(&db, true).unwrap())
+            .unwrap()
+            .with_checkpoint_interval(Some(10));
+        for i in 1..=25u64 {
+            let op = if i % 3 == 0 {
+                peer.opmove(i / 4, format!("peer{}", i), i)
+            } else {
+                stored.replica().opmove(i / 4, format!("node{}", i), i)
+            };
+            peer.apply_op(op.clone());
+            stored.apply_op(op).unwrap();
+        }
+        assert_eq!(stored.checkpoint_id(), 2);
+
+        let ops: Vec<_> = Storage::<TypeId, TypeMeta, TypeActor>::scan_ops(
+            stored.storage(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+        )
+        .unwrap();
+        assert!(!ops.is_empty());
+        assert!(ops.windows(2).all(|w| w[0].timestamp() < w[1].timestamp()));
+        let some = Storage::<TypeId, TypeMeta, TypeActor>::scan_ops(
+            stored.storage(),
+            Bound::Excluded(ops[0].timestamp()),
+            Bound::Included(ops[2].timestamp()),
+        )
+        .unwrap();
+        assert_eq!(some, ops[1..3].to_vec());
+
+        copy_dir(&path, &crashed);
+        let expected = stored.replica().clone();
+        drop(stored);
+        drop(db);
+
+        let db = sled::open(&crashed).unwrap();
+        let reopened = TypeStored::open(1, SledStorage::new(&db, false).unwrap()).unwrap();
+        assert_eq!(reopened.checkpoint_id(), 2);
+        assert_eq!(reopened.replica().tree(), expected.tree());
+        assert_eq!(reopened.replica().time(), expected.time());
+        assert_eq!(reopened.replica().tree(), peer.tree());
+        drop(reopened);
+        drop(db);
+
+        std::fs::remove_dir_all(path).unwrap();
+        std::fs::remove_dir_all(crashed).unwrap();
+    }
+}
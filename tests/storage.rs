@@ -0,0 +1,98 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree storage backends
+use crdt_tree::{MemoryStorage, Storage, StoredReplica, TreeReplica};
+use std::ops::Bound;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = String;
+type TypeStorage = MemoryStorage<TypeId, TypeMeta, TypeActor>;
+type TypeStored = StoredReplica<TypeId, TypeMeta, TypeActor, TypeStorage>;
+
+// Tests that a stored replica persists ops and snapshots, and is
+// restored from them.
+#[test]
+fn stored_replica_reopen() {
+    let mut peer = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+    let mut stored = TypeStored::open(1, TypeStorage::new())
+        .unwrap()
+        .with_checkpoint_interval(Some(8));
+
+    for i in 1..=20u64 {
+        let op = if i % 2 == 0 {
+            stored.replica().opmove(0, format!("a{}", i), i)
+        } else {
+            peer.opmove(0, format!("b{}", i), i)
+        };
+        peer.apply_op(op.clone());
+        stored.apply_op(op).unwrap();
+    }
+    assert_eq!(stored.checkpoint_id(), 2);
+    assert_eq!(stored.storage().num_snapshots(), 2);
+    // stable ops were removed at the last checkpoint.
+    assert!(stored.storage().num_ops() < 20);
+
+    let (replica, storage) = stored.into_parts();
+    let reopened = TypeStored::open(9, storage).unwrap();
+    assert_eq!(reopened.replica().tree(), replica.tree());
+    assert_eq!(reopened.replica().time(), replica.time());
+    assert_eq!(reopened.replica().id(), &1);
+    assert_eq!(reopened.checkpoint_id(), 2);
+    assert_eq!(reopened.replica().tree(), peer.tree());
+}
+
+// Tests that without a snapshot, all stored ops are replayed.
+#[test]
+fn stored_replica_without_snapshot() {
+    let mut stored = TypeStored::open(1, TypeStorage::new()).unwrap();
+    let ops = stored
+        .replica()
+        .opmoves((1..=5).map(|i| (0, format!("n{}", i), i)).collect());
+    stored.apply_ops(ops.clone()).unwrap();
+    assert_eq!(stored.storage().num_snapshots(), 0);
+
+    let (replica, storage) = stored.into_parts();
+    assert_eq!(
+        storage
+            .scan_ops(
+                Bound::Included(ops[1].timestamp()),
+                Bound::Excluded(ops[3].timestamp())
+            )
+            .unwrap(),
+        ops[1..3].to_vec()
+    );
+    let reopened = TypeStored::open(1, storage).unwrap();
+    assert_eq!(reopened.replica().state(), replica.state());
+}
+
+// Tests that truncated log entries are archived to storage, oldest
+// first, rather than discarded.
+#[test]
+fn truncate_to_archive() {
+    let mut r1 = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(1);
+    let mut r2 = TreeReplica::<TypeId, TypeMeta, TypeActor>::new(2);
+    let ops = r1.opmoves((1..=4).map(|i| (0, format!("n{}", i), i)).collect());
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let op = r2.opmove(0, "m".to_string(), 5);
+    r1.apply_op(op.clone());
+
+    let mut archive = TypeStorage::new();
+    assert_eq!(r1.truncate_to_archive(&mut archive), Ok(3));
+    assert_eq!(r1.state().log().len(), 2);
+    let archived = archive
+        .scan_ops(Bound::Unbounded, Bound::Unbounded)
+        .unwrap();
+    assert_eq!(archived, ops[..3]);
+
+    // nothing more is causally stable.
+    assert_eq!(r1.truncate_to_archive(&mut archive), Ok(0));
+    assert_eq!(archive.num_ops(), 3);
+    assert!(r1.has_seen(ops[0].timestamp()));
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree op and snapshot streams
+#[cfg(feature = "codec")]
+mod streaming {
+    use crdt_tree::codec::CodecError;
+    use crdt_tree::streaming::{read_snapshot, write_ops, write_snapshot, OpStream};
+    use crdt_tree::{OpMove, Snapshot, TreeReplica};
+    use std::io::BufReader;
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns ops creating n nodes under a root.
+    fn new_ops(r: &TypeReplica, n: u64) -> Vec<TypeOp> {
+        let mut moves = vec![(0, "root".to_string(), 1)];
+        moves.extend((2..n + 2).map(|i| (1, format!("n{}", i), i)));
+        r.opmoves(moves)
+    }
+
+    // Tests that ops are applied from a stream as they are read.
+    #[test]
+    fn op_stream_round_trip() {
+        let mut r1 = TypeReplica::new(1);
+        let ops = new_ops(&r1, 50);
+        r1.apply_ops_byref(&ops);
+
+        let mut bytes = vec![];
+        assert_eq!(write_ops(&mut bytes, ops.iter().cloned()), Ok(51));
+        let read: Vec<TypeOp> = OpStream::new(&bytes[..]).collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, ops);
+
+        let mut r2 = TypeReplica::new(2);
+        assert_eq!(r2.apply_op_stream(BufReader::new(&bytes[..])), Ok(51));
+        assert_eq!(r2.state(), r1.state());
+
+        // an empty stream holds no ops.
+        assert_eq!(r2.apply_op_stream(&b""[..]), Ok(0));
+    }
+
+    // Tests that a truncated stream applies the ops before the error.
+    #[test]
+    fn op_stream_truncated() {
+        let r1 = TypeReplica::new(1);
+        let ops = new_ops(&r1, 3);
+        let mut bytes = vec![];
+        write_ops(&mut bytes, ops).unwrap();
+        bytes.truncate(bytes.len() - 2);
+
+        let mut stream = OpStream::<_, TypeId, TypeMeta, TypeActor>::new(&bytes[..]);
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.by_ref().take(3).any(|r| r.is_err()));
+        assert!(stream.next().is_none());
+
+        let mut r2 = TypeReplica::new(2);
+        assert!(r2.apply_op_stream(&bytes[..]).is_err());
+        assert_eq!(r2.tree().num_nodes(), 3);
+    }
+
+    // Tests that a snapshot round-trips through a stream, and restores a
+    // replica.
+    #[test]
+    fn snapshot_stream_round_trip() {
+        let mut r1 = TypeReplica::new(1);
+        let mut r2 = TypeReplica::new(2);
+        let ops = new_ops(&r1, 20);
+        r1.apply_ops_byref(&ops);
+        r2.apply_ops_byref(&ops);
+        let op = r2.opmove(1, "m".to_string(), 99);
+        r1.apply_op(op);
+        let snapshot = r1.snapshot(7);
+
+        let mut bytes = vec![];
+        write_snapshot(&mut bytes, &snapshot).unwrap();
+        let read: Snapshot<TypeId, TypeMeta, TypeActor> = read_snapshot(&bytes[..]).unwrap();
+        assert_eq!(read, snapshot);
+        let restored = TypeReplica::restore(read, vec![]);
+        assert_eq!(restored.tree(), r1.tree());
+
+        assert_eq!(
+            read_snapshot::<_, TypeId, TypeMeta, TypeActor>(&bytes[..bytes.len() - 1]),
+            Err(CodecError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree strict mode
+use crdt_tree::{ConflictKind, TreeReplica};
+use std::sync::{Arc, Mutex};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+const ROOT: TypeId = 0;
+const TRASH: TypeId = 99;
+
+fn setup(id: TypeActor) -> TypeReplica {
+    let mut r = TypeReplica::new(id);
+    r.set_strict(Some(vec![ROOT, TRASH]));
+    r
+}
+
+#[test]
+fn moves_under_missing_parents_ignored() {
+    let mut r1 = setup(1);
+    assert!(r1.state().is_strict());
+    let ops = r1.opmoves(vec![
+        (ROOT, "a", 1),
+        (1, "b", 2),
+        (7, "c", 3),
+        (TRASH, "d", 4),
+    ]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().find(&3), None);
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &TRASH);
+    assert_eq!(r1.tree().roots().len(), 2);
+
+    // an existing node may not be moved under a missing parent either.
+    let op = r1.opmove(8, "b", 2);
+    r1.apply_op(op);
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &1);
+
+    // without strict mode, 3 starts a disconnected subtree.
+    let mut r2 = TypeReplica::new(2);
+    r2.apply_op(r2.opmove(7, "c", 3));
+    assert_eq!(r2.tree().find(&3).unwrap().parent_id(), &7);
+}
+
+#[test]
+fn parent_arriving_late_reinstates_op() {
+    let mut r1 = setup(1);
+    let mut r2 = setup(2);
+    let parent = r1.opmove(ROOT, "a", 1);
+    r1.apply_op(parent.clone());
+    let child = r1.opmove(1, "b", 2);
+    r1.apply_op(child.clone());
+
+    // r2 receives the child first, which is ignored until its parent,
+    // an older op, arrives.
+    r2.apply_op(child);
+    assert_eq!(r2.tree().find(&2), None);
+    r2.apply_op(parent);
+    assert_eq!(r2.tree().find(&2).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree(), r2.tree());
+}
+
+#[test]
+fn root_parent_admitted_and_conflict_reported() {
+    let mut r1 = TypeReplica::new(1);
+    r1.set_root(ROOT, 1000);
+    r1.set_strict(Some(vec![]));
+    let events = Arc::new(Mutex::new(vec![]));
+    let sink = events.clone();
+    r1.set_conflict_handler(Some(Box::new(move |e| sink.lock().unwrap().push(e))));
+
+    r1.create_root("root");
+    let ops = r1.opmoves(vec![(ROOT, "a", 1), (TRASH, "b", 2)]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.root(), Some(&ROOT));
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &ROOT);
+    assert_eq!(r1.tree().find(&2), None);
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind(), ConflictKind::ParentMissing);
+    assert_eq!(events[0].child_id(), &2);
+}
@@ -0,0 +1,59 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree op subscriptions
+#[cfg(feature = "tokio")]
+mod subscribe {
+    use crdt_tree::{ApplyOutcome, DriftGuard, DriftPolicy, TreeReplica};
+    use std::time::Duration;
+    use tokio_stream::StreamExt;
+
+    type TypeId = u64;
+    type TypeActor = u8;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+    // Tests that subscribers see every op passed to apply_op, with its
+    // outcome.
+    #[tokio::test]
+    async fn stream_applied_ops() {
+        let mut r1 = TypeReplica::new(1);
+        r1.apply_op(r1.opmove(0, "unseen".to_string(), 1));
+
+        let s1 = r1.subscribe();
+        let ops = r1.opmoves(vec![(0, "a".to_string(), 2), (0, "b".to_string(), 3)]);
+        r1.apply_ops_byref(&ops);
+        r1.apply_op(ops[0].clone());
+
+        let mut s2 = r1.subscribe();
+        r1.set_drift_guard(Some(DriftGuard::new(
+            Duration::from_secs(1),
+            DriftPolicy::Reject,
+        )));
+        let future = r1.opmove(0, "c".to_string(), 4).with_wall_time(u64::MAX);
+        r1.apply_op(future.clone());
+        drop(r1);
+
+        let seen: Vec<_> = s1.collect().await;
+        let expected = vec![
+            (&ops[0], ApplyOutcome::Applied),
+            (&ops[1], ApplyOutcome::Applied),
+            (&ops[0], ApplyOutcome::Duplicate),
+            (&future, ApplyOutcome::Rejected),
+        ];
+        assert_eq!(
+            seen.iter()
+                .map(|a| (a.op(), a.outcome()))
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        let seen = s2.next().await.unwrap();
+        assert_eq!(seen.outcome(), ApplyOutcome::Rejected);
+        assert_eq!(seen.into_op(), future);
+        assert!(s2.next().await.is_none());
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree subtree export, import and copy
+use crdt_tree::TreeReplica;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// returns a replica holding folder 1, with a subtree, beside folder 5.
+fn setup() -> TypeReplica {
+    let mut r1 = TypeReplica::new(1);
+    let ops = r1.opmoves(vec![
+        (0, "folder", 1),
+        (1, "a", 2),
+        (2, "b", 3),
+        (1, "c", 4),
+        (0, "other", 5),
+    ]);
+    r1.apply_ops(ops);
+    r1
+}
+
+#[test]
+fn export_subtree_is_topological() {
+    let r1 = setup();
+    let ops = r1.state().export_subtree(&1);
+
+    let ids: Vec<TypeId> = ops.iter().map(|op| *op.child_id()).collect();
+    assert_eq!(ids.len(), 4);
+    assert_eq!(ids[0], 1);
+    for op in &ops[1..] {
+        let parent = ids.iter().position(|id| id == op.parent_id()).unwrap();
+        let child = ids.iter().position(|id| id == op.child_id()).unwrap();
+        assert!(parent < child);
+    }
+    assert!(r1.state().export_subtree(&9).is_empty());
+}
+
+#[test]
+fn import_subtree_into_independent_tree() {
+    let r1 = setup();
+    let mut r2 = TypeReplica::new(2);
+    let mut r3 = TypeReplica::new(3);
+    let op = r2.opmove(0, "shared", 10);
+    r2.apply_op(op.clone());
+    r3.apply_op(op);
+
+    let ops = r2.import_subtree(r1.state().export_subtree(&1), 10);
+    assert!(ops.iter().all(|op| op.timestamp().actor_id() == &2));
+    let tree = r2.tree();
+    assert_eq!(tree.find(&1).unwrap().parent_id(), &10);
+    assert_eq!(tree.find(&1).unwrap().metadata(), &"folder");
+    assert_eq!(tree.find(&3).unwrap().parent_id(), &2);
+    assert_eq!(tree.subtree_size(&10), 4);
+    assert_eq!(tree.find(&5), None);
+
+    // the imported ops are ordinary ops of replica 2.
+    r3.apply_ops(ops);
+    assert_eq!(r2.tree(), r3.tree());
+}
+
+#[test]
+fn import_subtree_below_top_level_parent() {
+    // exporting the root exports its children, not the root itself.
+    let r1 = setup();
+    let mut r2 = TypeReplica::new(2);
+    let ops = r2.import_subtree(r1.state().export_subtree(&0), 7);
+
+    assert_eq!(ops.len(), 5);
+    let mut top = r2.tree().children(&7);
+    top.sort_unstable();
+    assert_eq!(top, vec![1, 5]);
+    assert_eq!(r2.tree().subtree_size(&7), 5);
+}
+
+#[test]
+fn copy_subtree_with_fresh_ids() {
+    let mut r1 = setup();
+    let mut r2 = TypeReplica::new(2);
+    r2.apply_ops(
+        r1.state()
+            .log()
+            .iter()
+            .rev()
+            .cloned()
+            .map(Into::into)
+            .collect(),
+    );
+
+    // copy folder 1 into itself.
+    let ops = r1.copy_subtree(&1, 3, |id| id + 100);
+    assert_eq!(ops.len(), 4);
+    let tree = r1.tree();
+    assert_eq!(tree.find(&101).unwrap().parent_id(), &3);
+    assert_eq!(tree.find(&101).unwrap().metadata(), &"folder");
+    assert_eq!(tree.find(&102).unwrap().parent_id(), &101);
+    assert_eq!(tree.find(&103).unwrap().parent_id(), &102);
+    assert_eq!(tree.find(&104).unwrap().parent_id(), &101);
+    assert_eq!(tree.find(&104).unwrap().metadata(), &"c");
+    // the original is unchanged.
+    assert_eq!(tree.find(&1).unwrap().parent_id(), &0);
+    assert_eq!(tree.subtree_size(&1), 7);
+
+    r2.apply_ops(ops);
+    assert_eq!(r1.tree(), r2.tree());
+
+    assert!(r1.copy_subtree(&9, 0, |id| id + 100).is_empty());
+}
+
+#[test]
+fn graft_independent_state() {
+    let mut r1 = setup();
+    let mut r2 = TypeReplica::new(2);
+    let ops = r2.opmoves(vec![
+        (0, "folder", 1),
+        (1, "d", 10),
+        (0, "e", 11),
+        (99, "trashed", 12),
+    ]);
+    r2.apply_ops(ops);
+    let mut r3 = TypeReplica::new(3);
+    r3.apply_ops(
+        r1.state()
+            .log()
+            .iter()
+            .rev()
+            .cloned()
+            .map(Into::into)
+            .collect(),
+    );
+
+    // 1 collides, so is grafted as 101.
+    let ops = r1.graft(r2.state(), &0, 5, |id| id + 100);
+    assert_eq!(ops.len(), 3);
+    assert!(ops.iter().all(|op| op.timestamp().actor_id() == &1));
+    let tree = r1.tree();
+    assert_eq!(tree.find(&101).unwrap().parent_id(), &5);
+    assert_eq!(tree.find(&101).unwrap().metadata(), &"folder");
+    assert_eq!(tree.find(&10).unwrap().parent_id(), &101);
+    assert_eq!(tree.find(&11).unwrap().parent_id(), &5);
+    assert_eq!(tree.find(&12), None);
+    // the original is unchanged.
+    assert_eq!(tree.find(&1).unwrap().parent_id(), &0);
+    assert_eq!(tree.subtree_size(&1), 3);
+
+    r3.apply_ops(ops);
+    assert_eq!(r1.tree(), r3.tree());
+
+    // a node is grafted with its subtree.
+    let ops = r1.graft(r2.state(), &1, 0, |id| id + 200);
+    let ids: Vec<TypeId> = ops.iter().map(|op| *op.child_id()).collect();
+    assert_eq!(ids, vec![201, 210]);
+    assert_eq!(r1.tree().find(&201).unwrap().parent_id(), &0);
+}
@@ -0,0 +1,53 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree proptest strategies
+#[cfg(feature = "testing")]
+mod test_utils {
+    use crdt_tree::test_utils::{interleaving, op_history, OpHistory, TestOp, TestReplica};
+    use proptest::prelude::*;
+
+    // helper: returns a replica that has applied ops.
+    fn replica_from(ops: Vec<TestOp>) -> TestReplica {
+        let mut r = TestReplica::new(99);
+        r.apply_ops(ops);
+        r
+    }
+
+    // helper: a history, and an interleaving of its ops.
+    fn history_and_interleaving() -> impl Strategy<Value = (OpHistory, Vec<TestOp>)> {
+        op_history(3, 0..40).prop_flat_map(|h| {
+            let ops = h.ops().to_vec();
+            (Just(h), interleaving(ops))
+        })
+    }
+
+    proptest! {
+        // every interleaving of a history converges to the same tree.
+        #[test]
+        fn interleavings_converge((history, ops) in history_and_interleaving()) {
+            prop_assert_eq!(ops.len(), history.ops().len());
+            let expected = replica_from(history.ops().to_vec());
+            let actual = replica_from(ops);
+            prop_assert_eq!(expected.tree(), actual.tree());
+            prop_assert!(actual.tree().check_invariants().is_ok());
+        }
+
+        // simulated replicas agree once each has every op, and each
+        // actor's ops have increasing timestamps.
+        #[test]
+        fn replicas_converge(history in op_history(3, 0..40)) {
+            let all = replica_from(history.ops().to_vec());
+            for r in history.replicas() {
+                let mut r = r.clone();
+                r.apply_ops(history.ops().to_vec());
+                prop_assert_eq!(r.tree(), all.tree());
+                let own = history.ops_by(*r.id());
+                prop_assert!(own.windows(2).all(|w| w[0].timestamp() < w[1].timestamp()));
+            }
+        }
+    }
+}
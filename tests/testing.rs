@@ -0,0 +1,43 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree quickcheck Arbitrary impls
+#[cfg(feature = "testing")]
+mod testing {
+    use crdt_tree::{LogOpMove, OpMove, State, Tree};
+    use quickcheck::{quickcheck, Arbitrary};
+
+    type TypeId = u8;
+    type TypeActor = u8;
+    type TypeMeta = char;
+    type TypeState = State<TypeId, TypeMeta, TypeActor>;
+
+    quickcheck! {
+        // arbitrary states and trees are valid.
+        fn arbitrary_state_is_valid(state: TypeState) -> bool {
+            state.tree().check_invariants().is_ok()
+                && state.shrink().take(10).all(|s| s.tree().check_invariants().is_ok())
+        }
+
+        fn arbitrary_tree_is_valid(tree: Tree<TypeId, TypeMeta>) -> bool {
+            tree.check_invariants().is_ok()
+        }
+
+        // a state built from its own log equals itself.
+        fn state_replays_from_log(state: TypeState) -> bool {
+            let ops: Vec<OpMove<TypeId, TypeMeta, TypeActor>> =
+                state.log().iter().rev().cloned().map(Into::into).collect();
+            let mut replayed = TypeState::new();
+            replayed.apply_ops_into(ops);
+            replayed == state
+        }
+
+        fn log_entry_keeps_op(entry: LogOpMove<TypeId, TypeMeta, TypeActor>) -> bool {
+            let op: OpMove<TypeId, TypeMeta, TypeActor> = entry.clone().into();
+            op.timestamp() == entry.timestamp()
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree tie-break strategies
+use crdt_tree::{ActorOrder, Clock, HashOrder, TieBreak, TreeReplica};
+use std::cmp::Ordering;
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+
+// A strategy that lets actor 1 win all ties.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Priority;
+
+impl TieBreak<TypeActor> for Priority {
+    fn tie_break(a: &Clock<TypeActor>, b: &Clock<TypeActor>) -> Ordering {
+        (a.actor_id() == &1)
+            .cmp(&(b.actor_id() == &1))
+            .then_with(|| a.actor_id().cmp(b.actor_id()))
+    }
+}
+
+// returns the parent chosen for node 3 after two replicas concurrently
+// move it to different parents.
+fn winner<T: TieBreak<TypeActor>>() -> TypeId {
+    let mut r1: TreeReplica<TypeId, TypeMeta, TypeActor, T> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMeta, TypeActor, T> = TreeReplica::new(2);
+
+    let ops = r1.opmoves(vec![(0, "a", 1), (0, "b", 2), (0, "c", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+
+    // both ops have the same counter.
+    let m1 = r1.opmove(1, "c", 3);
+    let m2 = r2.opmove(2, "c", 3);
+    assert_eq!(m1.timestamp().counter(), m2.timestamp().counter());
+
+    r1.apply_op(m1.clone());
+    r1.apply_op(m2.clone());
+    r2.apply_op(m2);
+    r2.apply_op(m1);
+
+    assert_eq!(r1.state(), r2.state());
+    *r1.tree().find(&3).unwrap().parent_id()
+}
+
+// Tests that the strategy decides which concurrent op wins.
+#[test]
+fn strategy_picks_winner() {
+    // the default lets the greater actor win.
+    assert_eq!(winner::<ActorOrder>(), 2);
+    assert_eq!(
+        TreeReplica::<TypeId, TypeMeta, TypeActor>::new(1).state(),
+        TreeReplica::<TypeId, TypeMeta, TypeActor, ActorOrder>::new(1).state()
+    );
+
+    assert_eq!(winner::<Priority>(), 1);
+
+    let expected = if HashOrder::tie_break(&Clock::new(1u8, Some(4)), &Clock::new(2u8, Some(4)))
+        == Ordering::Greater
+    {
+        1
+    } else {
+        2
+    };
+    assert_eq!(winner::<HashOrder>(), expected);
+}
+
+// Tests that the hash order is a consistent total order that does not
+// always favour the same actor.
+#[test]
+fn hash_order() {
+    let mut wins = [0; 2];
+    for counter in 1..100 {
+        let a = Clock::<TypeActor>::new(1, Some(counter));
+        let b = Clock::<TypeActor>::new(2, Some(counter));
+        let ord = HashOrder::cmp(&a, &b);
+        assert_ne!(ord, Ordering::Equal);
+        assert_eq!(HashOrder::cmp(&b, &a), ord.reverse());
+        assert_eq!(HashOrder::cmp(&a, &a), Ordering::Equal);
+        wins[(ord == Ordering::Greater) as usize] += 1;
+
+        // counters are compared first.
+        let c = Clock::<TypeActor>::new(1, Some(counter + 1));
+        assert_eq!(HashOrder::cmp(&b, &c), Ordering::Less);
+    }
+    assert!(wins[0] > 0 && wins[1] > 0);
+}
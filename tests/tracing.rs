@@ -0,0 +1,106 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree tracing instrumentation
+#[cfg(feature = "tracing")]
+mod tracing {
+    use crdt_tree::{Clock, OpMove, TreeReplica};
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+    // a subscriber recording each span and event as its name, or message,
+    // followed by its fields.
+    #[derive(Default, Clone)]
+    struct Recorder {
+        lines: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0 += &format!(" {:?}", value);
+            } else {
+                self.0 += &format!(" {}={:?}", field.name(), value);
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let mut lines = self.lines.lock().unwrap();
+            lines.push(fields.0);
+            Id::from_u64(lines.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields("event".to_string());
+            event.record(&mut fields);
+            self.lines.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    // Tests that applying an out of order op records the undo depth, and
+    // the undos and redos it causes.
+    #[test]
+    fn spans() {
+        let recorder = Recorder::default();
+        let lines = recorder.lines.clone();
+        tracing::subscriber::with_default(recorder, || {
+            let mut r = TypeReplica::new(1);
+            let op = |counter, parent_id, child_id| {
+                OpMove::new(
+                    Clock::new(2, Some(counter)),
+                    parent_id,
+                    "m".to_string(),
+                    child_id,
+                )
+            };
+            r.apply_op(op(2, 0, 10));
+            r.apply_op(op(3, 0, 11));
+            r.apply_op(op(1, 0, 12));
+            // moving a node under itself is ignored.
+            r.apply_op(op(4, 10, 10));
+            r.truncate_log();
+        });
+
+        let lines = lines.lock().unwrap();
+        let has = |line: &str| lines.iter().any(|l| l == line);
+        assert!(has("apply_op actor=2 counter=1"));
+        assert!(has("state_apply_op counter=1 undo_depth=2"));
+        assert!(has("state_apply_op counter=3 undo_depth=0"));
+        assert!(has("event undo counter=3"));
+        assert!(has("event redo counter=2"));
+        assert!(has(
+            "event move ignored, it would introduce a cycle counter=4"
+        ));
+        assert!(has("truncate_log actor=1"));
+        assert!(has("truncate_log_before counter=4 log_len=4"));
+    }
+}
@@ -5,7 +5,10 @@
 // Please see the LICENSE file for more details.
 
 /// tests for crdt-tree
-use crdt_tree::{Clock, OpMove, State};
+use crdt_tree::{
+    ArcMeta, Clock, HashOrder, InvariantViolation, OpMove, RelativePath, State, Tree, TreeNode,
+    TreeReplica, UniqueNames, WalkControl,
+};
 
 // Define some "real" types for use in the tests.
 type TypeId = u8;
@@ -137,3 +140,1271 @@ fn concurrent_moves_cycle() {
 
     assert_eq!(r1, r2);
 }
+
+// Tests that ancestor queries remain correct while subtrees of a deep
+// tree are moved around, created and undone.
+//
+// Initial State:
+// root
+//  - n1
+//    - n2
+//      - ...
+//        - n50
+#[test]
+fn is_ancestor_deep_tree() {
+    let mut r1: State<u32, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    let root_id = 1000;
+    r1.apply_op(OpMove::new(r1t.tick(), 0, "root", root_id));
+    let mut parent_id = root_id;
+    for id in 1..=50 {
+        r1.apply_op(OpMove::new(r1t.tick(), parent_id, "n", id));
+        parent_id = id;
+    }
+
+    let tree = r1.tree();
+    assert!(tree.is_ancestor(&50, &1));
+    assert!(tree.is_ancestor(&50, &root_id));
+    assert!(tree.is_ancestor(&50, &0));
+    assert!(!tree.is_ancestor(&1, &50));
+    assert!(!tree.is_ancestor(&25, &25));
+    assert!(!tree.is_ancestor(&25, &9999));
+
+    // move n30 (and descendants) directly under root.
+    r1.apply_op(OpMove::new(r1t.tick(), root_id, "n", 30));
+    let tree = r1.tree();
+    assert!(tree.is_ancestor(&50, &30));
+    assert!(!tree.is_ancestor(&50, &29));
+    assert!(tree.is_ancestor(&29, &1));
+
+    // moving n1 under n50 would introduce a cycle, so it is ignored.
+    r1.apply_op(OpMove::new(r1t.tick(), 50, "n", 1));
+    assert!(r1.tree().is_ancestor(&50, &30));
+    assert!(!r1.tree().is_ancestor(&50, &1));
+
+    // moving n1 under n50 is fine once n30 has moved out.
+    r1.apply_op(OpMove::new(r1t.tick(), 50, "n", 1));
+    let tree = r1.tree();
+    assert!(tree.is_ancestor(&29, &50));
+    assert!(tree.is_ancestor(&29, &30));
+    assert!(!tree.is_ancestor(&30, &29));
+
+    // an older concurrent op forces undo/redo of everything after it.
+    let mut r2t = Clock::<TypeActor>::new(new_actor(), Some(1));
+    r1.apply_op(OpMove::new(r2t.tick(), 0, "other", 2000));
+    assert!(r1.tree().is_ancestor(&29, &50));
+    assert!(!r1.tree().is_ancestor(&2000, &root_id));
+
+    // the height index survives the undo/redo: root > n30..n50 > n1..n29.
+    assert_eq!(r1.tree().max_depth(), 51);
+    assert_eq!(r1.tree().check_invariants(), Ok(()));
+}
+
+// Tests borrowing iteration over Tree and State.
+#[test]
+fn iter_tree_and_state() {
+    let mut r1: State<TypeId, String, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root".to_string(), 1),
+        OpMove::new(r1t.tick(), 1, "a".to_string(), 2),
+        OpMove::new(r1t.tick(), 1, "b".to_string(), 3),
+    ]);
+
+    let mut ids: Vec<TypeId> = r1.iter().map(|(id, _)| *id).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!((&r1).into_iter().count(), r1.tree().iter().count());
+
+    for (_, meta) in r1.tree_mut().iter_mut() {
+        meta.make_ascii_uppercase();
+    }
+    assert_eq!(r1.tree().find(&2).unwrap().metadata(), "A");
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &1);
+}
+
+// Tests that try_walk can prune subtrees and stop early.
+//
+// root
+//  - a
+//    - c
+//  - b
+#[test]
+fn try_walk_prune_and_stop() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+        OpMove::new(r1t.tick(), 2, "c", 4),
+    ]);
+    let tree = r1.tree();
+
+    let mut visited = vec![];
+    let finished = tree.try_walk(&1, |_, id, depth| {
+        visited.push((*id, depth));
+        if *id == 2 {
+            WalkControl::Prune
+        } else {
+            WalkControl::Continue
+        }
+    });
+    visited.sort_unstable();
+    assert!(finished);
+    assert_eq!(visited, vec![(1, 0), (2, 1), (3, 1)]);
+
+    let mut count = 0;
+    let finished = tree.try_walk(&1, |_, _, _| {
+        count += 1;
+        WalkControl::Stop
+    });
+    assert!(!finished);
+    assert_eq!(count, 1);
+
+    let mut depths = vec![];
+    tree.walk(&1, |_, id, depth| depths.push((*id, depth)));
+    depths.sort_unstable();
+    assert_eq!(depths, vec![(1, 0), (2, 1), (3, 1), (4, 2)]);
+}
+
+// Tests that sorted dfs/bfs walks visit nodes in a reproducible order.
+//
+// root
+//  - a
+//    - c
+//    - d
+//  - b
+#[test]
+fn walk_dfs_bfs_ordered() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+        OpMove::new(r1t.tick(), 2, "d", 5),
+        OpMove::new(r1t.tick(), 2, "c", 4),
+    ]);
+    let tree = r1.tree();
+
+    let mut dfs = vec![];
+    tree.walk_dfs_by(
+        &1,
+        |a, b| a.cmp(b),
+        |_, id, _| {
+            dfs.push(*id);
+            WalkControl::Continue
+        },
+    );
+    assert_eq!(dfs, vec![1, 2, 4, 5, 3]);
+
+    let mut bfs = vec![];
+    tree.walk_bfs_by(
+        &1,
+        |a, b| a.cmp(b),
+        |_, id, depth| {
+            bfs.push((*id, depth));
+            WalkControl::Continue
+        },
+    );
+    assert_eq!(bfs, vec![(1, 0), (2, 1), (3, 1), (4, 2), (5, 2)]);
+
+    // order siblings by metadata, descending.
+    let by_meta = |a: &TypeId, b: &TypeId| {
+        let meta = |id| tree.find(id).map(|n| *n.metadata());
+        meta(b).cmp(&meta(a))
+    };
+    let mut bfs = vec![];
+    tree.walk_bfs_by(&1, by_meta, |_, id, _| {
+        bfs.push(*id);
+        WalkControl::Continue
+    });
+    assert_eq!(bfs, vec![1, 3, 2, 5, 4]);
+
+    let mut count = 0;
+    tree.walk_bfs(&1, |_, _, depth| {
+        count += 1;
+        if depth == 1 {
+            WalkControl::Prune
+        } else {
+            WalkControl::Continue
+        }
+    });
+    assert_eq!(count, 3);
+}
+
+// Tests that par_walk visits every node exactly once, at the same depth
+// as walk.
+#[cfg(feature = "rayon")]
+#[test]
+fn par_walk_matches_walk() {
+    let mut r1: State<u32, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    // a tree with fanout 3 and 1093 nodes.
+    let mut ops = vec![OpMove::new(r1t.tick(), 0, "root", 1)];
+    for id in 2..=1093 {
+        ops.push(OpMove::new(r1t.tick(), (id + 1) / 3, "n", id));
+    }
+    r1.apply_ops(&ops);
+    let tree = r1.tree();
+
+    let (mut count, mut depth_sum) = (0, 0);
+    tree.walk(&1, |_, _, depth| {
+        count += 1;
+        depth_sum += depth;
+    });
+
+    let (par_count, par_depth_sum) =
+        tree.par_walk(&1, |_, _, depth| (1, depth), |a, b| (a.0 + b.0, a.1 + b.1));
+    assert_eq!(count, 1093);
+    assert_eq!((par_count, par_depth_sum), (count, depth_sum));
+}
+
+// Tests that subtree sizes and depths are kept up to date as nodes
+// are created, moved and removed.
+//
+// root
+//  - a
+//    - c
+//      - d
+//  - b
+#[test]
+fn subtree_size_and_depth() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+        OpMove::new(r1t.tick(), 2, "c", 4),
+        OpMove::new(r1t.tick(), 4, "d", 5),
+    ]);
+    let tree = r1.tree();
+    assert_eq!(tree.subtree_size(&0), 5);
+    assert_eq!(tree.subtree_size(&1), 4);
+    assert_eq!(tree.subtree_size(&2), 2);
+    assert_eq!(tree.subtree_size(&3), 0);
+    assert_eq!(tree.depth(&1), Some(1));
+    assert_eq!(tree.depth(&5), Some(4));
+    assert_eq!(tree.depth(&0), None);
+    assert_eq!(tree.max_depth(), 4);
+
+    // move c (and d) under b.
+    r1.apply_op(OpMove::new(r1t.tick(), 3, "c", 4));
+    let tree = r1.tree();
+    assert_eq!(tree.subtree_size(&1), 4);
+    assert_eq!(tree.subtree_size(&2), 0);
+    assert_eq!(tree.subtree_size(&3), 2);
+    assert_eq!(tree.depth(&5), Some(4));
+
+    // move b (and c, d) to top-level.
+    r1.apply_op(OpMove::new(r1t.tick(), 0, "b", 3));
+    let tree = r1.tree();
+    assert_eq!(tree.subtree_size(&0), 5);
+    assert_eq!(tree.subtree_size(&1), 1);
+    assert_eq!(tree.depth(&5), Some(3));
+    assert_eq!(tree.max_depth(), 3);
+
+    // move c (and d) under another top-level parent, then undo and redo
+    // everything with an older op from a peer.
+    r1.apply_op(OpMove::new(r1t.tick(), 9, "c", 4));
+    let mut r2t = Clock::<TypeActor>::new(new_actor(), Some(1));
+    r1.apply_op(OpMove::new(r2t.tick(), 9, "e", 6));
+    let tree = r1.tree();
+    assert_eq!(tree.subtree_size(&0), 3);
+    assert_eq!(tree.subtree_size(&9), 3);
+    assert_eq!(tree.subtree_size(&4), 1);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    r1.apply_op(OpMove::new(r1t.tick(), 3, "c", 4));
+    assert_eq!(r1.tree().subtree_size(&0), 5);
+    assert_eq!(r1.tree().check_invariants(), Ok(()));
+
+    r1.tree_mut().rm_subtree(&3, true);
+    let tree = r1.tree();
+    assert_eq!(tree.subtree_size(&0), 2);
+    assert_eq!(tree.depth(&5), None);
+    assert_eq!(tree.max_depth(), 2);
+}
+
+// Tests roots and orphans queries.
+//
+// forest
+//  - root
+//    - a
+//      - c
+//  - trash
+//    - b
+#[test]
+fn roots_and_orphans() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 0, "trash", 2),
+        OpMove::new(r1t.tick(), 1, "a", 3),
+        OpMove::new(r1t.tick(), 2, "b", 4),
+        OpMove::new(r1t.tick(), 3, "c", 5),
+        OpMove::new(r1t.tick(), 9, "other", 6),
+    ]);
+
+    let mut roots = r1.tree().roots();
+    roots.sort_unstable();
+    assert_eq!(roots, vec![0, 9]);
+    assert!(r1.tree().orphans().is_empty());
+
+    // removing a leaves c orphaned.
+    r1.tree_mut().rm_child(&3);
+    let mut roots = r1.tree().roots();
+    roots.sort_unstable();
+    assert_eq!(roots, vec![0, 3, 9]);
+    assert_eq!(r1.tree().orphans(), vec![5]);
+
+    r1.tree_mut().rm_child(&5);
+    r1.tree_mut().rm_child(&6);
+    assert_eq!(r1.tree().roots(), vec![0]);
+    assert!(r1.tree().orphans().is_empty());
+    assert_eq!(format!("{}", r1.tree()).lines().count(), 4);
+}
+
+// Tests that check_invariants detects an inconsistent children index
+// and that repair fixes it.
+#[test]
+fn check_invariants_and_repair() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+        OpMove::new(r1t.tick(), 2, "c", 4),
+    ]);
+    assert_eq!(r1.tree().check_invariants(), Ok(()));
+    let good = r1.tree().clone();
+
+    // adding an existing node without removing it first leaves it
+    // listed under both its old and new parent.
+    r1.tree_mut().add_node(4, TreeNode::new(3, "c"));
+    assert_eq!(
+        r1.tree().check_invariants(),
+        Err(InvariantViolation::StaleChild {
+            parent_id: 2,
+            child_id: 4
+        })
+    );
+
+    r1.tree_mut().repair();
+    assert_eq!(r1.tree().check_invariants(), Ok(()));
+    assert_eq!(r1.tree().children(&2), Vec::<TypeId>::new());
+    assert_eq!(r1.tree().children(&3), vec![4]);
+
+    // moving 1 under 4 directly creates a cycle.
+    let mut cyclic = good;
+    cyclic.rm_child(&1);
+    cyclic.add_node(1, TreeNode::new(4, "root"));
+    assert!(matches!(
+        cyclic.check_invariants(),
+        Err(InvariantViolation::Cycle(_))
+    ));
+}
+
+// Tests that a tree with large ids behaves the same after ids are
+// removed and their internal handles reused.
+#[test]
+fn large_ids_reused() {
+    let id = |n: u8| [n; 32];
+    let mut t: Tree<[u8; 32], TypeMetaStr> = Tree::new();
+    t.add_node(id(1), TreeNode::new(id(0), "root"));
+    t.add_node(id(2), TreeNode::new(id(1), "a"));
+    t.add_node(id(3), TreeNode::new(id(2), "b"));
+    t.add_node(id(4), TreeNode::new(id(9), "other"));
+
+    // 4 and its parent 9 are no longer referenced, and 3 is orphaned.
+    t.rm_child(&id(4));
+    t.rm_child(&id(2));
+    t.add_node(id(5), TreeNode::new(id(1), "c"));
+    t.add_node(id(6), TreeNode::new(id(5), "d"));
+    assert_eq!(t.check_invariants(), Ok(()));
+
+    let mut fresh: Tree<[u8; 32], TypeMetaStr> = Tree::new();
+    fresh.add_node(id(1), TreeNode::new(id(0), "root"));
+    fresh.add_node(id(3), TreeNode::new(id(2), "b"));
+    fresh.add_node(id(5), TreeNode::new(id(1), "c"));
+    fresh.add_node(id(6), TreeNode::new(id(5), "d"));
+    assert_ne!(t, fresh); // only t has 3 as an orphan.
+    fresh.add_node(id(2), TreeNode::new(id(1), "a"));
+    fresh.rm_child(&id(2));
+    assert_eq!(t, fresh);
+
+    assert_eq!(t.iter().len(), 4);
+    assert_eq!(t.find(&id(4)), None);
+    assert_eq!(t.depth(&id(6)), Some(3));
+    assert_eq!(t.subtree_size(&id(0)), 3);
+    assert_eq!(t.orphans(), vec![id(3)]);
+    assert!(t.is_ancestor(&id(6), &id(0)));
+    let mut ids: Vec<_> = t.into_iter().map(|(child_id, _)| child_id[0]).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![1, 3, 5, 6]);
+}
+
+// Tests that a clone of a state is unaffected by later ops on the
+// original, and vice versa.
+#[test]
+fn clone_is_independent() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+    ]);
+    let snapshot = r1.clone();
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 3, "a", 2),
+        OpMove::new(r1t.tick(), 0, "c", 4),
+    ]);
+    r1.tree_mut().rm_child(&3);
+    assert_eq!(snapshot.tree().num_nodes(), 3);
+    assert_eq!(snapshot.tree().find(&2).unwrap().parent_id(), &1);
+    assert_eq!(snapshot.tree().children(&1).len(), 2);
+    assert_eq!(snapshot.tree().check_invariants(), Ok(()));
+    assert_eq!(r1.tree().num_nodes(), 3);
+    assert_eq!(r1.tree().orphans(), vec![2]);
+
+    let mut r2 = snapshot.clone();
+    r2.tree_mut().rm_child(&1);
+    assert_eq!(snapshot.tree().subtree_size(&0), 3);
+    assert_eq!(r2.tree().subtree_size(&0), 0);
+}
+
+// Tests that ArcMeta metadata is shared, not copied, by the tree and
+// log, including when ops are undone and redone.
+#[test]
+fn arc_meta_shared() {
+    let mut r1: TreeReplica<TypeId, ArcMeta<Vec<u8>>, TypeActor> = TreeReplica::new(1);
+    let r2: TreeReplica<TypeId, ArcMeta<Vec<u8>>, TypeActor> = TreeReplica::new(2);
+    let blob = ArcMeta::new(vec![7u8; 4096]);
+
+    let op1 = r1.opmove(0, blob.clone(), 1);
+    let op2 = r2.opmove(0, ArcMeta::new(vec![]), 2);
+    r1.apply_op(op1.clone());
+    // op2 is older than op1, so op1 is undone and redone.
+    r1.apply_op(op2);
+    drop(op1);
+
+    let node = r1.tree().find(&1).unwrap();
+    assert!(ArcMeta::ptr_eq(node.metadata(), &blob));
+    // blob, and the node shared by the tree and the log entry.
+    assert_eq!(ArcMeta::ref_count(&blob), 2);
+    assert_eq!(node.metadata(), &ArcMeta::new(vec![7u8; 4096]));
+    assert_eq!(
+        serde_json::to_string(&blob).unwrap(),
+        serde_json::to_string(&*blob).unwrap()
+    );
+
+    let mut copy = blob.clone();
+    ArcMeta::make_mut(&mut copy).push(8);
+    assert_eq!(copy.len(), 4097);
+    assert_eq!(r1.tree().find(&1).unwrap().metadata().len(), 4096);
+
+    // moving 1 records its old node in the new log entry, shared with
+    // the entry that created it.
+    r1.apply_op(r1.opmove(2, ArcMeta::new(vec![]), 1));
+    assert_eq!(ArcMeta::ref_count(&blob), 2);
+    let oldp: &Option<TreeNode<TypeId, ArcMeta<Vec<u8>>>> = r1.state().log()[0].oldp();
+    assert!(ArcMeta::ptr_eq(oldp.as_ref().unwrap().metadata(), &blob));
+
+    // a cloned node shares its parent and metadata.
+    let node = oldp.clone().unwrap();
+    assert_eq!(ArcMeta::ref_count(&blob), 2);
+    drop(node);
+
+    // undoing and redoing every op shares nodes rather than copying them.
+    let r3: TreeReplica<TypeId, ArcMeta<Vec<u8>>, TypeActor> = TreeReplica::new(0);
+    r1.apply_op(r3.opmove(0, ArcMeta::new(vec![]), 3));
+    assert_eq!(r1.state().log().len(), 4);
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &2);
+    assert_eq!(ArcMeta::ref_count(&blob), 2);
+    assert_eq!(r1.state().tree().check_invariants(), Ok(()));
+}
+
+// Tests lookup of nodes by metadata via a secondary index.
+#[test]
+fn find_by_meta_index() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.apply_op(OpMove::new(r1t.tick(), 0, "root", 1));
+    r1.tree_mut()
+        .index_metadata(|m: &TypeMetaStr| Some(m.to_string()));
+    r1.tree_mut()
+        .index_metadata(|m: &TypeMetaStr| m.chars().next());
+
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 1, "readme", 2),
+        OpMove::new(r1t.tick(), 1, "docs", 3),
+        OpMove::new(r1t.tick(), 3, "readme", 4),
+    ]);
+
+    let mut found = r1.tree().find_by_meta(&"readme".to_string());
+    found.sort_unstable();
+    assert_eq!(found, vec![2, 4]);
+    assert_eq!(r1.tree().find_by_meta(&'d'), vec![3]);
+
+    // rename docs, and an older concurrent op from another replica
+    // forces undo/redo of the rename.
+    r1.apply_op(OpMove::new(r1t.tick(), 1, "manual", 3));
+    let mut r2t = Clock::<TypeActor>::new(new_actor(), Some(2));
+    r1.apply_op(OpMove::new(r2t.tick(), 1, "readme", 5));
+
+    let mut found = r1.tree().find_by_meta(&"readme".to_string());
+    found.sort_unstable();
+    assert_eq!(found, vec![2, 4, 5]);
+    assert!(r1.tree().find_by_meta(&"docs".to_string()).is_empty());
+    assert_eq!(r1.tree().find_by_meta(&'m'), vec![3]);
+
+    // no index has this key type.
+    assert!(r1.tree().find_by_meta(&5u32).is_empty());
+}
+
+// Tests directory-entry lookup by (parent, name).
+//
+// root
+//  - home
+//    - bob
+//  - etc
+#[test]
+fn child_by_name_index() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+
+    r1.tree_mut()
+        .index_names(|m: &TypeMetaStr| Some(m.to_string()));
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "home", 2),
+        OpMove::new(r1t.tick(), 1, "etc", 3),
+        OpMove::new(r1t.tick(), 2, "bob", 4),
+    ]);
+
+    let tree = r1.tree();
+    assert_eq!(tree.child_by_name(&1, &"home".to_string()), Some(2));
+    assert_eq!(tree.child_by_name(&2, &"bob".to_string()), Some(4));
+    assert_eq!(tree.child_by_name(&1, &"bob".to_string()), None);
+
+    // move bob to etc.
+    r1.apply_op(OpMove::new(r1t.tick(), 3, "bob", 4));
+    let tree = r1.tree();
+    assert_eq!(tree.child_by_name(&2, &"bob".to_string()), None);
+    assert_eq!(tree.child_by_name(&3, &"bob".to_string()), Some(4));
+
+    // a second etc/bob.
+    r1.apply_op(OpMove::new(r1t.tick(), 3, "bob", 5));
+    let mut found = r1.tree().children_by_name(&3, &"bob".to_string());
+    found.sort_unstable();
+    assert_eq!(found, vec![4, 5]);
+}
+
+// Tests that concurrently created children with the same name are
+// renamed identically on all replicas.
+#[test]
+fn unique_names_policy() {
+    let mut r1: TreeReplica<TypeId, String, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, String, TypeActor> = TreeReplica::new(2);
+
+    let init = r1.opmoves(vec![(0, "root".to_string(), 1)]);
+    r1.apply_ops_byref(&init);
+    r2.apply_ops_byref(&init);
+
+    // both replicas concurrently create root/a.
+    let op1 = r1.opmove(1, "a".to_string(), 10);
+    let op2 = r2.opmove(1, "a".to_string(), 20);
+    r1.apply_ops(vec![op1.clone(), op2.clone()]);
+    r2.apply_ops(vec![op2, op1]);
+
+    let policy = UniqueNames::new(
+        |m: &String| Some(m.clone()),
+        |m: &String, id: &TypeId| format!("{}~{}", m, id),
+    );
+    assert_eq!(policy.conflicts(r1.state()), vec![(1, vec![10, 20])]);
+
+    // both replicas resolve the conflict, and exchange ops.
+    let fix1 = policy.resolve(&r1);
+    let fix2 = policy.resolve(&r2);
+    assert_eq!(fix1.len(), 1);
+    r1.apply_ops(fix1.clone());
+    r2.apply_ops(fix2.clone());
+    r1.apply_ops(fix2);
+    r2.apply_ops(fix1);
+
+    assert_eq!(r1.state(), r2.state());
+    assert!(policy.conflicts(r1.state()).is_empty());
+    assert_eq!(r1.tree().find(&10).unwrap().metadata(), "a");
+    assert_eq!(r1.tree().find(&20).unwrap().metadata(), "a~20");
+}
+
+// Tests that conflicts are resolved identically however far each replica
+// has truncated its log, even once every replica has truncated the ops
+// that placed the children, and whatever the replica's strategies.
+#[test]
+fn unique_names_truncated() {
+    let mut r1: TreeReplica<TypeId, String, TypeActor, HashOrder> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, String, TypeActor, HashOrder> = TreeReplica::new(2);
+
+    // root/a is created as 20, and later as 10.
+    let op1 = r1.opmove(1, "a".to_string(), 20);
+    r1.apply_op(op1.clone());
+    r2.apply_op(op1);
+    let op2 = r2.opmove(1, "a".to_string(), 10);
+    r1.apply_op(op2.clone());
+    r2.apply_op(op2);
+
+    // r1 truncates both ops, then r2 too.
+    let op3 = r1.opmove(2, "b".to_string(), 30);
+    let op4 = r2.opmove(2, "c".to_string(), 31);
+    r1.apply_ops(vec![op3.clone(), op4.clone()]);
+    assert_eq!(r1.truncate_log().len(), 2);
+
+    let policy = UniqueNames::new(
+        |m: &String| Some(m.clone()),
+        |m: &String, id: &TypeId| format!("{}~{}", m, id),
+    );
+    assert_eq!(policy.conflicts(r1.state()), vec![(1, vec![10, 20])]);
+    assert_eq!(policy.conflicts(r2.state()), vec![(1, vec![10, 20])]);
+    let fix1 = policy.resolve(&r1);
+    assert_eq!(fix1.len(), 1);
+
+    r2.apply_ops(vec![op3, op4]);
+    assert_eq!(r2.truncate_log().len(), 2);
+    let fix2 = policy.resolve(&r2);
+    r1.apply_ops(fix1.clone());
+    r2.apply_ops(fix2.clone());
+    r1.apply_ops(fix2);
+    r2.apply_ops(fix1);
+    assert_eq!(r1.tree(), r2.tree());
+    assert!(policy.conflicts(r1.state()).is_empty());
+    assert_eq!(r1.tree().find(&20).unwrap().metadata(), "a~20");
+}
+
+// Tests that a tree round-trips through its nested JSON representation.
+#[cfg(feature = "serde_json")]
+#[test]
+fn json_round_trip() {
+    use crdt_tree::Tree;
+
+    let mut tree: Tree<TypeId, String> = Tree::new();
+    tree.add_node(1, TreeNode::new(0, "home".to_string()));
+    tree.add_node(2, TreeNode::new(1, "bob".to_string()));
+    tree.add_node(3, TreeNode::new(1, "alice".to_string()));
+    tree.add_node(4, TreeNode::new(2, "projects".to_string()));
+    tree.add_node(6, TreeNode::new(5, "orphan".to_string()));
+
+    let nodes = tree.to_json_nodes();
+    assert_eq!(nodes.len(), 2);
+    let root = nodes.iter().find(|n| n.id == 0).unwrap();
+    assert_eq!(root.meta, None);
+    assert_eq!(root.children.len(), 1);
+    assert_eq!(root.children[0].meta.as_deref(), Some("home"));
+    assert_eq!(root.children[0].children.len(), 2);
+
+    let json = tree.to_json().unwrap();
+    assert!(json.contains("\"projects\""));
+    let tree2 = Tree::<TypeId, String>::from_json(&json).unwrap();
+    assert_eq!(tree, tree2);
+    assert_eq!(tree2.depth(&4), Some(3));
+}
+
+// Tests that redelivered ops are skipped, including ops older than the
+// truncated log.
+#[test]
+fn duplicate_ops_skipped() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let op = r2.opmove(3, "a", 2);
+    r1.apply_op(op.clone());
+    r2.apply_op(op.clone());
+    assert!(r1.has_seen(op.timestamp()));
+    assert!(!r1.has_seen(&op.timestamp().inc()));
+
+    // redeliver every op, newest and oldest.
+    r1.apply_op(op.clone());
+    r1.apply_ops_byref(&ops);
+    assert_eq!(r1.state(), r2.state());
+    assert_eq!(r1.state().log().len(), 4);
+
+    // after truncation, the truncated ops are still skipped.
+    let stable = r1.causally_stable_threshold().cloned().unwrap();
+    assert!(!r1.truncate_log().is_empty());
+    assert!(r1.state().log().len() < 4);
+    let (tree, log) = (r1.tree().clone(), r1.state().log().clone());
+    for op in ops.iter().filter(|o| o.timestamp() < &stable) {
+        assert!(r1.has_seen(op.timestamp()));
+    }
+    r1.apply_ops_byref(&ops);
+    r1.apply_op(op);
+    assert_eq!(r1.tree(), &tree);
+    assert_eq!(r1.state().log(), &log);
+}
+
+// Tests that tree_at reconstructs past trees, including across an undo
+// and redo, without changing the state.
+#[test]
+fn tree_at_past_timestamps() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let t = |counter| Clock::<TypeActor>::new(1, Some(counter));
+
+    r1.apply_ops(&[
+        OpMove::new(t(1), 0, "a", 1),
+        OpMove::new(t(2), 0, "b", 2),
+        OpMove::new(t(4), 2, "a", 1),
+    ]);
+    let before = r1.clone();
+    // applied late, so op 4 is undone and redone.
+    r1.apply_op(OpMove::new(t(3), 1, "c", 3));
+    let after = r1.clone();
+
+    assert_eq!(r1.tree_at(&t(4)), *r1.tree());
+    assert_eq!(r1.tree_at(&t(9)), *r1.tree());
+    assert_eq!(r1.tree_at(&t(2)), before.tree_at(&t(2)));
+    assert_eq!(r1.tree_at(&t(2)).num_nodes(), 2);
+    assert_eq!(r1.tree_at(&t(3)).find(&3).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree_at(&t(3)).find(&1).unwrap().parent_id(), &0);
+    assert_eq!(r1.tree_at(&t(0)).num_nodes(), 0);
+    assert_eq!(r1, after);
+
+    // truncated entries can not be undone.
+    r1.truncate_log_before(&t(3));
+    assert_eq!(r1.tree_at(&t(0)), after.tree_at(&t(2)));
+}
+
+// Tests that the latest timestamp seen from each replica is exposed, and
+// that its minimum is the causally stable threshold.
+#[test]
+fn latest_times_per_replica() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+    assert_eq!(r1.latest_times().count(), 0);
+    assert_eq!(r1.latest_time_of(&2), None);
+
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let op = r2.opmove(1, "c", 4);
+    r1.apply_op(op.clone());
+
+    assert_eq!(r1.latest_time_of(&1), ops.last().map(|o| o.timestamp()));
+    assert_eq!(r1.latest_time_of(&2), Some(op.timestamp()));
+    assert_eq!(r1.latest_time_of(&3), None);
+
+    let mut latest: Vec<_> = r1.latest_times().collect();
+    latest.sort();
+    assert_eq!(
+        latest,
+        vec![(&1, ops.last().unwrap().timestamp()), (&2, op.timestamp())]
+    );
+    assert_eq!(
+        latest.iter().map(|(_, t)| *t).min(),
+        r1.causally_stable_threshold()
+    );
+}
+
+// Tests that a forgotten actor no longer blocks log truncation, and that
+// its later ops are rejected once tombstoned.
+#[test]
+fn forget_actor_unblocks_truncation() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+
+    // r2 is seen once, then goes away.
+    let op = r2.opmove(0, "root", 1);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+    let ops = r1.opmoves(vec![(1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    assert!(r1.truncate_log().is_empty());
+    assert_eq!(r1.state().log().len(), 3);
+
+    // r2 holds the threshold back until forgotten.
+    assert_eq!(r1.forget_actor(&3, false), None);
+    let latest = r1.latest_time_of(&2).cloned();
+    assert_eq!(r1.forget_actor(&2, true), latest);
+    assert!(r1.is_tombstoned(&2));
+    assert!(!r1.is_tombstoned(&1));
+    assert_eq!(r1.latest_time_of(&2), None);
+    assert_eq!(
+        r1.causally_stable_threshold(),
+        ops.last().map(|o| o.timestamp())
+    );
+    assert_eq!(r1.truncate_log().len(), 2);
+    assert_eq!(r1.state().log().len(), 1);
+
+    // later ops from r2 are rejected.
+    let tree = r1.tree().clone();
+    r1.apply_op(r2.opmove(0, "c", 4));
+    assert_eq!(r1.tree(), &tree);
+    assert_eq!(r1.latest_time_of(&2), None);
+}
+
+// Tests that truncation returns the removed entries, and leaves an empty
+// log or a log with no older entries unchanged.
+#[test]
+fn truncate_log_returns_removed() {
+    let t = |c| Clock::<TypeActor>::new(1, Some(c));
+    let mut state: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    assert!(state.truncate_log_before(&t(5)).is_empty());
+
+    for id in 1..=4 {
+        state.apply_op(OpMove::new(t(u64::from(id)), 0, "n", id));
+    }
+    assert!(state.truncate_log_before(&t(1)).is_empty());
+    assert_eq!(state.log().len(), 4);
+
+    let expected: Vec<_> = state.log()[2..].to_vec();
+    let removed = state.truncate_log_before(&t(3));
+    assert_eq!(removed, expected);
+    assert_eq!(removed[0].timestamp(), &t(2));
+    assert_eq!(state.log().len(), 2);
+
+    assert_eq!(state.truncate_log_before(&t(9)).len(), 2);
+    assert!(state.log().is_empty());
+    assert!(state.truncate_log_before(&t(9)).is_empty());
+    assert_eq!(state.tree().num_nodes(), 4);
+}
+
+// Tests that stable moves of the same child collapse into the newest,
+// leaving the tree, and past trees outside the collapsed moves, as they
+// were.
+#[test]
+fn compact_log_collapses_moves() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+    assert_eq!(r1.compact_log(), 0);
+
+    // node 3 is moved back and forth.
+    let ops = r1.opmoves(vec![
+        (0, "root", 1),
+        (1, "a", 2),
+        (1, "b", 3),
+        (2, "b", 3),
+        (1, "b", 3),
+        (2, "b", 3),
+    ]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let first = ops[2].timestamp().clone();
+    let before = r1.state().tree_at(&ops[1].timestamp().clone());
+
+    // the last move of node 3 is not yet stable, so the earlier moves
+    // collapse into the third.
+    let op = r2.opmove(0, "x", 9);
+    r2.apply_op(op.clone());
+    let tree = r1.tree().clone();
+    r1.apply_op(op);
+    assert_eq!(r1.compact_log(), 2);
+    assert_eq!(r1.state().log().len(), 5);
+    assert!(r1.state().log().iter().all(|e| e.timestamp() != &first));
+    assert_eq!(r1.tree().find(&3), tree.find(&3));
+    assert_eq!(r1.state().tree_at(&ops[1].timestamp().clone()), before);
+    assert_eq!(r1.compact_log(), 0);
+}
+
+// Tests that past trees within a compacted range are those before the
+// oldest collapsed move, and that the range survives serialization.
+#[test]
+fn compact_log_range() {
+    let mut r1: TreeReplica<TypeId, String, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, String, TypeActor> = TreeReplica::new(2);
+
+    // the last move of node 3 is ignored, as it would introduce a cycle.
+    let ops = r1.opmoves(vec![
+        (0, "root".to_string(), 1),
+        (1, "a".to_string(), 2),
+        (1, "b".to_string(), 3),
+        (2, "b".to_string(), 3),
+        (3, "c".to_string(), 4),
+        (4, "b".to_string(), 3),
+        (1, "d".to_string(), 5),
+    ]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let op = r2.opmove(0, "x".to_string(), 9);
+    r2.apply_op(op.clone());
+    r1.apply_op(op);
+    let before = r1.state().tree_at(&ops[1].timestamp().clone());
+    let after = r1.state().tree_at(&ops[6].timestamp().clone());
+
+    assert_eq!(r1.compact_log(), 2);
+    let state = r1.state();
+    assert_eq!(state.compacted_from(), Some(ops[2].timestamp()));
+    assert_eq!(state.tree_at(&ops[1].timestamp().clone()), before);
+    assert_eq!(state.tree_at(&ops[3].timestamp().clone()), before);
+    assert_eq!(state.tree_at(&ops[5].timestamp().clone()), before);
+    assert_eq!(state.tree_at(&ops[6].timestamp().clone()), after);
+
+    let json = serde_json::to_string(state).unwrap();
+    let resumed: State<TypeId, String, TypeActor> = serde_json::from_str(&json).unwrap();
+    assert_eq!(&resumed, state);
+    assert_eq!(resumed.compacted_from(), state.compacted_from());
+    assert_eq!(resumed.truncated_before(), state.truncated_before());
+    assert_eq!(resumed.tree_at(&ops[3].timestamp().clone()), before);
+}
+
+// Tests that ops are applied straight from an iterator, eg a channel.
+#[test]
+fn apply_ops_iter() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+    let mut state: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    for op in &ops {
+        tx.send(op.clone()).unwrap();
+    }
+    drop(tx);
+    r2.apply_ops_iter(rx);
+    state.apply_ops_iter(ops.iter().cloned());
+
+    assert_eq!(r2.state(), r1.state());
+    assert_eq!(&state, r1.state());
+    // r2's clock catches up with r1's counter but keeps its own actor.
+    assert_eq!(r2.time().counter(), r1.time().counter());
+    assert_eq!(r2.time().actor_id(), &2);
+}
+
+// Tests that a tree sorted by ID returns children, walks and Display
+// output identically, whatever order the nodes were added in.
+#[test]
+fn sort_children_by_id() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r2: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(1, None);
+    let mut r2t = Clock::<TypeActor>::new(2, None);
+    for id in 1..40 {
+        r1.apply_op(OpMove::new(r1t.tick(), 100 + (id % 3), "n", id));
+    }
+    for id in (1..40).rev() {
+        r2.apply_op(OpMove::new(r2t.tick(), 100 + (id % 3), "n", id));
+    }
+    let (t1, t2) = (r1.tree_mut(), r2.tree_mut());
+    assert!(t1.child_order().is_none());
+    t1.sort_children_by_id();
+    t2.set_child_order(Some(|a: &TypeId, b: &TypeId| a.cmp(b)));
+
+    assert_eq!(t1.children(&101), (1..40).step_by(3).collect::<Vec<_>>());
+    assert_eq!(t1.roots(), vec![100, 101, 102]);
+    assert_eq!(t1.roots(), t2.roots());
+    assert_eq!(t1.to_string(), t2.to_string());
+
+    let walk = |tree: &Tree<TypeId, TypeMetaStr>| {
+        let mut ids = vec![];
+        tree.walk(&100, |_, id, _| ids.push(*id));
+        ids
+    };
+    assert_eq!(walk(t1), walk(t2));
+    assert_eq!(walk(t1)[..3], [100, 3, 6]);
+}
+
+// Tests that merging a sorted, delayed batch gives the same state as
+// applying its ops one at a time, including duplicates, unsorted input
+// and ops that conflict with newer entries.
+#[test]
+fn merge_sorted_ops() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+    let base = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&base);
+    r2.apply_ops_byref(&base);
+
+    // concurrent moves that would form a cycle, interleaved in time.
+    let mut batch1 = vec![];
+    let mut batch2 = vec![];
+    for i in 0..5 {
+        let op = r1.opmove(if i % 2 == 0 { 3 } else { 1 }, "a", 2);
+        r1.apply_op(op.clone());
+        batch1.push(op);
+        let op = r2.opmove(if i % 2 == 0 { 2 } else { 1 }, "b", 3);
+        r2.apply_op(op.clone());
+        batch2.push(op);
+    }
+    let op = r2.opmove(3, "c", 4);
+    r2.apply_op(op.clone());
+    batch2.push(op);
+
+    let mut expected: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    expected.apply_ops(&base);
+    expected.apply_ops(&batch1);
+    expected.apply_ops(&batch2);
+
+    let mut merged: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    merged.apply_ops(&base);
+    merged.apply_ops(&batch1);
+    merged.merge_sorted_ops(batch2.clone());
+    assert_eq!(merged, expected);
+
+    // duplicates are ignored, and unsorted input is sorted.
+    let mut batch = batch2.clone();
+    batch.extend(batch1.iter().cloned());
+    batch.reverse();
+    merged.merge_sorted_ops(batch);
+    assert_eq!(merged, expected);
+
+    let mut merged: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    merged.merge_sorted_ops(base.clone());
+    merged.merge_sorted_ops(vec![]);
+    merged.merge_sorted_ops(batch2);
+    merged.merge_sorted_ops(batch1);
+    assert_eq!(merged, expected);
+}
+
+// Tests that digests match for states that applied the same ops in
+// different orders, and are kept up to date by moves, undo and removal.
+#[test]
+fn state_digest() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r2: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(1, None);
+    // r2's ops are all newer than r1's.
+    let mut r2t = Clock::<TypeActor>::new(2, Some(10));
+    assert_eq!(r1.digest(), r2.digest());
+
+    let ops1 = vec![
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 2, "b", 3),
+    ];
+    let ops2 = vec![
+        OpMove::new(r2t.tick(), 0, "root", 1),
+        OpMove::new(r2t.tick(), 3, "a", 2),
+        OpMove::new(r2t.tick(), 1, "c", 4),
+    ];
+    r1.apply_ops(&ops1);
+    let before = r1.tree().digest();
+    r1.apply_ops(&ops2);
+    r2.apply_ops(&ops2);
+    r2.apply_ops(&ops1);
+    assert_eq!(r1, r2);
+    assert_eq!(r1.digest(), r2.digest());
+    assert_eq!(r1.tree().digest(), r2.tree().digest());
+    assert_ne!(r1.tree().digest(), before);
+
+    // the digest is the same as for a tree built afresh.
+    let mut tree = r1.tree().clone();
+    tree.repair();
+    assert_eq!(tree.digest(), r1.tree().digest());
+
+    // undo restores the earlier digest.
+    let tree_at = r1.tree_at(ops1.last().unwrap().timestamp());
+    let mut fresh: Tree<TypeId, TypeMetaStr> = Tree::new();
+    for (child_id, node) in tree_at.iter() {
+        fresh.add_node(*child_id, node.clone());
+    }
+    assert_eq!(tree_at.digest(), before);
+    assert_eq!(fresh.digest(), before);
+
+    // metadata is only included once enabled.
+    let mut other = r1.tree().clone();
+    for (_, meta) in other.iter_mut() {
+        *meta = "x";
+    }
+    assert_eq!(other.digest(), r1.tree().digest());
+    other.hash_metadata();
+    r1.tree_mut().hash_metadata();
+    assert_ne!(other.digest(), r1.tree().digest());
+
+    r1.tree_mut().rm_child(&4);
+    assert_ne!(r1.tree().digest(), r2.tree().digest());
+    r2.tree_mut().hash_metadata();
+    r2.tree_mut().rm_child(&4);
+    assert_eq!(r1.tree().digest(), r2.tree().digest());
+}
+
+// Tests paths to the root, lowest common ancestors and relative paths.
+//
+// 0
+//  - root
+//    - a
+//      - c
+//        - e
+//      - d
+//    - b
+// 9
+//  - trash
+#[test]
+fn lca_and_paths() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+        OpMove::new(r1t.tick(), 2, "c", 4),
+        OpMove::new(r1t.tick(), 2, "d", 5),
+        OpMove::new(r1t.tick(), 4, "e", 6),
+        OpMove::new(r1t.tick(), 9, "trash", 10),
+    ]);
+    let tree = r1.tree();
+
+    assert_eq!(tree.path_to_root(&6), vec![6, 4, 2, 1, 0]);
+    assert_eq!(tree.path_to_root(&0), vec![0]);
+    assert!(tree.path_to_root(&99).is_empty());
+
+    assert_eq!(tree.lca(&6, &5), Some(2));
+    assert_eq!(tree.lca(&6, &3), Some(1));
+    assert_eq!(tree.lca(&2, &6), Some(2));
+    assert_eq!(tree.lca(&6, &6), Some(6));
+    assert_eq!(tree.lca(&6, &0), Some(0));
+    assert_eq!(tree.lca(&6, &10), None);
+    assert_eq!(tree.lca(&6, &99), None);
+
+    assert_eq!(
+        tree.relative_path(&6, &3),
+        Some(RelativePath {
+            up: 3,
+            down: vec![3]
+        })
+    );
+    assert_eq!(
+        tree.relative_path(&3, &6),
+        Some(RelativePath {
+            up: 1,
+            down: vec![2, 4, 6]
+        })
+    );
+    assert_eq!(
+        tree.relative_path(&5, &5),
+        Some(RelativePath {
+            up: 0,
+            down: vec![]
+        })
+    );
+    assert_eq!(tree.relative_path(&10, &1), None);
+}
+
+// Tests that a subtree is iterated depth-first with depths, lazily, and
+// in the tree's child order if set.
+#[test]
+fn iter_subtree() {
+    let mut r1: State<TypeId, TypeMetaStr, TypeActor> = State::new();
+    let mut r1t = Clock::<TypeActor>::new(new_actor(), None);
+    r1.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root", 1),
+        OpMove::new(r1t.tick(), 1, "b", 3),
+        OpMove::new(r1t.tick(), 1, "a", 2),
+        OpMove::new(r1t.tick(), 2, "d", 5),
+        OpMove::new(r1t.tick(), 2, "c", 4),
+    ]);
+    r1.tree_mut().sort_children_by_id();
+    let tree = r1.tree();
+
+    let visited: Vec<(TypeId, usize)> = tree.iter_subtree(&0).map(|(id, _, d)| (*id, d)).collect();
+    assert_eq!(visited, vec![(1, 1), (2, 2), (4, 3), (5, 3), (3, 2)]);
+
+    let mut walked = vec![];
+    tree.walk(&1, |_, id, depth| walked.push((*id, depth)));
+    let iter = tree.iter_subtree(&1);
+    assert_eq!(iter.len(), 5);
+    assert_eq!(iter.map(|(id, _, d)| (*id, d)).collect::<Vec<_>>(), walked);
+
+    let names: Vec<&str> = tree
+        .iter_subtree(&2)
+        .filter(|(_, _, depth)| *depth > 0)
+        .map(|(_, node, _)| *node.metadata())
+        .collect();
+    assert_eq!(names, vec!["c", "d"]);
+    assert_eq!(tree.iter_subtree(&1).take(2).count(), 2);
+    assert_eq!(tree.iter_subtree(&99).count(), 0);
+}
+
+// Tests the parent and metadata getters.
+#[test]
+fn parent_and_metadata() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2)]);
+    r1.apply_ops_byref(&ops);
+
+    assert_eq!(r1.parent(&2), Some(&1));
+    assert_eq!(r1.metadata(&2), Some(&"a"));
+    assert_eq!(r1.tree().parent(&1), Some(&0));
+    assert_eq!(r1.tree().metadata(&1), Some(&"root"));
+    assert_eq!(r1.parent(&0), None);
+    assert_eq!(r1.metadata(&9), None);
+    assert_eq!(r1.tree().find(&2).unwrap().parts(), (&1, &"a"));
+}
+
+// Tests the Display summaries of State and TreeReplica.
+#[test]
+fn display_summary() {
+    let mut r1: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(1);
+    let mut r2: TreeReplica<TypeId, TypeMetaStr, TypeActor> = TreeReplica::new(2);
+    assert_eq!(
+        r1.to_string(),
+        "replica 1 at (1, 0), causally stable threshold none; \
+         tree: 0 nodes, max depth 0; log: 0 entries"
+    );
+
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (2, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let op = r2.opmove(1, "c", 4);
+    r1.apply_op(op);
+    assert_eq!(
+        r1.state().to_string(),
+        "tree: 4 nodes, max depth 3; log: 4 entries, (1, 1) to (2, 4), by actor {1: 3, 2: 1}"
+    );
+    assert_eq!(
+        r1.to_string(),
+        format!(
+            "replica 1 at (1, 4), causally stable threshold (1, 3); {}",
+            r1.state()
+        )
+    );
+}
+
+// Tests that a state serialized before IDs were interned, when the tree
+// held its children index rather than a list of detached parents, still
+// deserializes.
+#[test]
+fn deserialize_baseline_state() {
+    let json = r#"{
+        "log_op_list": [
+            {"op": {"timestamp": {"actor_id": 1, "counter": 2}, "parent_id": 1,
+                    "metadata": "a", "child_id": 2}, "oldp": null},
+            {"op": {"timestamp": {"actor_id": 1, "counter": 1}, "parent_id": 0,
+                    "metadata": "root", "child_id": 1}, "oldp": null}
+        ],
+        "tree": {
+            "triples": {
+                "1": {"parent_id": 0, "metadata": "root"},
+                "2": {"parent_id": 1, "metadata": "a"}
+            },
+            "children": {"0": [1], "1": [2]}
+        }
+    }"#;
+    let state: State<TypeId, String, TypeActor> = serde_json::from_str(json).unwrap();
+
+    let mut expected = State::new();
+    let mut r1t = Clock::<TypeActor>::new(1, None);
+    expected.apply_ops(&[
+        OpMove::new(r1t.tick(), 0, "root".to_string(), 1),
+        OpMove::new(r1t.tick(), 1, "a".to_string(), 2),
+    ]);
+    assert_eq!(state, expected);
+    assert!(state.tree().orphans().is_empty());
+    assert_eq!(state.tree().check_invariants(), Ok(()));
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree initial tree builder
+use crdt_tree::fs::FsError;
+use crdt_tree::{NodeDef, StringMeta, TreeBuilder, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = StringMeta;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+fn meta(name: &str) -> TypeMeta {
+    StringMeta::new(name)
+}
+
+// Tests that nested definitions are emitted parents first, as one batch.
+#[test]
+fn build_nested_nodes() {
+    let mut r1 = TypeReplica::new(1);
+    let builder = TreeBuilder::new(0)
+        .with_nodes(vec![
+            NodeDef::new(1, meta("home")).with_children(vec![
+                NodeDef::new(2, meta("bob")).with_child(NodeDef::new(4, meta("docs"))),
+                NodeDef::new(3, meta("alice")),
+            ]),
+            NodeDef::new(5, meta("tmp")),
+        ])
+        .with_nodes_under(3, vec![NodeDef::new(6, meta("music"))]);
+    assert_eq!(builder.len(), 6);
+
+    let ops = builder.clone().build(&r1);
+    let moves = builder.into_moves();
+    let moves: Vec<(TypeId, &str, TypeId)> =
+        moves.iter().map(|(p, m, c)| (*p, m.as_str(), *c)).collect();
+    assert_eq!(
+        moves,
+        vec![
+            (0, "home", 1),
+            (1, "bob", 2),
+            (2, "docs", 4),
+            (1, "alice", 3),
+            (0, "tmp", 5),
+            (3, "music", 6),
+        ]
+    );
+
+    // timestamps increase through the batch.
+    assert!(ops.windows(2).all(|w| w[0].timestamp() < w[1].timestamp()));
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().num_nodes(), 6);
+    assert_eq!(r1.tree().find(&6).unwrap().parent_id(), &3);
+}
+
+// Tests that paths create intermediate directories once, and that a
+// later path may set a directory's metadata.
+#[test]
+fn build_from_paths() {
+    let mut r1 = TypeReplica::new(1);
+    let mut next = 0;
+    let mut seen = vec![];
+    let builder = TreeBuilder::new(0)
+        .with_paths(
+            vec![
+                ("/etc/hosts", meta("hosts")),
+                ("etc/ssh/config", meta("config")),
+                ("/etc/", meta("etc!")),
+            ],
+            |path| {
+                seen.push(path.to_string());
+                next += 1;
+                next
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        seen,
+        vec!["/etc", "/etc/hosts", "/etc/ssh", "/etc/ssh/config"]
+    );
+
+    let ops = builder.build(&r1);
+    assert_eq!(ops.len(), 4);
+    r1.apply_ops(ops);
+    let tree = r1.tree();
+    assert_eq!(tree.find(&1).unwrap().metadata(), "etc!");
+    assert_eq!(tree.find(&3).unwrap().metadata(), "ssh");
+    assert_eq!(tree.find(&4).unwrap().parent_id(), &3);
+
+    assert_eq!(
+        TreeBuilder::<TypeId, TypeMeta>::new(0)
+            .with_paths(vec![("/", meta("root"))], |_| 1)
+            .unwrap_err(),
+        FsError::InvalidPath("/".to_string())
+    );
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree tree! macro
+use crdt_tree::{tree, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// Tests that nested nodes expand to the same ops as ::opmoves(), parents
+// first.
+#[test]
+fn tree_macro_ops() {
+    let mut r1 = TypeReplica::new(1);
+    let docs = 4;
+    let ops = tree!(r1, 0 => {
+        1: "home" => {
+            2: "bob",
+            3: "alice" => { docs: "docs" },
+        },
+        5: "tmp",
+        (docs + 2): "empty" => {},
+    });
+    assert_eq!(
+        ops,
+        r1.opmoves(vec![
+            (0, "home", 1),
+            (1, "bob", 2),
+            (1, "alice", 3),
+            (3, "docs", 4),
+            (0, "tmp", 5),
+            (0, "empty", 6),
+        ])
+    );
+
+    r1.apply_ops(ops);
+    assert_eq!(r1.tree().num_nodes(), 6);
+    assert_eq!(r1.tree().find(&4).unwrap().parent_id(), &3);
+}
+
+// Tests single nodes, without trailing commas.
+#[test]
+fn tree_macro_minimal() {
+    let r1 = TypeReplica::new(1);
+    assert!(tree!(r1, 0 => {}).is_empty());
+    let ops = tree!(r1, 0 => { 1: "a" => { 2: "b" } });
+    assert_eq!(ops, r1.opmoves(vec![(0, "a", 1), (1, "b", 2)]));
+}
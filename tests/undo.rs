@@ -0,0 +1,218 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree local undo and redo
+use crdt_tree::{State, TreeReplica};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// returns replicas 1 and 2 holding root 1 with children 2 and 3.
+fn setup() -> (TypeReplica, TypeReplica) {
+    let mut r1 = TypeReplica::new(1);
+    let mut r2 = TypeReplica::new(2);
+    let ops = r1.opmoves(vec![(0, "root", 1), (1, "a", 2), (1, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    (r1, r2)
+}
+
+#[test]
+fn undo_last_local_restores_parent_and_metadata() {
+    let (mut r1, mut r2) = setup();
+    let op = r1.opmove(3, "moved", 2);
+    r1.apply_op(op.clone());
+    r2.apply_op(op);
+
+    let undo = r1.undo_last_local().unwrap();
+    assert!(undo.timestamp() > r2.time());
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree().find(&2).unwrap().metadata(), &"a");
+
+    // the undo is an ordinary op, and propagates.
+    r2.apply_op(undo);
+    assert_eq!(r1.tree(), r2.tree());
+
+    // undoing again undoes the undo.
+    r1.undo_last_local().unwrap();
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &3);
+    assert_eq!(r1.tree().find(&2).unwrap().metadata(), &"moved");
+}
+
+#[test]
+fn undo_last_local_skips_remote_ops() {
+    let (mut r1, mut r2) = setup();
+    let mine = r1.opmove(3, "a", 2);
+    r1.apply_op(mine.clone());
+    r2.apply_op(mine);
+    // a later remote op on another node.
+    let theirs = r2.opmove(0, "b", 3);
+    r2.apply_op(theirs.clone());
+    r1.apply_op(theirs);
+
+    assert!(r1.undo_last_local().is_some());
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &0);
+}
+
+#[test]
+fn undo_last_local_yields_to_newer_moves() {
+    let (mut r1, mut r2) = setup();
+    let mine = r1.opmove(3, "a", 2);
+    r1.apply_op(mine.clone());
+    r2.apply_op(mine);
+    // a peer moves the node again after seeing the op.
+    let theirs = r2.opmove(0, "a", 2);
+    r2.apply_op(theirs.clone());
+    r1.apply_op(theirs);
+
+    let time = r1.time().clone();
+    assert_eq!(r1.undo_last_local(), None);
+    assert_eq!(r1.time(), &time);
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &0);
+}
+
+#[test]
+fn undo_last_local_yields_to_concurrent_winner() {
+    let (mut r1, r2) = setup();
+    // concurrent moves with equal counters, so replica 2 wins.
+    let mine = r1.opmove(3, "a", 2);
+    let theirs = r2.opmove(0, "a", 2);
+    r1.apply_op(mine);
+    r1.apply_op(theirs);
+
+    assert_eq!(r1.undo_last_local(), None);
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &0);
+}
+
+#[test]
+fn undo_last_local_nothing_to_undo() {
+    let mut r1 = TypeReplica::new(1);
+    assert_eq!(r1.undo_last_local(), None);
+
+    // creating a node can not be undone.
+    let op = r1.opmove(0, "a", 1);
+    r1.apply_op(op);
+    assert_eq!(r1.undo_last_local(), None);
+
+    // nor can an op ignored as it would introduce a cycle.
+    let ops = r1.opmoves(vec![(1, "b", 2), (2, "a", 1)]);
+    r1.apply_ops(ops);
+    assert_eq!(r1.undo_last_local(), None);
+    assert_eq!(r1.tree().find(&1).unwrap().parent_id(), &0);
+}
+
+#[test]
+fn undo_redo_stack() {
+    let (mut r1, mut r2) = setup();
+    assert!(!r1.can_undo());
+    assert!(!r1.can_redo());
+
+    let ops = r1.opmoves(vec![(3, "a", 2), (0, "c", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    let moved = r1.tree().clone();
+    assert!(r1.can_undo());
+
+    // undo both ops, newest first.
+    let mut sent = vec![r1.undo().unwrap()];
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree().find(&3).unwrap().metadata(), &"b");
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &3);
+    sent.push(r1.undo().unwrap());
+    assert!(!r1.can_undo());
+    assert_eq!(r1.undo(), None);
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &1);
+
+    // redo them, oldest first.
+    assert!(r1.can_redo());
+    sent.push(r1.redo().unwrap());
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &3);
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &1);
+    sent.push(r1.redo().unwrap());
+    assert!(!r1.can_redo());
+    assert_eq!(r1.redo(), None);
+    assert_eq!(r1.tree(), &moved);
+
+    // undo and redo propagate as ordinary ops.
+    r2.apply_ops(sent);
+    assert_eq!(r1.tree(), r2.tree());
+
+    // redone ops can be undone again.
+    r1.undo().unwrap();
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &1);
+}
+
+#[test]
+fn new_local_op_clears_redo() {
+    let (mut r1, mut r2) = setup();
+    let op = r1.opmove(3, "a", 2);
+    r1.apply_op(op);
+    r1.undo().unwrap();
+    assert!(r1.can_redo());
+
+    // remote ops leave the redo stack alone.
+    let theirs = r2.opmove(0, "d", 4);
+    r2.apply_op(theirs.clone());
+    r1.apply_op(theirs);
+    assert!(r1.can_redo());
+
+    let op = r1.opmove(0, "e", 5);
+    r1.apply_op(op);
+    assert!(!r1.can_redo());
+    assert_eq!(r1.redo(), None);
+}
+
+#[test]
+fn undo_skips_overridden_ops() {
+    let (mut r1, mut r2) = setup();
+    let ops = r1.opmoves(vec![(3, "a", 2), (0, "b", 3)]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    // a peer moves 3 again, overriding the newest local op.
+    let theirs = r2.opmove(1, "b", 3);
+    r2.apply_op(theirs.clone());
+    r1.apply_op(theirs);
+
+    assert!(r1.can_undo());
+    r1.undo().unwrap();
+    assert_eq!(r1.tree().find(&2).unwrap().parent_id(), &1);
+    assert_eq!(r1.tree().find(&3).unwrap().parent_id(), &1);
+    assert!(!r1.can_undo());
+}
+
+#[test]
+fn undo_last_local_after_deserializing() {
+    // metadata is owned, to be deserialized.
+    type StringReplica = TreeReplica<TypeId, String, TypeActor>;
+    let mut r1 = StringReplica::new(1);
+    let mut r2 = StringReplica::new(2);
+    let ops = r1.opmoves(vec![
+        (0, "root".to_string(), 1),
+        (1, "a".to_string(), 2),
+        (1, "b".to_string(), 3),
+        (3, "moved".to_string(), 2),
+    ]);
+    r1.apply_ops_byref(&ops);
+    r2.apply_ops_byref(&ops);
+    // a remote op ignored as it would introduce a cycle.
+    let theirs = r2.opmove(2, "root".to_string(), 1);
+    r2.apply_op(theirs.clone());
+    r1.apply_op(theirs);
+
+    let json = serde_json::to_string(r1.state()).unwrap();
+    let state: State<TypeId, String, TypeActor> = serde_json::from_str(&json).unwrap();
+    let mut resumed = StringReplica::from_state(1, state, r1.version_vector().clone());
+
+    let undo = resumed.undo_last_local().unwrap();
+    assert_eq!(Some(undo), r1.undo_last_local());
+    assert_eq!(resumed.tree(), r1.tree());
+    assert_eq!(resumed.tree().find(&2).unwrap().parent_id(), &1);
+}
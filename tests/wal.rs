@@ -0,0 +1,247 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree write-ahead log
+#[cfg(feature = "wal")]
+mod wal {
+    use crdt_tree::recovery::{self, RecoveryError};
+    use crdt_tree::{HashOrder, OpMove, TreeReplica, Wal, WalError, MAX_RECORD_LEN};
+    use std::env;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    type TypeId = u64;
+    type TypeActor = u64;
+    type TypeMeta = String;
+    type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+    type TypeOp = OpMove<TypeId, TypeMeta, TypeActor>;
+
+    // helper: returns a path for a new log under the system temp dir.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("crdt_tree_{}_{}.wal", name, rand::random::<u64>()))
+    }
+
+    // Tests that ops applied through the log are recovered after reopening.
+    #[test]
+    fn recover_applied_ops() {
+        let path = temp_path("recover");
+        let mut r1 = TypeReplica::new(1);
+        {
+            let mut wal = Wal::open(&path, true).unwrap();
+            let ops = r1.opmoves(vec![
+                (0, "a".to_string(), 1),
+                (1, "b".to_string(), 2),
+                (1, "c".to_string(), 3),
+            ]);
+            wal.apply_ops(&mut r1, ops).unwrap();
+            let op = r1.opmove(2, "c".to_string(), 3);
+            wal.apply_op(&mut r1, op).unwrap();
+        }
+
+        let wal = Wal::open(&path, true).unwrap();
+        let mut r2 = TypeReplica::new(1);
+        assert_eq!(wal.recover(&mut r2).unwrap(), 4);
+        assert_eq!(r1.state(), r2.state());
+        assert_eq!(r1.time(), r2.time());
+
+        // replaying again changes nothing.
+        assert_eq!(wal.recover(&mut r2).unwrap(), 4);
+        assert_eq!(r1.state(), r2.state());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // Tests that replicas with another TieBreak strategy are logged and
+    // recovered.
+    #[test]
+    fn recover_with_strategies() {
+        type HashReplica = TreeReplica<TypeId, TypeMeta, TypeActor, HashOrder>;
+        let path = temp_path("strategies");
+        let mut r1 = HashReplica::new(1);
+        let mut wal = Wal::open(&path, false).unwrap();
+        let ops = r1.opmoves(vec![(0, "a".to_string(), 1), (1, "b".to_string(), 2)]);
+        wal.apply_ops(&mut r1, ops).unwrap();
+        let op = r1.opmove(2, "a".to_string(), 1);
+        wal.apply_op(&mut r1, op).unwrap();
+
+        let mut r2 = HashReplica::new(1);
+        assert_eq!(wal.recover(&mut r2).unwrap(), 3);
+        assert_eq!(r1.state(), r2.state());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // Tests that reset empties the log.
+    #[test]
+    fn reset() {
+        let path = temp_path("reset");
+        let mut r1 = TypeReplica::new(1);
+        let mut wal = Wal::open(&path, false).unwrap();
+        let op = r1.opmove(0, "a".to_string(), 1);
+        wal.apply_op(&mut r1, op).unwrap();
+        wal.reset().unwrap();
+        assert!(wal
+            .read_ops::<TypeId, TypeMeta, TypeActor>()
+            .unwrap()
+            .is_empty());
+
+        let op = r1.opmove(0, "b".to_string(), 2);
+        wal.apply_op(&mut r1, op.clone()).unwrap();
+        wal.sync().unwrap();
+        assert_eq!(
+            wal.read_ops::<TypeId, TypeMeta, TypeActor>().unwrap(),
+            vec![op]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // Tests that a damaged record is reported rather than applied.
+    #[test]
+    fn corrupt_record() {
+        let path = temp_path("corrupt");
+        let r1 = TypeReplica::new(1);
+        let ops = r1.opmoves(vec![(0, "a".to_string(), 1), (1, "b".to_string(), 2)]);
+        let mut wal = Wal::open(&path, false).unwrap();
+        for op in ops.iter() {
+            wal.append(op).unwrap();
+        }
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // flip the last byte of the second record.
+        let mut bytes = std::fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        let first_len = len / 2; // both records have the same length.
+        assert!(matches!(
+            wal.read_ops::<TypeId, TypeMeta, TypeActor>(),
+            Err(WalError::BadChecksum { offset }) if offset == first_len
+        ));
+
+        // a partial record at the end is also reported.
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+        let result: Result<Vec<TypeOp>, WalError> = wal.read_ops();
+        assert!(matches!(result, Err(WalError::TruncatedTail { offset }) if offset == len));
+
+        // which can be discarded.
+        wal.truncate(len).unwrap();
+        assert_eq!(wal.read_ops::<TypeId, TypeMeta, TypeActor>().unwrap(), ops);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // Tests that a corrupt length field is reported without allocating a
+    // buffer of that length.
+    #[test]
+    fn corrupt_length() {
+        let path = temp_path("length");
+        let r1 = TypeReplica::new(1);
+        let ops = r1.opmoves(vec![(0, "a".to_string(), 1), (1, "b".to_string(), 2)]);
+        let mut wal = Wal::open(&path, false).unwrap();
+        for op in ops.iter() {
+            wal.append(op).unwrap();
+        }
+        let first_len = std::fs::metadata(&path).unwrap().len() / 2;
+        let at = first_len as usize;
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[at..at + 4].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            wal.read_ops::<TypeId, TypeMeta, TypeActor>(),
+            Err(WalError::RecordTooLarge { offset, len: 0xFFFF_FFFF }) if offset == first_len
+        ));
+
+        // a length within the limit, but past the end of the file.
+        bytes[at..at + 4].copy_from_slice(&MAX_RECORD_LEN.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            wal.read_ops::<TypeId, TypeMeta, TypeActor>(),
+            Err(WalError::TruncatedTail { offset }) if offset == first_len
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    // Tests recovery from a snapshot plus the ops logged after it,
+    // including after a crash between saving the snapshot and emptying
+    // the log.
+    #[test]
+    fn recover_snapshot_and_wal() {
+        let wal_path = temp_path("recovery");
+        let snapshot_path = wal_path.with_extension("snapshot");
+        let mut r1 = TypeReplica::new(1);
+        let mut wal = Wal::open(&wal_path, true).unwrap();
+
+        // nothing saved yet.
+        let r2: TypeReplica = recovery::recover(1, &snapshot_path, &wal).unwrap();
+        assert_eq!(r2, TypeReplica::new(1));
+
+        let ops = r1.opmoves(vec![(0, "a".to_string(), 1), (1, "b".to_string(), 2)]);
+        wal.apply_ops(&mut r1, ops).unwrap();
+        // as if the log were not emptied after saving the snapshot.
+        recovery::save_snapshot(&snapshot_path, &r1.snapshot(1)).unwrap();
+        let op = r1.opmove(1, "c".to_string(), 3);
+        wal.apply_op(&mut r1, op).unwrap();
+        let r2: TypeReplica = recovery::recover(1, &snapshot_path, &wal).unwrap();
+        assert_eq!(r1.tree(), r2.tree());
+        assert_eq!(r1.time(), r2.time());
+
+        recovery::checkpoint(&r1, 2, &snapshot_path, &mut wal).unwrap();
+        assert!(wal
+            .read_ops::<TypeId, TypeMeta, TypeActor>()
+            .unwrap()
+            .is_empty());
+        let op = r1.opmove(3, "d".to_string(), 2);
+        wal.apply_op(&mut r1, op).unwrap();
+        let r2: TypeReplica = recovery::recover(1, &snapshot_path, &wal).unwrap();
+        assert_eq!(r1.tree(), r2.tree());
+
+        // a damaged snapshot is reported.
+        let mut bytes = std::fs::read(&snapshot_path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&snapshot_path, &bytes).unwrap();
+        assert!(matches!(
+            recovery::recover::<TypeId, TypeMeta, TypeActor>(1, &snapshot_path, &wal),
+            Err(RecoveryError::SnapshotChecksum)
+        ));
+
+        std::fs::remove_file(wal_path).unwrap();
+        std::fs::remove_file(snapshot_path).unwrap();
+    }
+
+    // Tests that an incomplete record left by a crash is reported, and
+    // that recovery succeeds once it is discarded.
+    #[test]
+    fn recover_truncated_tail() {
+        let wal_path = temp_path("recovery_tail");
+        let snapshot_path = wal_path.with_extension("snapshot");
+        let mut r1 = TypeReplica::new(1);
+        let mut wal = Wal::open(&wal_path, true).unwrap();
+        let ops = r1.opmoves(vec![(0, "a".to_string(), 1), (1, "b".to_string(), 2)]);
+        wal.apply_ops(&mut r1, ops).unwrap();
+        let len = std::fs::metadata(&wal_path).unwrap().len();
+
+        let mut file = OpenOptions::new().append(true).open(&wal_path).unwrap();
+        file.write_all(&[9, 0, 0, 0, 1]).unwrap();
+        let offset = match recovery::recover::<TypeId, TypeMeta, TypeActor>(1, &snapshot_path, &wal)
+        {
+            Err(RecoveryError::Wal(WalError::TruncatedTail { offset })) => offset,
+            _ => panic!("expected truncated tail"),
+        };
+        assert_eq!(offset, len);
+
+        wal.truncate(offset).unwrap();
+        let r2: TypeReplica = recovery::recover(1, &snapshot_path, &wal).unwrap();
+        assert_eq!(r1.state(), r2.state());
+
+        std::fs::remove_file(wal_path).unwrap();
+    }
+}
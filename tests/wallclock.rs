@@ -0,0 +1,86 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for crdt-tree wall-clock time on ops
+use crdt_tree::{DriftGuard, DriftPolicy, LogOpMove, OpMove, TreeReplica};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Define some "real" types for use in the tests.
+type TypeId = u64;
+type TypeActor = u8;
+type TypeMeta = &'static str;
+type TypeReplica = TreeReplica<TypeId, TypeMeta, TypeActor>;
+
+// helper: returns the wall-clock time, in milliseconds since the epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Tests that generated ops carry wall-clock time only when enabled, and
+// that it survives the log.
+#[test]
+fn stamp_wall_clock() {
+    let mut r1 = TypeReplica::new(1);
+    assert_eq!(r1.opmove(0, "a", 1).wall_time(), None);
+
+    r1.set_wall_clock(true);
+    let before = now();
+    let op = r1.opmove(0, "a", 1);
+    let wall_time = op.wall_time().unwrap();
+    assert!(wall_time >= before && wall_time <= now());
+    assert!(r1
+        .opmoves(vec![(0, "b", 2), (0, "c", 3)])
+        .iter()
+        .all(|op| op.wall_time().is_some()));
+
+    r1.apply_op(op.clone());
+    let entry: &LogOpMove<TypeId, TypeMeta, TypeActor> = &r1.state().log()[0];
+    assert_eq!(entry.wall_time(), Some(wall_time));
+    assert_eq!(entry.clone().op_into(), op);
+    assert_eq!(OpMove::from(entry.clone()).wall_time(), Some(wall_time));
+}
+
+// Tests that ops claiming a time beyond max drift are flagged or
+// rejected, per the guard's policy.
+#[test]
+fn drift_guard() {
+    let r2 = TypeReplica::new(2);
+    let future = now() + 3_600_000;
+    let ops = vec![
+        r2.opmove(0, "a", 1).with_wall_time(now()),
+        r2.opmoves(vec![(0, "x", 0), (0, "b", 2)])[1]
+            .clone()
+            .with_wall_time(future),
+        r2.opmoves(vec![(0, "x", 0), (0, "x", 0), (0, "c", 3)])[2].clone(),
+    ];
+
+    let guard = |policy| DriftGuard::new(Duration::from_secs(60), policy);
+    assert!(guard(DriftPolicy::Flag).exceeds(future, now()));
+    assert!(!guard(DriftPolicy::Flag).exceeds(now() + 1000, now()));
+
+    // no guard: all ops applied.
+    let mut r1 = TypeReplica::new(1);
+    r1.apply_ops(ops.clone());
+    assert_eq!(r1.tree().num_nodes(), 3);
+    assert!(r1.drifted_ops().is_empty());
+
+    let mut r1 = TypeReplica::new(1);
+    r1.set_drift_guard(Some(guard(DriftPolicy::Flag)));
+    r1.apply_ops(ops.clone());
+    assert_eq!(r1.tree().num_nodes(), 3);
+    assert_eq!(r1.drifted_ops(), &[ops[1].timestamp().clone()]);
+
+    let mut r1 = TypeReplica::new(1);
+    r1.set_drift_guard(Some(guard(DriftPolicy::Reject)));
+    r1.apply_ops(ops.clone());
+    assert_eq!(r1.tree().num_nodes(), 2);
+    assert!(r1.tree().find(&2).is_none());
+    assert_eq!(r1.take_drifted_ops(), vec![ops[1].timestamp().clone()]);
+    assert!(r1.drifted_ops().is_empty());
+}
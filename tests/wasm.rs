@@ -0,0 +1,50 @@
+// Copyright (c) 2022, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+/// tests for the crdt-tree wasm bindings
+///
+/// These run natively, so only cover calls that do not construct JS
+/// values, ie not error paths.
+#[cfg(feature = "wasm")]
+mod wasm {
+    use crdt_tree::{OpMove, TreeReplica, WasmReplica};
+    use serde_json::{json, Value};
+
+    type TypeReplica = TreeReplica<String, Value, String>;
+
+    // Tests that ops exchanged as JSON converge a JS replica with a Rust
+    // one.
+    #[test]
+    fn json_ops() {
+        let mut js = WasmReplica::new("browser".to_string());
+        assert_eq!(js.actor_id(), "browser");
+        let op = js
+            .move_node("root".into(), r#"{"name":"a"}"#, "a".into())
+            .unwrap();
+
+        let mut rust = TypeReplica::new("server".to_string());
+        rust.apply_op(serde_json::from_str(&op).unwrap());
+        let op = rust.opmove("a".into(), json!([1, 2]), "b".into());
+        rust.apply_op(op.clone());
+        js.apply_ops(&serde_json::to_string(&vec![op]).unwrap())
+            .unwrap();
+
+        assert_eq!(js.children("a".into()), vec!["b".to_string()]);
+        assert_eq!(js.parent("b".into()), Some("a".to_string()));
+        assert_eq!(js.metadata("b".into()).unwrap().unwrap(), "[1,2]");
+        assert_eq!(js.metadata("c".into()).unwrap(), None);
+        let tree: Value = serde_json::from_str(&js.to_json().unwrap()).unwrap();
+        assert_eq!(tree[0]["children"][0]["meta"]["name"], "a");
+
+        // both have seen each other's ops.
+        let vv = js.version_vector().unwrap();
+        let ops: Vec<OpMove<String, Value, String>> =
+            serde_json::from_str(&js.missing_ops(&vv).unwrap().unwrap()).unwrap();
+        assert!(ops.is_empty());
+        let vv = serde_json::to_string(rust.version_vector()).unwrap();
+        assert_eq!(js.missing_ops(&vv).unwrap().unwrap(), "[]");
+    }
+}